@@ -0,0 +1,105 @@
+// Account allocations in the genesis.json / alloc.json format
+
+use primitive_types::{H160, H256, U256};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use vm::db::{AccountInfo, Changes};
+use vm::state::Account;
+use vm::trie::StateTrie;
+
+pub type Alloc = BTreeMap<H160, AllocAccount>;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AllocAccount {
+    #[serde(
+        default,
+        with = "vm::serde_hex::bytes",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub code: Vec<u8>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub storage: BTreeMap<U256, U256>,
+    pub balance: U256,
+    #[serde(default, with = "vm::serde_hex::u64", skip_serializing_if = "is_zero")]
+    pub nonce: u64,
+}
+
+// Fields of a genesis.json used as the pre-state of a run
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Genesis {
+    #[serde(default)]
+    pub alloc: Alloc,
+    #[serde(default)]
+    pub coinbase: H160,
+    #[serde(default, with = "vm::serde_hex::u64_opt")]
+    pub number: Option<u64>,
+    #[serde(default, with = "vm::serde_hex::u64_opt")]
+    pub timestamp: Option<u64>,
+    #[serde(default, with = "vm::serde_hex::u64_opt")]
+    pub gas_limit: Option<u64>,
+    pub difficulty: Option<U256>,
+    pub base_fee_per_gas: Option<U256>,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+impl From<&AllocAccount> for Account {
+    fn from(account: &AllocAccount) -> Self {
+        Account {
+            balance: account.balance,
+            nonce: account.nonce,
+            code: account.code.clone(),
+            storage: account
+                .storage
+                .iter()
+                .filter(|(_, value)| !value.is_zero())
+                .map(|(key, value)| (h256(*key), h256(*value)))
+                .collect(),
+        }
+    }
+}
+
+// Applies the changes of a run, leaving out zero storage slots
+pub fn apply_changes(alloc: &mut Alloc, changes: Changes) {
+    for (address, change) in changes {
+        let Some(account) = change.account else {
+            alloc.remove(&address);
+            continue;
+        };
+        let entry = alloc.entry(address).or_default();
+        if change.storage_cleared {
+            entry.storage.clear();
+        }
+        entry.balance = account.balance;
+        entry.nonce = account.nonce;
+        entry.code = account.code;
+        for (key, value) in account.storage {
+            let key = U256::from_big_endian(key.as_bytes());
+            if value.is_zero() {
+                entry.storage.remove(&key);
+            } else {
+                entry
+                    .storage
+                    .insert(key, U256::from_big_endian(value.as_bytes()));
+            }
+        }
+    }
+}
+
+pub fn state_root(alloc: &Alloc) -> H256 {
+    let mut trie = StateTrie::new();
+    for (address, account) in alloc {
+        let account = Account::from(account);
+        trie.insert_account(*address, &AccountInfo::from(&account), account.storage);
+    }
+    trie.root()
+}
+
+fn h256(value: U256) -> H256 {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    H256(bytes)
+}
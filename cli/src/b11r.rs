@@ -0,0 +1,254 @@
+// Block builder compatible with geth's `evm b11r`
+
+use crate::tool::{decode_hex, parse, read_file, read_stdin, write_json, ToolError};
+use primitive_types::{H160, H256, U256};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use vm::block::{withdrawals_root, SealedBlock, Withdrawal};
+use vm::bloom::Bloom;
+use vm::crypto::keccak256;
+use vm::header::Header;
+use vm::rlp;
+use vm::transaction::SignedTransaction;
+use vm::trie::{ordered_trie_root, EMPTY_ROOT_HASH};
+
+#[derive(clap::Args, Debug)]
+pub struct B11rArgs {
+    /// header file, or `stdin` to read a `{header, txs, ommers, withdrawals}`
+    /// object
+    #[clap(long = "input.header", default_value = "header.json", value_parser)]
+    input_header: String,
+    /// file with a JSON string holding an RLP list of transactions, or `stdin`
+    #[clap(long = "input.txs", default_value = "txs.rlp", value_parser)]
+    input_txs: String,
+    /// file with a JSON list of RLP encoded ommer headers, or `stdin`
+    #[clap(long = "input.ommers", value_parser)]
+    input_ommers: Option<String>,
+    /// file with a JSON list of withdrawals, or `stdin`
+    #[clap(long = "input.withdrawals", value_parser)]
+    input_withdrawals: Option<String>,
+    /// directory the output file is written to
+    #[clap(long = "output.basedir", default_value = ".", value_parser)]
+    output_basedir: PathBuf,
+    /// block file, or `stdout` / `stderr`
+    #[clap(long = "output.block", default_value = "block.json", value_parser)]
+    output_block: String,
+}
+
+// Header fields, the roots of the body are computed when missing
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeaderJson {
+    pub parent_hash: H256,
+    pub ommers_hash: Option<H256>,
+    #[serde(default)]
+    pub miner: H160,
+    pub state_root: H256,
+    pub transactions_root: Option<H256>,
+    pub receipts_root: Option<H256>,
+    #[serde(default, with = "vm::serde_hex::bytes")]
+    pub logs_bloom: Vec<u8>,
+    #[serde(default)]
+    pub difficulty: U256,
+    #[serde(with = "vm::serde_hex::u64")]
+    pub number: u64,
+    #[serde(with = "vm::serde_hex::u64")]
+    pub gas_limit: u64,
+    #[serde(default, with = "vm::serde_hex::u64")]
+    pub gas_used: u64,
+    #[serde(with = "vm::serde_hex::u64")]
+    pub timestamp: u64,
+    #[serde(default, with = "vm::serde_hex::bytes")]
+    pub extra_data: Vec<u8>,
+    #[serde(default)]
+    pub mix_hash: H256,
+    #[serde(default, with = "vm::serde_hex::u64")]
+    pub nonce: u64,
+    pub base_fee_per_gas: Option<U256>,
+    pub withdrawals_root: Option<H256>,
+    #[serde(default, with = "vm::serde_hex::u64_opt")]
+    pub blob_gas_used: Option<u64>,
+    #[serde(default, with = "vm::serde_hex::u64_opt")]
+    pub excess_blob_gas: Option<u64>,
+    pub parent_beacon_block_root: Option<H256>,
+    pub requests_hash: Option<H256>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BlockInfo {
+    #[serde(with = "vm::serde_hex::bytes")]
+    pub rlp: Vec<u8>,
+    pub hash: H256,
+}
+
+#[derive(Deserialize)]
+struct StdinInput {
+    header: Option<HeaderJson>,
+    txs: Option<String>,
+    ommers: Option<Vec<String>>,
+    withdrawals: Option<Vec<Withdrawal>>,
+}
+
+pub fn run(args: B11rArgs) -> Result<(), ToolError> {
+    let names = [
+        Some(&args.input_header),
+        Some(&args.input_txs),
+        args.input_ommers.as_ref(),
+        args.input_withdrawals.as_ref(),
+    ];
+    let mut stdin = None;
+    if names.iter().flatten().any(|name| *name == "stdin") {
+        stdin = Some(parse::<StdinInput>(&read_stdin()?)?);
+    }
+    let from_stdin = |name: &str| ToolError::Config(format!("no {} on stdin", name));
+
+    let header = match args.input_header.as_str() {
+        "stdin" => stdin
+            .as_mut()
+            .and_then(|input| input.header.take())
+            .ok_or_else(|| from_stdin("header"))?,
+        path => parse(&read_file(path)?)?,
+    };
+    let txs = match args.input_txs.as_str() {
+        "stdin" => stdin
+            .as_mut()
+            .and_then(|input| input.txs.take())
+            .unwrap_or_else(|| "0xc0".into()),
+        path => parse(&read_file(path)?)?,
+    };
+    let ommers = match args.input_ommers.as_deref() {
+        Some("stdin") => stdin
+            .as_mut()
+            .and_then(|input| input.ommers.take())
+            .unwrap_or_default(),
+        Some(path) => parse(&read_file(path)?)?,
+        None => Vec::new(),
+    };
+    let withdrawals = match args.input_withdrawals.as_deref() {
+        Some("stdin") => stdin.as_mut().and_then(|input| input.withdrawals.take()),
+        Some(path) => Some(parse(&read_file(path)?)?),
+        None => None,
+    };
+
+    let rlp_error = |e| ToolError::Rlp(format!("{:?}", e));
+    let transactions = rlp::decode(&decode_hex(&txs)?).map_err(rlp_error)?;
+    let ommers = ommers
+        .iter()
+        .map(|ommer| rlp::decode(&decode_hex(ommer)?).map_err(rlp_error))
+        .collect::<Result<_, _>>()?;
+    let block = build(header, transactions, ommers, withdrawals)?;
+    let info = BlockInfo {
+        rlp: rlp::encode(&block),
+        hash: block.hash(),
+    };
+    write_json(&args.output_basedir, &args.output_block, &info)
+}
+
+// Assembles the block, filling in the roots the header leaves out
+pub fn build(
+    header: HeaderJson,
+    transactions: Vec<SignedTransaction>,
+    ommers: Vec<Header>,
+    withdrawals: Option<Vec<Withdrawal>>,
+) -> Result<SealedBlock, ToolError> {
+    let logs_bloom = match header.logs_bloom.len() {
+        0 => Bloom::default(),
+        256 => Bloom(header.logs_bloom.try_into().expect("256 bytes")),
+        len => {
+            return Err(ToolError::Json(format!(
+                "logs bloom of {} bytes, expected 256",
+                len
+            )))
+        }
+    };
+    let withdrawals = match (withdrawals, header.withdrawals_root) {
+        (None, Some(_)) => Some(Vec::new()),
+        (withdrawals, _) => withdrawals,
+    };
+
+    let header = Header {
+        parent_hash: header.parent_hash,
+        ommers_hash: header
+            .ommers_hash
+            .unwrap_or_else(|| keccak256(&rlp::encode(&ommers))),
+        beneficiary: header.miner,
+        state_root: header.state_root,
+        transactions_root: header.transactions_root.unwrap_or_else(|| {
+            ordered_trie_root(transactions.iter().map(SignedTransaction::encode_enveloped))
+        }),
+        receipts_root: header.receipts_root.unwrap_or(EMPTY_ROOT_HASH),
+        logs_bloom,
+        difficulty: header.difficulty,
+        number: header.number,
+        gas_limit: header.gas_limit,
+        gas_used: header.gas_used,
+        timestamp: header.timestamp,
+        extra_data: header.extra_data,
+        mix_hash: header.mix_hash,
+        nonce: header.nonce,
+        base_fee_per_gas: header.base_fee_per_gas,
+        withdrawals_root: header
+            .withdrawals_root
+            .or_else(|| withdrawals.as_deref().map(withdrawals_root)),
+        blob_gas_used: header.blob_gas_used,
+        excess_blob_gas: header.excess_blob_gas,
+        parent_beacon_block_root: header.parent_beacon_block_root,
+        requests_hash: header.requests_hash,
+    };
+    Ok(SealedBlock {
+        header,
+        transactions,
+        ommers,
+        withdrawals,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use vm::block::validate_body;
+    use vm::transaction::{LegacyTx, LocalSigner, Transaction, TxKind};
+
+    #[test]
+    fn computes_missing_roots() {
+        let header: HeaderJson = serde_json::from_str(
+            r#"{
+                "parentHash": "0xd4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3",
+                "miner": "0x2adc25665018aa1fe0e6bc666dac8fc2697ff9ba",
+                "stateRoot": "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421",
+                "number": "0x1",
+                "gasLimit": "0x1c9c380",
+                "timestamp": "0xc",
+                "baseFeePerGas": "0x7"
+            }"#,
+        )
+        .unwrap();
+        let signer = LocalSigner::from_bytes(&[0x46; 32]).unwrap();
+        let tx = Transaction::Legacy(LegacyTx {
+            chain_id: Some(1),
+            gas_price: U256::from(10),
+            gas_limit: 21_000,
+            to: TxKind::Call(H160::from_low_u64_be(1)),
+            ..Default::default()
+        })
+        .sign(&signer);
+        let withdrawals = vec![Withdrawal {
+            index: 0,
+            validator_index: 1,
+            address: H160::from_low_u64_be(2),
+            amount: 3,
+        }];
+
+        let block = build(header, vec![tx], Vec::new(), Some(withdrawals)).unwrap();
+        assert_eq!(validate_body(&block), Ok(()));
+        assert_eq!(block.header.receipts_root, EMPTY_ROOT_HASH);
+
+        let info = BlockInfo {
+            rlp: rlp::encode(&block),
+            hash: block.hash(),
+        };
+        let decoded: SealedBlock = rlp::decode(&info.rlp).unwrap();
+        assert_eq!(decoded.hash(), info.hash);
+        assert_eq!(decoded, block);
+    }
+}
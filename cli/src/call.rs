@@ -0,0 +1,360 @@
+// Calls a contract function by its signature, like `cast call` on a local
+// VM: the arguments are ABI encoded and the returned values decoded
+
+use crate::alloc::Genesis;
+use crate::run::{
+    decode_hex_arg, execute, parse_u256, Outcome, OutcomeJson, RunArgs, GAS, RECEIVER, SENDER,
+};
+use crate::tool::{parse, read_file, write_json, ToolError};
+use primitive_types::{H160, U256};
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::path::Path;
+use vm::abi::{Abi, AbiError, Function, ParamType, Token};
+use vm::ExecutionResult;
+
+#[derive(clap::Args, Debug)]
+pub struct CallArgs {
+    /// signature such as `balanceOf(address)(uint256)`, or the name of a
+    /// function of the ABI
+    #[clap(long, value_parser)]
+    pub sig: String,
+    /// arguments of the function, which may be negative numbers
+    #[clap(value_parser)]
+    pub args: Vec<String>,
+    /// file with hex encoded runtime code, or a solc, foundry or hardhat
+    /// JSON artifact with the runtime code and the ABI
+    #[clap(long, value_parser)]
+    pub code: Option<String>,
+    /// JSON ABI, or an artifact, to decode the outputs and custom errors
+    #[clap(long, value_parser)]
+    pub abi: Option<String>,
+    /// gas available to the code
+    #[clap(long, default_value_t = GAS, value_parser)]
+    pub gas: u64,
+    /// account the code is called from
+    #[clap(long, default_value = SENDER, value_parser)]
+    pub sender: H160,
+    /// account the code runs at, which may have code in the pre-state
+    #[clap(long, default_value = RECEIVER, value_parser)]
+    pub receiver: H160,
+    /// wei sent along, decimal or hex with a 0x prefix
+    #[clap(long, default_value = "0", value_parser = parse_u256)]
+    pub value: U256,
+    /// genesis.json with the accounts and block of the call
+    #[clap(long, value_parser)]
+    pub prestate: Option<String>,
+    /// prints the result as JSON
+    #[clap(long, value_parser)]
+    pub json: bool,
+}
+
+// Runtime code and ABI read from a hex file, a JSON ABI or an artifact
+#[derive(Debug, Default)]
+pub struct Artifact {
+    pub code: Option<Vec<u8>>,
+    pub abi: Option<Abi>,
+}
+
+impl Artifact {
+    pub fn read(path: &str) -> Result<Self, ToolError> {
+        let content = read_file(path)?;
+        let invalid = |e: AbiError| ToolError::Json(format!("{}: {}", path, e));
+        let json: Value = match content.trim_start().chars().next() {
+            Some('{' | '[') => parse(&content)?,
+            _ => {
+                return Ok(Artifact {
+                    code: Some(decode_hex_arg(&content)?),
+                    abi: None,
+                })
+            }
+        };
+        if json.is_array() {
+            return Ok(Artifact {
+                code: None,
+                abi: Some(Abi::from_json(&content).map_err(invalid)?),
+            });
+        }
+        let abi = match &json["abi"] {
+            Value::Null => None,
+            // older solc versions emit the ABI as a string in combined JSON
+            Value::String(abi) => Some(Abi::from_json(abi).map_err(invalid)?),
+            abi => Some(Abi::from_json(&abi.to_string()).map_err(invalid)?),
+        };
+        // foundry and hardhat, solc's standard JSON and solc's combined JSON
+        let code = [
+            &json["deployedBytecode"]["object"],
+            &json["deployedBytecode"],
+            &json["evm"]["deployedBytecode"]["object"],
+            &json["bin-runtime"],
+        ]
+        .into_iter()
+        .find_map(Value::as_str)
+        .map(decode_hex_arg)
+        .transpose()?
+        // interfaces and abstract contracts have no code
+        .filter(|code| !code.is_empty());
+        Ok(Artifact { code, abi })
+    }
+}
+
+// A signature without outputs picks them up from the ABI
+pub fn resolve(sig: &str, abi: Option<&Abi>) -> Result<Function, ToolError> {
+    let config = |e: AbiError| ToolError::Config(e.to_string());
+    if !sig.contains('(') {
+        return abi
+            .and_then(|abi| abi.function(sig))
+            .cloned()
+            .ok_or_else(|| {
+                ToolError::Config(format!(
+                    "no function {:?} in the ABI, or it is overloaded",
+                    sig
+                ))
+            });
+    }
+    let function = Function::parse(sig).map_err(config)?;
+    if function.outputs.is_empty() {
+        if let Some(known) = abi.and_then(|abi| abi.function(&function.signature())) {
+            return Ok(known.clone());
+        }
+    }
+    Ok(function)
+}
+
+pub fn encode_args(function: &Function, args: &[String]) -> Result<Vec<u8>, ToolError> {
+    let config = |e: AbiError| ToolError::Config(e.to_string());
+    if args.len() != function.inputs.len() {
+        return Err(ToolError::Config(format!(
+            "{} takes {} arguments, got {}",
+            function.signature(),
+            function.inputs.len(),
+            args.len()
+        )));
+    }
+    let tokens = function
+        .inputs
+        .iter()
+        .zip(args)
+        .map(|(ty, arg)| Token::parse(ty, arg))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(config)?;
+    function.encode_input(&tokens).map_err(config)
+}
+
+// Result of a call, with the decoded return values of a successful one
+#[derive(Debug)]
+pub struct CallOutcome {
+    pub outcome: Outcome,
+    pub outputs: Vec<ParamType>,
+    pub returns: Option<Result<Vec<Token>, AbiError>>,
+}
+
+impl fmt::Display for CallOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.outcome)?;
+        match &self.returns {
+            Some(Ok(tokens)) => {
+                let values: Vec<String> = tokens.iter().map(Token::to_string).collect();
+                write!(f, "\nreturns:       {}", values.join(", "))
+            }
+            Some(Err(e)) => write!(
+                f,
+                "\nreturns:       cannot decode as {}: {}",
+                ParamType::Tuple(self.outputs.clone()),
+                e
+            ),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CallJson {
+    #[serde(flatten)]
+    outcome: OutcomeJson,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    returns: Option<Vec<Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    returns_error: Option<String>,
+}
+
+impl From<&CallOutcome> for CallJson {
+    fn from(call: &CallOutcome) -> Self {
+        let (returns, returns_error) = match &call.returns {
+            Some(Ok(tokens)) => (Some(tokens.iter().map(token_json).collect()), None),
+            Some(Err(e)) => (None, Some(e.to_string())),
+            None => (None, None),
+        };
+        CallJson {
+            outcome: OutcomeJson::from(&call.outcome),
+            returns,
+            returns_error,
+        }
+    }
+}
+
+// Integers are strings, which JSON numbers cannot hold losslessly
+fn token_json(token: &Token) -> Value {
+    match token {
+        Token::Bool(value) => Value::Bool(*value),
+        Token::String(s) => Value::String(s.clone()),
+        Token::Array(tokens) | Token::FixedArray(tokens) | Token::Tuple(tokens) => {
+            Value::Array(tokens.iter().map(token_json).collect())
+        }
+        token => Value::String(token.to_string()),
+    }
+}
+
+// Prints the result and returns the exit code of its status, as `run` does
+pub fn run(args: CallArgs) -> Result<u8, ToolError> {
+    let genesis: Genesis = match &args.prestate {
+        Some(path) => parse(&read_file(path)?)?,
+        None => Genesis::default(),
+    };
+    let artifact = match &args.code {
+        Some(path) => Artifact::read(path)?,
+        None => Artifact::default(),
+    };
+    let abi = match &args.abi {
+        Some(path) => Artifact::read(path)?.abi,
+        None => artifact.abi,
+    };
+    let call = call(&args, &genesis, artifact.code, abi.as_ref())?;
+    if args.json {
+        write_json(Path::new("."), "stdout", &CallJson::from(&call))?;
+    } else {
+        println!("{}", call);
+    }
+    Ok(call.outcome.exit_code())
+}
+
+// Calls the function of `args.sig` in `code`, or in the receiver's code in
+// the pre-state
+pub fn call(
+    args: &CallArgs,
+    genesis: &Genesis,
+    code: Option<Vec<u8>>,
+    abi: Option<&Abi>,
+) -> Result<CallOutcome, ToolError> {
+    let function = resolve(&args.sig, abi)?;
+    let input = encode_args(&function, &args.args)?;
+    let run_args = RunArgs {
+        input: hex::encode(input),
+        gas: args.gas,
+        sender: args.sender,
+        receiver: args.receiver,
+        value: args.value,
+        ..Default::default()
+    };
+    let errors = abi.map(|abi| abi.errors.as_slice()).unwrap_or_default();
+    let outcome = execute(&run_args, genesis, code, errors)?;
+    let returns = match &outcome.result {
+        ExecutionResult::Success { output, .. } if !function.outputs.is_empty() => {
+            Some(function.decode_output(output))
+        }
+        _ => None,
+    };
+    Ok(CallOutcome {
+        outcome,
+        outputs: function.outputs,
+        returns,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use clap::Parser;
+
+    // returns the first argument
+    const ECHO: &str = "60043560005260206000f3";
+
+    #[derive(Parser)]
+    #[clap(allow_negative_numbers = true)]
+    struct Cli {
+        #[clap(flatten)]
+        call: CallArgs,
+    }
+
+    fn parse_args(argv: &[&str]) -> CallArgs {
+        Cli::parse_from(std::iter::once("call").chain(argv.iter().copied())).call
+    }
+
+    #[test]
+    fn calls_a_function_by_signature() {
+        let args = parse_args(&["--sig", "echo(int8)(int16)", "-5"]);
+        let outcome = call(&args, &Genesis::default(), hex::decode(ECHO).ok(), None).unwrap();
+        assert_eq!(outcome.outcome.exit_code(), 0);
+        assert_eq!(outcome.returns, Some(Ok(vec![Token::Int(!U256::from(4))])));
+        assert!(outcome.to_string().ends_with("returns:       -5"));
+
+        // 2^16 - 1 is not an int16
+        let args = parse_args(&["--sig", "f(uint16)(int16)", "65535"]);
+        let outcome = call(&args, &Genesis::default(), hex::decode(ECHO).ok(), None).unwrap();
+        assert_eq!(outcome.returns, Some(Err(AbiError::InvalidValue)));
+        let json = serde_json::to_value(CallJson::from(&outcome)).unwrap();
+        assert_eq!(json["status"], "success");
+        assert_eq!(json["returnsError"], "value does not fit its type");
+
+        let args = parse_args(&["--sig", "f(uint8,bool)", "1"]);
+        let result = call(&args, &Genesis::default(), hex::decode(ECHO).ok(), None);
+        assert!(matches!(result, Err(ToolError::Config(_))));
+        let args = parse_args(&["--sig", "f(uint8)", "256"]);
+        let result = call(&args, &Genesis::default(), hex::decode(ECHO).ok(), None);
+        assert!(matches!(result, Err(ToolError::Config(_))));
+    }
+
+    #[test]
+    fn reads_code_and_abi_from_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Token.json");
+        let path = path.to_str().unwrap();
+        let abi = r#"[{"type": "function", "name": "balanceOf",
+            "inputs": [{"name": "owner", "type": "address"}],
+            "outputs": [{"name": "", "type": "uint256"}]}]"#;
+        // as written by foundry
+        let foundry = format!(
+            r#"{{"abi": {}, "bytecode": {{"object": "0x00"}},
+                "deployedBytecode": {{"object": "0x{}"}}}}"#,
+            abi, ECHO
+        );
+        std::fs::write(path, &foundry).unwrap();
+        let artifact = Artifact::read(path).unwrap();
+        assert_eq!(artifact.code, hex::decode(ECHO).ok());
+
+        let owner = "0x00000000000000000000000000000000000000ab";
+        let args = parse_args(&["--sig", "balanceOf", owner]);
+        let outcome = call(
+            &args,
+            &Genesis::default(),
+            artifact.code,
+            artifact.abi.as_ref(),
+        )
+        .unwrap();
+        assert_eq!(
+            outcome.returns,
+            Some(Ok(vec![Token::Uint(U256::from(0xab))]))
+        );
+        let json = serde_json::to_value(CallJson::from(&outcome)).unwrap();
+        assert_eq!(json["returns"], serde_json::json!(["171"]));
+
+        // solc's combined JSON, with the ABI as a string
+        let solc = format!(r#"{{"abi": {:?}, "bin-runtime": "{}"}}"#, abi, ECHO);
+        std::fs::write(path, solc).unwrap();
+        let artifact = Artifact::read(path).unwrap();
+        assert_eq!(artifact.code, hex::decode(ECHO).ok());
+        // outputs come from the ABI
+        let function = resolve("balanceOf(address)", artifact.abi.as_ref()).unwrap();
+        assert_eq!(function.outputs, [ParamType::Uint(256)]);
+        assert!(resolve("totalSupply", artifact.abi.as_ref()).is_err());
+
+        std::fs::write(path, abi).unwrap();
+        let artifact = Artifact::read(path).unwrap();
+        assert!(artifact.code.is_none() && artifact.abi.is_some());
+        std::fs::write(path, format!("0x{}\n", ECHO)).unwrap();
+        let artifact = Artifact::read(path).unwrap();
+        assert!(artifact.code.is_some() && artifact.abi.is_none());
+    }
+}
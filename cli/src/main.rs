@@ -1,24 +1,117 @@
-use clap::Parser;
-use hex::FromHex;
-use tracing::Level;
-use tracing_subscriber::FmtSubscriber;
-use vm::Vm;
+mod alloc;
+mod b11r;
+mod call;
+mod run;
+mod statetest;
+mod stats;
+mod t8n;
+mod t9n;
+mod tool;
+mod tracer;
+
+use clap::{Parser, Subcommand};
+use std::process::ExitCode;
+
+#[global_allocator]
+static ALLOCATOR: stats::CountingAllocator = stats::CountingAllocator;
 
 #[derive(Parser, Debug)]
-#[clap(name = "evm-rs", author, version)]
+#[clap(name = "evm-rs", author, version, subcommand_negates_reqs = true)]
 struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /// shorthand for `run --debug <bytecode>`
+    #[clap(short, long, required = true, value_parser)]
+    bytecode: Option<String>,
+
+    /// don't log each step of the bytecode run
     #[clap(short, long, value_parser)]
-    bytecode: String,
+    quiet: bool,
 }
 
-fn main() {
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(Level::TRACE)
-        .finish();
-    tracing::subscriber::set_global_default(subscriber).expect("Failed to set default subscriber");
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Runs bytecode like geth's `evm run`
+    Run(run::RunArgs),
+    /// Calls a contract function by its signature with ABI encoded arguments
+    #[clap(allow_negative_numbers = true)]
+    Call(call::CallArgs),
+    /// Executes transactions on top of a pre-state like geth's `evm t8n`
+    T8n(t8n::T8nArgs),
+    /// Validates transactions like geth's `evm t9n`
+    T9n(t9n::T9nArgs),
+    /// Assembles a block like geth's `evm b11r`
+    B11r(b11r::B11rArgs),
+    /// Runs a state test file and reports each variant, like geth's
+    /// `evm statetest`
+    Statetest(statetest::StatetestArgs),
+}
 
+fn main() -> ExitCode {
     let args = Args::parse();
-    let bytecode = <Vec<u8>>::from_hex(args.bytecode).unwrap();
-    let mut vm = Vm::new(&bytecode);
-    vm.run().ok();
+    let result = match args.command {
+        Some(Command::Run(args)) => run::run(args).map(ExitCode::from),
+        Some(Command::Call(args)) => call::run(args).map(ExitCode::from),
+        Some(Command::T8n(args)) => t8n::run(args).map(|()| ExitCode::SUCCESS),
+        Some(Command::T9n(args)) => t9n::run(args).map(|()| ExitCode::SUCCESS),
+        Some(Command::B11r(args)) => b11r::run(args).map(|()| ExitCode::SUCCESS),
+        // some tests failed
+        Some(Command::Statetest(args)) => statetest::run(args).map(|passed| {
+            if passed {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }),
+        None => {
+            let bytecode = args.bytecode.expect("required without a subcommand");
+            let args = run::RunArgs {
+                code: Some(bytecode),
+                debug: !args.quiet,
+                ..Default::default()
+            };
+            run::run(args).map(ExitCode::from)
+        }
+    };
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn verify_args() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn every_arg_has_a_description() {
+        let mut command = Args::command();
+        let commands = std::iter::once(&command).chain(command.get_subcommands());
+        for command in commands {
+            for arg in command.get_arguments() {
+                assert!(
+                    arg.get_help().is_some(),
+                    "{} --{} has no description",
+                    command.get_name(),
+                    arg.get_id()
+                );
+            }
+        }
+
+        let mut help = Vec::new();
+        let run = command.find_subcommand_mut("run").unwrap();
+        run.write_help(&mut help).unwrap();
+        let help = String::from_utf8(help).unwrap();
+        assert!(help.contains("wei sent along, decimal or hex with a 0x prefix"));
+    }
 }
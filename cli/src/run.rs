@@ -0,0 +1,557 @@
+// Runs bytecode outside of a transaction like geth's `evm run`
+
+use crate::alloc::Genesis;
+use crate::stats;
+use crate::tool::{parse, read_file, read_stdin, write_json, ToolError};
+use primitive_types::{H160, H256, U256};
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Instant;
+use tracing::Level;
+use tracing_subscriber::FmtSubscriber;
+use vm::abi::{Abi, Function};
+use vm::crypto::create_address;
+use vm::env::{BlockEnv, Env};
+use vm::error::VmError;
+use vm::revert::RevertReason;
+use vm::state::{Account, Log, State};
+use vm::{Context, ExecutionResult, Vm};
+
+// geth's defaults, the sender and receiver are "sender" and "receiver" in
+// ASCII
+pub const GAS: u64 = 10_000_000_000;
+pub const SENDER: &str = "0x000000000000000000000000000073656e646572";
+pub const RECEIVER: &str = "0x0000000000000000000000007265636569766572";
+
+#[derive(clap::Args, Debug)]
+pub struct RunArgs {
+    /// hex encoded code, read from `--codefile` if missing
+    #[clap(value_parser)]
+    pub code: Option<String>,
+    /// file with hex encoded code, `-` for stdin
+    #[clap(long, value_parser)]
+    pub codefile: Option<String>,
+    /// hex encoded calldata
+    #[clap(long, default_value = "", value_parser)]
+    pub input: String,
+    /// gas available to the code
+    #[clap(long, default_value_t = GAS, value_parser)]
+    pub gas: u64,
+    /// account the code is called from
+    #[clap(long, default_value = SENDER, value_parser)]
+    pub sender: H160,
+    /// account the code runs at, which may have code in the pre-state
+    #[clap(long, default_value = RECEIVER, value_parser)]
+    pub receiver: H160,
+    /// wei sent along, decimal or hex with a 0x prefix
+    #[clap(long, default_value = "0", value_parser = parse_u256)]
+    pub value: U256,
+    /// genesis.json with the accounts and block of the run
+    #[clap(long, value_parser)]
+    pub prestate: Option<String>,
+    /// runs the code, followed by `--input`, as init code, whose output is
+    /// the deployed code
+    #[clap(long, value_parser)]
+    pub create: bool,
+    /// prints gas used, execution time and allocations to stderr
+    #[clap(long, value_parser)]
+    pub statdump: bool,
+    /// logs each step of the run
+    #[clap(long, value_parser)]
+    pub debug: bool,
+    /// prints the result as JSON
+    #[clap(long, value_parser)]
+    pub json: bool,
+    /// JSON ABI whose custom errors are decoded in revert reasons
+    #[clap(long, value_parser)]
+    pub abi: Option<String>,
+}
+
+impl Default for RunArgs {
+    fn default() -> Self {
+        RunArgs {
+            code: None,
+            codefile: None,
+            input: String::new(),
+            gas: GAS,
+            sender: SENDER.parse().expect("valid address"),
+            receiver: RECEIVER.parse().expect("valid address"),
+            value: U256::zero(),
+            prestate: None,
+            create: false,
+            statdump: false,
+            debug: false,
+            json: false,
+            abi: None,
+        }
+    }
+}
+
+pub fn parse_u256(s: &str) -> Result<U256, String> {
+    match s.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16).map_err(|e| format!("{:?}", e)),
+        None => U256::from_dec_str(s).map_err(|e| format!("{:?}", e)),
+    }
+}
+
+pub fn decode_hex_arg(s: &str) -> Result<Vec<u8>, ToolError> {
+    hex::decode(s.trim().trim_start_matches("0x"))
+        .map_err(|e| ToolError::Config(format!("invalid hex: {}", e)))
+}
+
+// Result of a run
+#[derive(Debug)]
+pub struct Outcome {
+    pub result: ExecutionResult,
+    pub revert_reason: Option<RevertReason>,
+}
+
+impl Outcome {
+    // Decodes the revert reason with the custom `errors` of the contract
+    pub fn new(result: ExecutionResult, errors: &[Function]) -> Self {
+        Outcome {
+            revert_reason: result.revert_reason(errors),
+            result,
+        }
+    }
+
+    // 0 on success, 1 on revert and 4 on halt, apart from the codes of a
+    // `ToolError`
+    pub fn exit_code(&self) -> u8 {
+        match self.result {
+            ExecutionResult::Success { .. } => 0,
+            ExecutionResult::Revert { .. } => 1,
+            ExecutionResult::Halt { .. } => 4,
+        }
+    }
+
+    pub fn logs(&self) -> &[Log] {
+        match &self.result {
+            ExecutionResult::Success { logs, .. } => logs,
+            _ => &[],
+        }
+    }
+}
+
+// JSON ABI, whose custom errors are decoded in revert reasons
+pub fn read_abi(path: &str) -> Result<Abi, ToolError> {
+    Abi::from_json(&read_file(path)?).map_err(|e| ToolError::Json(format!("{}: {}", path, e)))
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutcomeJson {
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(with = "vm::serde_hex::u64")]
+    gas_used: u64,
+    #[serde(with = "vm::serde_hex::bytes")]
+    output: Vec<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    revert_reason: Option<String>,
+    logs: Vec<LogJson>,
+}
+
+#[derive(Debug, Serialize)]
+struct LogJson {
+    address: H160,
+    topics: Vec<H256>,
+    #[serde(with = "vm::serde_hex::bytes")]
+    data: Vec<u8>,
+}
+
+impl From<&Outcome> for OutcomeJson {
+    fn from(outcome: &Outcome) -> Self {
+        let (status, error) = match &outcome.result {
+            ExecutionResult::Success { .. } => ("success", None),
+            ExecutionResult::Revert { .. } => ("revert", None),
+            ExecutionResult::Halt {
+                reason, context, ..
+            } => ("halt", Some(format!("{} at {}", reason, context))),
+        };
+        OutcomeJson {
+            status,
+            error,
+            gas_used: outcome.result.gas().spent(),
+            output: outcome.result.output().to_vec(),
+            revert_reason: outcome.revert_reason.as_ref().map(|r| r.to_string()),
+            logs: outcome
+                .logs()
+                .iter()
+                .map(|log| LogJson {
+                    address: log.address,
+                    topics: log.topics.clone(),
+                    data: log.data.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.result {
+            ExecutionResult::Success { reason, .. } => {
+                writeln!(f, "status:        success ({:?})", reason)?
+            }
+            ExecutionResult::Revert { .. } => writeln!(f, "status:        revert")?,
+            ExecutionResult::Halt {
+                reason, context, ..
+            } => writeln!(f, "status:        halt ({} at {})", reason, context)?,
+        }
+        writeln!(f, "gas used:      {}", self.result.gas().spent())?;
+        writeln!(f, "return data:   0x{}", hex::encode(self.result.output()))?;
+        if let Some(reason) = &self.revert_reason {
+            writeln!(f, "revert reason: {}", reason)?;
+        }
+        write!(f, "logs:          {}", self.logs().len())?;
+        for log in self.logs() {
+            write!(f, "\n  {:?}", log.address)?;
+            for topic in &log.topics {
+                write!(f, "\n    topic {:?}", topic)?;
+            }
+            write!(f, "\n    data  0x{}", hex::encode(&log.data))?;
+        }
+        Ok(())
+    }
+}
+
+// Prints the result and returns the exit code of its status
+pub fn run(args: RunArgs) -> Result<u8, ToolError> {
+    if args.debug {
+        let subscriber = FmtSubscriber::builder()
+            .with_max_level(Level::TRACE)
+            .finish();
+        tracing::subscriber::set_global_default(subscriber)
+            .expect("Failed to set default subscriber");
+    }
+
+    let genesis: Genesis = match &args.prestate {
+        Some(path) => parse(&read_file(path)?)?,
+        None => Genesis::default(),
+    };
+    let code = match (&args.code, args.codefile.as_deref()) {
+        (Some(code), _) => Some(decode_hex_arg(code)?),
+        (None, Some("-")) => Some(decode_hex_arg(&read_stdin()?)?),
+        (None, Some(path)) => Some(decode_hex_arg(&read_file(path)?)?),
+        (None, None) => None,
+    };
+    let abi = match &args.abi {
+        Some(path) => read_abi(path)?,
+        None => Abi::default(),
+    };
+
+    let (allocations, bytes) = stats::allocations();
+    let start = Instant::now();
+    let outcome = execute(&args, &genesis, code, &abi.errors)?;
+    let elapsed = start.elapsed();
+    let (allocations, bytes) = {
+        let (total, total_bytes) = stats::allocations();
+        (total - allocations, total_bytes - bytes)
+    };
+
+    if args.json {
+        write_json(Path::new("."), "stdout", &OutcomeJson::from(&outcome))?;
+    } else {
+        println!("{}", outcome);
+    }
+    if args.statdump {
+        eprintln!("EVM gas used:    {}", outcome.result.gas().spent());
+        eprintln!("execution time:  {:?}", elapsed);
+        eprintln!("allocations:     {}", allocations);
+        eprintln!("allocated bytes: {}", bytes);
+    }
+    Ok(outcome.exit_code())
+}
+
+// Runs `code`, or the receiver's code in the pre-state, as a call from the
+// sender, or as init code with `--create`. Custom errors in a revert are
+// matched against `errors`.
+pub fn execute(
+    args: &RunArgs,
+    genesis: &Genesis,
+    code: Option<Vec<u8>>,
+    errors: &[Function],
+) -> Result<Outcome, ToolError> {
+    let (result, _) = run_frame(args, genesis, code)?;
+    Ok(Outcome::new(result, errors))
+}
+
+// Like `execute`, also returning the state after the run
+fn run_frame(
+    args: &RunArgs,
+    genesis: &Genesis,
+    code: Option<Vec<u8>>,
+) -> Result<(ExecutionResult, State<'static>), ToolError> {
+    let mut state = State::new();
+    for (address, account) in &genesis.alloc {
+        state.insert_account(*address, Account::from(account));
+    }
+    let code = match code {
+        Some(code) => code,
+        None => match genesis.alloc.get(&args.receiver) {
+            Some(account) if !args.create => account.code.clone(),
+            _ => return Err(ToolError::Config("no code to run".into())),
+        },
+    };
+    // as in geth, the input of a creation follows the init code, like the
+    // arguments of a constructor
+    let (code, input) = if args.create {
+        ([code, decode_hex_arg(&args.input)?].concat(), Vec::new())
+    } else {
+        (code, decode_hex_arg(&args.input)?)
+    };
+
+    let address = if args.create {
+        let nonce = state.nonce(&args.sender).map_err(ToolError::Evm)?;
+        state.increment_nonce(args.sender).map_err(ToolError::Evm)?;
+        create_address(args.sender, nonce)
+    } else {
+        state
+            .set_code(args.receiver, code.clone())
+            .map_err(ToolError::Evm)?;
+        args.receiver
+    };
+    state.warm_account(args.sender);
+    state.warm_account(address);
+    // the value moves with the frame, and back if it reverts or halts
+    let checkpoint = state.checkpoint();
+    state
+        .transfer(args.sender, address, args.value)
+        .map_err(|e| match e {
+            // the arguments are wrong, nothing was executed
+            VmError::InsufficientBalance => {
+                ToolError::Config(format!("sender balance too low for --value {}", args.value))
+            }
+            e => ToolError::Evm(e),
+        })?;
+
+    let defaults = BlockEnv::default();
+    let env = Env {
+        block: BlockEnv {
+            number: genesis.number.unwrap_or(defaults.number),
+            coinbase: genesis.coinbase,
+            timestamp: genesis.timestamp.unwrap_or(defaults.timestamp),
+            gas_limit: genesis.gas_limit.unwrap_or(defaults.gas_limit),
+            basefee: genesis.base_fee_per_gas.unwrap_or_default(),
+            difficulty: genesis.difficulty.unwrap_or_default(),
+            ..defaults
+        },
+        ..Default::default()
+    };
+    let context = Context {
+        address,
+        caller: args.sender,
+        value: args.value,
+        input,
+        depth: 0,
+        is_static: false,
+        gas_limit: args.gas,
+    };
+
+    let mut vm = Vm::with_state(&code, state, context).with_env(Rc::new(env));
+    let result = vm.run();
+    let mut state = vm.into_state();
+    if result.is_success() {
+        state.commit(checkpoint);
+    } else {
+        state.revert(checkpoint);
+    }
+    Ok((result, state))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::alloc::AllocAccount;
+
+    // returns CALLVALUE + the first word of calldata
+    const CODE: &str = "600035340160005260206000f3";
+
+    #[test]
+    fn runs_code_with_input_and_value() {
+        let args = RunArgs {
+            input: format!("{:064x}", 5),
+            gas: 100_000,
+            value: U256::from(2),
+            ..Default::default()
+        };
+        let genesis = Genesis {
+            alloc: [(
+                args.sender,
+                AllocAccount {
+                    balance: U256::from(10),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+        let outcome = execute(&args, &genesis, Some(hex::decode(CODE).unwrap()), &[]).unwrap();
+        assert!(outcome.result.is_success());
+        assert_eq!(outcome.exit_code(), 0);
+        assert_eq!(
+            U256::from_big_endian(outcome.result.output()),
+            U256::from(7)
+        );
+        assert!(outcome.result.gas().spent() > 0);
+
+        // the sender cannot pay the value without a pre-state
+        let result = execute(
+            &args,
+            &Genesis::default(),
+            Some(hex::decode(CODE).unwrap()),
+            &[],
+        );
+        assert!(matches!(result, Err(ToolError::Config(_))));
+    }
+
+    #[test]
+    fn value_is_returned_when_the_run_fails() {
+        let args = RunArgs {
+            value: U256::from(3),
+            ..Default::default()
+        };
+        let genesis = Genesis {
+            alloc: [(
+                args.sender,
+                AllocAccount {
+                    balance: U256::from(10),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+        let balances = |code: Vec<u8>| {
+            let (_, mut state) = run_frame(&args, &genesis, Some(code)).unwrap();
+            (
+                state.balance(&args.sender).unwrap().as_u64(),
+                state.balance(&args.receiver).unwrap().as_u64(),
+            )
+        };
+        // STOP, REVERT(0, 0) and an undefined opcode
+        assert_eq!(balances(vec![0x00]), (7, 3));
+        assert_eq!(balances(vec![0x60, 0x00, 0x80, 0xfd]), (10, 0));
+        assert_eq!(balances(vec![0x0c]), (10, 0));
+    }
+
+    #[test]
+    fn create_appends_the_input_to_the_init_code() {
+        let args = RunArgs {
+            input: "abcd".into(),
+            create: true,
+            ..Default::default()
+        };
+        // returns CODESIZE, then RETURNs before the appended input
+        let code = vec![0x38, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3];
+        let (result, _) = run_frame(&args, &Genesis::default(), Some(code)).unwrap();
+        assert!(result.is_success());
+        assert_eq!(U256::from_big_endian(result.output()), U256::from(11));
+    }
+
+    #[test]
+    fn runs_receiver_code_from_prestate() {
+        let genesis: Genesis = serde_json::from_str(&format!(
+            r#"{{"alloc": {{"{}": {{"balance": "0x0", "code": "0x{}"}}}}}}"#,
+            RECEIVER, CODE
+        ))
+        .unwrap();
+        let args = RunArgs {
+            input: format!("{:064x}", 1),
+            gas: 100_000,
+            ..Default::default()
+        };
+        let outcome = execute(&args, &genesis, None, &[]).unwrap();
+        assert_eq!(U256::from_big_endian(outcome.result.output()), U256::one());
+
+        let create = RunArgs {
+            create: true,
+            ..args
+        };
+        assert!(matches!(
+            execute(&create, &genesis, None, &[]),
+            Err(ToolError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn reports_revert_reason_and_halt() {
+        // Error("no") as ABI encoded by solidity
+        let mut reason = vec![0x08, 0xc3, 0x79, 0xa0];
+        reason.extend(H256::from_low_u64_be(32).as_bytes());
+        reason.extend(H256::from_low_u64_be(2).as_bytes());
+        reason.extend(b"no");
+        reason.resize(4 + 3 * 32, 0);
+        // MSTORE each word, then REVERT(0, len)
+        let mut revert = Vec::new();
+        let mut memory = reason.clone();
+        memory.resize(4 * 32, 0);
+        for (i, word) in memory.chunks(32).enumerate() {
+            revert.push(0x7f);
+            revert.extend(word);
+            revert.extend([0x60, i as u8 * 32, 0x52]);
+        }
+        revert.extend([0x60, reason.len() as u8, 0x60, 0x00, 0xfd]);
+        let args = RunArgs::default();
+        let outcome = execute(&args, &Genesis::default(), Some(revert), &[]).unwrap();
+        assert_eq!(outcome.exit_code(), 1);
+        assert_eq!(
+            outcome.revert_reason,
+            Some(RevertReason::Error("no".into()))
+        );
+        let json = serde_json::to_value(OutcomeJson::from(&outcome)).unwrap();
+        assert_eq!(json["status"], "revert");
+        assert_eq!(json["revertReason"], "no");
+
+        // LOG0 of nothing, then an undefined opcode
+        let outcome = execute(
+            &args,
+            &Genesis::default(),
+            Some(vec![0x60, 0x00, 0x80, 0xa0, 0x0c]),
+            &[],
+        )
+        .unwrap();
+        assert!(matches!(
+            outcome.result,
+            ExecutionResult::Halt {
+                reason: VmError::InvalidOpcode(0x0c),
+                ..
+            }
+        ));
+        assert_eq!(outcome.exit_code(), 4);
+        assert!(outcome.logs().is_empty());
+        assert_eq!(outcome.result.gas().spent(), args.gas);
+    }
+
+    #[test]
+    fn decodes_custom_errors_of_an_abi() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("abi.json");
+        let abi = r#"[
+            {"type": "function", "name": "transfer", "inputs": [{"name": "to", "type": "address"}]},
+            {"type": "error", "name": "TooLow", "inputs": [{"name": "min", "type": "uint8"}]}
+        ]"#;
+        std::fs::write(&path, abi).unwrap();
+        let abi = read_abi(path.to_str().unwrap()).unwrap();
+
+        let mut output = abi.errors[0].selector().to_vec();
+        output.extend(H256::from_low_u64_be(3).as_bytes());
+        let result = ExecutionResult::Revert {
+            output,
+            gas: vm::gas::Gas::new(0),
+        };
+        let outcome = Outcome::new(result.clone(), &abi.errors);
+        assert_eq!(outcome.revert_reason.unwrap().to_string(), "TooLow(3)");
+        assert_eq!(Outcome::new(result, &[]).revert_reason, None);
+
+        std::fs::write(&path, "{}").unwrap();
+        assert!(matches!(
+            read_abi(path.to_str().unwrap()),
+            Err(ToolError::Json(_))
+        ));
+    }
+}
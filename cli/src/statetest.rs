@@ -0,0 +1,150 @@
+// Runs a GeneralStateTests fixture like geth's `evm statetest`
+
+use crate::tool::{parse, parse_fork, read_file, write_json, ToolError};
+use crate::tracer::JsonTracer;
+use ethtests::models::{Indexes, StateTestFile};
+use ethtests::run_variant;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+#[derive(clap::Args, Debug)]
+pub struct StatetestArgs {
+    /// state test JSON file
+    #[clap(value_parser)]
+    path: String,
+    /// only runs the variants of this fork
+    #[clap(long, value_parser)]
+    fork: Option<String>,
+    /// reruns failing variants with an EIP-3155 trace on stderr
+    #[clap(long, value_parser)]
+    trace: bool,
+}
+
+// Outcome of a variant, as geth reports it
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TestResult {
+    pub name: String,
+    pub pass: bool,
+    pub fork: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Prints the results of every variant and tells whether they all passed
+pub fn run(args: StatetestArgs) -> Result<bool, ToolError> {
+    let fork = args.fork.as_deref().map(parse_fork).transpose()?;
+    let tests: StateTestFile = parse(&read_file(&args.path)?)?;
+    let results = run_tests(&tests, fork, args.trace);
+    write_json(Path::new("."), "stdout", &results)?;
+    Ok(results.iter().all(|result| result.pass))
+}
+
+// Runs the variants of the forks the VM implements, or only those of `only`
+pub fn run_tests(
+    tests: &StateTestFile,
+    only: Option<vm::env::Fork>,
+    trace: bool,
+) -> Vec<TestResult> {
+    let mut results = Vec::new();
+    for (name, test) in tests {
+        for (fork_name, posts) in &test.post {
+            let Some(fork) = ethtests::parse_fork(fork_name) else {
+                continue;
+            };
+            if only.is_some_and(|only| only != fork) {
+                continue;
+            }
+            for post in posts {
+                let Indexes { data, gas, value } = post.indexes;
+                let error = run_variant(test, fork, post, None)
+                    .err()
+                    .map(|e| e.to_string());
+                if error.is_some() && trace {
+                    let tracer = Rc::new(RefCell::new(JsonTracer::new(io::stderr())));
+                    run_variant(test, fork, post, Some(tracer)).ok();
+                }
+                results.push(TestResult {
+                    name: format!("{}/d{}g{}v{}", name, data, gas, value),
+                    pass: error.is_none(),
+                    fork: fork_name.clone(),
+                    error,
+                });
+            }
+        }
+    }
+    results
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use vm::env::Fork;
+
+    // Transfer whose Cancun variant expects a wrong state root
+    const FIXTURE: &str = r#"{
+        "transfer": {
+            "env": {
+                "currentCoinbase": "0x2adc25665018aa1fe0e6bc666dac8fc2697ff9ba",
+                "currentDifficulty": "0x020000",
+                "currentGasLimit": "0x05f5e100",
+                "currentNumber": "0x01",
+                "currentTimestamp": "0x03e8",
+                "currentBaseFee": "0x0a",
+                "currentRandom": "0x0000000000000000000000000000000000000000000000000000000000020000"
+            },
+            "pre": {
+                "0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b": {
+                    "balance": "0x0de0b6b3a7640000",
+                    "code": "0x",
+                    "nonce": "0x00",
+                    "storage": {}
+                }
+            },
+            "transaction": {
+                "data": ["0x"],
+                "gasLimit": ["0x5208"],
+                "gasPrice": "0x0a",
+                "nonce": "0x00",
+                "secretKey": "0x45a915e4d060149eb4365960e6a7a45f334393093061116b197e3240065ff2d8",
+                "to": "0x1000000000000000000000000000000000000000",
+                "value": ["0x01"]
+            },
+            "post": {
+                "Cancun": [
+                    {
+                        "hash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                        "logs": "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347",
+                        "indexes": { "data": 0, "gas": 0, "value": 0 }
+                    }
+                ],
+                "Shanghai": [
+                    {
+                        "hash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                        "logs": "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347",
+                        "indexes": { "data": 0, "gas": 0, "value": 0 }
+                    }
+                ]
+            }
+        }
+    }"#;
+
+    #[test]
+    fn reports_each_variant_of_the_selected_fork() {
+        let tests: StateTestFile = serde_json::from_str(FIXTURE).unwrap();
+        assert_eq!(run_tests(&tests, None, false).len(), 2);
+
+        let results = run_tests(&tests, Some(Fork::Cancun), false);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "transfer/d0g0v0");
+        assert_eq!(results[0].fork, "Cancun");
+        assert!(!results[0].pass);
+        assert!(results[0]
+            .error
+            .as_ref()
+            .unwrap()
+            .starts_with("state root mismatch"));
+    }
+}
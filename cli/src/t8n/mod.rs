@@ -0,0 +1,528 @@
+// State transition tool compatible with geth's `evm t8n`, the interface
+// execution-spec-tests uses to fill tests with a client
+
+mod types;
+
+pub use types::{Env, ExecutionResult, RejectedTx, TxJson};
+
+use crate::alloc::{self, Alloc};
+use crate::tool::{decode_hex, parse, parse_fork, read_file, read_stdin, Outputs, ToolError};
+use primitive_types::U256;
+use serde::Deserialize;
+use std::path::PathBuf;
+use types::{LogJson, Receipt};
+use vm::block::{self, Ommer};
+use vm::bloom::Bloom;
+use vm::crypto::keccak256;
+use vm::db::InMemoryDB;
+use vm::env::{BlockEnv, CfgEnv, Env as VmEnv, Fork};
+use vm::error::VmError;
+use vm::executor::transact;
+use vm::gas;
+use vm::rlp;
+use vm::state::{Account, State};
+use vm::system;
+use vm::transaction::SignedTransaction;
+use vm::trie::ordered_trie_root;
+
+#[derive(clap::Args, Debug)]
+pub struct T8nArgs {
+    /// pre-state file, or `stdin` to read a `{alloc, env, txs}` object
+    #[clap(long = "input.alloc", default_value = "alloc.json", value_parser)]
+    input_alloc: String,
+    /// block environment file, or `stdin`
+    #[clap(long = "input.env", default_value = "env.json", value_parser)]
+    input_env: String,
+    /// JSON list of transactions, an RLP list in a `.rlp` file, or `stdin`
+    #[clap(long = "input.txs", default_value = "txs.json", value_parser)]
+    input_txs: String,
+    /// directory the output files are written to
+    #[clap(long = "output.basedir", default_value = ".", value_parser)]
+    output_basedir: PathBuf,
+    /// result file, or `stdout` / `stderr`
+    #[clap(long = "output.result", default_value = "result.json", value_parser)]
+    output_result: String,
+    /// post-state file, or `stdout` / `stderr`
+    #[clap(long = "output.alloc", default_value = "alloc.json", value_parser)]
+    output_alloc: String,
+    /// file for the RLP list of the included transactions
+    #[clap(long = "output.body", value_parser)]
+    output_body: Option<String>,
+    /// fork whose rules apply
+    #[clap(long = "state.fork", default_value = "Cancun", value_parser)]
+    fork: String,
+    /// chain id that transactions are signed for
+    #[clap(long = "state.chainid", default_value_t = 1, value_parser)]
+    chain_id: u64,
+    /// block reward before the merge, -1 to skip rewards
+    #[clap(
+        long = "state.reward",
+        default_value_t = 0,
+        allow_hyphen_values = true,
+        value_parser
+    )]
+    reward: i64,
+}
+
+pub enum Txs {
+    Json(Vec<TxJson>),
+    Rlp(Vec<u8>),
+}
+
+pub struct Input {
+    pub alloc: Alloc,
+    pub env: Env,
+    pub txs: Txs,
+}
+
+pub struct Output {
+    pub alloc: Alloc,
+    pub result: ExecutionResult,
+    // RLP list of the included transactions
+    pub body: Vec<u8>,
+}
+
+pub fn run(args: T8nArgs) -> Result<(), ToolError> {
+    let fork = parse_fork(&args.fork)?;
+    let input = read_input(&args)?;
+    let reward = u64::try_from(args.reward).ok().map(U256::from);
+    let output = transition(input, fork, args.chain_id, reward)?;
+    write_output(&args, output)
+}
+
+// Applies the transactions on top of `alloc`. Invalid transactions are
+// rejected and skipped rather than failing the block.
+pub fn transition(
+    input: Input,
+    fork: Fork,
+    chain_id: u64,
+    reward: Option<U256>,
+) -> Result<Output, ToolError> {
+    let Input {
+        mut alloc,
+        env: input_env,
+        txs,
+    } = input;
+    let mut env = VmEnv {
+        cfg: CfgEnv { chain_id, fork },
+        block: block_env(&input_env, fork)?,
+        tx: Default::default(),
+    };
+    let txs: Vec<Result<SignedTransaction, String>> = match txs {
+        Txs::Json(txs) => txs.into_iter().map(|tx| tx.into_signed(chain_id)).collect(),
+        Txs::Rlp(bytes) => rlp::decode::<Vec<SignedTransaction>>(&bytes)
+            .map_err(|e| ToolError::Rlp(format!("{:?}", e)))?
+            .into_iter()
+            .map(Ok)
+            .collect(),
+    };
+
+    let block_hashes = input_env.block_hashes().map_err(ToolError::Config)?;
+    let mut db = InMemoryDB::default();
+    for (number, hash) in &block_hashes {
+        db.insert_block_hash(*number, *hash);
+    }
+    let mut state = State::with_db(db);
+    for (address, account) in &alloc {
+        state.insert_account(*address, Account::from(account));
+    }
+
+    if fork >= Fork::Cancun {
+        if let Some(root) = input_env.parent_beacon_block_root {
+            system::apply_beacon_root(&mut state, &env, root).map_err(ToolError::Evm)?;
+        }
+    }
+    if fork >= Fork::Prague {
+        let parent_hash = env
+            .block
+            .number
+            .checked_sub(1)
+            .and_then(|number| block_hashes.get(&number).copied())
+            .unwrap_or_default();
+        system::apply_parent_hash(&mut state, &env, parent_hash).map_err(ToolError::Evm)?;
+    }
+
+    let mut included = Vec::new();
+    let mut receipts = Vec::new();
+    let mut block_receipts = Vec::new();
+    let mut rejected = Vec::new();
+    let mut logs = Vec::new();
+    let mut logs_bloom = Bloom::default();
+    let mut gas_used = 0;
+    let mut blob_gas_used = 0;
+    for (index, tx) in txs.into_iter().enumerate() {
+        let mut reject = |error: String| rejected.push(RejectedTx { index, error });
+        let tx = match tx {
+            Ok(tx) => tx,
+            Err(e) => {
+                reject(e);
+                continue;
+            }
+        };
+        let sender = match tx.recover_sender() {
+            Ok(sender) => sender,
+            Err(e) => {
+                reject(format!("invalid signature: {:?}", e));
+                continue;
+            }
+        };
+        if tx.tx.gas_limit() > env.block.gas_limit - gas_used {
+            reject("gas limit reached".into());
+            continue;
+        }
+        env.tx = tx.tx_env(sender);
+        if env.tx.blob_gas() > gas::max_blob_gas(fork) - blob_gas_used {
+            reject("blob gas limit reached".into());
+            continue;
+        }
+        let result = match transact(&mut state, &env) {
+            Ok(result) => result,
+            Err(VmError::InvalidTransaction(e)) => {
+                reject(format!("{:?}", e));
+                continue;
+            }
+            Err(e) => return Err(ToolError::Evm(e)),
+        };
+
+        gas_used += result.gas_used;
+        blob_gas_used += result.blob_gas_used;
+        let hash = tx.hash();
+        let tx_index = included.len() as u64;
+        let bloom = Bloom::from_logs(&result.logs);
+        logs_bloom |= bloom;
+        receipts.push(Receipt {
+            tx_type: tx.tx.tx_type().into(),
+            root: Vec::new(),
+            status: result.success.into(),
+            cumulative_gas_used: gas_used,
+            logs_bloom: bloom.as_bytes().to_vec(),
+            logs: result
+                .logs
+                .iter()
+                .enumerate()
+                .map(|(i, log)| {
+                    let log_index = (logs.len() + i) as u64;
+                    LogJson::new(log, env.block.number, hash, tx_index, log_index)
+                })
+                .collect(),
+            transaction_hash: hash,
+            contract_address: result.contract_address.unwrap_or_default(),
+            gas_used: result.gas_used,
+            effective_gas_price: env.tx.effective_gas_price(env.block.basefee),
+            block_hash: Default::default(),
+            transaction_index: tx_index,
+        });
+        logs.extend(result.logs.iter().cloned());
+        block_receipts.push(block::Receipt {
+            tx_type: tx.tx.tx_type(),
+            success: result.success,
+            cumulative_gas_used: gas_used,
+            logs_bloom: bloom,
+            logs: result.logs,
+            gas_used: result.gas_used,
+            contract_address: result.contract_address,
+        });
+        included.push(tx);
+    }
+
+    let withdrawals = input_env.withdrawals.unwrap_or_default();
+    if fork >= Fork::Shanghai {
+        block::apply_withdrawals(&mut state, &withdrawals).map_err(ToolError::Evm)?;
+    }
+    let requests = match fork >= Fork::Prague {
+        true => Some(
+            block::collect_requests(&mut state, &env, &block_receipts).map_err(ToolError::Evm)?,
+        ),
+        false => None,
+    };
+    if let (Some(reward), true) = (reward, fork < Fork::Paris) {
+        let ommers: Vec<Ommer> = input_env
+            .ommers
+            .iter()
+            .map(|ommer| Ommer {
+                coinbase: ommer.address,
+                number: env.block.number.saturating_sub(ommer.delta),
+            })
+            .collect();
+        block::apply_rewards(&mut state, &env.block, &ommers, reward).map_err(ToolError::Evm)?;
+    }
+
+    alloc::apply_changes(&mut alloc, state.take_changes());
+    let result = ExecutionResult {
+        state_root: alloc::state_root(&alloc),
+        tx_root: ordered_trie_root(included.iter().map(SignedTransaction::encode_enveloped)),
+        receipts_root: block::receipts_root(&block_receipts),
+        logs_hash: keccak256(&rlp::encode_list(&logs)),
+        logs_bloom: logs_bloom.as_bytes().to_vec(),
+        receipts,
+        rejected,
+        current_difficulty: (fork < Fork::Paris).then_some(env.block.difficulty),
+        gas_used,
+        current_base_fee: (fork >= Fork::London).then_some(env.block.basefee),
+        withdrawals_root: (fork >= Fork::Shanghai).then(|| block::withdrawals_root(&withdrawals)),
+        current_excess_blob_gas: env.block.excess_blob_gas,
+        blob_gas_used: (fork >= Fork::Cancun).then_some(blob_gas_used),
+        requests_hash: requests.as_deref().map(block::requests_hash),
+        requests,
+    };
+    Ok(Output {
+        alloc,
+        result,
+        body: rlp::encode(&included),
+    })
+}
+
+// Block environment, with the base fee and excess blob gas derived from the
+// parent when they are not given
+fn block_env(env: &Env, fork: Fork) -> Result<BlockEnv, ToolError> {
+    let missing = |name: &str| ToolError::Config(format!("{} required in {:?}", name, fork));
+    let basefee = match (env.current_base_fee, fork >= Fork::London) {
+        (_, false) => U256::zero(),
+        (Some(base_fee), true) => base_fee,
+        (None, true) => match (
+            env.parent_base_fee,
+            env.parent_gas_used,
+            env.parent_gas_limit,
+        ) {
+            (Some(base_fee), Some(gas_used), Some(gas_limit)) => {
+                gas::next_base_fee(gas_used, gas_limit, base_fee)
+            }
+            _ => return Err(missing("currentBaseFee or the parent's base fee and gas")),
+        },
+    };
+    let excess_blob_gas = match (env.current_excess_blob_gas, fork >= Fork::Cancun) {
+        (_, false) => None,
+        (Some(excess), true) => Some(excess),
+        (None, true) => Some(gas::next_excess_blob_gas(
+            env.parent_excess_blob_gas.unwrap_or_default(),
+            env.parent_blob_gas_used.unwrap_or_default(),
+            fork,
+        )),
+    };
+
+    Ok(BlockEnv {
+        number: env.current_number,
+        coinbase: env.current_coinbase,
+        timestamp: env.current_timestamp,
+        gas_limit: env.current_gas_limit,
+        basefee,
+        difficulty: match fork < Fork::Paris {
+            true => env
+                .current_difficulty
+                .ok_or_else(|| missing("currentDifficulty"))?,
+            false => U256::zero(),
+        },
+        prevrandao: match fork >= Fork::Paris {
+            true => env.current_random.ok_or_else(|| missing("currentRandom"))?,
+            false => Default::default(),
+        },
+        excess_blob_gas,
+    })
+}
+
+// Input given on stdin as a single object
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StdinInput {
+    alloc: Option<Alloc>,
+    env: Option<Env>,
+    txs: Option<Vec<TxJson>>,
+    txs_rlp: Option<String>,
+}
+
+fn read_input(args: &T8nArgs) -> Result<Input, ToolError> {
+    let names = [&args.input_alloc, &args.input_env, &args.input_txs];
+    let mut stdin = StdinInput::default();
+    if names.iter().any(|name| *name == "stdin") {
+        stdin = parse(&read_stdin()?)?;
+    }
+    let missing = |name: &str| ToolError::Config(format!("no {} on stdin", name));
+
+    let alloc = match args.input_alloc.as_str() {
+        "stdin" => stdin.alloc.ok_or_else(|| missing("alloc"))?,
+        path => parse(&read_file(path)?)?,
+    };
+    let env = match args.input_env.as_str() {
+        "stdin" => stdin.env.ok_or_else(|| missing("env"))?,
+        path => parse(&read_file(path)?)?,
+    };
+    let txs = match args.input_txs.as_str() {
+        "stdin" => match (stdin.txs, stdin.txs_rlp) {
+            (Some(txs), _) => Txs::Json(txs),
+            (None, Some(rlp)) => Txs::Rlp(decode_hex(&rlp)?),
+            (None, None) => Txs::Json(Vec::new()),
+        },
+        path if path.ends_with(".rlp") => {
+            let rlp: String = parse(&read_file(path)?)?;
+            Txs::Rlp(decode_hex(&rlp)?)
+        }
+        path => Txs::Json(parse(&read_file(path)?)?),
+    };
+    Ok(Input { alloc, env, txs })
+}
+
+fn write_output(args: &T8nArgs, output: Output) -> Result<(), ToolError> {
+    let mut outputs = Outputs::new(&args.output_basedir);
+    outputs.add("result", &args.output_result, &output.result)?;
+    outputs.add("alloc", &args.output_alloc, &output.alloc)?;
+    if let Some(name) = &args.output_body {
+        outputs.add("body", name, format!("0x{}", hex::encode(&output.body)))?;
+    }
+    outputs.finish();
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use primitive_types::H160;
+
+    const SECRET_KEY: &str = "0x45a915e4d060149eb4365960e6a7a45f334393093061116b197e3240065ff2d8";
+
+    fn input(txs: &str) -> Input {
+        let alloc = r#"{
+            "0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b": { "balance": "0x0de0b6b3a7640000" },
+            "0x1000000000000000000000000000000000000000": {
+                "code": "0x4360005500",
+                "storage": { "0x01": "0x02" },
+                "balance": "0x0"
+            }
+        }"#;
+        let env = r#"{
+            "currentCoinbase": "0x2adc25665018aa1fe0e6bc666dac8fc2697ff9ba",
+            "currentGasLimit": "0x1000000",
+            "currentNumber": "0x10",
+            "currentTimestamp": "0x3e8",
+            "currentRandom": "0x0000000000000000000000000000000000000000000000000000000000020000",
+            "parentBaseFee": "0x0a",
+            "parentGasUsed": "0x0",
+            "parentGasLimit": "0x1000000",
+            "blockHashes": { "15": "0x00000000000000000000000000000000000000000000000000000000000000ff" },
+            "withdrawals": []
+        }"#;
+        Input {
+            alloc: serde_json::from_str(alloc).unwrap(),
+            env: serde_json::from_str(env).unwrap(),
+            txs: Txs::Json(serde_json::from_str(txs).unwrap()),
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_transactions_and_continues() {
+        let txs = format!(
+            r#"[
+                {{ "type": "0x2", "nonce": "0x0", "maxFeePerGas": "0x10", "maxPriorityFeePerGas": "0x1",
+                   "gas": "0x186a0", "to": "0x1000000000000000000000000000000000000000",
+                   "value": "0x0", "input": "0x", "secretKey": "{key}" }},
+                {{ "nonce": "0x5", "gasPrice": "0x10", "gas": "0x5208",
+                   "to": "0x2000000000000000000000000000000000000000",
+                   "value": "0x0", "input": "0x", "secretKey": "{key}" }},
+                {{ "nonce": "0x1", "gasPrice": "0x10", "gas": "0x5208",
+                   "to": "0x2000000000000000000000000000000000000000",
+                   "value": "0x1", "data": "0x", "secretKey": "{key}" }}
+            ]"#,
+            key = SECRET_KEY
+        );
+        let output = transition(input(&txs), Fork::Cancun, 1, None).unwrap();
+        let result = &output.result;
+
+        assert_eq!(result.rejected.len(), 1);
+        assert_eq!(result.rejected[0].index, 1);
+        assert!(result.rejected[0].error.starts_with("NonceTooHigh"));
+        assert_eq!(result.receipts.len(), 2);
+        assert_eq!(result.receipts[1].transaction_index, 1);
+        assert_eq!(result.gas_used, result.receipts[1].cumulative_gas_used);
+        // the base fee follows the parent's empty block
+        assert_eq!(result.current_base_fee, Some(U256::from(9)));
+        assert_eq!(result.current_excess_blob_gas, Some(0));
+        assert_eq!(result.state_root, alloc::state_root(&output.alloc));
+
+        // the contract stored the block number over the zero slot
+        let contract = &output.alloc[&"0x1000000000000000000000000000000000000000"
+            .parse()
+            .unwrap()];
+        assert_eq!(contract.storage[&U256::zero()], U256::from(0x10));
+        assert_eq!(contract.storage[&U256::one()], U256::from(2));
+        let recipient: H160 = "0x2000000000000000000000000000000000000000"
+            .parse()
+            .unwrap();
+        assert_eq!(output.alloc[&recipient].balance, U256::one());
+
+        let body: Vec<SignedTransaction> = rlp::decode(&output.body).unwrap();
+        assert_eq!(body.len(), 2);
+        assert_eq!(body[0].hash(), result.receipts[0].transaction_hash);
+        assert_eq!(
+            result.tx_root,
+            ordered_trie_root(body.iter().map(SignedTransaction::encode_enveloped))
+        );
+
+        // the same transactions given as RLP
+        let mut rlp_input = input("[]");
+        rlp_input.txs = Txs::Rlp(output.body.clone());
+        let from_rlp = transition(rlp_input, Fork::Cancun, 1, None).unwrap();
+        assert_eq!(from_rlp.result.state_root, result.state_root);
+    }
+
+    #[test]
+    fn signed_json_transaction() {
+        // EIP-155 example
+        let tx: TxJson = serde_json::from_str(
+            r#"{
+                "nonce": "0x9", "gasPrice": "0x4a817c800", "gas": "0x5208",
+                "to": "0x3535353535353535353535353535353535353535",
+                "value": "0xde0b6b3a7640000", "input": "0x", "v": "0x25",
+                "r": "0x28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276",
+                "s": "0x67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"
+            }"#,
+        )
+        .unwrap();
+        let tx = tx.into_signed(1).unwrap();
+        assert_eq!(tx.tx.chain_id(), Some(1));
+        assert_eq!(
+            tx.recover_sender().unwrap(),
+            "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f"
+                .parse()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn outputs_requests_since_prague() {
+        let mut prague = input("[]");
+        // one returns 0xabcd, the other nothing
+        for (address, code) in [
+            (system::WITHDRAWAL_REQUEST_ADDRESS, "61abcd5f526002601ef3"),
+            (system::CONSOLIDATION_REQUEST_ADDRESS, "00"),
+        ] {
+            let account = alloc::AllocAccount {
+                code: hex::decode(code).unwrap(),
+                ..Default::default()
+            };
+            prague.alloc.insert(address, account);
+        }
+        let output = transition(prague, Fork::Prague, 1, None).unwrap();
+        let result = &output.result;
+        assert_eq!(result.requests, Some(vec![vec![0x01, 0xab, 0xcd]]));
+        assert_eq!(
+            result.requests_hash,
+            result.requests.as_deref().map(block::requests_hash)
+        );
+        let json = serde_json::to_value(result).unwrap();
+        assert_eq!(json["requests"], serde_json::json!(["0x01abcd"]));
+
+        let output = transition(input("[]"), Fork::Cancun, 1, None).unwrap();
+        let json = serde_json::to_value(&output.result).unwrap();
+        assert!(json.get("requests").is_none());
+        assert!(json.get("requestsHash").is_none());
+    }
+
+    #[test]
+    fn requires_merge_fields() {
+        let mut input = input("[]");
+        input.env.current_random = None;
+        assert!(matches!(
+            transition(input, Fork::Cancun, 1, None),
+            Err(ToolError::Config(_))
+        ));
+    }
+}
@@ -0,0 +1,372 @@
+// JSON formats of geth's `evm t8n` tool
+
+use primitive_types::{H160, H256, U256};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use vm::block::Withdrawal;
+use vm::env::AccessListItem;
+use vm::state::Log;
+use vm::transaction::{
+    AccessListTx, BlobTx, DynamicFeeTx, LegacyTx, LocalSigner, SetCodeTx, Signature,
+    SignedAuthorization, SignedTransaction, Transaction, TxKind,
+};
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Env {
+    pub current_coinbase: H160,
+    #[serde(with = "vm::serde_hex::u64")]
+    pub current_gas_limit: u64,
+    #[serde(with = "vm::serde_hex::u64")]
+    pub current_number: u64,
+    #[serde(with = "vm::serde_hex::u64")]
+    pub current_timestamp: u64,
+    // before the merge
+    pub current_difficulty: Option<U256>,
+    // after the merge
+    pub current_random: Option<H256>,
+    // London, computed from the parent when missing
+    pub current_base_fee: Option<U256>,
+    pub parent_base_fee: Option<U256>,
+    #[serde(default, with = "vm::serde_hex::u64_opt")]
+    pub parent_gas_used: Option<u64>,
+    #[serde(default, with = "vm::serde_hex::u64_opt")]
+    pub parent_gas_limit: Option<u64>,
+    // Cancun, computed from the parent when missing
+    #[serde(default, with = "vm::serde_hex::u64_opt")]
+    pub current_excess_blob_gas: Option<u64>,
+    #[serde(default, with = "vm::serde_hex::u64_opt")]
+    pub parent_excess_blob_gas: Option<u64>,
+    #[serde(default, with = "vm::serde_hex::u64_opt")]
+    pub parent_blob_gas_used: Option<u64>,
+    pub parent_beacon_block_root: Option<H256>,
+    // keyed by block number in decimal or hex
+    #[serde(default)]
+    pub block_hashes: BTreeMap<String, H256>,
+    #[serde(default)]
+    pub ommers: Vec<Ommer>,
+    pub withdrawals: Option<Vec<Withdrawal>>,
+}
+
+impl Env {
+    pub fn block_hashes(&self) -> Result<BTreeMap<u64, H256>, String> {
+        self.block_hashes
+            .iter()
+            .map(|(number, hash)| {
+                let parsed = match number.strip_prefix("0x") {
+                    Some(hex) => u64::from_str_radix(hex, 16),
+                    None => number.parse(),
+                };
+                parsed
+                    .map(|number| (number, *hash))
+                    .map_err(|_| format!("invalid block number {}", number))
+            })
+            .collect()
+    }
+}
+
+// Ommer `delta` blocks below the current one
+#[derive(Clone, Debug, Deserialize)]
+pub struct Ommer {
+    #[serde(with = "vm::serde_hex::u64")]
+    pub delta: u64,
+    pub address: H160,
+}
+
+// Transaction in the JSON-RPC format. It is signed with `secretKey` when
+// given, otherwise `v`, `r` and `s` are used as they are.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxJson {
+    #[serde(rename = "type", default, with = "vm::serde_hex::u64_opt")]
+    pub tx_type: Option<u64>,
+    #[serde(default, with = "vm::serde_hex::u64_opt")]
+    pub chain_id: Option<u64>,
+    #[serde(with = "vm::serde_hex::u64")]
+    pub nonce: u64,
+    pub gas_price: Option<U256>,
+    pub max_priority_fee_per_gas: Option<U256>,
+    pub max_fee_per_gas: Option<U256>,
+    #[serde(with = "vm::serde_hex::u64")]
+    pub gas: u64,
+    // `null` creates a contract
+    pub to: Option<H160>,
+    pub value: U256,
+    #[serde(alias = "data", with = "vm::serde_hex::bytes")]
+    pub input: Vec<u8>,
+    pub access_list: Option<Vec<AccessListItem>>,
+    pub max_fee_per_blob_gas: Option<U256>,
+    pub blob_versioned_hashes: Option<Vec<H256>>,
+    pub authorization_list: Option<Vec<AuthorizationJson>>,
+    pub v: Option<U256>,
+    pub y_parity: Option<U256>,
+    pub r: Option<U256>,
+    pub s: Option<U256>,
+    pub secret_key: Option<H256>,
+    // legacy transactions signed with `secretKey` use EIP-155 unless false
+    pub protected: Option<bool>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorizationJson {
+    pub chain_id: U256,
+    pub address: H160,
+    #[serde(with = "vm::serde_hex::u64")]
+    pub nonce: u64,
+    pub v: Option<U256>,
+    pub y_parity: Option<U256>,
+    pub r: U256,
+    pub s: U256,
+}
+
+impl TxJson {
+    // The type is inferred from the fields when missing
+    pub fn into_signed(self, chain_id: u64) -> Result<SignedTransaction, String> {
+        let tx_type = self
+            .tx_type
+            .unwrap_or(if self.authorization_list.is_some() {
+                4
+            } else if self.blob_versioned_hashes.is_some() {
+                3
+            } else if self.max_fee_per_gas.is_some() {
+                2
+            } else if self.access_list.is_some() {
+                1
+            } else {
+                0
+            });
+        let chain_id = self.chain_id.unwrap_or(chain_id);
+        let missing = |name: &str| format!("missing {} for type {} transaction", name, tx_type);
+        let to = match self.to {
+            Some(to) => TxKind::Call(to),
+            None => TxKind::Create,
+        };
+        let call_only = |to: TxKind| {
+            to.to()
+                .ok_or_else(|| format!("type {} transaction cannot create contracts", tx_type))
+        };
+        let access_list = self.access_list.clone().unwrap_or_default();
+        let max_fee_per_gas = self.max_fee_per_gas.ok_or_else(|| missing("maxFeePerGas"));
+        let max_priority_fee_per_gas = self
+            .max_priority_fee_per_gas
+            .ok_or_else(|| missing("maxPriorityFeePerGas"));
+
+        let tx = match tx_type {
+            0 => Transaction::Legacy(LegacyTx {
+                chain_id: match (self.secret_key, self.protected, self.v) {
+                    (Some(_), Some(false), _) => None,
+                    (Some(_), _, _) => Some(chain_id),
+                    // EIP-155 folds the chain id into `v`
+                    (None, _, Some(v)) if v >= U256::from(35) => Some(((v - 35) / 2).low_u64()),
+                    (None, _, _) => None,
+                },
+                nonce: self.nonce,
+                gas_price: self.gas_price.ok_or_else(|| missing("gasPrice"))?,
+                gas_limit: self.gas,
+                to,
+                value: self.value,
+                data: self.input,
+            }),
+            1 => Transaction::AccessList(AccessListTx {
+                chain_id,
+                nonce: self.nonce,
+                gas_price: self.gas_price.ok_or_else(|| missing("gasPrice"))?,
+                gas_limit: self.gas,
+                to,
+                value: self.value,
+                data: self.input,
+                access_list,
+            }),
+            2 => Transaction::DynamicFee(DynamicFeeTx {
+                chain_id,
+                nonce: self.nonce,
+                max_priority_fee_per_gas: max_priority_fee_per_gas?,
+                max_fee_per_gas: max_fee_per_gas?,
+                gas_limit: self.gas,
+                to,
+                value: self.value,
+                data: self.input,
+                access_list,
+            }),
+            3 => Transaction::Blob(BlobTx {
+                chain_id,
+                nonce: self.nonce,
+                max_priority_fee_per_gas: max_priority_fee_per_gas?,
+                max_fee_per_gas: max_fee_per_gas?,
+                gas_limit: self.gas,
+                to: call_only(to)?,
+                value: self.value,
+                data: self.input,
+                access_list,
+                max_fee_per_blob_gas: self
+                    .max_fee_per_blob_gas
+                    .ok_or_else(|| missing("maxFeePerBlobGas"))?,
+                blob_versioned_hashes: self.blob_versioned_hashes.unwrap_or_default(),
+            }),
+            4 => Transaction::SetCode(SetCodeTx {
+                chain_id,
+                nonce: self.nonce,
+                max_priority_fee_per_gas: max_priority_fee_per_gas?,
+                max_fee_per_gas: max_fee_per_gas?,
+                gas_limit: self.gas,
+                to: call_only(to)?,
+                value: self.value,
+                data: self.input,
+                access_list,
+                authorization_list: self
+                    .authorization_list
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|auth| {
+                        Ok(SignedAuthorization {
+                            chain_id: auth.chain_id,
+                            address: auth.address,
+                            nonce: auth.nonce,
+                            y_parity: auth
+                                .y_parity
+                                .or(auth.v)
+                                .filter(|v| *v <= U256::from(u8::MAX))
+                                .ok_or("invalid authorization y parity")?
+                                .low_u32() as u8,
+                            r: auth.r,
+                            s: auth.s,
+                        })
+                    })
+                    .collect::<Result<_, String>>()?,
+            }),
+            _ => return Err(format!("unsupported transaction type {}", tx_type)),
+        };
+
+        if let Some(key) = self.secret_key {
+            let signer =
+                LocalSigner::from_bytes(key.as_fixed_bytes()).ok_or("invalid secret key")?;
+            return Ok(tx.sign(&signer));
+        }
+        let (Some(v), Some(r), Some(s)) = (self.y_parity.or(self.v), self.r, self.s) else {
+            return Err("transaction is neither signed nor has a secret key".into());
+        };
+        let y_parity = match &tx {
+            Transaction::Legacy(_) if v >= U256::from(35) => (v - 35) % 2 == U256::one(),
+            Transaction::Legacy(_) => y_parity(Some(v.saturating_sub(U256::from(27))))?,
+            _ => y_parity(Some(v))?,
+        };
+        Ok(SignedTransaction {
+            tx,
+            signature: Signature { y_parity, r, s },
+        })
+    }
+}
+
+fn y_parity(v: Option<U256>) -> Result<bool, String> {
+    match v.map(|v| v.low_u64()) {
+        Some(0) => Ok(false),
+        Some(1) => Ok(true),
+        _ => Err("invalid signature y parity".into()),
+    }
+}
+
+// result.json
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionResult {
+    pub state_root: H256,
+    pub tx_root: H256,
+    pub receipts_root: H256,
+    pub logs_hash: H256,
+    #[serde(with = "vm::serde_hex::bytes")]
+    pub logs_bloom: Vec<u8>,
+    pub receipts: Vec<Receipt>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rejected: Vec<RejectedTx>,
+    pub current_difficulty: Option<U256>,
+    #[serde(with = "vm::serde_hex::u64")]
+    pub gas_used: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_base_fee: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub withdrawals_root: Option<H256>,
+    #[serde(
+        with = "vm::serde_hex::u64_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub current_excess_blob_gas: Option<u64>,
+    #[serde(
+        with = "vm::serde_hex::u64_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub blob_gas_used: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requests_hash: Option<H256>,
+    #[serde(
+        with = "vm::serde_hex::bytes_list_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub requests: Option<Vec<Vec<u8>>>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Receipt {
+    #[serde(rename = "type", with = "vm::serde_hex::u64")]
+    pub tx_type: u64,
+    // the pre-Byzantium state root, always empty
+    #[serde(with = "vm::serde_hex::bytes")]
+    pub root: Vec<u8>,
+    #[serde(with = "vm::serde_hex::u64")]
+    pub status: u64,
+    #[serde(with = "vm::serde_hex::u64")]
+    pub cumulative_gas_used: u64,
+    #[serde(with = "vm::serde_hex::bytes")]
+    pub logs_bloom: Vec<u8>,
+    pub logs: Vec<LogJson>,
+    pub transaction_hash: H256,
+    // zero unless the transaction created a contract
+    pub contract_address: H160,
+    #[serde(with = "vm::serde_hex::u64")]
+    pub gas_used: u64,
+    pub effective_gas_price: U256,
+    pub block_hash: H256,
+    #[serde(with = "vm::serde_hex::u64")]
+    pub transaction_index: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogJson {
+    pub address: H160,
+    pub topics: Vec<H256>,
+    #[serde(with = "vm::serde_hex::bytes")]
+    pub data: Vec<u8>,
+    #[serde(with = "vm::serde_hex::u64")]
+    pub block_number: u64,
+    pub transaction_hash: H256,
+    #[serde(with = "vm::serde_hex::u64")]
+    pub transaction_index: u64,
+    pub block_hash: H256,
+    #[serde(with = "vm::serde_hex::u64")]
+    pub log_index: u64,
+    pub removed: bool,
+}
+
+impl LogJson {
+    pub fn new(log: &Log, block_number: u64, tx_hash: H256, tx_index: u64, log_index: u64) -> Self {
+        LogJson {
+            address: log.address,
+            topics: log.topics.clone(),
+            data: log.data.clone(),
+            block_number,
+            transaction_hash: tx_hash,
+            transaction_index: tx_index,
+            block_hash: H256::zero(),
+            log_index,
+            removed: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RejectedTx {
+    pub index: usize,
+    pub error: String,
+}
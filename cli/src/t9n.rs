@@ -0,0 +1,142 @@
+// Transaction validation tool compatible with geth's `evm t9n`
+
+use crate::tool::{decode_hex, parse, parse_fork, read_file, read_stdin, write_json, ToolError};
+use primitive_types::{H160, H256};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use vm::env::CfgEnv;
+use vm::error::VmError;
+use vm::executor::validate_tx;
+use vm::rlp;
+use vm::transaction::SignedTransaction;
+
+#[derive(clap::Args, Debug)]
+pub struct T9nArgs {
+    /// JSON string with an RLP list of transactions, or `stdin` to read a
+    /// `{"txsRlp": ...}` object
+    #[clap(long = "input.txs", default_value = "txs.rlp", value_parser)]
+    input_txs: String,
+    /// fork whose rules apply
+    #[clap(long = "state.fork", default_value = "Cancun", value_parser)]
+    fork: String,
+    /// chain id that transactions are signed for
+    #[clap(long = "state.chainid", default_value_t = 1, value_parser)]
+    chain_id: u64,
+    /// directory the output file is written to
+    #[clap(long = "output.basedir", default_value = ".", value_parser)]
+    output_basedir: PathBuf,
+    /// result file, or `stdout` / `stderr`
+    #[clap(long = "output.result", default_value = "stdout", value_parser)]
+    output_result: String,
+}
+
+// Sender and intrinsic gas of a valid transaction, or why it is invalid
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<H160>,
+    pub hash: H256,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "vm::serde_hex::u64_opt"
+    )]
+    pub intrinsic_gas: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StdinInput {
+    txs_rlp: String,
+}
+
+pub fn run(args: T9nArgs) -> Result<(), ToolError> {
+    let cfg = CfgEnv {
+        chain_id: args.chain_id,
+        fork: parse_fork(&args.fork)?,
+    };
+    let txs = match args.input_txs.as_str() {
+        "stdin" => parse::<StdinInput>(&read_stdin()?)?.txs_rlp,
+        path => parse::<String>(&read_file(path)?)?,
+    };
+    let txs: Vec<SignedTransaction> =
+        rlp::decode(&decode_hex(&txs)?).map_err(|e| ToolError::Rlp(format!("{:?}", e)))?;
+
+    let infos: Vec<TxInfo> = txs.iter().map(|tx| check(tx, &cfg)).collect();
+    write_json(&args.output_basedir, &args.output_result, &infos)
+}
+
+// Applies the checks that do not need the state or the block
+pub fn check(tx: &SignedTransaction, cfg: &CfgEnv) -> TxInfo {
+    let hash = tx.hash();
+    let result = tx
+        .recover_sender()
+        .map_err(|e| format!("invalid signature: {:?}", e))
+        .and_then(|sender| {
+            validate_tx(cfg, &tx.tx_env(sender))
+                .map(|intrinsic_gas| (sender, intrinsic_gas))
+                .map_err(|e| match e {
+                    VmError::InvalidTransaction(e) => format!("{:?}", e),
+                    e => format!("{:?}", e),
+                })
+        });
+    match result {
+        Ok((sender, intrinsic_gas)) => TxInfo {
+            address: Some(sender),
+            hash,
+            intrinsic_gas: Some(intrinsic_gas),
+            ..Default::default()
+        },
+        Err(error) => TxInfo {
+            error: Some(error),
+            hash,
+            ..Default::default()
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use primitive_types::U256;
+    use vm::env::Fork;
+    use vm::transaction::{DynamicFeeTx, LocalSigner, Transaction, TxKind};
+
+    #[test]
+    fn reports_intrinsic_gas_or_error_per_fork() {
+        let signer = LocalSigner::from_bytes(&[0x46; 32]).unwrap();
+        let tx = |data: Vec<u8>, gas_limit| {
+            Transaction::DynamicFee(DynamicFeeTx {
+                chain_id: 1,
+                max_fee_per_gas: U256::from(10),
+                gas_limit,
+                to: TxKind::Call(H160::from_low_u64_be(1)),
+                data,
+                ..Default::default()
+            })
+            .sign(&signer)
+        };
+        let cfg = |fork| CfgEnv { chain_id: 1, fork };
+
+        let info = check(&tx(vec![0, 1], 30_000), &cfg(Fork::Cancun));
+        assert_eq!(info.error, None);
+        assert_eq!(info.address, Some(signer.address()));
+        assert_eq!(info.intrinsic_gas, Some(21_000 + 4 + 16));
+
+        let info = check(&tx(vec![], 30_000), &cfg(Fork::Berlin));
+        assert_eq!(info.error.as_deref(), Some("DynamicFeeNotSupported"));
+        let info = check(&tx(vec![], 20_000), &cfg(Fork::Cancun));
+        assert_eq!(info.error.as_deref(), Some("IntrinsicGasTooLow"));
+        assert_eq!(info.intrinsic_gas, None);
+        let info = check(
+            &tx(vec![], 30_000),
+            &CfgEnv {
+                chain_id: 5,
+                fork: Fork::Cancun,
+            },
+        );
+        assert!(info.error.unwrap().starts_with("ChainIdMismatch"));
+    }
+}
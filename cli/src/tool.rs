@@ -0,0 +1,126 @@
+// Shared plumbing of the t8n, t9n and b11r tools: exit codes, inputs that
+// may come from stdin and outputs that may go to stdout or stderr
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use vm::env::Fork;
+use vm::error::VmError;
+
+// Failures with the exit codes used by geth's evm tool
+#[derive(Debug)]
+pub enum ToolError {
+    Evm(VmError),
+    Config(String),
+    Json(String),
+    Io(String),
+    Rlp(String),
+}
+
+impl ToolError {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ToolError::Evm(_) => 2,
+            ToolError::Config(_) => 3,
+            ToolError::Json(_) => 10,
+            ToolError::Io(_) => 11,
+            ToolError::Rlp(_) => 12,
+        }
+    }
+}
+
+impl fmt::Display for ToolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ToolError::Evm(e) => write!(f, "execution error: {}", e),
+            ToolError::Config(e) => write!(f, "invalid configuration: {}", e),
+            ToolError::Json(e) => write!(f, "invalid JSON: {}", e),
+            ToolError::Io(e) => write!(f, "I/O error: {}", e),
+            ToolError::Rlp(e) => write!(f, "invalid RLP: {}", e),
+        }
+    }
+}
+
+pub fn parse_fork(name: &str) -> Result<Fork, ToolError> {
+    name.parse()
+        .map_err(|e: vm::env::UnknownFork| ToolError::Config(e.to_string()))
+}
+
+pub fn read_file(path: &str) -> Result<String, ToolError> {
+    fs::read_to_string(path).map_err(|e| ToolError::Io(format!("{}: {}", path, e)))
+}
+
+pub fn read_stdin() -> Result<String, ToolError> {
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .map_err(|e| ToolError::Io(e.to_string()))?;
+    Ok(input)
+}
+
+pub fn parse<'a, T: Deserialize<'a>>(json: &'a str) -> Result<T, ToolError> {
+    serde_json::from_str(json).map_err(|e| ToolError::Json(e.to_string()))
+}
+
+pub fn decode_hex(s: &str) -> Result<Vec<u8>, ToolError> {
+    hex::decode(s.trim().trim_start_matches("0x")).map_err(|e| ToolError::Rlp(e.to_string()))
+}
+
+// Writes `value` to `stdout`, `stderr` or a file in `basedir`
+pub fn write_json<T: Serialize>(basedir: &Path, name: &str, value: &T) -> Result<(), ToolError> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| ToolError::Json(e.to_string()))?;
+    match name {
+        "stdout" => println!("{}", json),
+        "stderr" => eprintln!("{}", json),
+        name => {
+            let path = basedir.join(name);
+            fs::write(&path, json)
+                .map_err(|e| ToolError::Io(format!("{}: {}", path.display(), e)))?;
+        }
+    }
+    Ok(())
+}
+
+// Outputs written to files in `basedir`, or collected into one object per
+// stream for the names `stdout` and `stderr`
+pub struct Outputs {
+    basedir: PathBuf,
+    stdout: Map<String, Value>,
+    stderr: Map<String, Value>,
+}
+
+impl Outputs {
+    pub fn new(basedir: &Path) -> Self {
+        Outputs {
+            basedir: basedir.to_path_buf(),
+            stdout: Map::new(),
+            stderr: Map::new(),
+        }
+    }
+
+    pub fn add<T: Serialize>(&mut self, key: &str, name: &str, value: T) -> Result<(), ToolError> {
+        let value = serde_json::to_value(value).map_err(|e| ToolError::Json(e.to_string()))?;
+        match name {
+            "stdout" => {
+                self.stdout.insert(key.to_string(), value);
+            }
+            "stderr" => {
+                self.stderr.insert(key.to_string(), value);
+            }
+            name => write_json(&self.basedir, name, &value)?,
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) {
+        if !self.stdout.is_empty() {
+            println!("{}", Value::Object(self.stdout));
+        }
+        if !self.stderr.is_empty() {
+            eprintln!("{}", Value::Object(self.stderr));
+        }
+    }
+}
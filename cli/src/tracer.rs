@@ -0,0 +1,187 @@
+// EIP-3155 tracer writing one JSON object per executed opcode
+
+use serde::Serialize;
+use std::io::Write;
+use vm::error::VmError;
+use vm::executor::TxResult;
+use vm::gas::Gas;
+use vm::opcode::Opcode;
+use vm::tracer::{Step, Tracer};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StepLine {
+    pc: usize,
+    op: u8,
+    gas: String,
+    gas_cost: String,
+    mem_size: usize,
+    stack: Vec<String>,
+    depth: usize,
+    refund: i64,
+    op_name: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Summary {
+    output: String,
+    gas_used: String,
+}
+
+// Lines are held back until their opcode ran, which for calls and creations
+// is after the steps of the sub-frame. The cost of such an opcode is the gas
+// charged before the sub-frame runs, including the gas passed on to it, as
+// geth reports it.
+pub struct JsonTracer<W: Write> {
+    out: W,
+    lines: Vec<StepLine>,
+    // lines waiting for their cost, one per frame
+    pending: Vec<(usize, u64)>,
+}
+
+impl<W: Write> JsonTracer<W> {
+    pub fn new(out: W) -> Self {
+        JsonTracer {
+            out,
+            lines: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    fn write<T: Serialize>(&mut self, value: &T) {
+        // the trace is best effort, a closed stream ends it silently
+        if let Ok(json) = serde_json::to_string(value) {
+            writeln!(self.out, "{}", json).ok();
+        }
+    }
+}
+
+impl<W: Write> Tracer for JsonTracer<W> {
+    fn step(&mut self, step: Step) {
+        self.pending.push((self.lines.len(), step.gas));
+        self.lines.push(StepLine {
+            pc: step.pc,
+            op: step.op,
+            gas: format!("{:#x}", step.gas),
+            gas_cost: String::new(),
+            mem_size: step.memory_size,
+            stack: step
+                .stack
+                .iter()
+                .map(|value| format!("{:#x}", primitive_types::U256::from(value.as_bytes())))
+                .collect(),
+            depth: step.depth,
+            refund: step.refund,
+            op_name: Opcode::get(step.op)
+                .map(|op| op.mnemonic)
+                .unwrap_or(Opcode::INVALID.mnemonic),
+            error: None,
+        });
+    }
+
+    fn step_end(&mut self, gas: &Gas, error: Option<&VmError>) {
+        let Some((index, before)) = self.pending.pop() else {
+            return;
+        };
+        let line = &mut self.lines[index];
+        if line.gas_cost.is_empty() {
+            line.gas_cost = format!("{:#x}", before.saturating_sub(gas.remaining()));
+        }
+        line.error = error.map(|e| e.to_string());
+        if self.pending.is_empty() {
+            for line in std::mem::take(&mut self.lines) {
+                self.write(&line);
+            }
+        }
+    }
+
+    fn call_charged(&mut self, gas: &Gas) {
+        if let Some(&(index, before)) = self.pending.last() {
+            self.lines[index].gas_cost = format!("{:#x}", before.saturating_sub(gas.remaining()));
+        }
+    }
+
+    fn tx_end(&mut self, result: &TxResult) {
+        self.write(&Summary {
+            output: hex::encode(&result.output),
+            gas_used: format!("{:#x}", result.gas_used),
+        });
+        self.out.flush().ok();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use primitive_types::H160;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use vm::state::State;
+    use vm::tracer::SharedTracer;
+    use vm::{Context, Vm};
+
+    #[test]
+    fn writes_a_line_per_step() {
+        let tracer = Rc::new(RefCell::new(JsonTracer::new(Vec::new())));
+        let context = Context {
+            gas_limit: 100,
+            ..Default::default()
+        };
+        let shared: SharedTracer = tracer.clone();
+        // PUSH1 2, PUSH1 1, ADD, then an undefined opcode
+        let code = [0x60, 0x02, 0x60, 0x01, 0x01, 0x0c];
+        let mut vm = Vm::with_state(&code, State::new(), context).with_tracer(Some(shared));
+        assert!(!vm.run().is_success());
+
+        let out = String::from_utf8(tracer.borrow().out.clone()).unwrap();
+        let lines: Vec<serde_json::Value> = out
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[2],
+            serde_json::json!({
+                "pc": 4, "op": 1, "gas": "0x5e", "gasCost": "0x3", "memSize": 0,
+                "stack": ["0x2", "0x1"], "depth": 1, "refund": 0, "opName": "ADD"
+            })
+        );
+        assert_eq!(lines[3]["error"], "invalid opcode 0x0c");
+    }
+
+    #[test]
+    fn call_cost_includes_the_gas_passed_on() {
+        let tracer = Rc::new(RefCell::new(JsonTracer::new(Vec::new())));
+        let context = Context {
+            gas_limit: 10000,
+            ..Default::default()
+        };
+        let shared: SharedTracer = tracer.clone();
+        let mut state = State::new();
+        state
+            .set_code(H160::from_low_u64_be(0xbb), vec![0x00])
+            .unwrap();
+        // CALL 0xbb with 0xff gas, no value and no data, then STOP
+        let code = [
+            0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0xbb, 0x60, 0xff,
+            0xf1, 0x00,
+        ];
+        let mut vm = Vm::with_state(&code, state, context).with_tracer(Some(shared));
+        assert!(vm.run().is_success());
+
+        let out = String::from_utf8(tracer.borrow().out.clone()).unwrap();
+        let lines: Vec<serde_json::Value> = out
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let ops: Vec<_> = lines.iter().map(|line| line["opName"].clone()).collect();
+        assert_eq!(ops[7..], ["CALL", "STOP", "STOP"]);
+        // a cold account access and the 0xff gas passed on, of which the
+        // callee used none
+        assert_eq!(lines[7]["gasCost"], format!("{:#x}", 2600 + 0xff));
+        assert_eq!(lines[8]["depth"], 2);
+    }
+}
@@ -0,0 +1,435 @@
+use crate::models::{BlockchainTest, TestAccount};
+use crate::runner::{parse_fork, pre_account, Report, VariantError};
+use primitive_types::{H160, H256, U256};
+use std::collections::HashMap;
+use vm::block::{execute_block, validate_body, validate_result, SealedBlock};
+use vm::db::{Changes, DatabaseCommit, InMemoryDB};
+use vm::env::{CfgEnv, Fork};
+use vm::error::{InvalidBlock, VmError};
+use vm::header::{validate_header, Header};
+use vm::rlp;
+use vm::state::State;
+use vm::trie::StateTrie;
+
+// BLOCKHASH only reaches this far back
+const BLOCK_HASH_HISTORY: u64 = 256;
+
+// Imported block with the state after it
+#[derive(Clone)]
+struct ChainBlock {
+    header: Header,
+    total_difficulty: U256,
+    db: InMemoryDB,
+    trie: StateTrie,
+}
+
+// Imports the genesis and every block of a test, then checks the head and
+// its state. The failure names the offending block.
+pub fn run_blockchain_test(name: &str, test: &BlockchainTest, report: &mut Report) {
+    let Some(fork) = parse_fork(&test.network) else {
+        report.skipped += 1;
+        return;
+    };
+    match run_chain(test, fork) {
+        Ok(()) => report.passed += 1,
+        Err((_, VariantError::Unsupported(_))) => report.unsupported += 1,
+        Err((variant, VariantError::Failed(reason))) => {
+            report.fail(&test.network, name, variant, reason)
+        }
+    }
+}
+
+fn run_chain(test: &BlockchainTest, fork: Fork) -> Result<(), (String, VariantError)> {
+    let genesis_error = |reason: String| ("genesis".to_string(), reason.into());
+    let genesis: Header = rlp::decode(&test.genesis_rlp.0)
+        .map_err(|e| genesis_error(format!("invalid RLP: {:?}", e)))?;
+    if genesis.hash() != test.genesis_block_header.hash {
+        return Err(genesis_error(format!(
+            "hash mismatch: expected {:?}, got {:?}",
+            test.genesis_block_header.hash,
+            genesis.hash()
+        )));
+    }
+
+    let (db, mut trie) = pre_state(&test.pre);
+    let root = trie.root();
+    if root != genesis.state_root {
+        return Err(genesis_error(format!(
+            "state root mismatch: expected {:?}, got {:?}",
+            genesis.state_root, root
+        )));
+    }
+
+    let cfg = CfgEnv {
+        chain_id: test.config.chainid.and_then(|id| id.as_u64()).unwrap_or(1),
+        fork,
+    };
+    let mut head = genesis.hash();
+    let mut chain = HashMap::new();
+    chain.insert(
+        head,
+        ChainBlock {
+            total_difficulty: genesis.difficulty,
+            header: genesis,
+            db,
+            trie,
+        },
+    );
+
+    for (index, block) in test.blocks.iter().enumerate() {
+        let block_error = |reason| (format!("block {}", index), reason);
+        let imported = match rlp::decode::<SealedBlock>(&block.rlp.0) {
+            Ok(block) => match import_block(&chain, &cfg, &block) {
+                Err(e @ VmError::UnsupportedPrecompile(_)) => {
+                    return Err(block_error(VariantError::Unsupported(e)))
+                }
+                imported => imported.map_err(|e| format!("{:?}", e)),
+            },
+            Err(e) => Err(format!("invalid RLP: {:?}", e)),
+        };
+        match (imported, &block.expect_exception) {
+            (Ok(imported), None) => {
+                let hash = imported.header.hash();
+                // the latest block wins once the difficulty is zero
+                if imported.total_difficulty >= chain[&head].total_difficulty {
+                    head = hash;
+                }
+                chain.insert(hash, imported);
+            }
+            (Err(_), Some(_)) => {}
+            (Ok(_), Some(exception)) => {
+                return Err(block_error(
+                    format!("expected exception {}", exception).into(),
+                ))
+            }
+            (Err(e), None) => return Err(block_error(format!("rejected: {}", e).into())),
+        }
+    }
+
+    let post_error = |reason: String| ("post state".to_string(), reason.into());
+    if head != test.last_block_hash {
+        return Err(post_error(format!(
+            "last block hash mismatch: expected {:?}, got {:?}",
+            test.last_block_hash, head
+        )));
+    }
+    let expected = match (&test.post_state, test.post_state_hash) {
+        (Some(post), _) => pre_state(post).1.root(),
+        (None, Some(hash)) => hash,
+        (None, None) => return Err(post_error("fixture has no post state".into())),
+    };
+    // checked against the trie when the block was imported
+    let root = chain[&head].header.state_root;
+    if root != expected {
+        return Err(post_error(format!(
+            "state root mismatch: expected {:?}, got {:?}",
+            expected, root
+        )));
+    }
+    Ok(())
+}
+
+// Validates and executes a block on top of its parent's state
+fn import_block(
+    chain: &HashMap<H256, ChainBlock>,
+    cfg: &CfgEnv,
+    block: &SealedBlock,
+) -> Result<ChainBlock, VmError> {
+    let header = &block.header;
+    let parent = chain
+        .get(&header.parent_hash)
+        .ok_or(VmError::InvalidBlock(InvalidBlock::ParentHashMismatch))?;
+    validate_header(header, &parent.header, cfg.fork)?;
+    validate_body(block)?;
+
+    let mut db = parent.db.clone();
+    let mut ancestor = Some(parent);
+    while let Some(block) = ancestor {
+        if block.header.number + BLOCK_HASH_HISTORY < header.number {
+            break;
+        }
+        db.insert_block_hash(block.header.number, block.header.hash());
+        ancestor = chain.get(&block.header.parent_hash);
+    }
+
+    let mut state = State::with_db(&mut db);
+    let result = execute_block(&mut state, cfg, &block.block());
+    let changes = state.take_changes();
+    drop(state);
+    let result = result?;
+
+    let mut trie = parent.trie.clone();
+    trie.apply(&changes);
+    db.commit(changes)?;
+    validate_result(header, &result, trie.root())?;
+
+    Ok(ChainBlock {
+        header: header.clone(),
+        total_difficulty: parent.total_difficulty + header.difficulty,
+        db,
+        trie,
+    })
+}
+
+fn pre_state(accounts: &HashMap<H160, TestAccount>) -> (InMemoryDB, StateTrie) {
+    let changes: Changes = accounts
+        .iter()
+        .map(|(address, account)| (*address, Some(pre_account(account)).into()))
+        .collect();
+    let mut trie = StateTrie::new();
+    trie.apply(&changes);
+    let mut db = InMemoryDB::default();
+    db.commit(changes).expect("in-memory commit");
+    (db, trie)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::{
+        BlockchainTest, BlockchainTestFile, Bytes, TestBlock, TestConfig, TestHeader,
+    };
+    use std::path::Path;
+    use vm::block::withdrawals_root;
+    use vm::header::empty_ommers_hash;
+    use vm::transaction::{DynamicFeeTx, LocalSigner, Transaction, TxKind};
+    use vm::trie::ordered_trie_root;
+
+    const PRE: &str = r#"{
+        "0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b": {
+            "balance": "0x0de0b6b3a7640000",
+            "code": "0x",
+            "nonce": "0x00",
+            "storage": {}
+        },
+        "0x1000000000000000000000000000000000000000": {
+            "balance": "0x00",
+            "code": "0x4360005500",
+            "nonce": "0x01",
+            "storage": {}
+        }
+    }"#;
+
+    fn genesis(state_root: H256) -> Header {
+        Header {
+            ommers_hash: empty_ommers_hash(),
+            state_root,
+            transactions_root: ordered_trie_root(Vec::<Vec<u8>>::new()),
+            receipts_root: ordered_trie_root(Vec::<Vec<u8>>::new()),
+            gas_limit: 30_000_000,
+            base_fee_per_gas: Some(U256::from(7)),
+            withdrawals_root: Some(withdrawals_root(&[])),
+            blob_gas_used: Some(0),
+            excess_blob_gas: Some(0),
+            parent_beacon_block_root: Some(H256::zero()),
+            ..Default::default()
+        }
+    }
+
+    // Child of `parent` with `transactions`, sealed with the roots that
+    // result from executing it
+    fn seal(parent: &ChainBlock, transactions: Vec<Transaction>) -> (SealedBlock, ChainBlock) {
+        let signer = signer();
+        let transactions: Vec<_> = transactions
+            .into_iter()
+            .map(|tx| tx.sign(&signer))
+            .collect();
+        let header = Header {
+            parent_hash: parent.header.hash(),
+            beneficiary: H160::from_low_u64_be(0xc0),
+            transactions_root: ordered_trie_root(
+                transactions.iter().map(|tx| tx.encode_enveloped()),
+            ),
+            number: parent.header.number + 1,
+            timestamp: parent.header.timestamp + 12,
+            base_fee_per_gas: Some(parent.header.next_base_fee()),
+            excess_blob_gas: Some(parent.header.next_excess_blob_gas(Fork::Cancun)),
+            ..parent.header.clone()
+        };
+        let mut block = SealedBlock {
+            header,
+            transactions,
+            ommers: Vec::new(),
+            withdrawals: Some(Vec::new()),
+        };
+
+        let mut db = parent.db.clone();
+        let mut state = State::with_db(&mut db);
+        let result = execute_block(&mut state, &CfgEnv::default(), &block.block()).unwrap();
+        let mut trie = parent.trie.clone();
+        trie.apply(&state.take_changes());
+        block.header.gas_used = result.gas_used;
+        block.header.receipts_root = vm::block::receipts_root(&result.receipts);
+        block.header.logs_bloom = result.logs_bloom;
+        block.header.state_root = trie.root();
+
+        let chain = HashMap::from([(parent.header.hash(), parent.clone())]);
+        let imported = import_block(&chain, &CfgEnv::default(), &block).unwrap();
+        (block, imported)
+    }
+
+    fn signer() -> LocalSigner {
+        let key = hex::decode("45a915e4d060149eb4365960e6a7a45f334393093061116b197e3240065ff2d8")
+            .unwrap();
+        LocalSigner::from_bytes(&key.try_into().unwrap()).unwrap()
+    }
+
+    fn call(nonce: u64) -> Transaction {
+        Transaction::DynamicFee(DynamicFeeTx {
+            chain_id: 1,
+            nonce,
+            max_fee_per_gas: U256::from(10),
+            gas_limit: 100_000,
+            to: TxKind::Call(
+                "0x1000000000000000000000000000000000000000"
+                    .parse()
+                    .unwrap(),
+            ),
+            ..Default::default()
+        })
+    }
+
+    fn fixture() -> BlockchainTest {
+        let pre: HashMap<H160, TestAccount> = serde_json::from_str(PRE).unwrap();
+        let (db, mut trie) = pre_state(&pre);
+        let genesis = genesis(trie.root());
+        let genesis_block = ChainBlock {
+            header: genesis.clone(),
+            total_difficulty: U256::zero(),
+            db,
+            trie,
+        };
+        // stores the block number
+        let (first, first_imported) = seal(&genesis_block, vec![call(0)]);
+        let (second, second_imported) = seal(&first_imported, vec![call(1)]);
+
+        BlockchainTest {
+            network: "Cancun".into(),
+            genesis_block_header: TestHeader {
+                hash: genesis.hash(),
+            },
+            genesis_rlp: Bytes(rlp::encode(&genesis)),
+            pre,
+            post_state: None,
+            post_state_hash: Some(second_imported.header.state_root),
+            last_block_hash: second.hash(),
+            blocks: [first, second]
+                .iter()
+                .map(|block| TestBlock {
+                    rlp: Bytes(rlp::encode(block)),
+                    expect_exception: None,
+                })
+                .collect(),
+            config: TestConfig::default(),
+        }
+    }
+
+    fn run(test: &BlockchainTest) -> Report {
+        let mut report = Report::default();
+        run_blockchain_test("chain", test, &mut report);
+        report
+    }
+
+    #[test]
+    fn imports_chain() {
+        let test = fixture();
+        let report = run(&test);
+        assert!(report.is_success(), "{}", report);
+        assert_eq!(report.passed, 1);
+
+        let block: SealedBlock = rlp::decode(&test.blocks[1].rlp.0).unwrap();
+        assert_eq!(rlp::encode(&block), test.blocks[1].rlp.0);
+        assert_eq!(block.transactions.len(), 1);
+    }
+
+    // Sepolia's genesis, as dumped by geth, with its published hash as the
+    // last block hash
+    #[test]
+    fn imports_upstream_genesis() {
+        let path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/sepolia_genesis_blockchain.json");
+        let mut report = Report::default();
+        crate::run_path(&path, &mut report);
+        assert_eq!(report.passed, 1, "{}", report);
+
+        let json = std::fs::read_to_string(&path).unwrap();
+        let tests: BlockchainTestFile = serde_json::from_str(&json).unwrap();
+        let mut test = tests["sepoliaGenesis"].clone();
+        assert_eq!(
+            test.last_block_hash,
+            "0x25a5cc106eea7138acab33231d7160d69cb777ee0c2c553fcddf5138993e6dd9"
+                .parse()
+                .unwrap()
+        );
+        // one wei more anywhere changes the root
+        let account = test.pre.values_mut().next().unwrap();
+        account.balance.0 += U256::one();
+        let report = run(&test);
+        let failure = &report.failures["London"]["chain"][0];
+        assert_eq!(failure.variant, "genesis");
+        assert!(failure.reason.starts_with("state root mismatch"));
+    }
+
+    #[test]
+    fn checks_invalid_blocks() {
+        let mut test = fixture();
+        // a wrong state root invalidates the last block
+        let mut block: SealedBlock = rlp::decode(&test.blocks[1].rlp.0).unwrap();
+        block.header.state_root = H256::zero();
+        test.blocks[1].rlp = Bytes(rlp::encode(&block));
+
+        let report = run(&test);
+        let failure = &report.failures["Cancun"]["chain"][0];
+        assert_eq!(failure.variant, "block 1");
+        assert_eq!(failure.reason, "rejected: InvalidBlock(StateRootMismatch)");
+
+        // an expected exception keeps the first block as the head
+        test.blocks[1].expect_exception = Some("BlockException.INVALID_STATE_ROOT".into());
+        test.blocks.push(TestBlock {
+            rlp: Bytes(vec![0xc0]),
+            expect_exception: Some("BlockException.RLP_STRUCTURES_ENCODING".into()),
+        });
+        let first: SealedBlock = rlp::decode(&test.blocks[0].rlp.0).unwrap();
+        test.last_block_hash = first.hash();
+        test.post_state_hash = Some(first.header.state_root);
+        assert!(run(&test).is_success());
+
+        // an accepted block that should have been rejected
+        test.blocks[0].expect_exception = Some("BlockException.UNKNOWN".into());
+        let report = run(&test);
+        let failure = &report.failures["Cancun"]["chain"][0];
+        assert_eq!(failure.variant, "block 0");
+        assert!(failure.reason.starts_with("expected exception"));
+    }
+
+    #[test]
+    fn calls_to_precompiles_are_unsupported() {
+        let mut test = fixture();
+        let mut block: SealedBlock = rlp::decode(&test.blocks[1].rlp.0).unwrap();
+        let Transaction::DynamicFee(tx) = call(1) else {
+            unreachable!()
+        };
+        let tx = Transaction::DynamicFee(DynamicFeeTx {
+            to: TxKind::Call(H160::from_low_u64_be(6)),
+            ..tx
+        });
+        block.transactions = vec![tx.sign(&signer())];
+        block.header.transactions_root =
+            ordered_trie_root(block.transactions.iter().map(|tx| tx.encode_enveloped()));
+        test.blocks[1].rlp = Bytes(rlp::encode(&block));
+
+        let report = run(&test);
+        assert_eq!(report.unsupported, 1);
+        assert_eq!(report.failed(), 0);
+        assert!(!report.is_success());
+    }
+
+    #[test]
+    fn skips_transition_networks() {
+        let mut test = fixture();
+        test.network = "ShanghaiToCancunAtTime15k".into();
+        let report = run(&test);
+        assert_eq!(report.skipped, 1);
+        assert!(report.is_success());
+    }
+}
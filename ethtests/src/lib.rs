@@ -0,0 +1,8 @@
+// Runners for the JSON fixtures of the ethereum/tests repository
+
+pub mod blockchain;
+pub mod models;
+pub mod runner;
+
+pub use blockchain::run_blockchain_test;
+pub use runner::{parse_fork, run_path, run_test, run_variant, Failure, Report, VariantError};
@@ -0,0 +1,42 @@
+use clap::Parser;
+use ethtests::{run_path, Report};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Runs GeneralStateTests and BlockchainTests fixtures and reports the
+/// failing variants
+#[derive(Parser, Debug)]
+#[clap(name = "ethtests", author, version)]
+struct Args {
+    /// fixture files or directories, e.g. ethereum/tests/BlockchainTests
+    #[clap(required = true, value_parser)]
+    paths: Vec<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let mut report = Report::default();
+    for path in &args.paths {
+        run_path(path, &mut report);
+    }
+    println!("{}", report);
+    if report.is_success() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn paths_have_a_description() {
+        let command = Args::command();
+        command.clone().debug_assert();
+        let paths = command.get_arguments().find(|arg| arg.get_id() == "paths");
+        assert!(paths.and_then(|arg| arg.get_help()).is_some());
+    }
+}
@@ -0,0 +1,190 @@
+// JSON fixtures of the ethereum/tests repository
+
+use primitive_types::{H160, H256, U256};
+use serde::{de::Error, Deserialize, Deserializer};
+use std::collections::{BTreeMap, HashMap};
+use vm::env::AccessListItem;
+
+// Hex quantity, which may have leading zeros
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Quantity(pub U256);
+
+impl Quantity {
+    pub fn as_u64(&self) -> Option<u64> {
+        (self.0 <= U256::from(u64::MAX)).then(|| self.0.as_u64())
+    }
+}
+
+impl<'de> Deserialize<'de> for Quantity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let digits = match s.strip_prefix("0x") {
+            Some(hex) => hex.trim_start_matches('0'),
+            None => {
+                return U256::from_dec_str(&s)
+                    .map(Quantity)
+                    .map_err(D::Error::custom)
+            }
+        };
+        if digits.len() > 64 {
+            return Err(D::Error::custom(format!("quantity {} exceeds 256 bits", s)));
+        }
+        U256::from_str_radix(digits, 16)
+            .map(Quantity)
+            .map_err(D::Error::custom)
+    }
+}
+
+// 0x-prefixed byte string
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Bytes(pub Vec<u8>);
+
+impl<'de> Deserialize<'de> for Bytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        hex::decode(s.strip_prefix("0x").unwrap_or(&s))
+            .map(Bytes)
+            .map_err(D::Error::custom)
+    }
+}
+
+// A file of GeneralStateTests holds tests keyed by name
+pub type StateTestFile = BTreeMap<String, StateTest>;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct StateTest {
+    pub env: TestEnv,
+    pub pre: HashMap<H160, TestAccount>,
+    pub transaction: TestTransaction,
+    // expected results keyed by fork name
+    pub post: BTreeMap<String, Vec<PostState>>,
+    #[serde(default)]
+    pub config: TestConfig,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestEnv {
+    pub current_coinbase: H160,
+    pub current_difficulty: Option<Quantity>,
+    pub current_gas_limit: Quantity,
+    pub current_number: Quantity,
+    pub current_timestamp: Quantity,
+    pub current_base_fee: Option<Quantity>,
+    pub current_random: Option<H256>,
+    pub current_excess_blob_gas: Option<Quantity>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TestAccount {
+    pub balance: Quantity,
+    pub code: Bytes,
+    pub nonce: Quantity,
+    pub storage: HashMap<Quantity, Quantity>,
+}
+
+// Transaction template, the data, gas limit and value are picked by the
+// indexes of each post state
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestTransaction {
+    pub data: Vec<Bytes>,
+    pub gas_limit: Vec<Quantity>,
+    pub value: Vec<Quantity>,
+    pub gas_price: Option<Quantity>,
+    pub max_fee_per_gas: Option<Quantity>,
+    pub max_priority_fee_per_gas: Option<Quantity>,
+    pub nonce: Quantity,
+    pub secret_key: H256,
+    pub sender: Option<H160>,
+    #[serde(deserialize_with = "deserialize_to")]
+    pub to: Option<H160>,
+    // one per data entry, `null` for transactions without an access list
+    pub access_lists: Option<Vec<Option<Vec<AccessListItem>>>>,
+    pub max_fee_per_blob_gas: Option<Quantity>,
+    pub blob_versioned_hashes: Option<Vec<H256>>,
+    pub authorization_list: Option<Vec<TestAuthorization>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestAuthorization {
+    pub chain_id: Quantity,
+    pub address: H160,
+    pub nonce: Quantity,
+    pub v: Quantity,
+    pub r: Quantity,
+    pub s: Quantity,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostState {
+    // state root
+    pub hash: H256,
+    // keccak256 of the RLP encoded logs
+    pub logs: H256,
+    pub indexes: Indexes,
+    pub expect_exception: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub struct Indexes {
+    pub data: usize,
+    pub gas: usize,
+    pub value: usize,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct TestConfig {
+    pub chainid: Option<Quantity>,
+}
+
+// A file of BlockchainTests holds tests keyed by name
+pub type BlockchainTestFile = BTreeMap<String, BlockchainTest>;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockchainTest {
+    // fork name, transition networks such as `ShanghaiToCancunAtTime15k`
+    // are not supported
+    pub network: String,
+    pub genesis_block_header: TestHeader,
+    #[serde(rename = "genesisRLP")]
+    pub genesis_rlp: Bytes,
+    pub pre: HashMap<H160, TestAccount>,
+    // either the full post state or only its root is given
+    pub post_state: Option<HashMap<H160, TestAccount>>,
+    pub post_state_hash: Option<H256>,
+    #[serde(rename = "lastblockhash")]
+    pub last_block_hash: H256,
+    pub blocks: Vec<TestBlock>,
+    #[serde(default)]
+    pub config: TestConfig,
+}
+
+// Only the hash is checked, the fields are decoded from the RLP
+#[derive(Clone, Debug, Deserialize)]
+pub struct TestHeader {
+    pub hash: H256,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestBlock {
+    pub rlp: Bytes,
+    // set for blocks that must be rejected
+    pub expect_exception: Option<String>,
+}
+
+// An empty string creates a contract
+fn deserialize_to<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<H160>, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    if s.is_empty() {
+        return Ok(None);
+    }
+    s.trim_start_matches("0x")
+        .parse()
+        .map(Some)
+        .map_err(D::Error::custom)
+}
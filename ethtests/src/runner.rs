@@ -0,0 +1,520 @@
+use crate::blockchain::run_blockchain_test;
+use crate::models::{
+    BlockchainTestFile, Indexes, PostState, StateTest, StateTestFile, TestAccount,
+};
+use primitive_types::{H160, H256, U256};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use vm::crypto::keccak256;
+use vm::db::{AccountInfo, Database, EmptyDB};
+use vm::env::{BlockEnv, CfgEnv, Env, Fork, TxEnv};
+use vm::error::VmError;
+use vm::executor::transact_with_tracer;
+use vm::rlp;
+use vm::state::{Account, Log, State};
+use vm::tracer::SharedTracer;
+use vm::transaction::{LocalSigner, SignedAuthorization};
+use vm::trie::StateTrie;
+
+// Outcome of a test variant that did not match its fixture
+#[derive(Clone, Debug)]
+pub struct Failure {
+    // `d0g1v0` for state test indexes, the failing block for blockchain tests
+    pub variant: String,
+    pub reason: String,
+}
+
+// Why a test variant did not pass
+#[derive(Clone, Debug, PartialEq)]
+pub enum VariantError {
+    // the outcome does not match the fixture
+    Failed(String),
+    // the variant calls a precompiled contract, which the VM does not
+    // implement
+    Unsupported(VmError),
+}
+
+impl fmt::Display for VariantError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VariantError::Failed(reason) => write!(f, "{}", reason),
+            VariantError::Unsupported(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<String> for VariantError {
+    fn from(reason: String) -> Self {
+        VariantError::Failed(reason)
+    }
+}
+
+// Results of a run, with failures grouped by fork and test
+#[derive(Debug, Default)]
+pub struct Report {
+    pub passed: usize,
+    // variants of forks and networks the VM does not implement
+    pub skipped: usize,
+    // variants calling precompiled contracts that are not implemented, which
+    // fail the run like failures do
+    pub unsupported: usize,
+    pub failures: BTreeMap<String, BTreeMap<String, Vec<Failure>>>,
+    // files that could not be read or parsed
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl Report {
+    pub fn failed(&self) -> usize {
+        self.failures
+            .values()
+            .flat_map(|tests| tests.values())
+            .map(Vec::len)
+            .sum()
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.failed() == 0 && self.unsupported == 0 && self.errors.is_empty()
+    }
+}
+
+impl Report {
+    pub(crate) fn fail(&mut self, fork: &str, test: &str, variant: String, reason: String) {
+        self.failures
+            .entry(fork.to_string())
+            .or_default()
+            .entry(test.to_string())
+            .or_default()
+            .push(Failure { variant, reason });
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (fork, tests) in &self.failures {
+            writeln!(f, "{}:", fork)?;
+            for (test, failures) in tests {
+                writeln!(f, "  {}", test)?;
+                for failure in failures {
+                    writeln!(f, "    {}: {}", failure.variant, failure.reason)?;
+                }
+            }
+        }
+        for (path, error) in &self.errors {
+            writeln!(f, "{}: {}", path.display(), error)?;
+        }
+        write!(
+            f,
+            "{} passed, {} failed, {} skipped, {} unsupported, {} unreadable files",
+            self.passed,
+            self.failed(),
+            self.skipped,
+            self.unsupported,
+            self.errors.len()
+        )
+    }
+}
+
+// Runs every JSON fixture in `path`, which may be a file or a directory
+// searched recursively. State and blockchain tests are told apart by the
+// `blocks` of the latter.
+pub fn run_path(path: &Path, report: &mut Report) {
+    if path.is_dir() {
+        let mut entries: Vec<PathBuf> = match fs::read_dir(path) {
+            Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+            Err(e) => return report.errors.push((path.to_path_buf(), e.to_string())),
+        };
+        entries.sort();
+        for entry in entries {
+            if entry.is_dir() || entry.extension().is_some_and(|ext| ext == "json") {
+                run_path(&entry, report);
+            }
+        }
+        return;
+    }
+
+    let json = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| {
+            serde_json::from_str::<serde_json::Value>(&json).map_err(|e| e.to_string())
+        });
+    let result = json.and_then(|json| {
+        let is_blockchain = json
+            .as_object()
+            .and_then(|tests| tests.values().next())
+            .is_some_and(|test| test.get("blocks").is_some());
+        if is_blockchain {
+            let tests: BlockchainTestFile =
+                serde_json::from_value(json).map_err(|e| e.to_string())?;
+            for (name, test) in &tests {
+                run_blockchain_test(name, test, report);
+            }
+        } else {
+            let tests: StateTestFile = serde_json::from_value(json).map_err(|e| e.to_string())?;
+            for (name, test) in &tests {
+                run_test(name, test, report);
+            }
+        }
+        Ok(())
+    });
+    if let Err(e) = result {
+        report.errors.push((path.to_path_buf(), e));
+    }
+}
+
+// Runs every fork and index variant of a test
+pub fn run_test(name: &str, test: &StateTest, report: &mut Report) {
+    for (fork_name, posts) in &test.post {
+        let Some(fork) = parse_fork(fork_name) else {
+            report.skipped += posts.len();
+            continue;
+        };
+        for post in posts {
+            match run_variant(test, fork, post, None) {
+                Ok(()) => report.passed += 1,
+                Err(VariantError::Unsupported(_)) => report.unsupported += 1,
+                Err(VariantError::Failed(reason)) => {
+                    let Indexes { data, gas, value } = post.indexes;
+                    let variant = format!("d{}g{}v{}", data, gas, value);
+                    report.fail(fork_name, name, variant, reason)
+                }
+            }
+        }
+    }
+}
+
+pub fn parse_fork(name: &str) -> Option<Fork> {
+    name.parse().ok()
+}
+
+// Runs the variant of `post`, with `tracer` following the transaction
+pub fn run_variant(
+    test: &StateTest,
+    fork: Fork,
+    post: &PostState,
+    tracer: Option<SharedTracer>,
+) -> Result<(), VariantError> {
+    let mut state = State::with_db(TestDB);
+    let mut trie = StateTrie::new();
+    for (address, account) in &test.pre {
+        let account = pre_account(account);
+        trie.insert_account(
+            *address,
+            &AccountInfo::from(&account),
+            account.storage.clone(),
+        );
+        state.insert_account(*address, account);
+    }
+
+    let env = Env {
+        cfg: CfgEnv {
+            chain_id: test.config.chainid.and_then(|id| id.as_u64()).unwrap_or(1),
+            fork,
+        },
+        block: block_env(test, fork)?,
+        tx: Default::default(),
+    };
+    // a transaction that does not fit the VM's types is invalid as well
+    let result = match tx_env(test, post.indexes) {
+        Ok(tx) => match transact_with_tracer(&mut state, &Env { tx, ..env }, tracer) {
+            Err(e @ VmError::UnsupportedPrecompile(_)) => return Err(VariantError::Unsupported(e)),
+            result => result.map_err(|e| match e {
+                VmError::InvalidTransaction(e) => format!("{:?}", e),
+                e => format!("execution error: {:?}", e),
+            }),
+        },
+        Err(e) => Err(e),
+    };
+
+    let logs = match (result, &post.expect_exception) {
+        (Ok(result), None) => result.logs,
+        (Err(_), Some(_)) => Vec::new(),
+        (Ok(_), Some(exception)) => return Err(format!("expected exception {}", exception).into()),
+        (Err(e), None) => return Err(format!("unexpected invalid transaction: {}", e).into()),
+    };
+
+    trie.apply(&state.take_changes());
+    let root = trie.root();
+    if root != post.hash {
+        return Err(format!(
+            "state root mismatch: expected {:?}, got {:?}",
+            post.hash, root
+        )
+        .into());
+    }
+    let logs_hash = logs_hash(&logs);
+    if logs_hash != post.logs {
+        return Err(format!(
+            "logs hash mismatch: expected {:?}, got {:?}",
+            post.logs, logs_hash
+        )
+        .into());
+    }
+    Ok(())
+}
+
+pub(crate) fn pre_account(account: &TestAccount) -> Account {
+    Account {
+        balance: account.balance.0,
+        nonce: account.nonce.as_u64().unwrap_or(u64::MAX),
+        code: account.code.0.clone(),
+        storage: account
+            .storage
+            .iter()
+            .filter(|(_, value)| !value.0.is_zero())
+            .map(|(key, value)| (h256(key.0), h256(value.0)))
+            .collect(),
+    }
+}
+
+fn block_env(test: &StateTest, fork: Fork) -> Result<BlockEnv, String> {
+    let env = &test.env;
+    let u64_field = |value: crate::models::Quantity, name: &str| {
+        value
+            .as_u64()
+            .ok_or_else(|| format!("{} exceeds 64 bits", name))
+    };
+    Ok(BlockEnv {
+        number: u64_field(env.current_number, "block number")?,
+        coinbase: env.current_coinbase,
+        timestamp: u64_field(env.current_timestamp, "timestamp")?,
+        gas_limit: u64_field(env.current_gas_limit, "block gas limit")?,
+        basefee: env.current_base_fee.map(|fee| fee.0).unwrap_or_default(),
+        difficulty: env.current_difficulty.map(|d| d.0).unwrap_or_default(),
+        prevrandao: match env.current_random {
+            Some(random) if fork >= Fork::Paris => random,
+            _ => H256::zero(),
+        },
+        excess_blob_gas: match env.current_excess_blob_gas {
+            Some(excess) => Some(u64_field(excess, "excess blob gas")?),
+            None => None,
+        },
+    })
+}
+
+fn tx_env(test: &StateTest, indexes: Indexes) -> Result<TxEnv, String> {
+    let tx = &test.transaction;
+    let caller = match tx.sender {
+        Some(sender) => sender,
+        None => LocalSigner::from_bytes(tx.secret_key.as_fixed_bytes())
+            .ok_or("invalid secret key")?
+            .address(),
+    };
+    let (gas_price, gas_priority_fee) = match (tx.gas_price, tx.max_fee_per_gas) {
+        (Some(price), _) => (price.0, None),
+        (None, Some(max_fee)) => (
+            max_fee.0,
+            Some(tx.max_priority_fee_per_gas.unwrap_or_default().0),
+        ),
+        (None, None) => return Err("transaction without a gas price".into()),
+    };
+    let missing = |name| format!("no {} at index", name);
+
+    Ok(TxEnv {
+        caller,
+        gas_limit: tx
+            .gas_limit
+            .get(indexes.gas)
+            .ok_or_else(|| missing("gas limit"))?
+            .as_u64()
+            .ok_or("gas limit exceeds 64 bits")?,
+        gas_price,
+        gas_priority_fee,
+        to: tx.to,
+        value: tx
+            .value
+            .get(indexes.value)
+            .ok_or_else(|| missing("value"))?
+            .0,
+        data: tx
+            .data
+            .get(indexes.data)
+            .ok_or_else(|| missing("data"))?
+            .0
+            .clone(),
+        nonce: Some(tx.nonce.as_u64().ok_or("nonce exceeds 64 bits")?),
+        chain_id: None,
+        access_list: tx
+            .access_lists
+            .as_ref()
+            .and_then(|lists| lists.get(indexes.data).cloned().flatten())
+            .unwrap_or_default(),
+        blob_hashes: tx.blob_versioned_hashes.clone().unwrap_or_default(),
+        max_fee_per_blob_gas: tx.max_fee_per_blob_gas.map(|fee| fee.0),
+        authorization_list: match &tx.authorization_list {
+            Some(list) => Some(
+                list.iter()
+                    .map(|auth| {
+                        Ok(SignedAuthorization {
+                            chain_id: auth.chain_id.0,
+                            address: auth.address,
+                            nonce: auth.nonce.as_u64().ok_or("nonce exceeds 64 bits")?,
+                            y_parity: auth
+                                .v
+                                .as_u64()
+                                .and_then(|v| u8::try_from(v).ok())
+                                .ok_or("invalid authorization y parity")?,
+                            r: auth.r.0,
+                            s: auth.s.0,
+                        })
+                    })
+                    .collect::<Result<_, String>>()?,
+            ),
+            None => None,
+        },
+    })
+}
+
+// keccak256(rlp(logs))
+pub fn logs_hash(logs: &[Log]) -> H256 {
+    keccak256(&rlp::encode_list(logs))
+}
+
+fn h256(value: U256) -> H256 {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    H256(bytes)
+}
+
+// Empty database serving the block hashes used by the fixtures, which are
+// keccak256 of the block number in decimal
+struct TestDB;
+
+impl Database for TestDB {
+    fn basic(&mut self, address: H160) -> Result<Option<AccountInfo>, VmError> {
+        EmptyDB.basic(address)
+    }
+
+    fn code_by_hash(&mut self, code_hash: H256) -> Result<Vec<u8>, VmError> {
+        EmptyDB.code_by_hash(code_hash)
+    }
+
+    fn storage(&mut self, address: H160, index: H256) -> Result<H256, VmError> {
+        EmptyDB.storage(address, index)
+    }
+
+    fn block_hash(&mut self, number: u64) -> Result<H256, VmError> {
+        Ok(keccak256(number.to_string().as_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Value transfer with a wrong post state root for Cancun, an expected
+    // exception for a too low gas limit and a Frontier variant
+    const FIXTURE: &str = r#"{
+        "transfer": {
+            "env": {
+                "currentCoinbase": "0x2adc25665018aa1fe0e6bc666dac8fc2697ff9ba",
+                "currentDifficulty": "0x020000",
+                "currentGasLimit": "0x05f5e100",
+                "currentNumber": "0x01",
+                "currentTimestamp": "0x03e8",
+                "currentBaseFee": "0x0a",
+                "currentRandom": "0x0000000000000000000000000000000000000000000000000000000000020000"
+            },
+            "pre": {
+                "0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b": {
+                    "balance": "0x0de0b6b3a7640000",
+                    "code": "0x",
+                    "nonce": "0x00",
+                    "storage": {}
+                }
+            },
+            "transaction": {
+                "data": ["0x"],
+                "gasLimit": ["0x5208", "0x5207"],
+                "gasPrice": "0x0a",
+                "nonce": "0x00",
+                "secretKey": "0x45a915e4d060149eb4365960e6a7a45f334393093061116b197e3240065ff2d8",
+                "to": "0x1000000000000000000000000000000000000000",
+                "value": ["0x01"]
+            },
+            "post": {
+                "Cancun": [
+                    {
+                        "hash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                        "logs": "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347",
+                        "indexes": { "data": 0, "gas": 0, "value": 0 }
+                    },
+                    {
+                        "hash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                        "logs": "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347",
+                        "indexes": { "data": 0, "gas": 1, "value": 0 },
+                        "expectException": "TransactionException.INTRINSIC_GAS_TOO_LOW"
+                    }
+                ],
+                "Frontier": [
+                    {
+                        "hash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                        "logs": "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347",
+                        "indexes": { "data": 0, "gas": 0, "value": 0 }
+                    }
+                ]
+            }
+        }
+    }"#;
+
+    #[test]
+    fn reports_failures_by_fork_and_test() {
+        let tests: StateTestFile = serde_json::from_str(FIXTURE).unwrap();
+        let mut report = Report::default();
+        for (name, test) in &tests {
+            run_test(name, test, &mut report);
+        }
+
+        assert_eq!(report.skipped, 1);
+        assert_eq!(report.passed, 0);
+        let failures = &report.failures["Cancun"]["transfer"];
+        assert_eq!(failures.len(), 2);
+        assert!(failures
+            .iter()
+            .all(|f| f.reason.starts_with("state root mismatch")));
+        assert_eq!(failures[1].variant, "d0g1v0");
+        assert!(!report.is_success());
+    }
+
+    #[test]
+    fn calls_to_precompiles_are_unsupported() {
+        let mut tests: StateTestFile = serde_json::from_str(FIXTURE).unwrap();
+        let test = tests.get_mut("transfer").unwrap();
+        // BN254 addition, which is not implemented
+        test.transaction.to = Some(H160::from_low_u64_be(6));
+
+        let mut report = Report::default();
+        run_test("transfer", test, &mut report);
+        // the variant with too little gas never gets to the call
+        assert_eq!(report.unsupported, 1);
+        assert_eq!(report.failed(), 1);
+        assert!(report.to_string().contains("1 unsupported"));
+
+        // unsupported variants alone fail the run
+        let report = Report {
+            unsupported: 1,
+            ..Default::default()
+        };
+        assert!(!report.is_success());
+    }
+
+    // Sepolia's genesis state, as dumped by geth, under a transaction whose
+    // sender cannot pay for it. The expected root is the published state root
+    // of the genesis block.
+    #[test]
+    fn upstream_post_state_passes() {
+        let path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/sepolia_genesis_state.json");
+        let mut report = Report::default();
+        run_path(&path, &mut report);
+        assert_eq!(report.passed, 1, "{}", report);
+        assert!(report.is_success());
+        assert_eq!(
+            logs_hash(&[]),
+            "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347"
+                .parse()
+                .unwrap()
+        );
+    }
+}
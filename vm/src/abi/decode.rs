@@ -0,0 +1,299 @@
+use super::{sign_extend, AbiError, ParamType, Token};
+use primitive_types::{H160, U256};
+
+// Decodes the tuple of `types` at the start of `data`. Data after it is
+// ignored, as with return data.
+pub fn decode(types: &[ParamType], data: &[u8]) -> Result<Vec<Token>, AbiError> {
+    decode_tuple(types, data)
+}
+
+// `data` starts at the tuple, which offsets are relative to
+fn decode_tuple(types: &[ParamType], data: &[u8]) -> Result<Vec<Token>, AbiError> {
+    let mut head = 0;
+    let mut tokens = Vec::with_capacity(types.len());
+    for ty in types {
+        let token = if ty.is_dynamic() {
+            let offset = read_usize(data, head)?;
+            decode_token(ty, data.get(offset..).ok_or(AbiError::InvalidOffset)?)?
+        } else {
+            decode_token(ty, data.get(head..).ok_or(AbiError::InputTooShort)?)?
+        };
+        tokens.push(token);
+        head += ty.head_size();
+    }
+    Ok(tokens)
+}
+
+fn decode_token(ty: &ParamType, data: &[u8]) -> Result<Token, AbiError> {
+    let token = match ty {
+        ParamType::Address => {
+            let word = read_word(data, 0)?;
+            check_zeros(&word[..12])?;
+            Token::Address(H160::from_slice(&word[12..]))
+        }
+        ParamType::Bool => match read_uint(data, 0)? {
+            value if value.is_zero() => Token::Bool(false),
+            value if value == U256::one() => Token::Bool(true),
+            _ => return Err(AbiError::InvalidValue),
+        },
+        ParamType::Uint(bits) => {
+            let value = read_uint(data, 0)?;
+            if value.bits() > *bits {
+                return Err(AbiError::InvalidValue);
+            }
+            Token::Uint(value)
+        }
+        ParamType::Int(bits) => {
+            let value = read_uint(data, 0)?;
+            if sign_extend(value, *bits) != value {
+                return Err(AbiError::InvalidValue);
+            }
+            Token::Int(value)
+        }
+        ParamType::FixedBytes(len) => {
+            let word = read_word(data, 0)?;
+            check_zeros(&word[*len..])?;
+            Token::FixedBytes(word[..*len].to_vec())
+        }
+        ParamType::Function => decode_token(&ParamType::FixedBytes(24), data)?,
+        ParamType::Bytes => Token::Bytes(read_bytes(data)?),
+        ParamType::String => {
+            Token::String(String::from_utf8(read_bytes(data)?).map_err(|_| AbiError::InvalidUtf8)?)
+        }
+        ParamType::Array(ty) => {
+            check_element_size(ty)?;
+            let len = read_usize(data, 0)?;
+            // each element takes at least a word, which bounds the allocation
+            if len > data.len() / 32 {
+                return Err(AbiError::InvalidOffset);
+            }
+            Token::Array(decode_tuple(&vec![*ty.clone(); len], &data[32..])?)
+        }
+        ParamType::FixedArray(ty, len) => {
+            check_element_size(ty)?;
+            if len.saturating_mul(ty.head_size()) > data.len() {
+                return Err(AbiError::InputTooShort);
+            }
+            Token::FixedArray(decode_tuple(&vec![*ty.clone(); *len], data)?)
+        }
+        ParamType::Tuple(types) => Token::Tuple(decode_tuple(types, data)?),
+    };
+    Ok(token)
+}
+
+// Elements encoded in no bytes, such as `()` or `uint256[0]`, would let an
+// array have any length whatever the size of the data
+fn check_element_size(ty: &ParamType) -> Result<(), AbiError> {
+    match ty.head_size() {
+        0 => Err(AbiError::Unsupported("array of zero-sized elements")),
+        _ => Ok(()),
+    }
+}
+
+fn read_word(data: &[u8], offset: usize) -> Result<&[u8], AbiError> {
+    data.get(offset..offset + 32).ok_or(AbiError::InputTooShort)
+}
+
+fn read_uint(data: &[u8], offset: usize) -> Result<U256, AbiError> {
+    Ok(U256::from_big_endian(read_word(data, offset)?))
+}
+
+// An offset or a length, which cannot exceed the data anyway
+fn read_usize(data: &[u8], offset: usize) -> Result<usize, AbiError> {
+    let value = read_uint(data, offset)?;
+    if value > U256::from(u32::MAX) {
+        return Err(AbiError::InvalidOffset);
+    }
+    Ok(value.as_usize())
+}
+
+// Length followed by the padded bytes
+fn read_bytes(data: &[u8]) -> Result<Vec<u8>, AbiError> {
+    let len = read_usize(data, 0)?;
+    let padded = 32 + len.div_ceil(32) * 32;
+    let bytes = data.get(32..padded).ok_or(AbiError::InvalidOffset)?;
+    check_zeros(&bytes[len..])?;
+    Ok(bytes[..len].to_vec())
+}
+
+fn check_zeros(padding: &[u8]) -> Result<(), AbiError> {
+    match padding.iter().all(|&b| b == 0) {
+        true => Ok(()),
+        false => Err(AbiError::InvalidValue),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::encode;
+    use super::*;
+    use primitive_types::H256;
+    use proptest::prelude::*;
+
+    fn word(value: u64) -> Vec<u8> {
+        H256::from_low_u64_be(value).as_bytes().to_vec()
+    }
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let types = [
+            ParamType::Address,
+            ParamType::Int(24),
+            ParamType::Tuple(vec![
+                ParamType::String,
+                ParamType::FixedArray(Box::new(ParamType::Bool), 2),
+            ]),
+            ParamType::Array(Box::new(ParamType::FixedBytes(3))),
+            ParamType::FixedArray(Box::new(ParamType::Bytes), 2),
+            ParamType::Function,
+        ];
+        let tokens = vec![
+            Token::Address(H160::repeat_byte(0xaa)),
+            Token::Int(!U256::from(41)),
+            Token::Tuple(vec![
+                Token::String("ünïcode".into()),
+                Token::FixedArray(vec![Token::Bool(true), Token::Bool(false)]),
+            ]),
+            Token::Array(vec![Token::FixedBytes(vec![1, 2, 3])]),
+            Token::FixedArray(vec![Token::Bytes(vec![]), Token::Bytes(vec![0xff; 33])]),
+            Token::FixedBytes(vec![0xbb; 24]),
+        ];
+        let data = encode(&tokens);
+        assert_eq!(decode(&types, &data).unwrap(), tokens);
+        // return data may have trailing bytes
+        let mut longer = data.clone();
+        longer.extend([0; 32]);
+        assert_eq!(decode(&types, &longer).unwrap(), tokens);
+        assert_eq!(
+            decode(&types, &data[..data.len() - 1]),
+            Err(AbiError::InvalidOffset)
+        );
+    }
+
+    #[test]
+    fn decodes_dynamic_tokens() {
+        // (string, uint8[]) with "hi" and [1, 2]
+        let mut data = [word(0x40), word(0x80), word(2)].concat();
+        data.extend(b"hi");
+        data.resize(0x80, 0);
+        data.extend([word(2), word(1), word(2)].concat());
+        let types = [
+            ParamType::String,
+            ParamType::Array(Box::new(ParamType::Uint(8))),
+        ];
+        let tokens = vec![
+            Token::String("hi".into()),
+            Token::Array(vec![Token::Uint(U256::one()), Token::Uint(U256::from(2))]),
+        ];
+        assert_eq!(decode(&types, &data).unwrap(), tokens);
+        // return data may have trailing bytes
+        data.extend(word(0));
+        assert_eq!(decode(&types, &data).unwrap(), tokens);
+        // the array is cut after its length
+        assert_eq!(
+            decode(&types, &data[..0x80 + 32]),
+            Err(AbiError::InvalidOffset)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_data() {
+        let mut word = [0; 32];
+        word[31] = 2;
+        assert_eq!(
+            decode(&[ParamType::Bool], &word),
+            Err(AbiError::InvalidValue)
+        );
+        word[31] = 0xff;
+        assert_eq!(
+            decode(&[ParamType::Uint(8)], &word).unwrap(),
+            [Token::Uint(U256::from(255))]
+        );
+        // 255 is not an int8, but -1 is
+        assert_eq!(
+            decode(&[ParamType::Int(8)], &word),
+            Err(AbiError::InvalidValue)
+        );
+        assert_eq!(
+            decode(&[ParamType::Int(8)], &[0xff; 32]).unwrap(),
+            [Token::Int(U256::MAX)]
+        );
+        word[0] = 1;
+        assert_eq!(
+            decode(&[ParamType::Address], &word),
+            Err(AbiError::InvalidValue)
+        );
+        assert_eq!(
+            decode(&[ParamType::FixedBytes(1)], &word),
+            Err(AbiError::InvalidValue)
+        );
+        assert_eq!(
+            decode(&[ParamType::Uint(8)], &word[1..]),
+            Err(AbiError::InputTooShort)
+        );
+
+        // an array claiming more elements than the data holds
+        let mut array = vec![0; 64];
+        array[31] = 0x20;
+        array[62] = 0xff;
+        let ty = ParamType::Array(Box::new(ParamType::Uint(256)));
+        assert_eq!(decode(&[ty], &array), Err(AbiError::InvalidOffset));
+    }
+
+    #[test]
+    fn rejects_zero_sized_array_elements() {
+        let unsupported = Err(AbiError::Unsupported("array of zero-sized elements"));
+        let empty = ParamType::Tuple(vec![]);
+        let ty = ParamType::FixedArray(Box::new(empty.clone()), u32::MAX as usize);
+        assert_eq!(decode(&[ty], &[]), unsupported);
+
+        let empty_array = ParamType::FixedArray(Box::new(ParamType::Uint(256)), 0);
+        let ty = ParamType::FixedArray(Box::new(empty_array), usize::MAX);
+        assert_eq!(decode(&[ty], &[]), unsupported);
+
+        let ty = ParamType::Array(Box::new(empty));
+        assert_eq!(decode(&[ty], &[word(32), word(3)].concat()), unsupported);
+    }
+
+    fn token() -> impl Strategy<Value = (ParamType, Token)> {
+        let leaf = prop_oneof![
+            any::<[u8; 20]>().prop_map(|b| (ParamType::Address, Token::Address(H160(b)))),
+            any::<bool>().prop_map(|b| (ParamType::Bool, Token::Bool(b))),
+            any::<u64>().prop_map(|v| (ParamType::Uint(64), Token::Uint(U256::from(v)))),
+            any::<i32>().prop_map(|v| {
+                let value = sign_extend(U256::from(v as u32), 32);
+                (ParamType::Int(32), Token::Int(value))
+            }),
+            prop::collection::vec(any::<u8>(), 1..=32)
+                .prop_map(|b| (ParamType::FixedBytes(b.len()), Token::FixedBytes(b))),
+            any::<[u8; 24]>().prop_map(|b| (ParamType::Function, Token::FixedBytes(b.to_vec()))),
+            prop::collection::vec(any::<u8>(), 0..70)
+                .prop_map(|b| (ParamType::Bytes, Token::Bytes(b))),
+            ".{0,40}".prop_map(|s| (ParamType::String, Token::String(s))),
+        ];
+        leaf.prop_recursive(3, 24, 4, |inner| {
+            prop_oneof![
+                // arrays of empty tuples are rejected
+                prop::collection::vec(inner.clone(), 1..4).prop_map(|items| {
+                    let (types, tokens) = items.into_iter().unzip();
+                    (ParamType::Tuple(types), Token::Tuple(tokens))
+                }),
+                (inner, 0..3usize).prop_map(|((ty, token), len)| {
+                    (
+                        ParamType::FixedArray(Box::new(ty), len.max(1)),
+                        Token::FixedArray(vec![token; len.max(1)]),
+                    )
+                }),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn round_trips(items in prop::collection::vec(token(), 0..4)) {
+            let (types, tokens): (Vec<_>, Vec<_>) = items.into_iter().unzip();
+            let data = encode(&tokens);
+            prop_assert_eq!(decode(&types, &data).unwrap(), tokens);
+        }
+    }
+}
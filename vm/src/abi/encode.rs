@@ -0,0 +1,215 @@
+use super::{type_check, AbiError, ParamType, Token};
+use primitive_types::{H256, U256};
+
+// Encodes `tokens` as the tuple of a call's arguments or return values
+pub fn encode(tokens: &[Token]) -> Vec<u8> {
+    let mut out = Vec::new();
+    encode_tuple(tokens, &mut out);
+    out
+}
+
+// Heads in order, with the offsets of dynamic tokens pointing into the tails
+// that follow them
+fn encode_tuple(tokens: &[Token], out: &mut Vec<u8>) {
+    let encoded: Vec<Vec<u8>> = tokens
+        .iter()
+        .map(|token| {
+            let mut data = Vec::new();
+            encode_token(token, &mut data);
+            data
+        })
+        .collect();
+    let heads_size: usize = tokens
+        .iter()
+        .zip(&encoded)
+        .map(|(token, data)| if token.is_dynamic() { 32 } else { data.len() })
+        .sum();
+    let mut offset = heads_size;
+    for (token, data) in tokens.iter().zip(&encoded) {
+        if token.is_dynamic() {
+            out.extend_from_slice(&word(U256::from(offset)));
+            offset += data.len();
+        } else {
+            out.extend_from_slice(data);
+        }
+    }
+    for (token, data) in tokens.iter().zip(&encoded) {
+        if token.is_dynamic() {
+            out.extend_from_slice(data);
+        }
+    }
+}
+
+fn encode_token(token: &Token, out: &mut Vec<u8>) {
+    match token {
+        Token::Address(address) => out.extend_from_slice(H256::from(*address).as_bytes()),
+        Token::Bool(value) => out.extend_from_slice(&word(U256::from(*value as u8))),
+        Token::Uint(value) | Token::Int(value) => out.extend_from_slice(&word(*value)),
+        Token::FixedBytes(bytes) => pad_right(bytes, out),
+        Token::Bytes(bytes) => {
+            out.extend_from_slice(&word(U256::from(bytes.len())));
+            pad_right(bytes, out);
+        }
+        Token::String(s) => {
+            out.extend_from_slice(&word(U256::from(s.len())));
+            pad_right(s.as_bytes(), out);
+        }
+        Token::Array(tokens) => {
+            out.extend_from_slice(&word(U256::from(tokens.len())));
+            encode_tuple(tokens, out);
+        }
+        Token::FixedArray(tokens) | Token::Tuple(tokens) => encode_tuple(tokens, out),
+    }
+}
+
+fn word(value: U256) -> [u8; 32] {
+    let mut word = [0; 32];
+    value.to_big_endian(&mut word);
+    word
+}
+
+// `bytes` followed by zeros up to a multiple of 32 bytes
+fn pad_right(bytes: &[u8], out: &mut Vec<u8>) {
+    out.extend_from_slice(bytes);
+    out.resize(out.len() + (32 - bytes.len() % 32) % 32, 0);
+}
+
+// Non-standard packed encoding of `abi.encodePacked`: values take the size
+// of their type and dynamic ones have no length, while array elements are
+// still padded to 32 bytes
+pub fn encode_packed(types: &[ParamType], tokens: &[Token]) -> Result<Vec<u8>, AbiError> {
+    if !type_check(tokens, types) {
+        return Err(AbiError::TypeMismatch);
+    }
+    let mut out = Vec::new();
+    for (token, ty) in tokens.iter().zip(types) {
+        encode_packed_token(token, ty, &mut out)?;
+    }
+    Ok(out)
+}
+
+fn encode_packed_token(token: &Token, ty: &ParamType, out: &mut Vec<u8>) -> Result<(), AbiError> {
+    match (token, ty) {
+        (Token::Address(address), _) => out.extend_from_slice(address.as_bytes()),
+        (Token::Bool(value), _) => out.push(*value as u8),
+        (Token::Uint(value) | Token::Int(value), ParamType::Uint(bits) | ParamType::Int(bits)) => {
+            out.extend_from_slice(&word(*value)[32 - bits / 8..])
+        }
+        (Token::FixedBytes(bytes) | Token::Bytes(bytes), _) => out.extend_from_slice(bytes),
+        (Token::String(s), _) => out.extend_from_slice(s.as_bytes()),
+        (
+            Token::Array(tokens) | Token::FixedArray(tokens),
+            ParamType::Array(ty) | ParamType::FixedArray(ty, _),
+        ) => {
+            if ty.is_dynamic() {
+                return Err(AbiError::Unsupported("packed array of dynamic values"));
+            }
+            for token in tokens {
+                encode_token(token, out);
+            }
+        }
+        (Token::Tuple(_), _) => return Err(AbiError::Unsupported("packed tuple")),
+        _ => return Err(AbiError::TypeMismatch),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use primitive_types::H160;
+
+    #[test]
+    fn encodes_static_and_dynamic_tokens() {
+        // f(uint256,uint32[],bytes10,bytes) from the Solidity ABI spec
+        let data = encode(&[
+            Token::Uint(U256::from(0x123)),
+            Token::Array(vec![
+                Token::Uint(U256::from(0x456)),
+                Token::Uint(U256::from(0x789)),
+            ]),
+            Token::FixedBytes(b"1234567890".to_vec()),
+            Token::Bytes(b"Hello, world!".to_vec()),
+        ]);
+        let expected = "\
+            0000000000000000000000000000000000000000000000000000000000000123\
+            0000000000000000000000000000000000000000000000000000000000000080\
+            3132333435363738393000000000000000000000000000000000000000000000\
+            00000000000000000000000000000000000000000000000000000000000000e0\
+            0000000000000000000000000000000000000000000000000000000000000002\
+            0000000000000000000000000000000000000000000000000000000000000456\
+            0000000000000000000000000000000000000000000000000000000000000789\
+            000000000000000000000000000000000000000000000000000000000000000d\
+            48656c6c6f2c20776f726c642100000000000000000000000000000000000000";
+        assert_eq!(hex::encode(data), expected);
+    }
+
+    #[test]
+    fn encodes_nested_dynamic_arrays() {
+        // g(uint256[][],string[]) with [[1, 2], [3]] and ["one", "two", "three"]
+        let uints = |values: &[u64]| {
+            Token::Array(values.iter().map(|&v| Token::Uint(U256::from(v))).collect())
+        };
+        let data = encode(&[
+            Token::Array(vec![uints(&[1, 2]), uints(&[3])]),
+            Token::Array(
+                ["one", "two", "three"]
+                    .iter()
+                    .map(|s| Token::String(s.to_string()))
+                    .collect(),
+            ),
+        ]);
+        let words: Vec<String> = data.chunks(32).map(hex::encode).collect();
+        let offsets = [
+            0x40, 0x140, 2, 0x40, 0xa0, 2, 1, 2, 1, 3, 3, 0x60, 0xa0, 0xe0,
+        ];
+        for (word, offset) in words.iter().zip(offsets) {
+            assert_eq!(*word, format!("{:064x}", offset));
+        }
+        assert_eq!(words.len(), 20);
+        assert_eq!(words[14], format!("{:064x}", 3));
+        assert!(words[15].starts_with(&hex::encode("one")));
+        assert!(words[19].starts_with(&hex::encode("three")));
+    }
+
+    #[test]
+    fn encodes_packed() {
+        // abi.encodePacked(int16(-1), bytes1(0x42), uint16(0x03), "Hello, world!")
+        let data = encode_packed(
+            &[
+                ParamType::Int(16),
+                ParamType::FixedBytes(1),
+                ParamType::Uint(16),
+                ParamType::String,
+            ],
+            &[
+                Token::Int(!U256::zero()),
+                Token::FixedBytes(vec![0x42]),
+                Token::Uint(U256::from(3)),
+                Token::String("Hello, world!".into()),
+            ],
+        )
+        .unwrap();
+        assert_eq!(hex::encode(data), "ffff42000348656c6c6f2c20776f726c6421");
+
+        let data = encode_packed(
+            &[
+                ParamType::Address,
+                ParamType::Bool,
+                ParamType::Array(Box::new(ParamType::Uint(8))),
+            ],
+            &[
+                Token::Address(H160::repeat_byte(0x11)),
+                Token::Bool(true),
+                Token::Array(vec![Token::Uint(U256::from(7))]),
+            ],
+        )
+        .unwrap();
+        assert_eq!(data.len(), 20 + 1 + 32);
+        assert_eq!(data[20], 1);
+        assert_eq!(data[52], 7);
+
+        let result = encode_packed(&[ParamType::Uint(8)], &[Token::Uint(U256::from(256))]);
+        assert_eq!(result, Err(AbiError::TypeMismatch));
+    }
+}
@@ -0,0 +1,146 @@
+// JSON ABI, as output by solc with `--abi` and found in build artifacts
+
+use super::{AbiError, Function, ParamType};
+use serde::Deserialize;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Abi {
+    pub functions: Vec<Function>,
+    pub errors: Vec<Function>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Entry {
+    #[serde(rename = "type", default = "function")]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    inputs: Vec<Param>,
+    #[serde(default)]
+    outputs: Vec<Param>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Param {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    components: Vec<Param>,
+}
+
+// solc omits the type of functions in older ABIs
+fn function() -> String {
+    "function".into()
+}
+
+impl Param {
+    // tuples are described by their components, e.g. `tuple[]`
+    fn param_type(&self) -> Result<ParamType, AbiError> {
+        match self.kind.strip_prefix("tuple") {
+            Some(suffix) => {
+                let tuple = ParamType::Tuple(params(&self.components)?);
+                format!("{}{}", tuple, suffix).parse()
+            }
+            None => self.kind.parse(),
+        }
+    }
+}
+
+fn params(params: &[Param]) -> Result<Vec<ParamType>, AbiError> {
+    params.iter().map(Param::param_type).collect()
+}
+
+impl Abi {
+    // Functions and custom errors of the ABI; constructors, events, fallback
+    // and receive functions are skipped
+    pub fn from_json(json: &str) -> Result<Self, AbiError> {
+        let entries: Vec<Entry> =
+            serde_json::from_str(json).map_err(|e| AbiError::InvalidJson(e.to_string()))?;
+        let mut abi = Abi::default();
+        for entry in entries {
+            let item = Function {
+                inputs: params(&entry.inputs)?,
+                outputs: params(&entry.outputs)?,
+                name: entry.name,
+            };
+            match entry.kind.as_str() {
+                "function" => abi.functions.push(item),
+                "error" => abi.errors.push(item),
+                _ => {}
+            }
+        }
+        Ok(abi)
+    }
+
+    // Function by name, or by signature for overloaded functions
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        let mut matches = self.functions.iter().filter(|f| {
+            if name.contains('(') {
+                f.signature() == name
+            } else {
+                f.name == name
+            }
+        });
+        let function = matches.next()?;
+        // an overloaded name is ambiguous
+        matches.next().is_none().then_some(function)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ABI: &str = r#"[
+        {"type": "constructor", "inputs": [{"name": "supply", "type": "uint256"}]},
+        {"type": "function", "name": "balanceOf", "stateMutability": "view",
+         "inputs": [{"name": "owner", "type": "address"}],
+         "outputs": [{"name": "", "type": "uint256"}]},
+        {"type": "function", "name": "transfer",
+         "inputs": [{"name": "to", "type": "address"}, {"name": "amount", "type": "uint256"}],
+         "outputs": [{"name": "", "type": "bool"}]},
+        {"type": "function", "name": "transfer",
+         "inputs": [{"name": "to", "type": "address"}], "outputs": []},
+        {"type": "event", "name": "Transfer", "anonymous": false, "inputs": []},
+        {"type": "error", "name": "BadOrders", "inputs": [
+            {"name": "orders", "type": "tuple[2][]", "components": [
+                {"name": "id", "type": "uint256"}, {"name": "owner", "type": "address"}
+            ]},
+            {"name": "limit", "type": "uint8"}
+        ]},
+        {"name": "legacy", "inputs": [], "outputs": []}
+    ]"#;
+
+    #[test]
+    fn reads_functions_and_errors() {
+        let abi = Abi::from_json(ABI).unwrap();
+        let names: Vec<_> = abi.functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["balanceOf", "transfer", "transfer", "legacy"]);
+        assert_eq!(abi.functions[0].outputs, [ParamType::Uint(256)]);
+        assert_eq!(abi.functions[1].signature(), "transfer(address,uint256)");
+        assert_eq!(
+            abi.function("balanceOf").unwrap().outputs,
+            [ParamType::Uint(256)]
+        );
+        assert_eq!(abi.function("transfer"), None);
+        let transfer = abi.function("transfer(address,uint256)").unwrap();
+        assert_eq!(transfer.outputs, [ParamType::Bool]);
+        assert_eq!(abi.function("missing"), None);
+
+        assert_eq!(abi.errors.len(), 1);
+        assert_eq!(
+            abi.errors[0].signature(),
+            "BadOrders((uint256,address)[2][],uint8)"
+        );
+        assert!(matches!(
+            Abi::from_json("{}"),
+            Err(AbiError::InvalidJson(_))
+        ));
+        let invalid = r#"[{"type": "function", "name": "f", "inputs": [{"type": "uint7"}]}]"#;
+        assert!(matches!(
+            Abi::from_json(invalid),
+            Err(AbiError::InvalidType(_))
+        ));
+    }
+}
@@ -0,0 +1,271 @@
+// Solidity contract ABI encoding
+//
+// Values are `Token`s typed by `ParamType`s. Integers are held as U256,
+// signed ones in two's complement. Decoding is strict: padding must be zero
+// and values must fit their type, as checked by the Solidity decoder.
+
+mod decode;
+mod encode;
+mod json;
+mod parse;
+mod signature;
+
+pub use decode::decode;
+pub use encode::{encode, encode_packed};
+pub use json::Abi;
+pub use signature::Function;
+
+use crate::crypto::keccak256;
+use primitive_types::{H160, U256};
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AbiError {
+    InvalidType(String),
+    InvalidSignature(String),
+    InvalidJson(String),
+    // a token that does not match its type
+    TypeMismatch,
+    InputTooShort,
+    // an offset or length past the data
+    InvalidOffset,
+    // non-zero padding, or a value that does not fit its type
+    InvalidValue,
+    InvalidUtf8,
+    SelectorMismatch,
+    Unsupported(&'static str),
+    // a value written by hand that does not parse as its type
+    InvalidArgument(String),
+}
+
+impl fmt::Display for AbiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AbiError::InvalidType(ty) => write!(f, "invalid type {:?}", ty),
+            AbiError::InvalidSignature(s) => write!(f, "invalid signature {:?}", s),
+            AbiError::InvalidJson(e) => write!(f, "invalid JSON ABI: {}", e),
+            AbiError::TypeMismatch => write!(f, "value does not match its type"),
+            AbiError::InputTooShort => write!(f, "input too short"),
+            AbiError::InvalidOffset => write!(f, "offset or length out of bounds"),
+            AbiError::InvalidValue => write!(f, "value does not fit its type"),
+            AbiError::InvalidUtf8 => write!(f, "string is not UTF-8"),
+            AbiError::SelectorMismatch => write!(f, "selector mismatch"),
+            AbiError::Unsupported(what) => write!(f, "unsupported {}", what),
+            AbiError::InvalidArgument(e) => write!(f, "invalid argument: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for AbiError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParamType {
+    Address,
+    Bool,
+    // bit size, a multiple of 8 up to 256
+    Uint(usize),
+    Int(usize),
+    // byte size, from 1 to 32
+    FixedBytes(usize),
+    // an address followed by a selector, held and encoded as bytes24
+    Function,
+    Bytes,
+    String,
+    Array(Box<ParamType>),
+    FixedArray(Box<ParamType>, usize),
+    Tuple(Vec<ParamType>),
+}
+
+impl ParamType {
+    // Dynamic types are encoded after the heads of the tuple holding them
+    pub fn is_dynamic(&self) -> bool {
+        match self {
+            ParamType::Bytes | ParamType::String | ParamType::Array(_) => true,
+            ParamType::FixedArray(ty, _) => ty.is_dynamic(),
+            ParamType::Tuple(types) => types.iter().any(ParamType::is_dynamic),
+            _ => false,
+        }
+    }
+
+    // Size of the encoding of a static type
+    fn head_size(&self) -> usize {
+        match self {
+            ParamType::FixedArray(ty, len) if !self.is_dynamic() => {
+                ty.head_size().saturating_mul(*len)
+            }
+            ParamType::Tuple(types) if !self.is_dynamic() => types
+                .iter()
+                .map(ParamType::head_size)
+                .fold(0, usize::saturating_add),
+            _ => 32,
+        }
+    }
+}
+
+// Canonical form, as used in signatures
+impl fmt::Display for ParamType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParamType::Address => write!(f, "address"),
+            ParamType::Bool => write!(f, "bool"),
+            ParamType::Uint(bits) => write!(f, "uint{}", bits),
+            ParamType::Int(bits) => write!(f, "int{}", bits),
+            ParamType::FixedBytes(len) => write!(f, "bytes{}", len),
+            ParamType::Function => write!(f, "function"),
+            ParamType::Bytes => write!(f, "bytes"),
+            ParamType::String => write!(f, "string"),
+            ParamType::Array(ty) => write!(f, "{}[]", ty),
+            ParamType::FixedArray(ty, len) => write!(f, "{}[{}]", ty, len),
+            ParamType::Tuple(types) => {
+                let types: Vec<String> = types.iter().map(ParamType::to_string).collect();
+                write!(f, "({})", types.join(","))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    Address(H160),
+    Bool(bool),
+    Uint(U256),
+    // two's complement
+    Int(U256),
+    FixedBytes(Vec<u8>),
+    Bytes(Vec<u8>),
+    String(String),
+    Array(Vec<Token>),
+    FixedArray(Vec<Token>),
+    Tuple(Vec<Token>),
+}
+
+impl Token {
+    pub fn type_check(&self, ty: &ParamType) -> bool {
+        match (self, ty) {
+            (Token::Address(_), ParamType::Address)
+            | (Token::Bool(_), ParamType::Bool)
+            | (Token::Bytes(_), ParamType::Bytes)
+            | (Token::String(_), ParamType::String) => true,
+            (Token::Uint(value), ParamType::Uint(bits)) => value.bits() <= *bits,
+            (Token::Int(value), ParamType::Int(bits)) => sign_extend(*value, *bits) == *value,
+            (Token::FixedBytes(bytes), ParamType::FixedBytes(len)) => bytes.len() == *len,
+            (Token::FixedBytes(bytes), ParamType::Function) => bytes.len() == 24,
+            (Token::Array(tokens), ParamType::Array(ty)) => {
+                tokens.iter().all(|token| token.type_check(ty))
+            }
+            (Token::FixedArray(tokens), ParamType::FixedArray(ty, len)) => {
+                tokens.len() == *len && tokens.iter().all(|token| token.type_check(ty))
+            }
+            (Token::Tuple(tokens), ParamType::Tuple(types)) => type_check(tokens, types),
+            _ => false,
+        }
+    }
+
+    fn is_dynamic(&self) -> bool {
+        match self {
+            Token::Bytes(_) | Token::String(_) | Token::Array(_) => true,
+            Token::FixedArray(tokens) | Token::Tuple(tokens) => {
+                tokens.iter().any(Token::is_dynamic)
+            }
+            _ => false,
+        }
+    }
+}
+
+pub fn type_check(tokens: &[Token], types: &[ParamType]) -> bool {
+    tokens.len() == types.len() && tokens.iter().zip(types).all(|(t, ty)| t.type_check(ty))
+}
+
+// Integers in decimal, signed ones with a minus sign, and bytes in hex
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Address(address) => write!(f, "{:?}", address),
+            Token::Bool(value) => write!(f, "{}", value),
+            Token::Uint(value) => write!(f, "{}", value),
+            Token::Int(value) if value.bit(255) => {
+                write!(f, "-{}", (!*value).overflowing_add(U256::one()).0)
+            }
+            Token::Int(value) => write!(f, "{}", value),
+            Token::FixedBytes(bytes) | Token::Bytes(bytes) => write!(f, "0x{}", hex::encode(bytes)),
+            Token::String(s) => write!(f, "{:?}", s),
+            Token::Array(tokens) | Token::FixedArray(tokens) => write!(f, "[{}]", join(tokens)),
+            Token::Tuple(tokens) => write!(f, "({})", join(tokens)),
+        }
+    }
+}
+
+fn join<T: fmt::Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(T::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// `value` with the bits above `bits` copied from its sign bit
+fn sign_extend(value: U256, bits: usize) -> U256 {
+    if bits >= 256 {
+        return value;
+    }
+    let mask = (U256::one() << bits) - 1;
+    let low = value & mask;
+    if low.bit(bits - 1) {
+        low | !mask
+    } else {
+        low
+    }
+}
+
+// First 4 bytes of the keccak256 of a signature such as
+// `transfer(address,uint256)`
+pub fn selector(signature: &str) -> [u8; 4] {
+    let hash = keccak256(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn displays_types_and_tokens() {
+        let ty = ParamType::Array(Box::new(ParamType::Tuple(vec![
+            ParamType::Uint(256),
+            ParamType::FixedArray(Box::new(ParamType::FixedBytes(4)), 2),
+        ])));
+        assert_eq!(ty.to_string(), "(uint256,bytes4[2])[]");
+        assert!(ty.is_dynamic());
+        assert_eq!(
+            ParamType::FixedArray(Box::new(ParamType::Int(8)), 3).head_size(),
+            96
+        );
+
+        let token = Token::Tuple(vec![
+            Token::Int(!U256::zero()),
+            Token::Array(vec![Token::Bool(true), Token::Bool(false)]),
+            Token::String("hi".into()),
+            Token::FixedBytes(vec![0xca, 0xfe]),
+        ]);
+        assert_eq!(token.to_string(), r#"(-1, [true, false], "hi", 0xcafe)"#);
+        assert_eq!(
+            selector("transfer(address,uint256)"),
+            [0xa9, 0x05, 0x9c, 0xbb]
+        );
+    }
+
+    #[test]
+    fn checks_tokens_against_types() {
+        assert!(Token::Uint(U256::from(255)).type_check(&ParamType::Uint(8)));
+        assert!(!Token::Uint(U256::from(256)).type_check(&ParamType::Uint(8)));
+        // -128 and 127 fit an int8, 128 does not
+        let minus_128 = !U256::from(127);
+        assert!(Token::Int(minus_128).type_check(&ParamType::Int(8)));
+        assert!(Token::Int(U256::from(127)).type_check(&ParamType::Int(8)));
+        assert!(!Token::Int(U256::from(128)).type_check(&ParamType::Int(8)));
+        assert!(!Token::FixedBytes(vec![0; 3]).type_check(&ParamType::FixedBytes(4)));
+        let pair = ParamType::FixedArray(Box::new(ParamType::Address), 2);
+        assert!(!Token::FixedArray(vec![Token::Address(H160::zero())]).type_check(&pair));
+        assert!(!Token::Bool(true).type_check(&ParamType::Uint(8)));
+    }
+}
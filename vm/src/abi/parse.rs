@@ -0,0 +1,206 @@
+// Values written by hand, e.g. command line arguments: integers in decimal
+// or 0x-prefixed hex, bytes in hex, arrays as `[1, 2]` and tuples as
+// `(0xab.., "text")`
+
+use super::{AbiError, ParamType, Token};
+use primitive_types::{H160, U256};
+
+impl Token {
+    pub fn parse(ty: &ParamType, s: &str) -> Result<Token, AbiError> {
+        let invalid = || AbiError::InvalidArgument(format!("{:?} is not a {}", s, ty));
+        let value = s.trim();
+        let token = match ty {
+            ParamType::Address => {
+                let hex = value.strip_prefix("0x").unwrap_or(value);
+                match hex::decode(hex) {
+                    Ok(bytes) if bytes.len() == 20 => Token::Address(H160::from_slice(&bytes)),
+                    _ => return Err(invalid()),
+                }
+            }
+            ParamType::Bool => match value {
+                "true" => Token::Bool(true),
+                "false" => Token::Bool(false),
+                _ => return Err(invalid()),
+            },
+            ParamType::Uint(_) => Token::Uint(parse_uint(value).ok_or_else(invalid)?),
+            ParamType::Int(bits) => match value.strip_prefix('-') {
+                Some(abs) => {
+                    let abs = parse_uint(abs).ok_or_else(invalid)?;
+                    // larger magnitudes would wrap around to a valid value
+                    if abs > U256::one() << (bits - 1) {
+                        return Err(invalid());
+                    }
+                    Token::Int((!abs).overflowing_add(U256::one()).0)
+                }
+                None => {
+                    let value = parse_uint(value).ok_or_else(invalid)?;
+                    // positive values above the int256 range would wrap around
+                    if value.bit(255) {
+                        return Err(invalid());
+                    }
+                    Token::Int(value)
+                }
+            },
+            ParamType::FixedBytes(len) => match parse_hex(value) {
+                Some(bytes) if bytes.len() == *len => Token::FixedBytes(bytes),
+                _ => return Err(invalid()),
+            },
+            // the length is checked with the type below
+            ParamType::Function => Token::FixedBytes(parse_hex(value).ok_or_else(invalid)?),
+            ParamType::Bytes => Token::Bytes(parse_hex(value).ok_or_else(invalid)?),
+            ParamType::String => Token::String(unquote(value).to_string()),
+            ParamType::Array(ty) => {
+                let items = split_list(value, '[', ']').ok_or_else(invalid)?;
+                Token::Array(parse_all(ty, &items)?)
+            }
+            ParamType::FixedArray(ty, len) => {
+                let items = split_list(value, '[', ']').ok_or_else(invalid)?;
+                if items.len() != *len {
+                    return Err(invalid());
+                }
+                Token::FixedArray(parse_all(ty, &items)?)
+            }
+            ParamType::Tuple(types) => {
+                let items = split_list(value, '(', ')').ok_or_else(invalid)?;
+                if items.len() != types.len() {
+                    return Err(invalid());
+                }
+                let tokens = types
+                    .iter()
+                    .zip(&items)
+                    .map(|(ty, item)| Token::parse(ty, item))
+                    .collect::<Result<_, _>>()?;
+                Token::Tuple(tokens)
+            }
+        };
+        match token.type_check(ty) {
+            true => Ok(token),
+            false => Err(invalid()),
+        }
+    }
+}
+
+fn parse_all(ty: &ParamType, items: &[&str]) -> Result<Vec<Token>, AbiError> {
+    items.iter().map(|item| Token::parse(ty, item)).collect()
+}
+
+fn parse_uint(s: &str) -> Option<U256> {
+    match s.strip_prefix("0x") {
+        Some(hex) if !hex.is_empty() => U256::from_str_radix(hex, 16).ok(),
+        Some(_) => None,
+        None if s.is_empty() => None,
+        None => U256::from_dec_str(s).ok(),
+    }
+}
+
+fn parse_hex(s: &str) -> Option<Vec<u8>> {
+    hex::decode(s.strip_prefix("0x").unwrap_or(s)).ok()
+}
+
+// Strings in arrays and tuples may be quoted to hold commas
+fn unquote(s: &str) -> &str {
+    s.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(s)
+}
+
+// Items of `[a, b]` or `(a, b)`, split at the commas outside of nested
+// lists and quotes
+fn split_list(s: &str, open: char, close: char) -> Option<Vec<&str>> {
+    let inner = s.strip_prefix(open)?.strip_suffix(close)?;
+    if inner.trim().is_empty() {
+        return Some(Vec::new());
+    }
+    let mut items = Vec::new();
+    let (mut depth, mut quoted, mut start) = (0, false, 0);
+    for (i, c) in inner.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '[' | '(' if !quoted => depth += 1,
+            ']' | ')' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 => {
+                items.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(inner[start..].trim());
+    Some(items)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(ty: &str, s: &str) -> Result<Token, AbiError> {
+        Token::parse(&ty.parse().unwrap(), s)
+    }
+
+    #[test]
+    fn parses_values() {
+        let address = "0x00000000000000000000000000000000000000ab";
+        assert_eq!(
+            parse("address", address),
+            Ok(Token::Address(H160::from_low_u64_be(0xab)))
+        );
+        assert_eq!(parse("uint8", "255"), Ok(Token::Uint(U256::from(255))));
+        assert_eq!(parse("uint16", "0x100"), Ok(Token::Uint(U256::from(256))));
+        assert_eq!(parse("int8", "-128").unwrap().to_string(), "-128");
+        assert_eq!(parse("bool", "true"), Ok(Token::Bool(true)));
+        assert_eq!(
+            parse("bytes2", "0xcafe"),
+            Ok(Token::FixedBytes(vec![0xca, 0xfe]))
+        );
+        assert_eq!(parse("bytes", ""), Ok(Token::Bytes(vec![])));
+        assert_eq!(
+            parse("function", &format!("0x{}", "ab".repeat(24))),
+            Ok(Token::FixedBytes(vec![0xab; 24]))
+        );
+        assert_eq!(parse("string", "a, b"), Ok(Token::String("a, b".into())));
+
+        let token = parse(
+            "(uint256[],string[2],bool)",
+            r#"([1, 0x2], ["a, b", c], false)"#,
+        )
+        .unwrap();
+        assert_eq!(token.to_string(), r#"([1, 2], ["a, b", "c"], false)"#);
+        assert_eq!(
+            parse("uint8[][]", "[[], [3]]").unwrap().to_string(),
+            "[[], [3]]"
+        );
+
+        for (ty, invalid) in [
+            ("uint8", "256"),
+            ("uint8", "-1"),
+            ("uint256", "0x"),
+            ("uint256", ""),
+            ("int256", "-"),
+            ("int8", "128"),
+            ("int8", "-129"),
+            (
+                "int256",
+                "-115792089237316195423570985008687907853269984665640564039457584007913129639935",
+            ),
+            ("int8", "-0x101"),
+            (
+                "int256",
+                "0x8000000000000000000000000000000000000000000000000000000000000000",
+            ),
+            ("address", "0xab"),
+            ("function", address),
+            ("bool", "1"),
+            ("bytes2", "0xca"),
+            ("uint8[2]", "[1]"),
+            ("uint8[]", "1, 2"),
+            ("(bool,bool)", "(true)"),
+        ] {
+            assert!(
+                matches!(parse(ty, invalid), Err(AbiError::InvalidArgument(_))),
+                "{} {}",
+                ty,
+                invalid
+            );
+        }
+    }
+}
@@ -0,0 +1,336 @@
+// Human-readable signatures, from the canonical `transfer(address,uint256)`
+// to `function balanceOf(address owner) external view returns (uint256)`
+
+use super::{decode, encode, selector, type_check, AbiError, ParamType, Token};
+use std::str::FromStr;
+
+// A function, or a custom error, which has no outputs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Function {
+    pub name: String,
+    pub inputs: Vec<ParamType>,
+    pub outputs: Vec<ParamType>,
+}
+
+impl Function {
+    // Outputs follow the inputs in parentheses, optionally after `returns`,
+    // as in `balanceOf(address)(uint256)`. Parameter names, data locations
+    // and modifiers are skipped.
+    pub fn parse(s: &str) -> Result<Self, AbiError> {
+        let invalid = || AbiError::InvalidSignature(s.to_string());
+        let trimmed = s.trim();
+        let trimmed = ["function ", "error "]
+            .iter()
+            .find_map(|keyword| trimmed.strip_prefix(keyword))
+            .unwrap_or(trimmed)
+            .trim_start();
+        let open = trimmed.find('(').ok_or_else(invalid)?;
+        let name = trimmed[..open].trim();
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+        {
+            return Err(invalid());
+        }
+        let close = open + closing_paren(&trimmed[open..]).ok_or_else(invalid)?;
+        let inputs = parse_params(&trimmed[open + 1..close])?;
+
+        let rest = trimmed[close + 1..].trim();
+        let outputs = match rest.find('(') {
+            Some(start) => {
+                let modifiers = &rest[..start];
+                if !modifiers.is_empty() && !modifiers.trim_end().ends_with("returns") {
+                    return Err(invalid());
+                }
+                let end = start + closing_paren(&rest[start..]).ok_or_else(invalid)?;
+                if !rest[end + 1..].trim().is_empty() {
+                    return Err(invalid());
+                }
+                parse_params(&rest[start + 1..end])?
+            }
+            None => Vec::new(),
+        };
+        Ok(Function {
+            name: name.to_string(),
+            inputs,
+            outputs,
+        })
+    }
+
+    // Canonical signature, which the selector is the hash of
+    pub fn signature(&self) -> String {
+        let inputs: Vec<String> = self.inputs.iter().map(ParamType::to_string).collect();
+        format!("{}({})", self.name, inputs.join(","))
+    }
+
+    pub fn selector(&self) -> [u8; 4] {
+        selector(&self.signature())
+    }
+
+    // Calldata of a call with `args`
+    pub fn encode_input(&self, args: &[Token]) -> Result<Vec<u8>, AbiError> {
+        if !type_check(args, &self.inputs) {
+            return Err(AbiError::TypeMismatch);
+        }
+        let mut data = self.selector().to_vec();
+        data.extend(encode(args));
+        Ok(data)
+    }
+
+    // Arguments of calldata, or of the revert data of a custom error
+    pub fn decode_input(&self, data: &[u8]) -> Result<Vec<Token>, AbiError> {
+        let args = data
+            .strip_prefix(&self.selector())
+            .ok_or(AbiError::SelectorMismatch)?;
+        decode(&self.inputs, args)
+    }
+
+    pub fn decode_output(&self, data: &[u8]) -> Result<Vec<Token>, AbiError> {
+        decode(&self.outputs, data)
+    }
+}
+
+impl FromStr for ParamType {
+    type Err = AbiError;
+
+    // Canonical types, and `tuple(...)` as spelled by some tools
+    fn from_str(s: &str) -> Result<Self, AbiError> {
+        let (ty, rest) = parse_type(s.trim())?;
+        if !rest.is_empty() {
+            return Err(AbiError::InvalidType(s.to_string()));
+        }
+        Ok(ty)
+    }
+}
+
+// Comma separated parameters, each a type followed by optional names
+fn parse_params(s: &str) -> Result<Vec<ParamType>, AbiError> {
+    if s.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    split_params(s)
+        .into_iter()
+        .map(|param| {
+            let (ty, rest) = parse_type(param.trim())?;
+            // e.g. `uint256 amount`, `bytes calldata data`
+            match rest.is_empty() || rest.starts_with(char::is_whitespace) {
+                true => Ok(ty),
+                false => Err(AbiError::InvalidType(param.to_string())),
+            }
+        })
+        .collect()
+}
+
+// Splits at the commas outside of parentheses
+fn split_params(s: &str) -> Vec<&str> {
+    let mut params = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                params.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    params.push(&s[start..]);
+    params
+}
+
+// Byte index of the parenthesis closing the one `s` starts with
+fn closing_paren(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 1 => return Some(i),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+// Parses the type at the start of `s`, with its array suffixes, and returns
+// what follows it
+fn parse_type(s: &str) -> Result<(ParamType, &str), AbiError> {
+    let invalid = || AbiError::InvalidType(s.to_string());
+    let tuple = match s.strip_prefix("tuple") {
+        Some(rest) if rest.starts_with('(') => Some(rest),
+        _ if s.starts_with('(') => Some(s),
+        _ => None,
+    };
+    let (mut ty, mut rest) = match tuple {
+        Some(tuple) => {
+            let close = closing_paren(tuple).ok_or_else(invalid)?;
+            let types = parse_params(&tuple[1..close])?;
+            (ParamType::Tuple(types), &tuple[close + 1..])
+        }
+        None => {
+            let end = s
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or(s.len());
+            (elementary(&s[..end]).ok_or_else(invalid)?, &s[end..])
+        }
+    };
+    while let Some(suffix) = rest.strip_prefix('[') {
+        let close = suffix.find(']').ok_or_else(invalid)?;
+        ty = match &suffix[..close] {
+            "" => ParamType::Array(Box::new(ty)),
+            len => match len.parse() {
+                Ok(len) if len > 0 => ParamType::FixedArray(Box::new(ty), len),
+                _ => return Err(invalid()),
+            },
+        };
+        rest = &suffix[close + 1..];
+    }
+    Ok((ty, rest))
+}
+
+fn elementary(s: &str) -> Option<ParamType> {
+    let size = |digits: &str, default| match digits {
+        "" => Some(default),
+        // no leading zeros, as in `uint08`
+        digits if digits.starts_with('0') => None,
+        digits => digits.parse().ok(),
+    };
+    let ty = match s {
+        "address" => ParamType::Address,
+        "bool" => ParamType::Bool,
+        "string" => ParamType::String,
+        "bytes" => ParamType::Bytes,
+        "function" => ParamType::Function,
+        _ => {
+            if let Some(bits) = s.strip_prefix("uint") {
+                ParamType::Uint(size(bits, 256).filter(|&b| valid_bits(b))?)
+            } else if let Some(bits) = s.strip_prefix("int") {
+                ParamType::Int(size(bits, 256).filter(|&b| valid_bits(b))?)
+            } else {
+                let len = size(s.strip_prefix("bytes")?, 0)?;
+                ParamType::FixedBytes(Some(len).filter(|len| (1..=32).contains(len))?)
+            }
+        }
+    };
+    Some(ty)
+}
+
+fn valid_bits(bits: usize) -> bool {
+    bits > 0 && bits <= 256 && bits.is_multiple_of(8)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use primitive_types::{H160, H256, U256};
+
+    #[test]
+    fn parses_types() {
+        let ty: ParamType = "(uint,bytes32[2],(int8,string)[])[3][]".parse().unwrap();
+        assert_eq!(ty.to_string(), "(uint256,bytes32[2],(int8,string)[])[3][]");
+        assert_eq!(
+            "tuple(address,bool)".parse(),
+            Ok(ParamType::Tuple(vec![ParamType::Address, ParamType::Bool]))
+        );
+        assert_eq!("function".parse(), Ok(ParamType::Function));
+        assert_eq!(
+            "function[2]".parse::<ParamType>().unwrap().to_string(),
+            "function[2]"
+        );
+        for invalid in [
+            "uint7", "uint264", "int08", "bytes0", "bytes33", "foo", "uint[0]", "(bool", "bool]",
+        ] {
+            assert!(invalid.parse::<ParamType>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn decodes_inputs() {
+        let transfer = Function {
+            name: "transfer".into(),
+            inputs: vec![ParamType::Address, ParamType::Uint(256)],
+            outputs: Vec::new(),
+        };
+        assert_eq!(transfer.signature(), "transfer(address,uint256)");
+        assert_eq!(transfer.selector(), [0xa9, 0x05, 0x9c, 0xbb]);
+
+        let mut data = hex::decode("a9059cbb").unwrap();
+        data.extend(H256::from(H160::repeat_byte(0xab)).as_bytes());
+        data.extend(H256::from_low_u64_be(1000).as_bytes());
+        assert_eq!(
+            transfer.decode_input(&data).unwrap(),
+            [
+                Token::Address(H160::repeat_byte(0xab)),
+                Token::Uint(U256::from(1000))
+            ]
+        );
+        assert_eq!(
+            transfer.decode_input(&data[1..]),
+            Err(AbiError::SelectorMismatch)
+        );
+    }
+
+    #[test]
+    fn parses_signatures() {
+        let transfer = Function::parse("transfer(address,uint256)").unwrap();
+        assert_eq!(transfer.inputs, [ParamType::Address, ParamType::Uint(256)]);
+        assert!(transfer.outputs.is_empty());
+        assert_eq!(transfer.selector(), [0xa9, 0x05, 0x9c, 0xbb]);
+
+        let balance = Function::parse("balanceOf(address)(uint256)").unwrap();
+        let verbose = Function::parse(
+            "function balanceOf(address owner) external view returns (uint256 balance)",
+        )
+        .unwrap();
+        assert_eq!(balance, verbose);
+        assert_eq!(balance.signature(), "balanceOf(address)");
+
+        let swap = Function::parse(
+            "swap((address to, uint amount)[] calldata orders, bytes memory data) returns (bool)",
+        )
+        .unwrap();
+        assert_eq!(swap.signature(), "swap((address,uint256)[],bytes)");
+        assert_eq!(swap.outputs, [ParamType::Bool]);
+
+        for invalid in [
+            "",
+            "transfer",
+            "(address)",
+            "f(address",
+            "f(uint7)",
+            "f() view (bool)",
+            "f()(bool) x",
+        ] {
+            assert!(Function::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn encodes_calls_and_decodes_returns() {
+        let balance = Function::parse("balanceOf(address)(uint256)").unwrap();
+        let owner = H160::repeat_byte(0xab);
+        let data = balance.encode_input(&[Token::Address(owner)]).unwrap();
+        assert_eq!(hex::encode(&data[..4]), "70a08231");
+        assert_eq!(
+            balance.decode_input(&data).unwrap(),
+            [Token::Address(owner)]
+        );
+        assert_eq!(
+            balance.decode_input(&data[1..]),
+            Err(AbiError::SelectorMismatch)
+        );
+        assert_eq!(
+            balance.encode_input(&[Token::Bool(true)]),
+            Err(AbiError::TypeMismatch)
+        );
+
+        let output = encode(&[Token::Uint(U256::from(1000))]);
+        assert_eq!(
+            balance.decode_output(&output).unwrap(),
+            [Token::Uint(U256::from(1000))]
+        );
+    }
+}
@@ -0,0 +1,669 @@
+use crate::bloom::Bloom;
+use crate::crypto::keccak256;
+use crate::env::{BlockEnv, CfgEnv, Env, Fork};
+use crate::error::{InvalidBlock, VmError};
+use crate::executor::transact;
+use crate::gas;
+use crate::header::Header;
+use crate::rlp::{self, Decodable, Encodable, Header as RlpHeader, RlpError};
+use crate::state::{Log, State};
+use crate::system;
+use crate::transaction::SignedTransaction;
+use crate::trie::ordered_trie_root;
+use primitive_types::{H160, H256, U256};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+// Types of the requests a block sends to the consensus layer (EIP-7685)
+pub const DEPOSIT_REQUEST_TYPE: u8 = 0x00;
+pub const WITHDRAWAL_REQUEST_TYPE: u8 = 0x01;
+pub const CONSOLIDATION_REQUEST_TYPE: u8 = 0x02;
+
+// Block to be applied on top of its parent's state
+#[derive(Clone, Debug, Default)]
+pub struct Block {
+    pub env: BlockEnv,
+    pub transactions: Vec<SignedTransaction>,
+    pub ommers: Vec<Ommer>,
+    pub parent_hash: H256,
+    // Cancun and later
+    pub parent_beacon_block_root: Option<H256>,
+    // Shanghai and later
+    pub withdrawals: Vec<Withdrawal>,
+}
+
+// Block as it appears in the chain:
+// `rlp([header, transactions, ommers, withdrawals?])`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SealedBlock {
+    pub header: Header,
+    pub transactions: Vec<SignedTransaction>,
+    pub ommers: Vec<Header>,
+    // Shanghai and later
+    pub withdrawals: Option<Vec<Withdrawal>>,
+}
+
+impl SealedBlock {
+    pub fn hash(&self) -> H256 {
+        self.header.hash()
+    }
+
+    // Execution input of the block
+    pub fn block(&self) -> Block {
+        Block {
+            env: self.header.block_env(),
+            transactions: self.transactions.clone(),
+            ommers: self
+                .ommers
+                .iter()
+                .map(|ommer| Ommer {
+                    coinbase: ommer.beneficiary,
+                    number: ommer.number,
+                })
+                .collect(),
+            parent_hash: self.header.parent_hash,
+            parent_beacon_block_root: self.header.parent_beacon_block_root,
+            withdrawals: self.withdrawals.clone().unwrap_or_default(),
+        }
+    }
+}
+
+impl Encodable for SealedBlock {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        let mut payload = Vec::new();
+        self.header.rlp_append(&mut payload);
+        self.transactions.rlp_append(&mut payload);
+        self.ommers.rlp_append(&mut payload);
+        if let Some(withdrawals) = &self.withdrawals {
+            withdrawals.rlp_append(&mut payload);
+        }
+        out.extend(rlp::wrap_list(&payload));
+    }
+}
+
+impl Decodable for SealedBlock {
+    fn rlp_decode(buf: &mut &[u8]) -> Result<Self, RlpError> {
+        let mut payload = RlpHeader::decode_list(buf)?;
+        let buf = &mut payload;
+        let block = SealedBlock {
+            header: Decodable::rlp_decode(buf)?,
+            transactions: Decodable::rlp_decode(buf)?,
+            ommers: Decodable::rlp_decode(buf)?,
+            withdrawals: match buf.is_empty() {
+                true => None,
+                false => Some(Decodable::rlp_decode(buf)?),
+            },
+        };
+        if !buf.is_empty() {
+            return Err(RlpError::ListLengthMismatch);
+        }
+        Ok(block)
+    }
+}
+
+// Uncle header of a proof-of-work block, only what is needed for rewards
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ommer {
+    pub coinbase: H160,
+    pub number: u64,
+}
+
+// Validator withdrawal from the beacon chain (EIP-4895)
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Withdrawal {
+    #[serde(with = "crate::serde_hex::u64")]
+    pub index: u64,
+    #[serde(with = "crate::serde_hex::u64")]
+    pub validator_index: u64,
+    pub address: H160,
+    // in gwei
+    #[serde(with = "crate::serde_hex::u64")]
+    pub amount: u64,
+}
+
+impl_rlp!(Withdrawal {
+    index,
+    validator_index,
+    address,
+    amount
+});
+
+pub fn withdrawals_root(withdrawals: &[Withdrawal]) -> H256 {
+    ordered_trie_root(withdrawals.iter().map(rlp::encode))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Receipt {
+    pub tx_type: u8,
+    pub success: bool,
+    pub cumulative_gas_used: u64,
+    pub logs_bloom: Bloom,
+    pub logs: Vec<Log>,
+    // not part of the consensus encoding
+    pub gas_used: u64,
+    pub contract_address: Option<H160>,
+}
+
+impl Receipt {
+    // `rlp([status, cumulative_gas_used, logs_bloom, logs])`, prefixed with
+    // the transaction type for typed transactions
+    pub fn encode_enveloped(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        self.success.rlp_append(&mut payload);
+        self.cumulative_gas_used.rlp_append(&mut payload);
+        self.logs_bloom.rlp_append(&mut payload);
+        self.logs.rlp_append(&mut payload);
+
+        let mut out = Vec::new();
+        if self.tx_type != 0 {
+            out.push(self.tx_type);
+        }
+        out.extend(rlp::wrap_list(&payload));
+        out
+    }
+}
+
+pub fn receipts_root(receipts: &[Receipt]) -> H256 {
+    ordered_trie_root(receipts.iter().map(Receipt::encode_enveloped))
+}
+
+// sha256 of the sha256 of each request, skipping those without data
+pub fn requests_hash(requests: &[Vec<u8>]) -> H256 {
+    let mut hasher = Sha256::new();
+    for request in requests.iter().filter(|request| request.len() > 1) {
+        hasher.update(Sha256::digest(request));
+    }
+    H256::from_slice(&hasher.finalize())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockResult {
+    pub receipts: Vec<Receipt>,
+    pub gas_used: u64,
+    pub blob_gas_used: u64,
+    pub logs_bloom: Bloom,
+    // Prague and later
+    pub requests: Option<Vec<Vec<u8>>>,
+}
+
+// Checks the body against the roots committed to by the header
+pub fn validate_body(block: &SealedBlock) -> Result<(), VmError> {
+    let invalid = |e| Err(VmError::InvalidBlock(e));
+    let header = &block.header;
+
+    let transactions_root = ordered_trie_root(
+        block
+            .transactions
+            .iter()
+            .map(SignedTransaction::encode_enveloped),
+    );
+    if transactions_root != header.transactions_root {
+        return invalid(InvalidBlock::TransactionsRootMismatch);
+    }
+    if keccak256(&rlp::encode(&block.ommers)) != header.ommers_hash {
+        return invalid(InvalidBlock::OmmersHashMismatch);
+    }
+    let withdrawals_root = block.withdrawals.as_deref().map(withdrawals_root);
+    if withdrawals_root != header.withdrawals_root {
+        return invalid(InvalidBlock::WithdrawalsRootMismatch);
+    }
+    Ok(())
+}
+
+// Checks the outcome of executing the block against its header. The state
+// root is computed by the caller, which owns the trie.
+pub fn validate_result(
+    header: &Header,
+    result: &BlockResult,
+    state_root: H256,
+) -> Result<(), VmError> {
+    let invalid = |e| Err(VmError::InvalidBlock(e));
+
+    if result.gas_used != header.gas_used {
+        return invalid(InvalidBlock::GasUsedMismatch {
+            expected: header.gas_used,
+            got: result.gas_used,
+        });
+    }
+    if let Some(expected) = header.blob_gas_used {
+        if result.blob_gas_used != expected {
+            return invalid(InvalidBlock::BlobGasUsedMismatch {
+                expected,
+                got: result.blob_gas_used,
+            });
+        }
+    }
+    if receipts_root(&result.receipts) != header.receipts_root {
+        return invalid(InvalidBlock::ReceiptsRootMismatch);
+    }
+    if result.logs_bloom != header.logs_bloom {
+        return invalid(InvalidBlock::LogsBloomMismatch);
+    }
+    if state_root != header.state_root {
+        return invalid(InvalidBlock::StateRootMismatch);
+    }
+    if result.requests.as_deref().map(requests_hash) != header.requests_hash {
+        return invalid(InvalidBlock::RequestsHashMismatch);
+    }
+    Ok(())
+}
+
+// Applies the block on top of its parent's state: the pre-block system
+// calls, the transactions in order, then withdrawals, the requests and block
+// rewards. On
+// error `state` holds a partially applied block and must be discarded.
+pub fn execute_block(
+    state: &mut State,
+    cfg: &CfgEnv,
+    block: &Block,
+) -> Result<BlockResult, VmError> {
+    let invalid = |e| Err(VmError::InvalidBlock(e));
+    let mut env = Env {
+        cfg: cfg.clone(),
+        block: block.env.clone(),
+        tx: Default::default(),
+    };
+    let mut receipts = Vec::with_capacity(block.transactions.len());
+    let mut logs_bloom = Bloom::default();
+    let mut gas_used = 0;
+    let mut blob_gas_used = 0;
+
+    if cfg.fork >= Fork::Cancun {
+        if let Some(root) = block.parent_beacon_block_root {
+            system::apply_beacon_root(state, &env, root)?;
+        }
+    }
+    if cfg.fork >= Fork::Prague {
+        system::apply_parent_hash(state, &env, block.parent_hash)?;
+    }
+
+    for (index, tx) in block.transactions.iter().enumerate() {
+        if tx.tx.gas_limit() > block.env.gas_limit - gas_used {
+            return invalid(InvalidBlock::GasLimitExceeded { index });
+        }
+        let Ok(sender) = tx.recover_sender() else {
+            return invalid(InvalidBlock::InvalidSignature { index });
+        };
+        env.tx = tx.tx_env(sender);
+        if env.tx.blob_gas() > gas::max_blob_gas(cfg.fork) - blob_gas_used {
+            return invalid(InvalidBlock::BlobGasUsedExceedsMax);
+        }
+
+        let result = match transact(state, &env) {
+            Ok(result) => result,
+            Err(VmError::InvalidTransaction(reason)) => {
+                return invalid(InvalidBlock::InvalidTransaction { index, reason })
+            }
+            Err(e) => return Err(e),
+        };
+
+        gas_used += result.gas_used;
+        blob_gas_used += result.blob_gas_used;
+        let bloom = Bloom::from_logs(&result.logs);
+        logs_bloom |= bloom;
+        receipts.push(Receipt {
+            tx_type: tx.tx.tx_type(),
+            success: result.success,
+            cumulative_gas_used: gas_used,
+            logs_bloom: bloom,
+            logs: result.logs,
+            gas_used: result.gas_used,
+            contract_address: result.contract_address,
+        });
+    }
+
+    if cfg.fork >= Fork::Shanghai {
+        apply_withdrawals(state, &block.withdrawals)?;
+    }
+    let requests = match cfg.fork >= Fork::Prague {
+        true => Some(collect_requests(state, &env, &receipts)?),
+        false => None,
+    };
+    if let Some(reward) = block_reward(cfg.fork) {
+        apply_rewards(state, &block.env, &block.ommers, reward)?;
+    }
+
+    Ok(BlockResult {
+        receipts,
+        gas_used,
+        blob_gas_used,
+        logs_bloom,
+        requests,
+    })
+}
+
+// Requests of the block since Prague: the deposits logged by its
+// transactions, then the withdrawals and consolidations dequeued from their
+// system contracts. Each is its type followed by its data, and types without
+// any are left out.
+pub fn collect_requests(
+    state: &mut State,
+    env: &Env,
+    receipts: &[Receipt],
+) -> Result<Vec<Vec<u8>>, VmError> {
+    let deposits = system::deposit_requests(receipts.iter().flat_map(|receipt| &receipt.logs))?;
+    let withdrawals = system::dequeue_withdrawal_requests(state, env)?;
+    let consolidations = system::dequeue_consolidation_requests(state, env)?;
+    let requests = [
+        (DEPOSIT_REQUEST_TYPE, deposits),
+        (WITHDRAWAL_REQUEST_TYPE, withdrawals),
+        (CONSOLIDATION_REQUEST_TYPE, consolidations),
+    ];
+    Ok(requests
+        .into_iter()
+        .filter(|(_, data)| !data.is_empty())
+        .map(|(request_type, data)| [vec![request_type], data].concat())
+        .collect())
+}
+
+// Credits the withdrawn amounts, which are given in gwei
+pub fn apply_withdrawals(state: &mut State, withdrawals: &[Withdrawal]) -> Result<(), VmError> {
+    for withdrawal in withdrawals {
+        let amount = U256::from(withdrawal.amount) * U256::exp10(9);
+        state.add_balance(withdrawal.address, amount)?;
+    }
+    state.finalize();
+    Ok(())
+}
+
+// Mining rewards, which ended with the merge
+pub fn block_reward(fork: Fork) -> Option<U256> {
+    if fork >= Fork::Paris {
+        return None;
+    }
+    // EIP-1234
+    Some(U256::from(2) * U256::exp10(18))
+}
+
+// Pays the miner and the miners of the included ommers
+pub fn apply_rewards(
+    state: &mut State,
+    env: &BlockEnv,
+    ommers: &[Ommer],
+    reward: U256,
+) -> Result<(), VmError> {
+    let number = env.number;
+    let mut miner_reward = reward;
+    for ommer in ommers {
+        let depth = number.saturating_sub(ommer.number);
+        let ommer_reward = reward * U256::from(8u64.saturating_sub(depth)) / 8;
+        state.add_balance(ommer.coinbase, ommer_reward)?;
+        miner_reward += reward / 32;
+    }
+    state.add_balance(env.coinbase, miner_reward)?;
+    state.finalize();
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::Account;
+    use crate::transaction::{DynamicFeeTx, LegacyTx, LocalSigner, Transaction, TxKind};
+
+    fn addr(n: u64) -> H160 {
+        H160::from_low_u64_be(n)
+    }
+
+    fn setup() -> (State<'static>, LocalSigner) {
+        let signer = LocalSigner::from_bytes(&[0x46; 32]).unwrap();
+        let mut state = State::new();
+        state.insert_account(
+            signer.address(),
+            Account {
+                balance: U256::exp10(18),
+                ..Default::default()
+            },
+        );
+        // emits a log with topic 0xaa
+        state.insert_account(
+            addr(0x10),
+            Account {
+                code: vec![0x60, 0xaa, 0x60, 0x00, 0x60, 0x00, 0xa1, 0x00],
+                ..Default::default()
+            },
+        );
+        (state, signer)
+    }
+
+    fn transfer(nonce: u64, to: H160, gas_limit: u64) -> Transaction {
+        Transaction::DynamicFee(DynamicFeeTx {
+            chain_id: 1,
+            nonce,
+            max_priority_fee_per_gas: U256::from(1),
+            max_fee_per_gas: U256::from(10),
+            gas_limit,
+            to: TxKind::Call(to),
+            ..Default::default()
+        })
+    }
+
+    fn block(transactions: Vec<SignedTransaction>) -> Block {
+        Block {
+            env: BlockEnv {
+                number: 10,
+                coinbase: addr(0xc0),
+                basefee: U256::from(7),
+                gas_limit: 100_000,
+                ..Default::default()
+            },
+            transactions,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn receipts_accumulate_gas_and_logs() {
+        let (mut state, signer) = setup();
+        let legacy = Transaction::Legacy(LegacyTx {
+            chain_id: Some(1),
+            nonce: 1,
+            gas_price: U256::from(10),
+            gas_limit: 30_000,
+            to: TxKind::Call(addr(0x10)),
+            ..Default::default()
+        });
+        let block = block(vec![
+            transfer(0, addr(0x20), 21_000).sign(&signer),
+            legacy.sign(&signer),
+        ]);
+
+        let result = execute_block(&mut state, &CfgEnv::default(), &block).unwrap();
+        let [first, second] = &result.receipts[..] else {
+            panic!("expected two receipts");
+        };
+        assert_eq!(first.cumulative_gas_used, 21_000);
+        assert!(first.logs_bloom.is_empty());
+        assert_eq!(first.encode_enveloped()[0], 2);
+
+        // 3 pushes and LOG1 with no data
+        assert_eq!(second.gas_used, 21_000 + 9 + 750);
+        assert_eq!(second.cumulative_gas_used, result.gas_used);
+        assert_eq!(result.gas_used, 42_759);
+        assert!(second.encode_enveloped()[0] >= 0xc0);
+        assert_eq!(second.logs.len(), 1);
+        assert_eq!(result.logs_bloom, second.logs_bloom);
+        assert!(result.logs_bloom.contains(addr(0x10).as_bytes()));
+        assert!(result
+            .logs_bloom
+            .contains(H256::from_low_u64_be(0xaa).as_bytes()));
+
+        // tips only, no reward after the merge
+        assert_eq!(
+            state.balance(&addr(0xc0)).unwrap(),
+            U256::from(21_000 + 21_759 * 3)
+        );
+    }
+
+    #[test]
+    fn enforces_block_gas_limit() {
+        let (mut state, signer) = setup();
+        let block = block(vec![
+            transfer(0, addr(0x20), 60_000).sign(&signer),
+            // only 79000 gas is left after the first transfer
+            transfer(1, addr(0x20), 80_000).sign(&signer),
+        ]);
+        assert_eq!(
+            execute_block(&mut state, &CfgEnv::default(), &block),
+            Err(VmError::InvalidBlock(InvalidBlock::GasLimitExceeded {
+                index: 1
+            }))
+        );
+    }
+
+    #[test]
+    fn pays_rewards_before_the_merge() {
+        let (mut state, _) = setup();
+        let mut block = block(vec![]);
+        block.ommers.push(Ommer {
+            coinbase: addr(0x0e),
+            number: 9,
+        });
+        let cfg = CfgEnv {
+            fork: Fork::London,
+            ..Default::default()
+        };
+
+        execute_block(&mut state, &cfg, &block).unwrap();
+        let reward = U256::from(2) * U256::exp10(18);
+        assert_eq!(state.balance(&addr(0xc0)).unwrap(), reward + reward / 32);
+        assert_eq!(state.balance(&addr(0x0e)).unwrap(), reward * 7 / 8);
+    }
+
+    #[test]
+    fn credits_withdrawals_in_gwei() {
+        let (mut state, _) = setup();
+        let mut block = block(vec![]);
+        block.withdrawals = vec![
+            Withdrawal {
+                index: 0,
+                validator_index: 1,
+                address: addr(0xaa),
+                amount: 5,
+            },
+            // leaves no empty account behind
+            Withdrawal {
+                index: 1,
+                validator_index: 2,
+                address: addr(0xbb),
+                amount: 0,
+            },
+        ];
+
+        execute_block(&mut state, &CfgEnv::default(), &block).unwrap();
+        assert_eq!(
+            state.balance(&addr(0xaa)).unwrap(),
+            U256::from(5) * U256::exp10(9)
+        );
+        assert!(!state.exists(&addr(0xbb)).unwrap());
+
+        // ignored before Shanghai
+        let (mut state, _) = setup();
+        let cfg = CfgEnv {
+            fork: Fork::Paris,
+            ..Default::default()
+        };
+        execute_block(&mut state, &cfg, &block).unwrap();
+        assert!(!state.exists(&addr(0xaa)).unwrap());
+    }
+
+    #[test]
+    fn collects_requests_since_prague() {
+        let (mut state, _) = setup();
+        let cfg = CfgEnv {
+            fork: Fork::Prague,
+            ..Default::default()
+        };
+        let block = block(vec![]);
+        assert_eq!(
+            execute_block(&mut state, &cfg, &block),
+            Err(VmError::InvalidBlock(InvalidBlock::MissingSystemContract(
+                system::WITHDRAWAL_REQUEST_ADDRESS
+            )))
+        );
+
+        // returns 0xabcd, and nothing
+        let (mut state, _) = setup();
+        for (address, code) in [
+            (system::WITHDRAWAL_REQUEST_ADDRESS, "61abcd5f526002601ef3"),
+            (system::CONSOLIDATION_REQUEST_ADDRESS, "00"),
+        ] {
+            let account = Account {
+                code: hex::decode(code).unwrap(),
+                ..Default::default()
+            };
+            state.insert_account(address, account);
+        }
+        let result = execute_block(&mut state, &cfg, &block).unwrap();
+        assert_eq!(result.requests, Some(vec![vec![0x01, 0xab, 0xcd]]));
+
+        let mut header = Header {
+            logs_bloom: Bloom::default(),
+            receipts_root: receipts_root(&[]),
+            requests_hash: Some(requests_hash(&[])),
+            ..Default::default()
+        };
+        assert_eq!(
+            validate_result(&header, &result, header.state_root),
+            Err(VmError::InvalidBlock(InvalidBlock::RequestsHashMismatch))
+        );
+        header.requests_hash = result.requests.as_deref().map(requests_hash);
+        assert_eq!(validate_result(&header, &result, header.state_root), Ok(()));
+    }
+
+    #[test]
+    fn hashes_requests() {
+        // sha256 of nothing
+        assert_eq!(
+            requests_hash(&[]),
+            "0xe3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+                .parse()
+                .unwrap()
+        );
+        // a type without data is skipped
+        assert_eq!(requests_hash(&[vec![0x01]]), requests_hash(&[]));
+        let request = vec![0x01, 0xab, 0xcd];
+        let mut hasher = Sha256::new();
+        hasher.update(Sha256::digest(&request));
+        assert_eq!(
+            requests_hash(&[request]),
+            H256::from_slice(&hasher.finalize())
+        );
+    }
+
+    #[test]
+    fn sealed_block_roundtrip_and_body_roots() {
+        let (_, signer) = setup();
+        let transactions = vec![transfer(0, addr(0x20), 21_000).sign(&signer)];
+        let mut block = SealedBlock {
+            header: Header {
+                transactions_root: ordered_trie_root(
+                    transactions.iter().map(SignedTransaction::encode_enveloped),
+                ),
+                ommers_hash: crate::header::empty_ommers_hash(),
+                base_fee_per_gas: Some(U256::from(7)),
+                withdrawals_root: Some(withdrawals_root(&[])),
+                ..Default::default()
+            },
+            transactions,
+            ommers: Vec::new(),
+            withdrawals: Some(Vec::new()),
+        };
+        assert_eq!(rlp::decode(&rlp::encode(&block)), Ok(block.clone()));
+        assert_eq!(validate_body(&block), Ok(()));
+
+        block.withdrawals = None;
+        assert_eq!(
+            validate_body(&block),
+            Err(VmError::InvalidBlock(InvalidBlock::WithdrawalsRootMismatch))
+        );
+        block.transactions.clear();
+        assert_eq!(
+            validate_body(&block),
+            Err(VmError::InvalidBlock(
+                InvalidBlock::TransactionsRootMismatch
+            ))
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use crate::crypto::keccak256;
+use crate::rlp::{Decodable, Encodable, Header, RlpError};
+use crate::state::Log;
+use std::ops::BitOrAssign;
+
+pub const BLOOM_SIZE: usize = 256;
+
+// 2048-bit filter over the addresses and topics of logs, as found in
+// receipts and block headers
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Bloom(pub [u8; BLOOM_SIZE]);
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self([0; BLOOM_SIZE])
+    }
+}
+
+impl Bloom {
+    pub fn from_logs<'a>(logs: impl IntoIterator<Item = &'a Log>) -> Self {
+        let mut bloom = Self::default();
+        for log in logs {
+            bloom.accrue_log(log);
+        }
+        bloom
+    }
+
+    pub fn accrue_log(&mut self, log: &Log) {
+        self.accrue(log.address.as_bytes());
+        for topic in &log.topics {
+            self.accrue(topic.as_bytes());
+        }
+    }
+
+    // Sets the three bits selected by the first six bytes of keccak256(input)
+    pub fn accrue(&mut self, input: &[u8]) {
+        for (bit, index) in bits(input) {
+            self.0[index] |= bit;
+        }
+    }
+
+    pub fn contains(&self, input: &[u8]) -> bool {
+        bits(input).all(|(bit, index)| self.0[index] & bit != 0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|b| *b == 0)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+// Bit masks and byte indices of the bits set for `input`
+fn bits(input: &[u8]) -> impl Iterator<Item = (u8, usize)> {
+    let hash = keccak256(input);
+    (0..3).map(move |i| {
+        let bit = (((hash[2 * i] as usize) << 8) | hash[2 * i + 1] as usize) & 2047;
+        (1 << (bit % 8), BLOOM_SIZE - 1 - bit / 8)
+    })
+}
+
+impl BitOrAssign for Bloom {
+    fn bitor_assign(&mut self, rhs: Self) {
+        for (a, b) in self.0.iter_mut().zip(rhs.0) {
+            *a |= b;
+        }
+    }
+}
+
+impl Encodable for Bloom {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        self.0.as_slice().rlp_append(out)
+    }
+}
+
+impl Decodable for Bloom {
+    fn rlp_decode(buf: &mut &[u8]) -> Result<Self, RlpError> {
+        let bytes = Header::decode_bytes(buf)?;
+        bytes
+            .try_into()
+            .map(Bloom)
+            .map_err(|_| RlpError::UnexpectedLength)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use primitive_types::{H160, H256};
+
+    #[test]
+    fn log_bits() {
+        let log = Log {
+            address: H160::repeat_byte(0x11),
+            topics: vec![H256::repeat_byte(0x22)],
+            data: vec![1, 2, 3],
+        };
+        let bloom = Bloom::from_logs([&log]);
+        assert_eq!(bloom.0.iter().map(|b| b.count_ones()).sum::<u32>(), 6);
+        assert!(bloom.contains(log.address.as_bytes()));
+        assert!(bloom.contains(log.topics[0].as_bytes()));
+        assert!(!bloom.contains(H160::repeat_byte(0x33).as_bytes()));
+        // the data is not indexed
+        assert!(!bloom.contains(&log.data));
+
+        let encoded = crate::rlp::encode(&bloom);
+        assert_eq!(encoded[..3], [0xb9, 0x01, 0x00]);
+        assert_eq!(crate::rlp::decode::<Bloom>(&encoded), Ok(bloom));
+    }
+}
@@ -0,0 +1,66 @@
+use crate::rlp::{self, Encodable};
+use primitive_types::{H160, H256};
+use tiny_keccak::{Hasher, Keccak};
+
+// keccak256 of the empty string, i.e. the code hash of accounts without code
+pub const KECCAK_EMPTY: H256 = H256([
+    0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c, 0x92, 0x7e, 0x7d, 0xb2, 0xdc, 0xc7, 0x03, 0xc0,
+    0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b, 0x7b, 0xfa, 0xd8, 0x04, 0x5d, 0x85, 0xa4, 0x70,
+]);
+
+pub fn keccak256(data: &[u8]) -> H256 {
+    let mut hasher = Keccak::v256();
+    let mut out = [0u8; 32];
+    hasher.update(data);
+    hasher.finalize(&mut out);
+    H256(out)
+}
+
+// Address of a contract created by `sender` with CREATE, i.e. the last 20
+// bytes of keccak256(rlp([sender, nonce]))
+pub fn create_address(sender: H160, nonce: u64) -> H160 {
+    let mut payload = Vec::new();
+    sender.rlp_append(&mut payload);
+    nonce.rlp_append(&mut payload);
+    H160::from_slice(&keccak256(&rlp::wrap_list(&payload))[12..])
+}
+
+// Address of a contract created with CREATE2, i.e. the last 20 bytes of
+// keccak256(0xff ++ sender ++ salt ++ keccak256(init_code))
+pub fn create2_address(sender: H160, salt: H256, init_code_hash: H256) -> H160 {
+    let mut data = Vec::with_capacity(85);
+    data.push(0xff);
+    data.extend_from_slice(sender.as_bytes());
+    data.extend_from_slice(salt.as_bytes());
+    data.extend_from_slice(init_code_hash.as_bytes());
+    H160::from_slice(&keccak256(&data)[12..])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn empty_hash() {
+        assert_eq!(keccak256(&[]), KECCAK_EMPTY);
+    }
+
+    #[test]
+    fn contract_address() {
+        let sender: H160 = "6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0".parse().unwrap();
+        assert_eq!(
+            create_address(sender, 0),
+            "cd234a471b72ba2f1ccf0a70fcaba648a5eecd8d".parse().unwrap()
+        );
+        assert_eq!(
+            create_address(sender, 1),
+            "343c43a37d37dff08ae8c4a11544c718abb4fcf8".parse().unwrap()
+        );
+
+        // example 0 of EIP-1014
+        assert_eq!(
+            create2_address(H160::zero(), H256::zero(), keccak256(&[0x00])),
+            "4d1a2e2bb4f88f0250f26ffff098b0b30b26bf38".parse().unwrap()
+        );
+    }
+}
@@ -0,0 +1,292 @@
+use super::{AccountInfo, Changes, Database, DatabaseCommit, EmptyDB};
+use crate::crypto::KECCAK_EMPTY;
+use crate::error::VmError;
+use primitive_types::{H160, H256};
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AccountState {
+    // looked up in the underlying database but not found there
+    NotExisting,
+    // read from the underlying database and unchanged since
+    #[default]
+    Loaded,
+    // written by a commit
+    Changed,
+    // written by a commit after being deleted, so its storage in the
+    // underlying database is stale
+    StorageCleared,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DbAccount {
+    pub info: AccountInfo,
+    pub state: AccountState,
+    // loaded and changed slots
+    pub storage: HashMap<H256, H256>,
+}
+
+// Caching layer over another database. It records every account, slot and
+// block hash read through it, and keeps committed changes on top of the
+// underlying database, which is never written to.
+#[derive(Clone, Debug, Default)]
+pub struct CacheDB<ExtDB> {
+    pub accounts: HashMap<H160, DbAccount>,
+    pub contracts: HashMap<H256, Vec<u8>>,
+    pub block_hashes: HashMap<u64, H256>,
+    pub db: ExtDB,
+}
+
+pub type InMemoryDB = CacheDB<EmptyDB>;
+
+impl<ExtDB> CacheDB<ExtDB> {
+    pub fn new(db: ExtDB) -> Self {
+        Self {
+            accounts: HashMap::new(),
+            contracts: HashMap::new(),
+            block_hashes: HashMap::new(),
+            db,
+        }
+    }
+
+    // Inserts an account as changed, e.g. from a genesis allocation
+    pub fn insert_account_info(&mut self, address: H160, mut info: AccountInfo) {
+        self.insert_contract(&mut info);
+        let account = self.accounts.entry(address).or_default();
+        account.info = info;
+        account.state = AccountState::Changed;
+    }
+
+    pub fn insert_account_storage(&mut self, address: H160, slot: H256, value: H256) {
+        let account = self.accounts.entry(address).or_default();
+        account.storage.insert(slot, value);
+        account.state = AccountState::Changed;
+    }
+
+    pub fn insert_block_hash(&mut self, number: u64, hash: H256) {
+        self.block_hashes.insert(number, hash);
+    }
+
+    // Accounts that have been changed on top of the underlying database
+    pub fn changed_accounts(&self) -> impl Iterator<Item = (&H160, &DbAccount)> {
+        self.accounts.iter().filter(|(_, account)| {
+            matches!(
+                account.state,
+                AccountState::Changed | AccountState::StorageCleared
+            )
+        })
+    }
+
+    // Moves the code of `info` into the contracts map
+    fn insert_contract(&mut self, info: &mut AccountInfo) {
+        if let Some(code) = info.code.take() {
+            if !code.is_empty() {
+                self.contracts.insert(info.code_hash, code);
+            }
+        }
+    }
+}
+
+impl<ExtDB: Database> Database for CacheDB<ExtDB> {
+    fn basic(&mut self, address: H160) -> Result<Option<AccountInfo>, VmError> {
+        if let Some(account) = self.accounts.get(&address) {
+            return Ok(match account.state {
+                AccountState::NotExisting => None,
+                _ => Some(account.info.clone()),
+            });
+        }
+
+        let account = match self.db.basic(address)? {
+            Some(mut info) => {
+                self.insert_contract(&mut info);
+                DbAccount {
+                    info,
+                    state: AccountState::Loaded,
+                    storage: HashMap::new(),
+                }
+            }
+            None => DbAccount {
+                state: AccountState::NotExisting,
+                ..Default::default()
+            },
+        };
+        let info = match account.state {
+            AccountState::NotExisting => None,
+            _ => Some(account.info.clone()),
+        };
+        self.accounts.insert(address, account);
+        Ok(info)
+    }
+
+    fn code_by_hash(&mut self, code_hash: H256) -> Result<Vec<u8>, VmError> {
+        if code_hash == KECCAK_EMPTY {
+            return Ok(Vec::new());
+        }
+        if let Some(code) = self.contracts.get(&code_hash) {
+            return Ok(code.clone());
+        }
+
+        let code = self.db.code_by_hash(code_hash)?;
+        self.contracts.insert(code_hash, code.clone());
+        Ok(code)
+    }
+
+    fn storage(&mut self, address: H160, index: H256) -> Result<H256, VmError> {
+        if !self.accounts.contains_key(&address) {
+            self.basic(address)?;
+        }
+
+        let account = self.accounts.get_mut(&address).expect("account is loaded");
+        if let Some(value) = account.storage.get(&index) {
+            return Ok(*value);
+        }
+        // storage of accounts unknown to the underlying database, or deleted
+        // on top of it, is empty
+        if matches!(
+            account.state,
+            AccountState::NotExisting | AccountState::StorageCleared
+        ) {
+            return Ok(H256::zero());
+        }
+
+        let value = self.db.storage(address, index)?;
+        account.storage.insert(index, value);
+        Ok(value)
+    }
+
+    fn block_hash(&mut self, number: u64) -> Result<H256, VmError> {
+        if let Some(hash) = self.block_hashes.get(&number) {
+            return Ok(*hash);
+        }
+
+        let hash = self.db.block_hash(number)?;
+        self.block_hashes.insert(number, hash);
+        Ok(hash)
+    }
+}
+
+impl<ExtDB> DatabaseCommit for CacheDB<ExtDB> {
+    fn commit(&mut self, changes: Changes) -> Result<(), VmError> {
+        for (address, change) in changes {
+            match change.account {
+                Some(account) => {
+                    let mut info = AccountInfo::from(&account);
+                    self.insert_contract(&mut info);
+                    let db_account = self.accounts.entry(address).or_default();
+                    db_account.info = info;
+                    // an account created on top of a deleted one, or of none,
+                    // has no storage in the underlying database
+                    if change.storage_cleared || db_account.state == AccountState::NotExisting {
+                        db_account.storage.clear();
+                        db_account.state = AccountState::StorageCleared;
+                    } else if db_account.state != AccountState::StorageCleared {
+                        db_account.state = AccountState::Changed;
+                    }
+                    db_account.storage.extend(account.storage);
+                }
+                None => {
+                    self.accounts.insert(
+                        address,
+                        DbAccount {
+                            state: AccountState::NotExisting,
+                            ..Default::default()
+                        },
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::State;
+    use primitive_types::U256;
+
+    #[test]
+    fn commit_on_top_of_loaded_state() {
+        let address = H160::from_low_u64_be(1);
+        let slot = H256::from_low_u64_be(1);
+
+        let mut base = InMemoryDB::default();
+        base.insert_account_info(
+            address,
+            AccountInfo {
+                balance: U256::from(10),
+                ..Default::default()
+            }
+            .with_code(vec![0x00]),
+        );
+        base.insert_account_storage(address, slot, H256::from_low_u64_be(3));
+
+        let mut cache = CacheDB::new(&mut base);
+        let changes = {
+            let mut state = State::with_db(&mut cache);
+            assert_eq!(state.storage(&address, &slot).unwrap().to_low_u64_be(), 3);
+            state
+                .set_storage(address, slot, H256::from_low_u64_be(4))
+                .unwrap();
+            state.add_balance(address, U256::from(5)).unwrap();
+            state.finalize();
+            state.take_changes()
+        };
+        cache.commit(changes).unwrap();
+
+        // the cache sees the change, the underlying database does not
+        assert_eq!(cache.storage(address, slot).unwrap().to_low_u64_be(), 4);
+        assert_eq!(
+            cache.basic(address).unwrap().unwrap().balance,
+            U256::from(15)
+        );
+        assert_eq!(
+            cache
+                .code_by_hash(cache.accounts[&address].info.code_hash)
+                .unwrap(),
+            vec![0x00]
+        );
+        assert_eq!(base.storage(address, slot).unwrap().to_low_u64_be(), 3);
+    }
+
+    #[test]
+    fn commit_of_a_recreated_account_clears_its_storage() {
+        let address = H160::from_low_u64_be(1);
+        let (old, new) = (H256::from_low_u64_be(1), H256::from_low_u64_be(2));
+        let one = H256::from_low_u64_be(1);
+
+        let mut base = InMemoryDB::default();
+        base.insert_account_info(address, AccountInfo::default().with_code(vec![0x00]));
+        base.insert_account_storage(address, old, one);
+
+        let mut cache = CacheDB::new(&mut base);
+        let changes = {
+            let mut state = State::with_db(&mut cache);
+            state.selfdestruct(address);
+            state.finalize();
+            state.set_code(address, vec![0x01]).unwrap();
+            state.set_storage(address, new, one).unwrap();
+            state.finalize();
+            assert!(state.storage(&address, &old).unwrap().is_zero());
+            state.take_changes()
+        };
+        assert!(changes[&address].storage_cleared);
+        cache.commit(changes).unwrap();
+
+        assert!(cache.storage(address, old).unwrap().is_zero());
+        assert_eq!(cache.storage(address, new).unwrap(), one);
+        assert_eq!(cache.accounts[&address].state, AccountState::StorageCleared);
+
+        // later changes keep the storage cleared
+        let changes = {
+            let mut state = State::with_db(&mut cache);
+            state.add_balance(address, U256::one()).unwrap();
+            state.finalize();
+            state.take_changes()
+        };
+        assert!(!changes[&address].storage_cleared);
+        cache.commit(changes).unwrap();
+        assert!(cache.storage(address, old).unwrap().is_zero());
+        assert_eq!(base.storage(address, old).unwrap(), one);
+    }
+}
@@ -0,0 +1,301 @@
+use super::{AccountInfo, Changes, Database, DatabaseCommit};
+use crate::crypto::KECCAK_EMPTY;
+use crate::error::VmError;
+use primitive_types::{H160, H256, U256};
+use redb::{Table, TableDefinition, WriteTransaction};
+use std::path::Path;
+
+// address => balance (32) | nonce (8) | code hash (32)
+const ACCOUNTS: TableDefinition<&[u8], &[u8]> = TableDefinition::new("accounts");
+// code hash => code
+const CODE: TableDefinition<&[u8], &[u8]> = TableDefinition::new("code");
+// address | slot => value, zero values are not stored
+const STORAGE: TableDefinition<&[u8], &[u8]> = TableDefinition::new("storage");
+// block number => block hash
+const BLOCK_HASHES: TableDefinition<u64, &[u8]> = TableDefinition::new("block_hashes");
+// "head" => block number (8) | block hash (32)
+const META: TableDefinition<&str, &[u8]> = TableDefinition::new("meta");
+
+const HEAD_KEY: &str = "head";
+
+// Persistent state database on top of an embedded key-value store. Changes
+// are written in a single transaction, so after a crash the database reopens
+// at the last successful commit.
+pub struct DiskDB {
+    db: redb::Database,
+}
+
+impl DiskDB {
+    // Opens the database at `path`, creating it if it does not exist
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, VmError> {
+        let db = redb::Database::create(path).map_err(db_error)?;
+
+        // make sure every table exists so that reads never fail on a fresh file
+        let tx = db.begin_write().map_err(db_error)?;
+        tx.open_table(ACCOUNTS).map_err(db_error)?;
+        tx.open_table(CODE).map_err(db_error)?;
+        tx.open_table(STORAGE).map_err(db_error)?;
+        tx.open_table(BLOCK_HASHES).map_err(db_error)?;
+        tx.open_table(META).map_err(db_error)?;
+        tx.commit().map_err(db_error)?;
+
+        Ok(Self { db })
+    }
+
+    // Number and hash of the last committed block
+    pub fn head(&self) -> Result<Option<(u64, H256)>, VmError> {
+        let tx = self.db.begin_read().map_err(db_error)?;
+        let table = tx.open_table(META).map_err(db_error)?;
+        let head = table.get(HEAD_KEY).map_err(db_error)?.map(|v| {
+            let bytes = v.value();
+            let number = u64::from_be_bytes(bytes[..8].try_into().unwrap());
+            (number, H256::from_slice(&bytes[8..40]))
+        });
+        Ok(head)
+    }
+
+    // Atomically writes the changes made by block `number` and moves the
+    // head to it
+    pub fn commit_block(
+        &mut self,
+        number: u64,
+        hash: H256,
+        changes: Changes,
+    ) -> Result<(), VmError> {
+        let tx = self.db.begin_write().map_err(db_error)?;
+        write_changes(&tx, changes)?;
+        {
+            let mut block_hashes = tx.open_table(BLOCK_HASHES).map_err(db_error)?;
+            block_hashes
+                .insert(number, hash.as_bytes())
+                .map_err(db_error)?;
+
+            let mut head = [0u8; 40];
+            head[..8].copy_from_slice(&number.to_be_bytes());
+            head[8..].copy_from_slice(hash.as_bytes());
+            let mut meta = tx.open_table(META).map_err(db_error)?;
+            meta.insert(HEAD_KEY, &head[..]).map_err(db_error)?;
+        }
+        tx.commit().map_err(db_error)
+    }
+}
+
+impl Database for DiskDB {
+    fn basic(&mut self, address: H160) -> Result<Option<AccountInfo>, VmError> {
+        let tx = self.db.begin_read().map_err(db_error)?;
+        let table = tx.open_table(ACCOUNTS).map_err(db_error)?;
+        let info = table
+            .get(address.as_bytes())
+            .map_err(db_error)?
+            .map(|v| decode_account(v.value()));
+        Ok(info)
+    }
+
+    fn code_by_hash(&mut self, code_hash: H256) -> Result<Vec<u8>, VmError> {
+        if code_hash == KECCAK_EMPTY {
+            return Ok(Vec::new());
+        }
+
+        let tx = self.db.begin_read().map_err(db_error)?;
+        let table = tx.open_table(CODE).map_err(db_error)?;
+        match table.get(code_hash.as_bytes()).map_err(db_error)? {
+            Some(code) => Ok(code.value().to_vec()),
+            None => Err(VmError::Database(format!(
+                "unknown code hash {:?}",
+                code_hash
+            ))),
+        }
+    }
+
+    fn storage(&mut self, address: H160, index: H256) -> Result<H256, VmError> {
+        let tx = self.db.begin_read().map_err(db_error)?;
+        let table = tx.open_table(STORAGE).map_err(db_error)?;
+        let value = table
+            .get(&storage_key(&address, &index)[..])
+            .map_err(db_error)?
+            .map(|v| H256::from_slice(v.value()))
+            .unwrap_or_default();
+        Ok(value)
+    }
+
+    fn block_hash(&mut self, number: u64) -> Result<H256, VmError> {
+        let tx = self.db.begin_read().map_err(db_error)?;
+        let table = tx.open_table(BLOCK_HASHES).map_err(db_error)?;
+        let hash = table
+            .get(number)
+            .map_err(db_error)?
+            .map(|v| H256::from_slice(v.value()))
+            .unwrap_or_default();
+        Ok(hash)
+    }
+}
+
+impl DatabaseCommit for DiskDB {
+    // Writes the changes without moving the head
+    fn commit(&mut self, changes: Changes) -> Result<(), VmError> {
+        let tx = self.db.begin_write().map_err(db_error)?;
+        write_changes(&tx, changes)?;
+        tx.commit().map_err(db_error)
+    }
+}
+
+fn write_changes(tx: &WriteTransaction, changes: Changes) -> Result<(), VmError> {
+    let mut accounts = tx.open_table(ACCOUNTS).map_err(db_error)?;
+    let mut code = tx.open_table(CODE).map_err(db_error)?;
+    let mut storage = tx.open_table(STORAGE).map_err(db_error)?;
+
+    for (address, change) in changes {
+        match change.account {
+            Some(account) => {
+                let info = AccountInfo::from(&account);
+                if !account.code.is_empty() {
+                    code.insert(info.code_hash.as_bytes(), &account.code[..])
+                        .map_err(db_error)?;
+                }
+                accounts
+                    .insert(address.as_bytes(), &encode_account(&info)[..])
+                    .map_err(db_error)?;
+
+                // storage left from before the account was deleted
+                if change.storage_cleared {
+                    clear_storage(&mut storage, &address)?;
+                }
+                for (index, value) in account.storage {
+                    let key = storage_key(&address, &index);
+                    if value.is_zero() {
+                        storage.remove(&key[..]).map_err(db_error)?;
+                    } else {
+                        storage
+                            .insert(&key[..], value.as_bytes())
+                            .map_err(db_error)?;
+                    }
+                }
+            }
+            None => {
+                accounts.remove(address.as_bytes()).map_err(db_error)?;
+                clear_storage(&mut storage, &address)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn clear_storage(storage: &mut Table<&[u8], &[u8]>, address: &H160) -> Result<(), VmError> {
+    let start = storage_key(address, &H256::zero());
+    let end = storage_key(address, &H256::repeat_byte(0xff));
+    storage
+        .retain_in(&start[..]..=&end[..], |_, _| false)
+        .map_err(db_error)
+}
+
+fn encode_account(info: &AccountInfo) -> [u8; 72] {
+    let mut out = [0u8; 72];
+    info.balance.to_big_endian(&mut out[..32]);
+    out[32..40].copy_from_slice(&info.nonce.to_be_bytes());
+    out[40..].copy_from_slice(info.code_hash.as_bytes());
+    out
+}
+
+fn decode_account(bytes: &[u8]) -> AccountInfo {
+    AccountInfo {
+        balance: U256::from_big_endian(&bytes[..32]),
+        nonce: u64::from_be_bytes(bytes[32..40].try_into().unwrap()),
+        code_hash: H256::from_slice(&bytes[40..72]),
+        code: None,
+    }
+}
+
+fn storage_key(address: &H160, index: &H256) -> [u8; 52] {
+    let mut key = [0u8; 52];
+    key[..20].copy_from_slice(address.as_bytes());
+    key[20..].copy_from_slice(index.as_bytes());
+    key
+}
+
+fn db_error(e: impl std::fmt::Display) -> VmError {
+    VmError::Database(e.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::{Account, State};
+    use std::collections::HashMap;
+
+    #[test]
+    fn reopens_at_last_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.redb");
+        let address = H160::from_low_u64_be(1);
+        let slot = H256::from_low_u64_be(2);
+
+        {
+            let mut db = DiskDB::open(&path).unwrap();
+            assert_eq!(db.head().unwrap(), None);
+
+            let account = Account {
+                balance: U256::from(7),
+                nonce: 1,
+                code: vec![0x60, 0x00],
+                storage: HashMap::from([(slot, H256::from_low_u64_be(3))]),
+            };
+            let changes = HashMap::from([(address, Some(account).into())]);
+            db.commit_block(1, H256::repeat_byte(1), changes).unwrap();
+        }
+
+        let mut db = DiskDB::open(&path).unwrap();
+        assert_eq!(db.head().unwrap(), Some((1, H256::repeat_byte(1))));
+        assert_eq!(db.block_hash(1).unwrap(), H256::repeat_byte(1));
+
+        let info = db.basic(address).unwrap().unwrap();
+        assert_eq!(info.balance, U256::from(7));
+        assert_eq!(db.code_by_hash(info.code_hash).unwrap(), vec![0x60, 0x00]);
+        assert_eq!(db.storage(address, slot).unwrap().to_low_u64_be(), 3);
+
+        // deleting the account drops its storage
+        db.commit(HashMap::from([(address, None.into())])).unwrap();
+        assert_eq!(db.basic(address).unwrap(), None);
+        assert!(db.storage(address, slot).unwrap().is_zero());
+    }
+
+    #[test]
+    fn recreated_account_drops_its_old_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.redb");
+        let address = H160::from_low_u64_be(1);
+        let (old, new) = (H256::from_low_u64_be(1), H256::from_low_u64_be(2));
+        let one = H256::from_low_u64_be(1);
+
+        {
+            let mut db = DiskDB::open(&path).unwrap();
+            let account = Account {
+                code: vec![0x00],
+                storage: HashMap::from([(old, one)]),
+                ..Default::default()
+            };
+            db.commit_block(
+                1,
+                H256::repeat_byte(1),
+                HashMap::from([(address, Some(account).into())]),
+            )
+            .unwrap();
+
+            // destroyed and created again by later transactions of a block
+            let mut state = State::with_db(&mut db);
+            state.selfdestruct(address);
+            state.finalize();
+            state.set_code(address, vec![0x01]).unwrap();
+            state.set_storage(address, new, one).unwrap();
+            state.finalize();
+            let changes = state.take_changes();
+            drop(state);
+            db.commit_block(2, H256::repeat_byte(2), changes).unwrap();
+        }
+
+        let mut db = DiskDB::open(&path).unwrap();
+        let info = db.basic(address).unwrap().unwrap();
+        assert_eq!(db.code_by_hash(info.code_hash).unwrap(), vec![0x01]);
+        assert!(db.storage(address, old).unwrap().is_zero());
+        assert_eq!(db.storage(address, new).unwrap(), one);
+    }
+}
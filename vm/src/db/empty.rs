@@ -0,0 +1,33 @@
+use super::{AccountInfo, Database};
+use crate::crypto::KECCAK_EMPTY;
+use crate::error::VmError;
+use primitive_types::{H160, H256};
+
+// Database without any accounts
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EmptyDB;
+
+impl Database for EmptyDB {
+    fn basic(&mut self, _address: H160) -> Result<Option<AccountInfo>, VmError> {
+        Ok(None)
+    }
+
+    fn code_by_hash(&mut self, code_hash: H256) -> Result<Vec<u8>, VmError> {
+        if code_hash == KECCAK_EMPTY {
+            Ok(Vec::new())
+        } else {
+            Err(VmError::Database(format!(
+                "unknown code hash {:?}",
+                code_hash
+            )))
+        }
+    }
+
+    fn storage(&mut self, _address: H160, _index: H256) -> Result<H256, VmError> {
+        Ok(H256::zero())
+    }
+
+    fn block_hash(&mut self, _number: u64) -> Result<H256, VmError> {
+        Ok(H256::zero())
+    }
+}
@@ -0,0 +1,121 @@
+mod cache;
+mod disk;
+mod empty;
+
+pub use cache::{AccountState, CacheDB, DbAccount, InMemoryDB};
+pub use disk::DiskDB;
+pub use empty::EmptyDB;
+
+use crate::crypto::{keccak256, KECCAK_EMPTY};
+use crate::error::VmError;
+use crate::state::Account;
+use primitive_types::{H160, H256, U256};
+use std::collections::HashMap;
+
+// Account fields stored by a database, without storage
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountInfo {
+    pub balance: U256,
+    pub nonce: u64,
+    pub code_hash: H256,
+    // may be left out, in which case it is looked up by `code_hash`
+    pub code: Option<Vec<u8>>,
+}
+
+impl Default for AccountInfo {
+    fn default() -> Self {
+        Self {
+            balance: U256::zero(),
+            nonce: 0,
+            code_hash: KECCAK_EMPTY,
+            code: None,
+        }
+    }
+}
+
+impl AccountInfo {
+    pub fn with_code(mut self, code: Vec<u8>) -> Self {
+        self.code_hash = if code.is_empty() {
+            KECCAK_EMPTY
+        } else {
+            keccak256(&code)
+        };
+        self.code = Some(code);
+        self
+    }
+}
+
+impl From<&Account> for AccountInfo {
+    fn from(account: &Account) -> Self {
+        AccountInfo {
+            balance: account.balance,
+            nonce: account.nonce,
+            ..Default::default()
+        }
+        .with_code(account.code.clone())
+    }
+}
+
+// Change to an account after execution
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AccountChange {
+    // `None` marks an account that no longer exists, and only the touched
+    // storage slots are included
+    pub account: Option<Account>,
+    // set if the account was deleted, so its previous storage is gone even
+    // if it exists again, e.g. after a SELFDESTRUCT and a CREATE2
+    pub storage_cleared: bool,
+}
+
+// A change that keeps the storage of the account
+impl From<Option<Account>> for AccountChange {
+    fn from(account: Option<Account>) -> Self {
+        AccountChange {
+            account,
+            storage_cleared: false,
+        }
+    }
+}
+
+// Changed accounts after execution
+pub type Changes = HashMap<H160, AccountChange>;
+
+// Source the world state is lazily loaded from
+pub trait Database {
+    fn basic(&mut self, address: H160) -> Result<Option<AccountInfo>, VmError>;
+
+    fn code_by_hash(&mut self, code_hash: H256) -> Result<Vec<u8>, VmError>;
+
+    fn storage(&mut self, address: H160, index: H256) -> Result<H256, VmError>;
+
+    fn block_hash(&mut self, number: u64) -> Result<H256, VmError>;
+}
+
+// Database that changes can be written back to
+pub trait DatabaseCommit {
+    fn commit(&mut self, changes: Changes) -> Result<(), VmError>;
+}
+
+impl<T: Database + ?Sized> Database for &mut T {
+    fn basic(&mut self, address: H160) -> Result<Option<AccountInfo>, VmError> {
+        (**self).basic(address)
+    }
+
+    fn code_by_hash(&mut self, code_hash: H256) -> Result<Vec<u8>, VmError> {
+        (**self).code_by_hash(code_hash)
+    }
+
+    fn storage(&mut self, address: H160, index: H256) -> Result<H256, VmError> {
+        (**self).storage(address, index)
+    }
+
+    fn block_hash(&mut self, number: u64) -> Result<H256, VmError> {
+        (**self).block_hash(number)
+    }
+}
+
+impl<T: DatabaseCommit + ?Sized> DatabaseCommit for &mut T {
+    fn commit(&mut self, changes: Changes) -> Result<(), VmError> {
+        (**self).commit(changes)
+    }
+}
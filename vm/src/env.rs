@@ -0,0 +1,157 @@
+use crate::gas;
+use crate::transaction::SignedAuthorization;
+use primitive_types::{H160, H256, U256};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+// Hard forks with distinct execution rules, in activation order
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Fork {
+    Berlin,
+    London,
+    // the merge
+    Paris,
+    Shanghai,
+    #[default]
+    Cancun,
+    Prague,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnknownFork(pub String);
+
+impl fmt::Display for UnknownFork {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown or unsupported fork {}", self.0)
+    }
+}
+
+// Fork names as used by the ethereum/tests fixtures and geth's evm tool
+impl FromStr for Fork {
+    type Err = UnknownFork;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "Berlin" => Ok(Fork::Berlin),
+            "London" => Ok(Fork::London),
+            "Paris" | "Merge" => Ok(Fork::Paris),
+            "Shanghai" => Ok(Fork::Shanghai),
+            "Cancun" => Ok(Fork::Cancun),
+            "Prague" => Ok(Fork::Prague),
+            _ => Err(UnknownFork(name.to_string())),
+        }
+    }
+}
+
+// Environment a transaction is executed in
+#[derive(Clone, Debug, Default)]
+pub struct Env {
+    pub cfg: CfgEnv,
+    pub block: BlockEnv,
+    pub tx: TxEnv,
+}
+
+#[derive(Clone, Debug)]
+pub struct CfgEnv {
+    pub chain_id: u64,
+    pub fork: Fork,
+}
+
+impl Default for CfgEnv {
+    fn default() -> Self {
+        Self {
+            chain_id: 1,
+            fork: Fork::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BlockEnv {
+    pub number: u64,
+    pub coinbase: H160,
+    pub timestamp: u64,
+    pub gas_limit: u64,
+    pub basefee: U256,
+    // only used before the merge
+    pub difficulty: U256,
+    pub prevrandao: H256,
+    // Cancun and later
+    pub excess_blob_gas: Option<u64>,
+}
+
+impl BlockEnv {
+    // Price of blob gas in this block (EIP-4844)
+    pub fn blob_base_fee(&self, fork: Fork) -> U256 {
+        gas::blob_base_fee(self.excess_blob_gas.unwrap_or_default(), fork)
+    }
+}
+
+impl Default for BlockEnv {
+    fn default() -> Self {
+        Self {
+            number: 0,
+            coinbase: H160::zero(),
+            timestamp: 0,
+            gas_limit: 30_000_000,
+            basefee: U256::zero(),
+            difficulty: U256::zero(),
+            prevrandao: H256::zero(),
+            excess_blob_gas: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TxEnv {
+    pub caller: H160,
+    pub gas_limit: u64,
+    // gas price of legacy and access list transactions, max fee per gas of
+    // EIP-1559 transactions
+    pub gas_price: U256,
+    pub gas_priority_fee: Option<U256>,
+    // `None` creates a contract
+    pub to: Option<H160>,
+    pub value: U256,
+    pub data: Vec<u8>,
+    // checked against the sender's nonce if set
+    pub nonce: Option<u64>,
+    // `None` for legacy transactions without replay protection
+    pub chain_id: Option<u64>,
+    pub access_list: Vec<AccessListItem>,
+    // versioned hashes of the blobs of a blob transaction
+    pub blob_hashes: Vec<H256>,
+    // set for blob transactions
+    pub max_fee_per_blob_gas: Option<U256>,
+    // set for set-code transactions
+    pub authorization_list: Option<Vec<SignedAuthorization>>,
+}
+
+impl TxEnv {
+    // Price paid per unit of gas given the block's base fee
+    pub fn effective_gas_price(&self, basefee: U256) -> U256 {
+        match self.gas_priority_fee {
+            Some(priority_fee) => {
+                std::cmp::min(self.gas_price, basefee.saturating_add(priority_fee))
+            }
+            None => self.gas_price,
+        }
+    }
+
+    pub fn blob_gas(&self) -> u64 {
+        self.blob_hashes.len() as u64 * gas::GAS_PER_BLOB
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessListItem {
+    pub address: H160,
+    pub storage_keys: Vec<H256>,
+}
+
+impl_rlp!(AccessListItem {
+    address,
+    storage_keys
+});
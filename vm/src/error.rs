@@ -1,11 +1,261 @@
-#[derive(Debug, PartialEq)]
+use crate::opcode::Opcode;
+use primitive_types::{H160, U256};
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum VmError {
     // stack errors
     StackOverflow,
     StackUnderflow,
     // runtime error
     UnsuccessfulRun,
-    InvalidOpcode,
+    // undefined, or not yet defined in the configured fork
+    InvalidOpcode(u8),
     UnsupportedOperation,
-    InvalidJump,
+    // jump target that is not a JUMPDEST
+    InvalidJump(U256),
+    ReturnDataOutOfBounds,
+    OutOfGas,
+    // state errors
+    InsufficientBalance,
+    WriteProtection,
+    Database(String),
+    // call into a precompiled contract that is not implemented
+    UnsupportedPrecompile(H160),
+    // the transaction cannot be included, the state is left untouched
+    InvalidTransaction(InvalidTransaction),
+    // the block cannot be applied, the state it was applied to must be
+    // discarded
+    InvalidBlock(InvalidBlock),
 }
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum InvalidTransaction {
+    ChainIdMismatch { expected: u64, got: u64 },
+    NonceTooLow { expected: u64, got: u64 },
+    NonceTooHigh { expected: u64, got: u64 },
+    NonceOverflow,
+    GasLimitExceedsBlock,
+    IntrinsicGasTooLow,
+    GasPriceBelowBaseFee,
+    // EIP-1559
+    DynamicFeeNotSupported,
+    PriorityFeeAboveMaxFee,
+    InsufficientFunds,
+    // EIP-3607
+    SenderHasCode,
+    // EIP-3860
+    InitCodeTooLarge,
+    // EIP-4844
+    BlobsNotSupported,
+    BlobCreateTransaction,
+    EmptyBlobs,
+    TooManyBlobs,
+    InvalidVersionedHash,
+    BlobGasPriceBelowBaseFee,
+    // EIP-7702
+    SetCodeNotSupported,
+    SetCodeCreateTransaction,
+    EmptyAuthorizationList,
+    // EIP-7623
+    FloorDataGasTooLow,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum InvalidBlock {
+    // the transaction's gas limit exceeds the gas left in the block
+    GasLimitExceeded {
+        index: usize,
+    },
+    InvalidSignature {
+        index: usize,
+    },
+    InvalidTransaction {
+        index: usize,
+        reason: InvalidTransaction,
+    },
+    // header checks against the parent header
+    ParentHashMismatch,
+    NumberMismatch,
+    TimestampNotIncreasing,
+    GasLimitOutOfBounds,
+    GasUsedExceedsLimit,
+    ExtraDataTooLong,
+    BaseFeeMismatch {
+        expected: U256,
+        got: U256,
+    },
+    BlobGasUsedExceedsMax,
+    ExcessBlobGasMismatch {
+        expected: u64,
+        got: u64,
+    },
+    // a field the fork introduced is missing or one it removed is present
+    FieldMismatch(&'static str),
+    // body checks against the header
+    TransactionsRootMismatch,
+    OmmersHashMismatch,
+    WithdrawalsRootMismatch,
+    // requests (EIP-7685)
+    InvalidDepositLog,
+    MissingSystemContract(H160),
+    SystemCallFailed(H160),
+    // execution results that differ from the header
+    GasUsedMismatch {
+        expected: u64,
+        got: u64,
+    },
+    BlobGasUsedMismatch {
+        expected: u64,
+        got: u64,
+    },
+    ReceiptsRootMismatch,
+    LogsBloomMismatch,
+    StateRootMismatch,
+    RequestsHashMismatch,
+}
+
+// Where in the execution a frame halted
+#[derive(Clone, Debug, PartialEq)]
+pub struct ErrorContext {
+    pub pc: usize,
+    pub opcode: u8,
+    // 0 for the frame of the transaction
+    pub depth: usize,
+    // account whose code was running
+    pub address: H160,
+    // before the halt consumed the frame's gas
+    pub gas_remaining: u64,
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mnemonic = Opcode::get(self.opcode)
+            .map(|op| op.mnemonic)
+            .unwrap_or(Opcode::INVALID.mnemonic);
+        write!(
+            f,
+            "pc {} ({} {:#04x}), depth {}, address {:?}, {} gas left",
+            self.pc, mnemonic, self.opcode, self.depth, self.address, self.gas_remaining
+        )
+    }
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VmError::StackOverflow => write!(f, "stack overflow"),
+            VmError::StackUnderflow => write!(f, "stack underflow"),
+            VmError::UnsuccessfulRun => write!(f, "unsuccessful run"),
+            VmError::InvalidOpcode(op) => write!(f, "invalid opcode {:#04x}", op),
+            VmError::UnsupportedOperation => write!(f, "unsupported operation"),
+            VmError::InvalidJump(dest) => write!(f, "invalid jump destination {}", dest),
+            VmError::ReturnDataOutOfBounds => write!(f, "return data out of bounds"),
+            VmError::OutOfGas => write!(f, "out of gas"),
+            VmError::InsufficientBalance => write!(f, "insufficient balance"),
+            VmError::WriteProtection => write!(f, "write protection"),
+            VmError::Database(e) => write!(f, "database error: {}", e),
+            VmError::UnsupportedPrecompile(address) => {
+                write!(f, "unsupported precompile {:?}", address)
+            }
+            VmError::InvalidTransaction(e) => write!(f, "invalid transaction: {}", e),
+            VmError::InvalidBlock(e) => write!(f, "invalid block: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for VmError {}
+
+impl VmError {
+    // Errors that abort the whole transaction, not only the frame they
+    // occur in
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            VmError::Database(_) | VmError::UnsupportedPrecompile(_)
+        )
+    }
+}
+
+impl fmt::Display for InvalidTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use InvalidTransaction::*;
+        match self {
+            ChainIdMismatch { expected, got } => {
+                write!(f, "chain id {} instead of {}", got, expected)
+            }
+            NonceTooLow { expected, got } => {
+                write!(f, "nonce too low: {} instead of {}", got, expected)
+            }
+            NonceTooHigh { expected, got } => {
+                write!(f, "nonce too high: {} instead of {}", got, expected)
+            }
+            NonceOverflow => write!(f, "nonce has max value"),
+            GasLimitExceedsBlock => write!(f, "gas limit exceeds the block gas limit"),
+            IntrinsicGasTooLow => write!(f, "intrinsic gas too low"),
+            GasPriceBelowBaseFee => write!(f, "max fee per gas below the base fee"),
+            DynamicFeeNotSupported => write!(f, "dynamic fee transactions are not supported"),
+            PriorityFeeAboveMaxFee => write!(f, "max priority fee per gas above max fee"),
+            InsufficientFunds => write!(f, "insufficient funds for gas * price + value"),
+            SenderHasCode => write!(f, "sender is not an EOA"),
+            InitCodeTooLarge => write!(f, "init code too large"),
+            BlobsNotSupported => write!(f, "blob transactions are not supported"),
+            BlobCreateTransaction => write!(f, "blob transaction creates a contract"),
+            EmptyBlobs => write!(f, "blob transaction without blobs"),
+            TooManyBlobs => write!(f, "too many blobs"),
+            InvalidVersionedHash => write!(f, "invalid blob versioned hash"),
+            BlobGasPriceBelowBaseFee => write!(f, "max fee per blob gas below the blob base fee"),
+            SetCodeNotSupported => write!(f, "set code transactions are not supported"),
+            SetCodeCreateTransaction => write!(f, "set code transaction creates a contract"),
+            EmptyAuthorizationList => write!(f, "empty authorization list"),
+            FloorDataGasTooLow => write!(f, "gas limit below the calldata floor cost"),
+        }
+    }
+}
+
+impl std::error::Error for InvalidTransaction {}
+
+impl fmt::Display for InvalidBlock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use InvalidBlock::*;
+        let mismatch =
+            |f: &mut fmt::Formatter, name, expected: &dyn fmt::Display, got: &dyn fmt::Display| {
+                write!(f, "{} {} instead of {}", name, got, expected)
+            };
+        match self {
+            GasLimitExceeded { index } => {
+                write!(f, "transaction {} exceeds the gas left in the block", index)
+            }
+            InvalidSignature { index } => {
+                write!(f, "transaction {} has an invalid signature", index)
+            }
+            InvalidTransaction { index, reason } => write!(f, "transaction {}: {}", index, reason),
+            ParentHashMismatch => write!(f, "parent hash mismatch"),
+            NumberMismatch => write!(f, "block number does not follow the parent"),
+            TimestampNotIncreasing => write!(f, "timestamp not after the parent"),
+            GasLimitOutOfBounds => write!(f, "gas limit changed too much from the parent"),
+            GasUsedExceedsLimit => write!(f, "gas used exceeds the gas limit"),
+            ExtraDataTooLong => write!(f, "extra data too long"),
+            BaseFeeMismatch { expected, got } => mismatch(f, "base fee", expected, got),
+            BlobGasUsedExceedsMax => write!(f, "blob gas used exceeds the maximum"),
+            ExcessBlobGasMismatch { expected, got } => {
+                mismatch(f, "excess blob gas", expected, got)
+            }
+            FieldMismatch(field) => write!(f, "unexpected presence or absence of {}", field),
+            TransactionsRootMismatch => write!(f, "transactions root mismatch"),
+            OmmersHashMismatch => write!(f, "ommers hash mismatch"),
+            WithdrawalsRootMismatch => write!(f, "withdrawals root mismatch"),
+            InvalidDepositLog => write!(f, "invalid deposit log"),
+            MissingSystemContract(address) => write!(f, "no code at system contract {:?}", address),
+            SystemCallFailed(address) => write!(f, "system call to {:?} failed", address),
+            GasUsedMismatch { expected, got } => mismatch(f, "gas used", expected, got),
+            BlobGasUsedMismatch { expected, got } => mismatch(f, "blob gas used", expected, got),
+            ReceiptsRootMismatch => write!(f, "receipts root mismatch"),
+            LogsBloomMismatch => write!(f, "logs bloom mismatch"),
+            StateRootMismatch => write!(f, "state root mismatch"),
+            RequestsHashMismatch => write!(f, "requests hash mismatch"),
+        }
+    }
+}
+
+impl std::error::Error for InvalidBlock {}
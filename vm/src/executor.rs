@@ -0,0 +1,785 @@
+use crate::crypto::create_address;
+use crate::env::{CfgEnv, Env, Fork, TxEnv};
+use crate::error::{InvalidTransaction, VmError};
+use crate::gas;
+use crate::opcode::{call_frame, create_frame};
+use crate::precompile;
+use crate::state::{delegation, delegation_code, Log, State};
+use crate::tracer::SharedTracer;
+use crate::transaction::SignedAuthorization;
+use crate::Context;
+use primitive_types::{H160, U256};
+use std::rc::Rc;
+
+// Outcome of an included transaction
+#[derive(Clone, Debug, PartialEq)]
+pub struct TxResult {
+    // whether the call or creation succeeded
+    pub success: bool,
+    // after refunds
+    pub gas_used: u64,
+    pub gas_refunded: u64,
+    pub blob_gas_used: u64,
+    pub output: Vec<u8>,
+    pub logs: Vec<Log>,
+    pub contract_address: Option<H160>,
+}
+
+// Validates the transaction in `env.tx` and executes it on top of `state`.
+// The transaction's changes are finalized in `state`, while an invalid
+// transaction leaves it untouched.
+pub fn transact(state: &mut State, env: &Env) -> Result<TxResult, VmError> {
+    transact_with_tracer(state, env, None)
+}
+
+// Like `transact`, reporting each executed opcode to `tracer`
+pub fn transact_with_tracer(
+    state: &mut State,
+    env: &Env,
+    tracer: Option<SharedTracer>,
+) -> Result<TxResult, VmError> {
+    let intrinsic_gas = validate(state, env)?;
+    // a fatal error leaves the state as it was before the transaction
+    let checkpoint = state.checkpoint();
+    let mut result = match execute(state, env, tracer.as_ref(), intrinsic_gas) {
+        Ok(result) => result,
+        Err(e) => {
+            state.revert(checkpoint);
+            return Err(e);
+        }
+    };
+    state.commit(checkpoint);
+    result.logs = state.finalize();
+    if let Some(tracer) = tracer {
+        tracer.borrow_mut().tx_end(&result);
+    }
+    Ok(result)
+}
+
+// Buys the gas and runs the validated transaction, returning its result
+// without the logs
+fn execute(
+    state: &mut State,
+    env: &Env,
+    tracer: Option<&SharedTracer>,
+    intrinsic_gas: u64,
+) -> Result<TxResult, VmError> {
+    let tx = &env.tx;
+    let fork = env.cfg.fork;
+    let gas_price = tx.effective_gas_price(env.block.basefee);
+
+    // buy gas at the effective price, the balance check used the max fee
+    state.sub_balance(tx.caller, gas_price * tx.gas_limit)?;
+    // blob gas is burned at the blob base fee and never refunded
+    let blob_gas_used = tx.blob_gas();
+    state.sub_balance(
+        tx.caller,
+        env.block.blob_base_fee(fork) * U256::from(blob_gas_used),
+    )?;
+
+    state.warm_account(tx.caller);
+    for address in precompile::addresses(fork) {
+        state.warm_account(address);
+    }
+    if fork >= Fork::Shanghai {
+        state.warm_account(env.block.coinbase);
+    }
+    for item in &tx.access_list {
+        state.warm_account(item.address);
+        for key in &item.storage_keys {
+            state.warm_slot(item.address, *key);
+        }
+    }
+
+    let nonce = state.nonce(&tx.caller)?;
+    state.increment_nonce(tx.caller)?;
+
+    // kept even if the execution fails
+    let authorization_refund = match &tx.authorization_list {
+        Some(authorizations) => apply_authorizations(state, env, authorizations)?,
+        None => 0,
+    };
+
+    let gas_limit = tx.gas_limit - intrinsic_gas;
+    let shared_env = Rc::new(env.clone());
+    let frame = match tx.to {
+        Some(to) => {
+            state.warm_account(to);
+            if let Some(delegate) = delegation(&state.code(&to)?) {
+                state.warm_account(delegate);
+            }
+            let context = Context {
+                address: to,
+                caller: tx.caller,
+                value: tx.value,
+                input: tx.data.clone(),
+                depth: 0,
+                is_static: false,
+                gas_limit,
+            };
+            call_frame(state, &shared_env, tracer, context, to, true)?
+        }
+        None => create_frame(
+            state,
+            &shared_env,
+            tracer,
+            tx.caller,
+            create_address(tx.caller, nonce),
+            tx.value,
+            tx.data.clone(),
+            gas_limit,
+            0,
+        )?,
+    };
+
+    let spent = tx.gas_limit - frame.gas.remaining();
+    let gas_refunded = std::cmp::min(
+        (frame.gas.refunded() + authorization_refund).max(0) as u64,
+        spent / gas::max_refund_quotient(fork),
+    );
+    let mut gas_used = spent - gas_refunded;
+    if fork >= Fork::Prague {
+        gas_used = gas_used.max(gas::calldata_floor(&tx.data));
+    }
+
+    // return what was not used and pay the priority fee to the coinbase
+    state.add_balance(tx.caller, gas_price * (tx.gas_limit - gas_used))?;
+    let tip = if fork >= Fork::London {
+        gas_price - env.block.basefee
+    } else {
+        gas_price
+    };
+    state.add_balance(env.block.coinbase, tip * gas_used)?;
+
+    Ok(TxResult {
+        success: frame.success,
+        gas_used,
+        gas_refunded,
+        blob_gas_used,
+        output: frame.output,
+        logs: Vec::new(),
+        contract_address: frame.address,
+    })
+}
+
+// Checks the transaction against the environment and the sender's account
+// and returns its intrinsic gas
+pub fn validate(state: &mut State, env: &Env) -> Result<u64, VmError> {
+    let invalid = |e| Err(VmError::InvalidTransaction(e));
+    let intrinsic_gas = validate_tx(&env.cfg, &env.tx)?;
+    let tx = &env.tx;
+    let fork = env.cfg.fork;
+
+    if fork >= Fork::London && tx.gas_price < env.block.basefee {
+        return invalid(InvalidTransaction::GasPriceBelowBaseFee);
+    }
+    if tx.gas_limit > env.block.gas_limit {
+        return invalid(InvalidTransaction::GasLimitExceedsBlock);
+    }
+    if matches!(tx.max_fee_per_blob_gas, Some(fee) if fee < env.block.blob_base_fee(fork)) {
+        return invalid(InvalidTransaction::BlobGasPriceBelowBaseFee);
+    }
+
+    let nonce = state.nonce(&tx.caller)?;
+    if nonce == u64::MAX {
+        return invalid(InvalidTransaction::NonceOverflow);
+    }
+    match tx.nonce {
+        Some(got) if got < nonce => {
+            return invalid(InvalidTransaction::NonceTooLow {
+                expected: nonce,
+                got,
+            })
+        }
+        Some(got) if got > nonce => {
+            return invalid(InvalidTransaction::NonceTooHigh {
+                expected: nonce,
+                got,
+            })
+        }
+        _ => (),
+    }
+
+    // delegated accounts can still send transactions (EIP-7702)
+    let code = state.code(&tx.caller)?;
+    if !code.is_empty() && delegation(&code).is_none() {
+        return invalid(InvalidTransaction::SenderHasCode);
+    }
+
+    let blob_fee = tx.max_fee_per_blob_gas.unwrap_or_default() * U256::from(tx.blob_gas());
+    let cost = tx
+        .gas_price
+        .checked_mul(U256::from(tx.gas_limit))
+        .and_then(|fee| fee.checked_add(blob_fee))
+        .and_then(|fee| fee.checked_add(tx.value));
+    match cost {
+        Some(cost) if cost <= state.balance(&tx.caller)? => Ok(intrinsic_gas),
+        _ => invalid(InvalidTransaction::InsufficientFunds),
+    }
+}
+
+// Sets the delegations of a set-code transaction (EIP-7702), skipping
+// invalid authorizations, and returns the refund for authorities that
+// already existed
+fn apply_authorizations(
+    state: &mut State,
+    env: &Env,
+    authorizations: &[SignedAuthorization],
+) -> Result<i64, VmError> {
+    let mut refund = 0;
+    for authorization in authorizations {
+        let chain_id = authorization.chain_id;
+        if !chain_id.is_zero() && chain_id != U256::from(env.cfg.chain_id) {
+            continue;
+        }
+        if authorization.nonce == u64::MAX || authorization.y_parity > 1 {
+            continue;
+        }
+        let Some(authority) = authorization.authority() else {
+            continue;
+        };
+        state.warm_account(authority);
+
+        let code = state.code(&authority)?;
+        if !code.is_empty() && delegation(&code).is_none() {
+            continue;
+        }
+        if state.nonce(&authority)? != authorization.nonce {
+            continue;
+        }
+        if state.exists(&authority)? {
+            refund += (gas::PER_EMPTY_ACCOUNT_COST - gas::PER_AUTH_BASE_COST) as i64;
+        }
+
+        // delegating to the zero address clears the delegation
+        let code = if authorization.address.is_zero() {
+            Vec::new()
+        } else {
+            delegation_code(authorization.address)
+        };
+        state.set_code(authority, code)?;
+        state.increment_nonce(authority)?;
+    }
+    Ok(refund)
+}
+
+// Checks that only depend on the transaction and the rules of the fork,
+// e.g. before it enters a pool, and returns its intrinsic gas
+pub fn validate_tx(cfg: &CfgEnv, tx: &TxEnv) -> Result<u64, VmError> {
+    let invalid = |e| Err(VmError::InvalidTransaction(e));
+    let fork = cfg.fork;
+
+    if let Some(chain_id) = tx.chain_id {
+        if chain_id != cfg.chain_id {
+            return invalid(InvalidTransaction::ChainIdMismatch {
+                expected: cfg.chain_id,
+                got: chain_id,
+            });
+        }
+    }
+    if let Some(priority_fee) = tx.gas_priority_fee {
+        if fork < Fork::London {
+            return invalid(InvalidTransaction::DynamicFeeNotSupported);
+        }
+        if priority_fee > tx.gas_price {
+            return invalid(InvalidTransaction::PriorityFeeAboveMaxFee);
+        }
+    }
+    if fork >= Fork::Shanghai && tx.to.is_none() && tx.data.len() > gas::MAX_INITCODE_SIZE {
+        return invalid(InvalidTransaction::InitCodeTooLarge);
+    }
+    if tx.max_fee_per_blob_gas.is_some() {
+        validate_blobs(fork, tx)?;
+    }
+    if let Some(authorizations) = &tx.authorization_list {
+        if fork < Fork::Prague {
+            return invalid(InvalidTransaction::SetCodeNotSupported);
+        }
+        if tx.to.is_none() {
+            return invalid(InvalidTransaction::SetCodeCreateTransaction);
+        }
+        if authorizations.is_empty() {
+            return invalid(InvalidTransaction::EmptyAuthorizationList);
+        }
+    }
+
+    let intrinsic_gas = gas::intrinsic_gas(
+        &tx.data,
+        tx.to.is_none(),
+        &tx.access_list,
+        tx.authorization_list.as_ref().map_or(0, Vec::len),
+        fork,
+    );
+    if intrinsic_gas > tx.gas_limit {
+        return invalid(InvalidTransaction::IntrinsicGasTooLow);
+    }
+    if fork >= Fork::Prague && gas::calldata_floor(&tx.data) > tx.gas_limit {
+        return invalid(InvalidTransaction::FloorDataGasTooLow);
+    }
+    Ok(intrinsic_gas)
+}
+
+// Checks the blobs of a blob transaction (EIP-4844)
+fn validate_blobs(fork: Fork, tx: &TxEnv) -> Result<(), VmError> {
+    let invalid = |e| Err(VmError::InvalidTransaction(e));
+
+    if fork < Fork::Cancun {
+        return invalid(InvalidTransaction::BlobsNotSupported);
+    }
+    if tx.to.is_none() {
+        return invalid(InvalidTransaction::BlobCreateTransaction);
+    }
+    if tx.blob_hashes.is_empty() {
+        return invalid(InvalidTransaction::EmptyBlobs);
+    }
+    if tx.blob_gas() > gas::max_blob_gas(fork) {
+        return invalid(InvalidTransaction::TooManyBlobs);
+    }
+    if tx
+        .blob_hashes
+        .iter()
+        .any(|hash| hash[0] != gas::VERSIONED_HASH_VERSION_KZG)
+    {
+        return invalid(InvalidTransaction::InvalidVersionedHash);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::env::TxEnv;
+    use crate::state::Account;
+    use primitive_types::H256;
+
+    fn addr(n: u64) -> H160 {
+        H160::from_low_u64_be(n)
+    }
+
+    fn funded_state() -> State<'static> {
+        let mut state = State::new();
+        state.insert_account(
+            addr(1),
+            Account {
+                balance: U256::exp10(18),
+                ..Default::default()
+            },
+        );
+        state
+    }
+
+    fn env(tx: TxEnv) -> Env {
+        let mut env = Env::default();
+        env.block.basefee = U256::from(7);
+        env.block.coinbase = addr(0xc0);
+        env.tx = tx;
+        env
+    }
+
+    #[test]
+    fn value_transfer_pays_gas_and_tip() {
+        let mut state = funded_state();
+        let env = env(TxEnv {
+            caller: addr(1),
+            gas_limit: 50_000,
+            gas_price: U256::from(10),
+            gas_priority_fee: Some(U256::from(2)),
+            to: Some(addr(0x20)),
+            value: U256::from(1000),
+            nonce: Some(0),
+            ..Default::default()
+        });
+
+        let result = transact(&mut state, &env).unwrap();
+        assert!(result.success);
+        assert_eq!(result.gas_used, 21_000);
+        // effective price is min(10, 7 + 2)
+        assert_eq!(
+            state.balance(&addr(1)).unwrap(),
+            U256::exp10(18) - 1000 - 21_000 * 9
+        );
+        assert_eq!(state.balance(&addr(0x20)).unwrap(), U256::from(1000));
+        assert_eq!(state.balance(&addr(0xc0)).unwrap(), U256::from(21_000 * 2));
+        assert_eq!(state.nonce(&addr(1)).unwrap(), 1);
+    }
+
+    #[test]
+    fn create_deploys_returned_code() {
+        let mut state = funded_state();
+        // SSTORE(0, 1), then return the single byte runtime code 0x00
+        let init_code = vec![
+            0x60, 0x01, 0x60, 0x00, 0x55, // sstore
+            0x60, 0x00, 0x60, 0x00, 0x53, // mstore8(0, 0)
+            0x60, 0x01, 0x60, 0x00, 0xf3, // return(0, 1)
+        ];
+        let env = env(TxEnv {
+            caller: addr(1),
+            gas_limit: 100_000,
+            gas_price: U256::from(7),
+            data: init_code,
+            ..Default::default()
+        });
+
+        let result = transact(&mut state, &env).unwrap();
+        assert!(result.success);
+        let address = create_address(addr(1), 0);
+        assert_eq!(result.contract_address, Some(address));
+        assert_eq!(state.code(&address).unwrap(), vec![0x00]);
+        assert_eq!(state.nonce(&address).unwrap(), 1);
+        // intrinsic with 4 zero and 11 non-zero bytes, pushes, mstore8 with
+        // memory, sstore and the code deposit
+        assert_eq!(
+            result.gas_used,
+            53_000 + 4 * 4 + 11 * 16 + 2 + 6 * 3 + 6 + 22_100 + 200
+        );
+    }
+
+    #[test]
+    fn refund_is_capped() {
+        let mut state = funded_state();
+        let slot = primitive_types::H256::zero();
+        state.insert_account(
+            addr(0x20),
+            Account {
+                // SSTORE(0, 0)
+                code: vec![0x60, 0x00, 0x60, 0x00, 0x55],
+                storage: [(slot, primitive_types::H256::from_low_u64_be(1))].into(),
+                ..Default::default()
+            },
+        );
+        let env = env(TxEnv {
+            caller: addr(1),
+            gas_limit: 100_000,
+            gas_price: U256::from(7),
+            to: Some(addr(0x20)),
+            ..Default::default()
+        });
+
+        let result = transact(&mut state, &env).unwrap();
+        let spent = 21_000 + 3 + 3 + 2100 + 2900;
+        assert_eq!(result.gas_refunded, 4800);
+        assert_eq!(result.gas_used, spent - 4800);
+        assert!(state.storage(&addr(0x20), &slot).unwrap().is_zero());
+    }
+
+    #[test]
+    fn calls_to_precompiles_are_unsupported() {
+        let mut state = funded_state();
+        // CALL(gas, to, 0, 0, 0, 0, 0)
+        let call = |to: u8| {
+            vec![
+                0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, to, 0x5a, 0xf1,
+                0x00,
+            ]
+        };
+        // BN254 addition and pairing, which are not implemented
+        for (address, code) in [(0x20, call(0x30)), (0x30, call(0x06))] {
+            let account = Account {
+                code,
+                ..Default::default()
+            };
+            state.insert_account(addr(address), account);
+        }
+        let tx = |to| TxEnv {
+            caller: addr(1),
+            gas_limit: 100_000,
+            gas_price: U256::from(7),
+            to: Some(to),
+            ..Default::default()
+        };
+
+        // the failure is not swallowed by the calling frames
+        assert_eq!(
+            transact(&mut state, &env(tx(addr(0x20)))),
+            Err(VmError::UnsupportedPrecompile(addr(6)))
+        );
+        assert_eq!(
+            transact(&mut state, &env(tx(addr(8)))),
+            Err(VmError::UnsupportedPrecompile(addr(8)))
+        );
+        // nor is the gas bought, the nonce bumped or the callee warmed
+        assert_eq!(state.balance(&addr(1)), Ok(U256::exp10(18)));
+        assert_eq!(state.nonce(&addr(1)), Ok(0));
+        assert!(state.warm_account(addr(0x20)));
+    }
+
+    #[test]
+    fn calls_precompiles() {
+        let mut state = funded_state();
+        let tx = TxEnv {
+            caller: addr(1),
+            gas_limit: 100_000,
+            gas_price: U256::from(7),
+            to: Some(addr(4)),
+            data: vec![1, 2, 3],
+            ..Default::default()
+        };
+        let result = transact(&mut state, &env(tx)).unwrap();
+        assert!(result.success);
+        assert_eq!(result.output, [1, 2, 3]);
+        // intrinsic gas, then the base and word cost of IDENTITY
+        assert_eq!(result.gas_used, 21_000 + 3 * 16 + 15 + 3);
+    }
+
+    #[test]
+    fn invalid_transactions_leave_state_untouched() {
+        let mut state = funded_state();
+        let tx = TxEnv {
+            caller: addr(1),
+            gas_limit: 21_000,
+            gas_price: U256::from(7),
+            to: Some(addr(0x20)),
+            ..Default::default()
+        };
+
+        let cases = [
+            (
+                TxEnv {
+                    nonce: Some(1),
+                    ..tx.clone()
+                },
+                InvalidTransaction::NonceTooHigh {
+                    expected: 0,
+                    got: 1,
+                },
+            ),
+            (
+                TxEnv {
+                    gas_limit: 20_999,
+                    ..tx.clone()
+                },
+                InvalidTransaction::IntrinsicGasTooLow,
+            ),
+            (
+                TxEnv {
+                    gas_price: U256::from(6),
+                    ..tx.clone()
+                },
+                InvalidTransaction::GasPriceBelowBaseFee,
+            ),
+            (
+                TxEnv {
+                    value: U256::exp10(18),
+                    ..tx.clone()
+                },
+                InvalidTransaction::InsufficientFunds,
+            ),
+            (
+                TxEnv {
+                    chain_id: Some(5),
+                    ..tx.clone()
+                },
+                InvalidTransaction::ChainIdMismatch {
+                    expected: 1,
+                    got: 5,
+                },
+            ),
+        ];
+        for (tx, error) in cases {
+            assert_eq!(
+                transact(&mut state, &env(tx)),
+                Err(VmError::InvalidTransaction(error))
+            );
+        }
+        assert_eq!(state.balance(&addr(1)).unwrap(), U256::exp10(18));
+        assert_eq!(state.nonce(&addr(1)).unwrap(), 0);
+    }
+
+    #[test]
+    fn blob_transaction_burns_blob_gas() {
+        let mut state = funded_state();
+        // stores BLOBHASH(0), BLOBHASH(1) and BLOBBASEFEE in slots 0 to 2
+        state.insert_account(
+            addr(0x20),
+            Account {
+                code: vec![
+                    0x60, 0x00, 0x49, 0x60, 0x00, 0x55, 0x60, 0x01, 0x49, 0x60, 0x01, 0x55, 0x4a,
+                    0x60, 0x02, 0x55, 0x00,
+                ],
+                ..Default::default()
+            },
+        );
+        let mut blob_hash = H256::repeat_byte(0xb1);
+        blob_hash.0[0] = gas::VERSIONED_HASH_VERSION_KZG;
+        let mut env = env(TxEnv {
+            caller: addr(1),
+            gas_limit: 100_000,
+            gas_price: U256::from(7),
+            to: Some(addr(0x20)),
+            blob_hashes: vec![blob_hash],
+            max_fee_per_blob_gas: Some(U256::from(100)),
+            ..Default::default()
+        });
+        env.block.excess_blob_gas = Some(10_000_000);
+        let blob_base_fee = env.block.blob_base_fee(env.cfg.fork);
+        assert!(blob_base_fee > U256::one());
+
+        let result = transact(&mut state, &env).unwrap();
+        assert!(result.success);
+        assert_eq!(result.blob_gas_used, gas::GAS_PER_BLOB);
+        let storage = |state: &mut State, n| state.storage(&addr(0x20), &H256::from_low_u64_be(n));
+        assert_eq!(storage(&mut state, 0).unwrap(), blob_hash);
+        assert!(storage(&mut state, 1).unwrap().is_zero());
+        assert_eq!(
+            storage(&mut state, 2).unwrap(),
+            H256::from_low_u64_be(blob_base_fee.as_u64())
+        );
+        assert_eq!(
+            state.balance(&addr(1)).unwrap(),
+            U256::exp10(18)
+                - U256::from(result.gas_used * 7)
+                - blob_base_fee * U256::from(gas::GAS_PER_BLOB)
+        );
+
+        let cases = [
+            (
+                TxEnv {
+                    blob_hashes: vec![H256::repeat_byte(2)],
+                    ..env.tx.clone()
+                },
+                InvalidTransaction::InvalidVersionedHash,
+            ),
+            (
+                TxEnv {
+                    max_fee_per_blob_gas: Some(U256::one()),
+                    ..env.tx.clone()
+                },
+                InvalidTransaction::BlobGasPriceBelowBaseFee,
+            ),
+            (
+                TxEnv {
+                    blob_hashes: vec![blob_hash; 7],
+                    ..env.tx.clone()
+                },
+                InvalidTransaction::TooManyBlobs,
+            ),
+            (
+                TxEnv {
+                    to: None,
+                    ..env.tx.clone()
+                },
+                InvalidTransaction::BlobCreateTransaction,
+            ),
+        ];
+        for (tx, error) in cases {
+            let env = Env {
+                tx: TxEnv { nonce: None, ..tx },
+                ..env.clone()
+            };
+            assert_eq!(
+                transact(&mut state, &env),
+                Err(VmError::InvalidTransaction(error))
+            );
+        }
+    }
+
+    #[test]
+    fn prague_charges_the_calldata_floor() {
+        use crate::env::Fork;
+
+        // 100 non-zero bytes cost 21000 + 100 * 16 to send but have a floor
+        // of 21000 + 400 * 10
+        let mut env = env(TxEnv {
+            caller: addr(1),
+            gas_limit: 30_000,
+            gas_price: U256::from(7),
+            to: Some(addr(0x20)),
+            data: vec![1; 100],
+            ..Default::default()
+        });
+        env.cfg.fork = Fork::Prague;
+        let mut state = funded_state();
+        let result = transact(&mut state, &env).unwrap();
+        assert_eq!(result.gas_used, 25_000);
+        assert_eq!(
+            state.balance(&addr(1)).unwrap(),
+            U256::exp10(18) - U256::from(7 * 25_000)
+        );
+
+        env.tx.gas_limit = 24_999;
+        env.tx.nonce = None;
+        assert_eq!(
+            transact(&mut state, &env),
+            Err(VmError::InvalidTransaction(
+                InvalidTransaction::FloorDataGasTooLow
+            ))
+        );
+
+        env.cfg.fork = Fork::Cancun;
+        assert_eq!(transact(&mut state, &env).unwrap().gas_used, 22_600);
+    }
+
+    #[test]
+    fn set_code_transaction_delegates_authority() {
+        use crate::env::Fork;
+        use crate::transaction::LocalSigner;
+
+        let mut state = funded_state();
+        let signer = LocalSigner::from_bytes(&[0x46; 32]).unwrap();
+        let authority = signer.address();
+        state.insert_account(
+            authority,
+            Account {
+                balance: U256::one(),
+                ..Default::default()
+            },
+        );
+        // SSTORE(0, 1)
+        state.insert_account(
+            addr(0x10),
+            Account {
+                code: vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x00],
+                ..Default::default()
+            },
+        );
+        let authorization = |chain_id: u64| {
+            SignedAuthorization {
+                chain_id: U256::from(chain_id),
+                address: addr(0x10),
+                nonce: 0,
+                ..Default::default()
+            }
+            .sign(&signer)
+        };
+        let mut env = env(TxEnv {
+            caller: addr(1),
+            gas_limit: 150_000,
+            gas_price: U256::from(7),
+            to: Some(authority),
+            // the second one is for another chain and the third one has an
+            // invalid parity, both are skipped
+            authorization_list: Some(vec![
+                authorization(0),
+                authorization(5),
+                SignedAuthorization {
+                    y_parity: 2,
+                    ..authorization(0)
+                },
+            ]),
+            ..Default::default()
+        });
+        env.cfg.fork = Fork::Prague;
+
+        let result = transact(&mut state, &env).unwrap();
+        assert!(result.success);
+        assert_eq!(state.code(&authority).unwrap(), delegation_code(addr(0x10)));
+        assert_eq!(state.nonce(&authority).unwrap(), 1);
+        assert_eq!(
+            state.storage(&authority, &H256::zero()).unwrap(),
+            H256::from_low_u64_be(1)
+        );
+        // the authority existed, refunding part of its intrinsic cost
+        let spent = 21_000 + 3 * 25_000 + 3 + 3 + 22_100;
+        assert_eq!(result.gas_refunded, 12_500);
+        assert_eq!(result.gas_used, spent - 12_500);
+
+        env.cfg.fork = Fork::Cancun;
+        env.tx.nonce = None;
+        assert_eq!(
+            transact(&mut state, &env),
+            Err(VmError::InvalidTransaction(
+                InvalidTransaction::SetCodeNotSupported
+            ))
+        );
+    }
+}
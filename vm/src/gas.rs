@@ -0,0 +1,311 @@
+use crate::env::{AccessListItem, Fork};
+use primitive_types::{H256, U256};
+
+pub const TX_BASE: u64 = 21000;
+pub const TX_CREATE: u64 = 32000;
+pub const TX_DATA_ZERO: u64 = 4;
+pub const TX_DATA_NON_ZERO: u64 = 16;
+pub const ACCESS_LIST_ADDRESS: u64 = 2400;
+pub const ACCESS_LIST_STORAGE_KEY: u64 = 1900;
+// EIP-7702
+pub const PER_EMPTY_ACCOUNT_COST: u64 = 25000;
+pub const PER_AUTH_BASE_COST: u64 = 12500;
+// EIP-7623
+pub const TOTAL_COST_FLOOR_PER_TOKEN: u64 = 10;
+
+pub const MEMORY: u64 = 3;
+pub const COPY_WORD: u64 = 3;
+pub const KECCAK_WORD: u64 = 6;
+pub const EXP_BYTE: u64 = 50;
+pub const LOG_TOPIC: u64 = 375;
+pub const LOG_DATA: u64 = 8;
+
+// EIP-2929
+pub const COLD_SLOAD: u64 = 2100;
+pub const COLD_ACCOUNT_ACCESS: u64 = 2600;
+pub const WARM_STORAGE_READ: u64 = 100;
+
+pub const SSTORE_SET: u64 = 20000;
+pub const SSTORE_RESET: u64 = 5000 - COLD_SLOAD;
+// SSTORE fails if no more than this is left (EIP-2200)
+pub const SSTORE_SENTRY: u64 = 2300;
+
+pub const CALL_VALUE: u64 = 9000;
+pub const CALL_STIPEND: u64 = 2300;
+pub const NEW_ACCOUNT: u64 = 25000;
+pub const SELFDESTRUCT_REFUND: i64 = 24000;
+
+pub const CREATE: u64 = 32000;
+pub const CODE_DEPOSIT: u64 = 200;
+pub const INITCODE_WORD: u64 = 2;
+pub const MAX_CODE_SIZE: usize = 24576;
+pub const MAX_INITCODE_SIZE: usize = 2 * MAX_CODE_SIZE;
+
+// precompiles, priced per word of input
+pub const ECRECOVER: u64 = 3000;
+pub const SHA256: u64 = 60;
+pub const SHA256_WORD: u64 = 12;
+pub const RIPEMD160: u64 = 600;
+pub const RIPEMD160_WORD: u64 = 120;
+pub const IDENTITY: u64 = 15;
+pub const IDENTITY_WORD: u64 = 3;
+// EIP-2565
+pub const MODEXP_MIN: u64 = 200;
+
+// EIP-1559
+pub const INITIAL_BASE_FEE: u64 = 1_000_000_000;
+pub const BASE_FEE_MAX_CHANGE_DENOMINATOR: u64 = 8;
+pub const ELASTICITY_MULTIPLIER: u64 = 2;
+
+// EIP-4844
+pub const GAS_PER_BLOB: u64 = 1 << 17;
+pub const MIN_BLOB_BASE_FEE: u64 = 1;
+pub const VERSIONED_HASH_VERSION_KZG: u8 = 0x01;
+
+// Gas of a frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gas {
+    limit: u64,
+    remaining: u64,
+    refunded: i64,
+}
+
+impl Gas {
+    pub fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            remaining: limit,
+            refunded: 0,
+        }
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    pub fn spent(&self) -> u64 {
+        self.limit - self.remaining
+    }
+
+    pub fn refunded(&self) -> i64 {
+        self.refunded
+    }
+
+    // Deducts `cost`, failing without change if not enough gas is left
+    pub fn record_cost(&mut self, cost: u64) -> bool {
+        match self.remaining.checked_sub(cost) {
+            Some(remaining) => {
+                self.remaining = remaining;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Gives back gas a sub-call did not use
+    pub fn erase_cost(&mut self, returned: u64) {
+        self.remaining += returned;
+    }
+
+    pub fn record_refund(&mut self, refund: i64) {
+        self.refunded += refund;
+    }
+
+    // Used when the frame halts exceptionally
+    pub fn spend_all(&mut self) {
+        self.remaining = 0;
+    }
+
+    // Refunds only apply if the frame succeeds
+    pub fn clear_refund(&mut self) {
+        self.refunded = 0;
+    }
+}
+
+// Total cost of `words` words of memory
+pub fn memory_cost(words: u64) -> u64 {
+    MEMORY * words + words * words / 512
+}
+
+pub fn words(size: usize) -> u64 {
+    (size as u64).div_ceil(32)
+}
+
+pub fn copy_cost(size: usize) -> u64 {
+    COPY_WORD * words(size)
+}
+
+// Gas charged before execution starts
+pub fn intrinsic_gas(
+    data: &[u8],
+    is_create: bool,
+    access_list: &[AccessListItem],
+    authorizations: usize,
+    fork: Fork,
+) -> u64 {
+    let zeros = data.iter().filter(|&&b| b == 0).count() as u64;
+    let non_zeros = data.len() as u64 - zeros;
+    let mut gas = TX_BASE + zeros * TX_DATA_ZERO + non_zeros * TX_DATA_NON_ZERO;
+
+    for item in access_list {
+        gas += ACCESS_LIST_ADDRESS + item.storage_keys.len() as u64 * ACCESS_LIST_STORAGE_KEY;
+    }
+
+    gas += authorizations as u64 * PER_EMPTY_ACCOUNT_COST;
+
+    if is_create {
+        gas += TX_CREATE;
+        if fork >= Fork::Shanghai {
+            gas += INITCODE_WORD * words(data.len());
+        }
+    }
+    gas
+}
+
+// Least gas a transaction uses since Prague, whatever it executes and
+// refunds: its calldata priced per token, a non-zero byte being 4 tokens
+// (EIP-7623)
+pub fn calldata_floor(data: &[u8]) -> u64 {
+    let zeros = data.iter().filter(|&&b| b == 0).count() as u64;
+    let tokens = zeros + (data.len() as u64 - zeros) * 4;
+    TX_BASE + tokens * TOTAL_COST_FLOOR_PER_TOKEN
+}
+
+// Refund for clearing a storage slot
+pub fn sstore_clears_schedule(fork: Fork) -> i64 {
+    if fork >= Fork::London {
+        4800
+    } else {
+        15000
+    }
+}
+
+// Largest share of the gas used that is refunded (EIP-3529)
+pub fn max_refund_quotient(fork: Fork) -> u64 {
+    if fork >= Fork::London {
+        5
+    } else {
+        2
+    }
+}
+
+// Cost and refund of an SSTORE of `new` into a slot holding `current`,
+// which held `original` when the transaction started (EIP-2200, EIP-2929)
+pub fn sstore_cost(
+    original: H256,
+    current: H256,
+    new: H256,
+    is_cold: bool,
+    fork: Fork,
+) -> (u64, i64) {
+    let clears = sstore_clears_schedule(fork);
+    let mut cost = if is_cold { COLD_SLOAD } else { 0 };
+    let mut refund = 0;
+
+    if current == new {
+        cost += WARM_STORAGE_READ;
+        return (cost, refund);
+    }
+
+    if original == current {
+        if original.is_zero() {
+            cost += SSTORE_SET;
+        } else {
+            cost += SSTORE_RESET;
+            if new.is_zero() {
+                refund += clears;
+            }
+        }
+        return (cost, refund);
+    }
+
+    cost += WARM_STORAGE_READ;
+    if !original.is_zero() {
+        if current.is_zero() {
+            refund -= clears;
+        } else if new.is_zero() {
+            refund += clears;
+        }
+    }
+    if original == new {
+        refund += if original.is_zero() {
+            (SSTORE_SET - WARM_STORAGE_READ) as i64
+        } else {
+            (SSTORE_RESET - WARM_STORAGE_READ) as i64
+        };
+    }
+    (cost, refund)
+}
+
+// Base fee of the block following a parent that used `gas_used` out of
+// `gas_limit` at `base_fee`
+pub fn next_base_fee(gas_used: u64, gas_limit: u64, base_fee: U256) -> U256 {
+    let target = gas_limit / ELASTICITY_MULTIPLIER;
+    if target == 0 || gas_used == target {
+        return base_fee;
+    }
+    let delta = |diff: u64| {
+        base_fee * U256::from(diff)
+            / U256::from(target)
+            / U256::from(BASE_FEE_MAX_CHANGE_DENOMINATOR)
+    };
+    if gas_used > target {
+        base_fee + std::cmp::max(delta(gas_used - target), U256::one())
+    } else {
+        base_fee.saturating_sub(delta(target - gas_used))
+    }
+}
+
+// Blob gas targeted and allowed per block (EIP-4844, EIP-7691)
+pub fn blob_gas_target(fork: Fork) -> u64 {
+    match fork {
+        Fork::Prague => 6 * GAS_PER_BLOB,
+        _ => 3 * GAS_PER_BLOB,
+    }
+}
+
+pub fn max_blob_gas(fork: Fork) -> u64 {
+    match fork {
+        Fork::Prague => 9 * GAS_PER_BLOB,
+        _ => 6 * GAS_PER_BLOB,
+    }
+}
+
+fn blob_base_fee_update_fraction(fork: Fork) -> u64 {
+    match fork {
+        Fork::Prague => 5_007_716,
+        _ => 3_338_477,
+    }
+}
+
+// Excess blob gas of the block following a parent with the given values
+pub fn next_excess_blob_gas(excess_blob_gas: u64, blob_gas_used: u64, fork: Fork) -> u64 {
+    (excess_blob_gas + blob_gas_used).saturating_sub(blob_gas_target(fork))
+}
+
+pub fn blob_base_fee(excess_blob_gas: u64, fork: Fork) -> U256 {
+    fake_exponential(
+        MIN_BLOB_BASE_FEE,
+        excess_blob_gas,
+        blob_base_fee_update_fraction(fork),
+    )
+}
+
+// Approximates factor * e ** (numerator / denominator) with a Taylor
+// expansion, as specified by EIP-4844
+pub fn fake_exponential(factor: u64, numerator: u64, denominator: u64) -> U256 {
+    let (numerator, denominator) = (U256::from(numerator), U256::from(denominator));
+    let mut output = U256::zero();
+    let mut accum = U256::from(factor) * denominator;
+    let mut i = U256::one();
+    while !accum.is_zero() {
+        output += accum;
+        accum = accum * numerator / (denominator * i);
+        i += U256::one();
+    }
+    output / denominator
+}
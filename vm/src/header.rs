@@ -0,0 +1,440 @@
+use crate::bloom::Bloom;
+use crate::crypto::keccak256;
+use crate::env::{BlockEnv, Fork};
+use crate::error::{InvalidBlock, VmError};
+use crate::gas;
+use crate::rlp::{self, Decodable, Encodable, Header as RlpHeader, RlpError};
+use primitive_types::{H160, H256, U256};
+
+pub const MAX_EXTRA_DATA_SIZE: usize = 32;
+pub const MIN_GAS_LIMIT: u64 = 5000;
+// the gas limit moves by less than 1/1024 of the parent's per block
+pub const GAS_LIMIT_BOUND_DIVISOR: u64 = 1024;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Header {
+    pub parent_hash: H256,
+    pub ommers_hash: H256,
+    pub beneficiary: H160,
+    pub state_root: H256,
+    pub transactions_root: H256,
+    pub receipts_root: H256,
+    pub logs_bloom: Bloom,
+    pub difficulty: U256,
+    pub number: u64,
+    pub gas_limit: u64,
+    pub gas_used: u64,
+    pub timestamp: u64,
+    pub extra_data: Vec<u8>,
+    pub mix_hash: H256,
+    // encoded as 8 bytes
+    pub nonce: u64,
+    // London
+    pub base_fee_per_gas: Option<U256>,
+    // Shanghai
+    pub withdrawals_root: Option<H256>,
+    // Cancun
+    pub blob_gas_used: Option<u64>,
+    pub excess_blob_gas: Option<u64>,
+    pub parent_beacon_block_root: Option<H256>,
+    // Prague
+    pub requests_hash: Option<H256>,
+}
+
+impl Header {
+    pub fn hash(&self) -> H256 {
+        keccak256(&rlp::encode(self))
+    }
+
+    // Environment of the block's transactions
+    pub fn block_env(&self) -> BlockEnv {
+        BlockEnv {
+            number: self.number,
+            coinbase: self.beneficiary,
+            timestamp: self.timestamp,
+            gas_limit: self.gas_limit,
+            basefee: self.base_fee_per_gas.unwrap_or_default(),
+            difficulty: self.difficulty,
+            prevrandao: self.mix_hash,
+            excess_blob_gas: self.excess_blob_gas,
+        }
+    }
+
+    // Base fee of the child block, the initial base fee at the London fork
+    pub fn next_base_fee(&self) -> U256 {
+        match self.base_fee_per_gas {
+            Some(base_fee) => gas::next_base_fee(self.gas_used, self.gas_limit, base_fee),
+            None => U256::from(gas::INITIAL_BASE_FEE),
+        }
+    }
+
+    // Excess blob gas of the child block, zero at the Cancun fork
+    pub fn next_excess_blob_gas(&self, fork: Fork) -> u64 {
+        gas::next_excess_blob_gas(
+            self.excess_blob_gas.unwrap_or_default(),
+            self.blob_gas_used.unwrap_or_default(),
+            fork,
+        )
+    }
+
+    pub fn blob_base_fee(&self, fork: Fork) -> Option<U256> {
+        self.excess_blob_gas
+            .map(|excess_blob_gas| gas::blob_base_fee(excess_blob_gas, fork))
+    }
+}
+
+// Optional fields are appended in order while present
+impl Encodable for Header {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        let mut payload = Vec::new();
+        self.parent_hash.rlp_append(&mut payload);
+        self.ommers_hash.rlp_append(&mut payload);
+        self.beneficiary.rlp_append(&mut payload);
+        self.state_root.rlp_append(&mut payload);
+        self.transactions_root.rlp_append(&mut payload);
+        self.receipts_root.rlp_append(&mut payload);
+        self.logs_bloom.rlp_append(&mut payload);
+        self.difficulty.rlp_append(&mut payload);
+        self.number.rlp_append(&mut payload);
+        self.gas_limit.rlp_append(&mut payload);
+        self.gas_used.rlp_append(&mut payload);
+        self.timestamp.rlp_append(&mut payload);
+        self.extra_data.rlp_append(&mut payload);
+        self.mix_hash.rlp_append(&mut payload);
+        self.nonce.to_be_bytes().as_slice().rlp_append(&mut payload);
+
+        let optional: [Option<&dyn Encodable>; 6] = [
+            self.base_fee_per_gas.as_ref().map(|v| v as _),
+            self.withdrawals_root.as_ref().map(|v| v as _),
+            self.blob_gas_used.as_ref().map(|v| v as _),
+            self.excess_blob_gas.as_ref().map(|v| v as _),
+            self.parent_beacon_block_root.as_ref().map(|v| v as _),
+            self.requests_hash.as_ref().map(|v| v as _),
+        ];
+        for field in optional.into_iter().map_while(|field| field) {
+            field.rlp_append(&mut payload);
+        }
+        out.extend(rlp::wrap_list(&payload));
+    }
+}
+
+impl Decodable for Header {
+    fn rlp_decode(buf: &mut &[u8]) -> Result<Self, RlpError> {
+        let mut payload = RlpHeader::decode_list(buf)?;
+        let buf = &mut payload;
+        let mut header = Header {
+            parent_hash: Decodable::rlp_decode(buf)?,
+            ommers_hash: Decodable::rlp_decode(buf)?,
+            beneficiary: Decodable::rlp_decode(buf)?,
+            state_root: Decodable::rlp_decode(buf)?,
+            transactions_root: Decodable::rlp_decode(buf)?,
+            receipts_root: Decodable::rlp_decode(buf)?,
+            logs_bloom: Decodable::rlp_decode(buf)?,
+            difficulty: Decodable::rlp_decode(buf)?,
+            number: Decodable::rlp_decode(buf)?,
+            gas_limit: Decodable::rlp_decode(buf)?,
+            gas_used: Decodable::rlp_decode(buf)?,
+            timestamp: Decodable::rlp_decode(buf)?,
+            extra_data: Decodable::rlp_decode(buf)?,
+            mix_hash: Decodable::rlp_decode(buf)?,
+            nonce: RlpHeader::decode_bytes(buf)?
+                .try_into()
+                .map(u64::from_be_bytes)
+                .map_err(|_| RlpError::UnexpectedLength)?,
+            ..Default::default()
+        };
+
+        fn optional<T: Decodable>(buf: &mut &[u8]) -> Result<Option<T>, RlpError> {
+            if buf.is_empty() {
+                return Ok(None);
+            }
+            T::rlp_decode(buf).map(Some)
+        }
+        header.base_fee_per_gas = optional(buf)?;
+        header.withdrawals_root = optional(buf)?;
+        header.blob_gas_used = optional(buf)?;
+        header.excess_blob_gas = optional(buf)?;
+        header.parent_beacon_block_root = optional(buf)?;
+        header.requests_hash = optional(buf)?;
+        if !buf.is_empty() {
+            return Err(RlpError::ListLengthMismatch);
+        }
+        Ok(header)
+    }
+}
+
+// keccak256(rlp([])), the ommers hash of blocks without ommers
+pub fn empty_ommers_hash() -> H256 {
+    keccak256(&rlp::wrap_list(&[]))
+}
+
+// Checks `header` against its parent under the rules of `fork`. The body
+// and the post-state are checked by executing the block.
+pub fn validate_header(header: &Header, parent: &Header, fork: Fork) -> Result<(), VmError> {
+    let invalid = |e| Err(VmError::InvalidBlock(e));
+
+    if header.parent_hash != parent.hash() {
+        return invalid(InvalidBlock::ParentHashMismatch);
+    }
+    if Some(header.number) != parent.number.checked_add(1) {
+        return invalid(InvalidBlock::NumberMismatch);
+    }
+    if header.timestamp <= parent.timestamp {
+        return invalid(InvalidBlock::TimestampNotIncreasing);
+    }
+    if header.extra_data.len() > MAX_EXTRA_DATA_SIZE {
+        return invalid(InvalidBlock::ExtraDataTooLong);
+    }
+    if header.gas_used > header.gas_limit {
+        return invalid(InvalidBlock::GasUsedExceedsLimit);
+    }
+
+    // the gas target is kept at the London fork, doubling the limit
+    let parent_gas_limit = if fork >= Fork::London && parent.base_fee_per_gas.is_none() {
+        parent.gas_limit * gas::ELASTICITY_MULTIPLIER
+    } else {
+        parent.gas_limit
+    };
+    if header.gas_limit.abs_diff(parent_gas_limit) >= parent_gas_limit / GAS_LIMIT_BOUND_DIVISOR
+        || header.gas_limit < MIN_GAS_LIMIT
+    {
+        return invalid(InvalidBlock::GasLimitOutOfBounds);
+    }
+
+    check_fields(header, fork)?;
+
+    if let Some(got) = header.base_fee_per_gas {
+        let expected = parent.next_base_fee();
+        if got != expected {
+            return invalid(InvalidBlock::BaseFeeMismatch { expected, got });
+        }
+    }
+
+    if let (Some(blob_gas_used), Some(got)) = (header.blob_gas_used, header.excess_blob_gas) {
+        if blob_gas_used > gas::max_blob_gas(fork) {
+            return invalid(InvalidBlock::BlobGasUsedExceedsMax);
+        }
+        let expected = parent.next_excess_blob_gas(fork);
+        if got != expected {
+            return invalid(InvalidBlock::ExcessBlobGasMismatch { expected, got });
+        }
+    }
+    Ok(())
+}
+
+// Fields that must be present or absent in `fork`
+fn check_fields(header: &Header, fork: Fork) -> Result<(), VmError> {
+    let fields = [
+        (header.base_fee_per_gas.is_some(), Fork::London, "base fee"),
+        (
+            header.withdrawals_root.is_some(),
+            Fork::Shanghai,
+            "withdrawals root",
+        ),
+        (
+            header.blob_gas_used.is_some(),
+            Fork::Cancun,
+            "blob gas used",
+        ),
+        (
+            header.excess_blob_gas.is_some(),
+            Fork::Cancun,
+            "excess blob gas",
+        ),
+        (
+            header.parent_beacon_block_root.is_some(),
+            Fork::Cancun,
+            "parent beacon block root",
+        ),
+        (
+            header.requests_hash.is_some(),
+            Fork::Prague,
+            "requests hash",
+        ),
+    ];
+    for (present, since, name) in fields {
+        if present != (fork >= since) {
+            return Err(VmError::InvalidBlock(InvalidBlock::FieldMismatch(name)));
+        }
+    }
+
+    // no proof of work after the merge
+    if fork >= Fork::Paris {
+        if !header.difficulty.is_zero() {
+            return Err(VmError::InvalidBlock(InvalidBlock::FieldMismatch(
+                "difficulty",
+            )));
+        }
+        if header.nonce != 0 {
+            return Err(VmError::InvalidBlock(InvalidBlock::FieldMismatch("nonce")));
+        }
+        if header.ommers_hash != empty_ommers_hash() {
+            return Err(VmError::InvalidBlock(InvalidBlock::FieldMismatch(
+                "ommers hash",
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn h256(s: &str) -> H256 {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn mainnet_genesis_hash() {
+        let empty_trie = h256("56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421");
+        let genesis = Header {
+            ommers_hash: empty_ommers_hash(),
+            state_root: h256("d7f8974fb5ac78d9ac099b9ad5018bedc2ce0a72dad1827a1709da30580f0544"),
+            transactions_root: empty_trie,
+            receipts_root: empty_trie,
+            difficulty: U256::from(0x400000000u64),
+            gas_limit: 5000,
+            extra_data: hex::decode(
+                "11bbe8db4e347b4e8c937c1c8370e4b5ed33adb3db69cbdb7a38e1e50b1b82fa",
+            )
+            .unwrap(),
+            nonce: 0x42,
+            ..Default::default()
+        };
+        assert_eq!(
+            genesis.hash(),
+            h256("d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3")
+        );
+        assert_eq!(rlp::decode::<Header>(&rlp::encode(&genesis)), Ok(genesis));
+    }
+
+    #[test]
+    fn base_fee_follows_gas_used() {
+        let base_fee = U256::from(gas::INITIAL_BASE_FEE);
+        let next = |gas_used| gas::next_base_fee(gas_used, 30_000_000, base_fee);
+        assert_eq!(next(15_000_000), base_fee);
+        assert_eq!(next(30_000_000), U256::from(1_125_000_000));
+        assert_eq!(next(0), U256::from(875_000_000));
+        // increases by at least one wei
+        assert_eq!(
+            gas::next_base_fee(15_000_001, 30_000_000, U256::from(7)),
+            U256::from(8)
+        );
+    }
+
+    #[test]
+    fn blob_base_fee() {
+        for (factor, numerator, denominator, expected) in [
+            (1, 0, 1, 1),
+            (38493, 0, 1000, 38493),
+            (0, 1234, 2345, 0),
+            (1, 2, 1, 6),
+            (1, 4, 2, 6),
+            (1, 3, 1, 16),
+            (1, 6, 2, 18),
+            (1, 8, 2, 50),
+        ] {
+            assert_eq!(
+                gas::fake_exponential(factor, numerator, denominator),
+                U256::from(expected)
+            );
+        }
+
+        let target = gas::blob_gas_target(Fork::Cancun);
+        assert_eq!(gas::next_excess_blob_gas(0, target, Fork::Cancun), 0);
+        assert_eq!(
+            gas::next_excess_blob_gas(target, 2 * target, Fork::Cancun),
+            2 * target
+        );
+        assert_eq!(gas::blob_base_fee(0, Fork::Cancun), U256::one());
+    }
+
+    #[test]
+    fn validates_against_parent() {
+        let parent = Header {
+            ommers_hash: empty_ommers_hash(),
+            number: 1,
+            gas_limit: 30_000_000,
+            gas_used: 30_000_000,
+            timestamp: 12,
+            base_fee_per_gas: Some(U256::from(gas::INITIAL_BASE_FEE)),
+            withdrawals_root: Some(H256::zero()),
+            blob_gas_used: Some(gas::GAS_PER_BLOB),
+            excess_blob_gas: Some(gas::blob_gas_target(Fork::Cancun)),
+            parent_beacon_block_root: Some(H256::zero()),
+            ..Default::default()
+        };
+        let header = Header {
+            parent_hash: parent.hash(),
+            number: 2,
+            timestamp: 24,
+            base_fee_per_gas: Some(parent.next_base_fee()),
+            excess_blob_gas: Some(gas::GAS_PER_BLOB),
+            ..parent.clone()
+        };
+        assert_eq!(validate_header(&header, &parent, Fork::Cancun), Ok(()));
+
+        let check = |header: Header, error| {
+            assert_eq!(
+                validate_header(&header, &parent, Fork::Cancun),
+                Err(VmError::InvalidBlock(error))
+            );
+        };
+        check(
+            Header {
+                timestamp: 12,
+                ..header.clone()
+            },
+            InvalidBlock::TimestampNotIncreasing,
+        );
+        check(
+            Header {
+                gas_limit: 30_000_000 + 30_000_000 / 1024,
+                ..header.clone()
+            },
+            InvalidBlock::GasLimitOutOfBounds,
+        );
+        check(
+            Header {
+                gas_used: 30_000_001,
+                ..header.clone()
+            },
+            InvalidBlock::GasUsedExceedsLimit,
+        );
+        check(
+            Header {
+                base_fee_per_gas: Some(U256::from(gas::INITIAL_BASE_FEE)),
+                ..header.clone()
+            },
+            InvalidBlock::BaseFeeMismatch {
+                expected: U256::from(1_125_000_000),
+                got: U256::from(gas::INITIAL_BASE_FEE),
+            },
+        );
+        check(
+            Header {
+                excess_blob_gas: Some(0),
+                ..header.clone()
+            },
+            InvalidBlock::ExcessBlobGasMismatch {
+                expected: gas::GAS_PER_BLOB,
+                got: 0,
+            },
+        );
+        check(
+            Header {
+                parent_beacon_block_root: None,
+                ..header.clone()
+            },
+            InvalidBlock::FieldMismatch("parent beacon block root"),
+        );
+        check(
+            Header {
+                difficulty: U256::one(),
+                ..header
+            },
+            InvalidBlock::FieldMismatch("difficulty"),
+        );
+    }
+}
@@ -1,34 +1,158 @@
 #[macro_use]
 pub mod error;
+#[macro_use]
+pub mod rlp;
+pub mod abi;
+pub mod block;
+pub mod bloom;
+pub mod crypto;
+pub mod db;
+pub mod env;
+pub mod executor;
+pub mod gas;
+pub mod header;
 pub mod memory;
 pub mod opcode;
+pub mod precompile;
+pub mod revert;
+pub mod serde_hex;
 pub mod stack;
+pub mod state;
+pub mod system;
+pub mod tracer;
+pub mod transaction;
+pub mod trie;
 
-use error::VmError;
+use env::Env;
+use error::{ErrorContext, VmError};
+use gas::Gas;
 use opcode::{Control, Opcode};
+use primitive_types::{H160, U256};
+use revert::RevertReason;
+use state::{Log, State};
 use std::fmt;
+use std::rc::Rc;
+use tracer::{SharedTracer, Step};
 use tracing::info;
 
-pub struct Vm {
+pub struct Vm<'a> {
     stack: stack::Stack,
     memory: memory::Memory,
     pc: usize, // program counter
     code: Vec<u8>,
     valid_jumps: Vec<usize>,
     return_data: (usize, usize),
+    returndata: Vec<u8>, // output of the last sub-call
+    context: Context,
+    state: State<'a>,
+    env: Rc<Env>,
+    gas: Gas,
+    tracer: Option<SharedTracer>,
+}
+
+// Parameters of the frame being executed
+#[derive(Clone, Debug)]
+pub struct Context {
+    pub address: H160,
+    pub caller: H160,
+    pub value: U256,
+    pub input: Vec<u8>,
+    pub depth: usize,
+    pub is_static: bool,
+    pub gas_limit: u64,
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self {
+            address: H160::zero(),
+            caller: H160::zero(),
+            value: U256::zero(),
+            input: Vec::new(),
+            depth: 0,
+            is_static: false,
+            // unmetered unless a limit is given
+            gas_limit: u64::MAX,
+        }
+    }
 }
 
-#[derive(Debug)]
-pub enum Execution {
-    Continue,
+// Why a frame stopped normally
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SuccessReason {
     Stop,
-    Revert,
     Return,
-    Error(VmError),
+    SelfDestruct,
+}
+
+// How a frame ended after the opcode it stopped at
+#[derive(Clone, Debug, PartialEq)]
+pub enum Exit {
+    Success(SuccessReason),
+    Revert,
+    Halt(VmError),
 }
 
-impl Vm {
+// Outcome of a frame. Only a successful frame keeps its state changes and
+// logs, and a halt consumes all the frame's gas.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExecutionResult {
+    Success {
+        reason: SuccessReason,
+        output: Vec<u8>,
+        gas: Gas,
+        // emitted by the frame and its sub-frames
+        logs: Vec<Log>,
+    },
+    Revert {
+        output: Vec<u8>,
+        gas: Gas,
+    },
+    Halt {
+        reason: VmError,
+        context: ErrorContext,
+        gas: Gas,
+    },
+}
+
+impl ExecutionResult {
+    pub fn is_success(&self) -> bool {
+        matches!(self, ExecutionResult::Success { .. })
+    }
+
+    // Return data of the frame, empty after a halt
+    pub fn output(&self) -> &[u8] {
+        match self {
+            ExecutionResult::Success { output, .. } | ExecutionResult::Revert { output, .. } => {
+                output
+            }
+            ExecutionResult::Halt { .. } => &[],
+        }
+    }
+
+    // Decoded output of a revert, see `revert::decode_revert`
+    pub fn revert_reason(&self, errors: &[abi::Function]) -> Option<RevertReason> {
+        match self {
+            ExecutionResult::Revert { output, .. } => revert::decode_revert(output, errors),
+            _ => None,
+        }
+    }
+
+    pub fn gas(&self) -> &Gas {
+        match self {
+            ExecutionResult::Success { gas, .. }
+            | ExecutionResult::Revert { gas, .. }
+            | ExecutionResult::Halt { gas, .. } => gas,
+        }
+    }
+}
+
+impl<'a> Vm<'a> {
     pub fn new(code: &[u8]) -> Self {
+        Self::with_state(code, State::default(), Context::default())
+    }
+
+    pub fn with_state(code: &[u8], state: State<'a>, context: Context) -> Self {
         // determine valid jumps
         let valid_jumps = {
             let mut jumps = Vec::new();
@@ -55,58 +179,131 @@ impl Vm {
             code: code.to_vec(),
             valid_jumps,
             return_data: (0, 0), // (offset, size)
+            returndata: Vec::new(),
+            gas: Gas::new(context.gas_limit),
+            context,
+            state,
+            env: Rc::new(Env::default()),
+            tracer: None,
         }
     }
 
+    pub fn with_env(mut self, env: Rc<Env>) -> Self {
+        self.env = env;
+        self
+    }
+
+    pub fn with_tracer(mut self, tracer: Option<SharedTracer>) -> Self {
+        self.tracer = tracer;
+        self
+    }
+
+    // Runs the frame to completion. State changes are kept if it stops
+    // normally and discarded on revert or halt.
     // #[tracing::instrument]
-    pub fn run(&mut self) -> Result<(), Execution> {
-        loop {
-            match self.step() {
-                Ok(reason) => match reason {
-                    Execution::Stop => {
-                        info!("Terminated with STOP(0x00)");
-                        return Ok(());
-                    }
-                    Execution::Return => {
-                        info!("Terminated with RETURN(0xf3)");
-                        return Ok(());
-                    }
-                    _ => (()),
-                },
-                Err(e) => {
-                    return Err(e);
+    pub fn run(&mut self) -> ExecutionResult {
+        let checkpoint = self.state.checkpoint();
+        let first_log = self.state.logs().len();
+        let exit = loop {
+            if let Some(exit) = self.step() {
+                break exit;
+            }
+        };
+
+        match exit {
+            Exit::Success(reason) => {
+                info!("Terminated with {:?}", reason);
+                self.state.commit(checkpoint);
+                ExecutionResult::Success {
+                    reason,
+                    output: self.get_return_data(),
+                    gas: self.gas,
+                    logs: self.state.logs()[first_log..].to_vec(),
+                }
+            }
+            Exit::Revert => {
+                self.state.revert(checkpoint);
+                self.gas.clear_refund();
+                ExecutionResult::Revert {
+                    output: self.get_return_data(),
+                    gas: self.gas,
+                }
+            }
+            Exit::Halt(reason) => {
+                let context = ErrorContext {
+                    pc: self.pc,
+                    opcode: self.code.get(self.pc).copied().unwrap_or_default(),
+                    depth: self.context.depth,
+                    address: self.context.address,
+                    gas_remaining: self.gas.remaining(),
+                };
+                info!("Halted with {} at {}", reason, context);
+                self.state.revert(checkpoint);
+                self.gas.clear_refund();
+                self.gas.spend_all();
+                ExecutionResult::Halt {
+                    reason,
+                    context,
+                    gas: self.gas,
                 }
             }
         }
     }
 
-    pub fn step(&mut self) -> Result<Execution, Execution> {
+    // Runs the opcode at the program counter, returning how the frame ended
+    // if it did
+    pub fn step(&mut self) -> Option<Exit> {
         if self.pc >= self.code.len() {
-            return Ok(Execution::Stop);
+            return Some(Exit::Success(SuccessReason::Stop));
         }
+        let Some(tracer) = self.tracer.clone() else {
+            return self.execute();
+        };
 
-        let opcode = if let Some(op) = self.code.get(self.pc).and_then(|&code| Opcode::get(code)) {
-            op
-        } else {
-            return Err(Execution::Error(VmError::InvalidOpcode));
+        tracer.borrow_mut().step(Step {
+            pc: self.pc,
+            op: self.code[self.pc],
+            gas: self.gas.remaining(),
+            refund: self.gas.refunded(),
+            depth: self.context.depth + 1,
+            stack: self.stack.values().to_vec(),
+            memory_size: self.memory.size(),
+        });
+        let exit = self.execute();
+        let error = match &exit {
+            Some(Exit::Halt(e)) => Some(e),
+            _ => None,
         };
+        tracer.borrow_mut().step_end(&self.gas, error);
+        exit
+    }
 
+    fn execute(&mut self) -> Option<Exit> {
+        let code = self.code[self.pc];
+        let Some(opcode) = Opcode::get(code) else {
+            return Some(Exit::Halt(VmError::InvalidOpcode(code)));
+        };
+
+        if !self.gas.record_cost(opcode.gas) {
+            return Some(Exit::Halt(VmError::OutOfGas));
+        }
         let func = opcode.exec;
 
         info!("{:?}", self);
         match func(self) {
             Control::Continue(n) => {
                 self.pc += n;
-                Ok(Execution::Continue)
+                None
             }
             Control::Jump(dest) => {
                 self.pc = dest;
-                Ok(Execution::Continue)
+                None
             }
-            Control::Return => Ok(Execution::Return),
-            Control::Stop => Ok(Execution::Stop),
-            Control::Revert => Err(Execution::Revert),
-            Control::Error(e) => Err(Execution::Error(e)),
+            Control::Return => Some(Exit::Success(SuccessReason::Return)),
+            Control::Stop => Some(Exit::Success(SuccessReason::Stop)),
+            Control::SelfDestruct => Some(Exit::Success(SuccessReason::SelfDestruct)),
+            Control::Revert => Some(Exit::Revert),
+            Control::Error(e) => Some(Exit::Halt(e)),
         }
     }
 
@@ -117,9 +314,62 @@ impl Vm {
     pub fn is_valid_jump(&self, dest: usize) -> bool {
         self.valid_jumps.contains(&dest)
     }
+
+    // Expands memory to cover `size` bytes at `offset` and charges for the
+    // expansion. Zero-sized ranges are free whatever their offset.
+    pub(crate) fn resize_memory(
+        &mut self,
+        offset: U256,
+        size: U256,
+    ) -> Result<(usize, usize), VmError> {
+        if size.is_zero() {
+            return Ok((0, 0));
+        }
+        // no gas limit could pay for memory this large
+        let limit = U256::from(u32::MAX);
+        if offset > limit || size > limit {
+            return Err(VmError::OutOfGas);
+        }
+        let (offset, size) = (offset.as_usize(), size.as_usize());
+
+        let words = gas::words(offset + size);
+        let current = gas::words(self.memory.size());
+        if words > current {
+            let cost = gas::memory_cost(words) - gas::memory_cost(current);
+            if !self.gas.record_cost(cost) {
+                return Err(VmError::OutOfGas);
+            }
+            self.memory.expand(offset, size);
+        }
+        Ok((offset, size))
+    }
+
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    pub fn env(&self) -> &Env {
+        &self.env
+    }
+
+    pub fn gas(&self) -> &Gas {
+        &self.gas
+    }
+
+    pub fn state(&self) -> &State<'a> {
+        &self.state
+    }
+
+    pub fn state_mut(&mut self) -> &mut State<'a> {
+        &mut self.state
+    }
+
+    pub fn into_state(self) -> State<'a> {
+        self.state
+    }
 }
 
-impl fmt::Debug for Vm {
+impl fmt::Debug for Vm<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let (opc, op_name) =
             if let Some(op) = self.code.get(self.pc).and_then(|&code| Opcode::get(code)) {
@@ -138,6 +388,7 @@ impl fmt::Debug for Vm {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use primitive_types::H256;
 
     #[test]
     fn it_works() {
@@ -159,7 +410,194 @@ mod tests {
         ];
 
         let mut vm = Vm::new(&code4);
-        vm.run().ok();
+        vm.run();
         let _sum = vm.get_return_data();
     }
+
+    #[test]
+    fn reverted_call_discards_callee_changes() {
+        let callee = H160::from_low_u64_be(0xbb);
+        let caller = H160::from_low_u64_be(0xaa);
+
+        let mut state = State::new();
+        state.insert_account(
+            callee,
+            state::Account {
+                // SSTORE(0, 1) then REVERT(0, 0)
+                code: vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x60, 0x00, 0x60, 0x00, 0xfd],
+                ..Default::default()
+            },
+        );
+
+        let code = vec![
+            0x60, 0x02, // PUSH1 (value)
+            0x60, 0x00, // PUSH1 (key)
+            0x55, // SSTORE
+            0x60, 0x00, // PUSH1 (out size)
+            0x60, 0x00, // PUSH1 (out offset)
+            0x60, 0x00, // PUSH1 (in size)
+            0x60, 0x00, // PUSH1 (in offset)
+            0x60, 0x00, // PUSH1 (value)
+            0x60, 0xbb, // PUSH1 (callee)
+            0x60, 0x00, // PUSH1 (gas)
+            0xf1, // CALL
+            0x60, 0x01, // PUSH1 (key)
+            0x55, // SSTORE      (store call result)
+            0x00, // STOP
+        ];
+
+        let context = Context {
+            address: caller,
+            ..Default::default()
+        };
+        let mut vm = Vm::with_state(&code, state, context);
+        assert!(vm.run().is_success());
+
+        let mut state = vm.into_state();
+        let mut slot = |address, key| {
+            state
+                .storage(&address, &primitive_types::H256::from_low_u64_be(key))
+                .unwrap()
+        };
+        assert_eq!(slot(caller, 0).to_low_u64_be(), 2);
+        assert!(slot(caller, 1).is_zero());
+        assert!(slot(callee, 0).is_zero());
+    }
+
+    // Runs `code` and returns the word left on top of the stack
+    fn eval(mut code: Vec<u8>) -> U256 {
+        // MSTORE(0, top) then RETURN(0, 32)
+        code.extend([0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3]);
+        let mut vm = Vm::new(&code);
+        assert!(vm.run().is_success());
+        U256::from_big_endian(&vm.get_return_data())
+    }
+
+    #[test]
+    fn push_cut_short_by_the_end_of_the_code_is_right_padded() {
+        for (code, expected) in [
+            (vec![0x61, 0xab], U256::from(0xab00)),
+            (vec![0x7f, 0x01], U256::one() << 248),
+            (vec![0x61], U256::zero()),
+        ] {
+            let mut vm = Vm::new(&code);
+            assert!(vm.run().is_success());
+            let mut word = [0u8; 32];
+            expected.to_big_endian(&mut word);
+            assert_eq!(vm.stack.values(), [H256(word)]);
+        }
+    }
+
+    #[test]
+    fn shifts_and_byte() {
+        // SHL(4, 0xff) and SHR(4, 0xff)
+        assert_eq!(eval(vec![0x60, 0xff, 0x60, 0x04, 0x1b]), U256::from(0xff0));
+        assert_eq!(eval(vec![0x60, 0xff, 0x60, 0x04, 0x1c]), U256::from(0xf));
+        // BYTE(31, 0xab) and BYTE(32, 0xab)
+        assert_eq!(eval(vec![0x60, 0xab, 0x60, 0x1f, 0x1a]), U256::from(0xab));
+        assert_eq!(eval(vec![0x60, 0xab, 0x60, 0x20, 0x1a]), U256::zero());
+    }
+
+    #[test]
+    fn modular_arithmetic_does_not_overflow() {
+        let max = |code: &mut Vec<u8>| {
+            code.push(0x7f);
+            code.extend([0xff; 32]);
+        };
+        // ADDMOD(MAX, MAX, 7), as MAX = 1 and 2 ** 256 = 2 (mod 7)
+        let mut code = vec![0x60, 0x07];
+        max(&mut code);
+        max(&mut code);
+        code.push(0x08);
+        assert_eq!(eval(code), U256::from(2));
+
+        // MULMOD(MAX, MAX, 5), as MAX = 0 (mod 5)
+        let mut code = vec![0x60, 0x05];
+        max(&mut code);
+        max(&mut code);
+        code.push(0x09);
+        assert_eq!(eval(code), U256::zero());
+
+        // SMOD(1, 0)
+        assert_eq!(eval(vec![0x60, 0x00, 0x60, 0x01, 0x07]), U256::zero());
+    }
+
+    #[test]
+    fn extcode_of_delegated_accounts_reads_the_designator() {
+        let delegated = H160::from_low_u64_be(0xdd);
+        let designator = state::delegation_code(H160::from_low_u64_be(0x10));
+        // <opcode>(0xdd) then RETURN(0, 32) of the result
+        let run = |opcode| {
+            let code = [
+                0x60, 0xdd, opcode, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3,
+            ];
+            let mut state = State::new();
+            state.insert_account(
+                delegated,
+                state::Account {
+                    code: designator.clone(),
+                    ..Default::default()
+                },
+            );
+            let mut vm = Vm::with_state(&code, state, Context::default());
+            assert!(vm.run().is_success());
+            vm.get_return_data()
+        };
+        assert_eq!(U256::from_big_endian(&run(0x3b)), U256::from(23));
+        assert_eq!(run(0x3f), crypto::keccak256(&designator).as_bytes());
+    }
+
+    #[test]
+    fn execution_results() {
+        // RETURN(0, 32) of 0x2a
+        let mut vm = Vm::new(&[0x60, 0x2a, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3]);
+        match vm.run() {
+            ExecutionResult::Success { reason, output, .. } => {
+                assert_eq!(reason, SuccessReason::Return);
+                assert_eq!(U256::from_big_endian(&output), U256::from(0x2a));
+            }
+            result => panic!("unexpected {:?}", result),
+        }
+
+        // REVERT(31, 1) of 0x2a keeps its output
+        let mut vm = Vm::new(&[0x60, 0x2a, 0x60, 0x00, 0x52, 0x60, 0x01, 0x60, 0x1f, 0xfd]);
+        let result = vm.run();
+        assert!(matches!(result, ExecutionResult::Revert { .. }));
+        assert_eq!(result.output(), [0x2a]);
+        assert_eq!(vm.get_return_data(), [0x2a]);
+
+        // JUMP(3), which is not a JUMPDEST
+        let context = Context {
+            gas_limit: 1000,
+            ..Default::default()
+        };
+        let mut vm = Vm::with_state(&[0x60, 0x03, 0x56, 0x00], State::new(), context);
+        let result = vm.run();
+        let ExecutionResult::Halt {
+            reason,
+            context,
+            gas,
+        } = &result
+        else {
+            panic!("unexpected {:?}", result);
+        };
+        assert_eq!(*reason, VmError::InvalidJump(U256::from(3)));
+        assert_eq!(
+            *context,
+            ErrorContext {
+                pc: 2,
+                opcode: 0x56,
+                depth: 0,
+                address: H160::zero(),
+                gas_remaining: 1000 - 3 - 8,
+            }
+        );
+        assert_eq!(
+            format!("{} at {}", reason, context),
+            "invalid jump destination 3 at pc 2 (JUMP 0x56), depth 0, address \
+             0x0000000000000000000000000000000000000000, 989 gas left"
+        );
+        assert_eq!(gas.remaining(), 0);
+        assert!(result.output().is_empty());
+    }
 }
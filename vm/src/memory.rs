@@ -31,7 +31,7 @@ impl Memory {
 
     pub fn read(&mut self, offset: usize, size: usize) -> Vec<u8> {
         self.expand(offset, size);
-        (&self.data[offset..(offset + size)]).to_vec()
+        self.data[offset..(offset + size)].to_vec()
     }
 
     // Stores a value in memory
@@ -42,8 +42,13 @@ impl Memory {
     }
 
     // Untouched memory expansion in 32 byte steps
-    fn expand(&mut self, offset: usize, size: usize) {
-        if offset + size - 1 >= self.data.len() {
+    pub fn expand(&mut self, offset: usize, size: usize) {
+        // zero-sized accesses never expand memory
+        if size == 0 {
+            return;
+        }
+
+        if offset + size > self.data.len() {
             let r = (offset + size) % 32;
             if r == 0 {
                 self.data.resize(offset + size, 0);
@@ -54,6 +59,12 @@ impl Memory {
     }
 }
 
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl fmt::Debug for Memory {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", hex::encode(&self.data))
@@ -1,7 +1,8 @@
 use super::Control;
+use crate::gas;
 use crate::opcode::i256::I256;
-use crate::Vm;
-use primitive_types::{H256, U256};
+use crate::{Vm, VmError};
+use primitive_types::{H256, U256, U512};
 
 // 0x01
 pub fn add(vm: &mut Vm) -> Control {
@@ -62,11 +63,12 @@ pub fn srem(vm: &mut Vm) -> Control {
 // 0x08
 pub fn addmod(vm: &mut Vm) -> Control {
     pop_u256!(vm, a, b, c);
+    // the sum is not truncated to 256 bits
     let res = if c == U256::zero() {
         U256::zero()
     } else {
-        let (x, _) = a.overflowing_add(b);
-        x.checked_rem(c).unwrap()
+        let x = (U512::from(a) + U512::from(b)) % U512::from(c);
+        U256::try_from(x).unwrap()
     };
 
     push_u256!(vm, res);
@@ -76,11 +78,11 @@ pub fn addmod(vm: &mut Vm) -> Control {
 // 0x09
 pub fn mulmod(vm: &mut Vm) -> Control {
     pop_u256!(vm, a, b, c);
+    // the product is not truncated to 256 bits
     let res = if c == U256::zero() {
         U256::zero()
     } else {
-        let (x, _) = a.overflowing_mul(b);
-        x.checked_rem(c).unwrap()
+        U256::try_from(a.full_mul(b) % U512::from(c)).unwrap()
     };
 
     push_u256!(vm, res);
@@ -90,13 +92,27 @@ pub fn mulmod(vm: &mut Vm) -> Control {
 // 0x0a
 pub fn exp(vm: &mut Vm) -> Control {
     pop_u256!(vm, a, b);
+    gas!(vm, gas::EXP_BYTE * b.bits().div_ceil(8) as u64);
     let (res, _) = a.overflowing_pow(b);
     push_u256!(vm, res);
     Control::Continue(1)
 }
 
 // 0x0b
-// pub fn signextend(vm: &mut Vm) -> Control {
-//     pop_u256!(vm, a, b);
-//     Control::Continue(1)
-// }
+pub fn signextend(vm: &mut Vm) -> Control {
+    pop_u256!(vm, b, x);
+    let res = if b < U256::from(31) {
+        // sign bit of the lowest b + 1 bytes
+        let bit = b.as_usize() * 8 + 7;
+        let mask = (U256::one() << bit) - U256::one();
+        if x.bit(bit) {
+            x | !mask
+        } else {
+            x & mask
+        }
+    } else {
+        x
+    };
+    push_u256!(vm, res);
+    Control::Continue(1)
+}
@@ -0,0 +1,72 @@
+use super::Control;
+use crate::env::Fork;
+use crate::{Vm, VmError};
+use primitive_types::{H256, U256};
+
+// 0x40
+pub fn blockhash(vm: &mut Vm) -> Control {
+    pop_u256!(vm, number);
+    let current = vm.env.block.number;
+    // only the 256 most recent blocks are available
+    let hash = if number < U256::from(current) && number + 256 >= U256::from(current) {
+        check!(vm.state.block_hash(number.as_u64()))
+    } else {
+        H256::zero()
+    };
+    push!(vm, hash);
+    Control::Continue(1)
+}
+
+// 0x41
+pub fn coinbase(vm: &mut Vm) -> Control {
+    push!(vm, H256::from(vm.env.block.coinbase));
+    Control::Continue(1)
+}
+
+// 0x42
+pub fn timestamp(vm: &mut Vm) -> Control {
+    push_u256!(vm, U256::from(vm.env.block.timestamp));
+    Control::Continue(1)
+}
+
+// 0x43
+pub fn number(vm: &mut Vm) -> Control {
+    push_u256!(vm, U256::from(vm.env.block.number));
+    Control::Continue(1)
+}
+
+// 0x44, DIFFICULTY before the merge
+pub fn prevrandao(vm: &mut Vm) -> Control {
+    if vm.env.cfg.fork >= Fork::Paris {
+        push!(vm, vm.env.block.prevrandao);
+    } else {
+        push_u256!(vm, vm.env.block.difficulty);
+    }
+    Control::Continue(1)
+}
+
+// 0x45
+pub fn gaslimit(vm: &mut Vm) -> Control {
+    push_u256!(vm, U256::from(vm.env.block.gas_limit));
+    Control::Continue(1)
+}
+
+// 0x46
+pub fn chainid(vm: &mut Vm) -> Control {
+    push_u256!(vm, U256::from(vm.env.cfg.chain_id));
+    Control::Continue(1)
+}
+
+// 0x48
+pub fn basefee(vm: &mut Vm) -> Control {
+    check_fork!(vm, Fork::London);
+    push_u256!(vm, vm.env.block.basefee);
+    Control::Continue(1)
+}
+
+// 0x4a
+pub fn blobbasefee(vm: &mut Vm) -> Control {
+    check_fork!(vm, Fork::Cancun);
+    push_u256!(vm, vm.env.block.blob_base_fee(vm.env.cfg.fork));
+    Control::Continue(1)
+}
@@ -0,0 +1,177 @@
+use super::Control;
+use crate::crypto::keccak256;
+use crate::env::Fork;
+use crate::gas;
+use crate::{Vm, VmError};
+use primitive_types::{H160, H256, U256};
+
+// 0x30
+pub fn address(vm: &mut Vm) -> Control {
+    push!(vm, H256::from(vm.context.address));
+    Control::Continue(1)
+}
+
+// 0x31
+pub fn balance(vm: &mut Vm) -> Control {
+    pop_address!(vm, address);
+    let cost = access_account(vm, address);
+    gas!(vm, cost);
+    let balance = check!(vm.state.balance(&address));
+    push_u256!(vm, balance);
+    Control::Continue(1)
+}
+
+// 0x32
+pub fn origin(vm: &mut Vm) -> Control {
+    push!(vm, H256::from(vm.env.tx.caller));
+    Control::Continue(1)
+}
+
+// 0x33
+pub fn caller(vm: &mut Vm) -> Control {
+    push!(vm, H256::from(vm.context.caller));
+    Control::Continue(1)
+}
+
+// 0x34
+pub fn callvalue(vm: &mut Vm) -> Control {
+    push_u256!(vm, vm.context.value);
+    Control::Continue(1)
+}
+
+// 0x35
+pub fn calldataload(vm: &mut Vm) -> Control {
+    pop_u256!(vm, offset);
+    let mut value = [0u8; 32];
+    copy_padded(&mut value, &vm.context.input, offset);
+    push!(vm, H256(value));
+    Control::Continue(1)
+}
+
+// 0x36
+pub fn calldatasize(vm: &mut Vm) -> Control {
+    push_u256!(vm, U256::from(vm.context.input.len()));
+    Control::Continue(1)
+}
+
+// 0x37
+pub fn calldatacopy(vm: &mut Vm) -> Control {
+    pop_u256!(vm, mem_offset, data_offset, size);
+    let (mem_offset, size) = memory_range!(vm, mem_offset, size);
+    gas!(vm, gas::copy_cost(size));
+    let mut data = vec![0u8; size];
+    copy_padded(&mut data, &vm.context.input, data_offset);
+    vm.memory.write(mem_offset, &data);
+    Control::Continue(1)
+}
+
+// 0x3a
+pub fn gasprice(vm: &mut Vm) -> Control {
+    let price = vm.env.tx.effective_gas_price(vm.env.block.basefee);
+    push_u256!(vm, price);
+    Control::Continue(1)
+}
+
+// 0x3b
+// The EXTCODE* opcodes read the code of a delegated account (EIP-7702) as
+// is, i.e. the 23 byte designator 0xef0100 || address, without following it
+pub fn extcodesize(vm: &mut Vm) -> Control {
+    pop_address!(vm, address);
+    let cost = access_account(vm, address);
+    gas!(vm, cost);
+    let code = check!(vm.state.code(&address));
+    push_u256!(vm, U256::from(code.len()));
+    Control::Continue(1)
+}
+
+// 0x3c
+// Copies the designator of a delegated account, like EXTCODESIZE
+pub fn extcodecopy(vm: &mut Vm) -> Control {
+    pop_address!(vm, address);
+    pop_u256!(vm, mem_offset, code_offset, size);
+    let cost = access_account(vm, address);
+    gas!(vm, cost);
+    let (mem_offset, size) = memory_range!(vm, mem_offset, size);
+    gas!(vm, gas::copy_cost(size));
+    let code = check!(vm.state.code(&address));
+    let mut data = vec![0u8; size];
+    copy_padded(&mut data, &code, code_offset);
+    vm.memory.write(mem_offset, &data);
+    Control::Continue(1)
+}
+
+// 0x3d
+pub fn returndatasize(vm: &mut Vm) -> Control {
+    push_u256!(vm, U256::from(vm.returndata.len()));
+    Control::Continue(1)
+}
+
+// 0x3e
+pub fn returndatacopy(vm: &mut Vm) -> Control {
+    pop_u256!(vm, mem_offset, data_offset, size);
+    match data_offset.checked_add(size) {
+        Some(end) if end <= U256::from(vm.returndata.len()) => (),
+        _ => return Control::Error(VmError::ReturnDataOutOfBounds),
+    }
+    let (mem_offset, size) = memory_range!(vm, mem_offset, size);
+    gas!(vm, gas::copy_cost(size));
+    let data_offset = data_offset.as_usize();
+    let data = vm.returndata[data_offset..(data_offset + size)].to_vec();
+    vm.memory.write(mem_offset, &data);
+    Control::Continue(1)
+}
+
+// 0x3f
+// Hash of the designator for a delegated account, like EXTCODESIZE
+pub fn extcodehash(vm: &mut Vm) -> Control {
+    pop_address!(vm, address);
+    let cost = access_account(vm, address);
+    gas!(vm, cost);
+    let hash = if check!(vm.state.is_empty(&address)) {
+        H256::zero()
+    } else {
+        keccak256(&check!(vm.state.code(&address)))
+    };
+    push!(vm, hash);
+    Control::Continue(1)
+}
+
+// 0x47
+pub fn selfbalance(vm: &mut Vm) -> Control {
+    let balance = check!(vm.state.balance(&vm.context.address));
+    push_u256!(vm, balance);
+    Control::Continue(1)
+}
+
+// 0x49
+pub fn blobhash(vm: &mut Vm) -> Control {
+    check_fork!(vm, Fork::Cancun);
+    pop_u256!(vm, index);
+    let hashes = &vm.env.tx.blob_hashes;
+    let hash = if index < U256::from(hashes.len()) {
+        hashes[index.as_usize()]
+    } else {
+        H256::zero()
+    };
+    push!(vm, hash);
+    Control::Continue(1)
+}
+
+// Warms the account and returns the cost of accessing it (EIP-2929)
+pub(crate) fn access_account(vm: &mut Vm, address: H160) -> u64 {
+    if vm.state.warm_account(address) {
+        gas::COLD_ACCOUNT_ACCESS
+    } else {
+        gas::WARM_STORAGE_READ
+    }
+}
+
+// Copies `src[offset..]` into `dest`, leaving the bytes past the end of `src` zeroed
+pub(crate) fn copy_padded(dest: &mut [u8], src: &[u8], offset: U256) {
+    if offset >= U256::from(src.len()) {
+        return;
+    }
+    let offset = offset.as_usize();
+    let n = std::cmp::min(dest.len(), src.len() - offset);
+    dest[..n].copy_from_slice(&src[offset..(offset + n)]);
+}
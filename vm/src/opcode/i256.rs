@@ -9,6 +9,7 @@ const SIGN_MASK: U256 = U256([
     0x7fffffffffffffff,
 ]);
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum Sign {
     Positive,
@@ -92,6 +93,9 @@ impl Rem for I256 {
     type Output = I256;
 
     fn rem(self, other: I256) -> I256 {
+        if other == I256::zero() {
+            return I256::zero();
+        }
         let r = self.1 % other.1;
 
         if r == U256::zero() {
@@ -137,4 +141,15 @@ mod test {
         let q = hundred / neg_one;
         assert_eq!(q, neg_hundred);
     }
+
+    #[test]
+    fn remainder() {
+        let hundred = I256(Sign::Positive, U256::from(100));
+        let neg_hundred = I256(Sign::Negative, U256::from(100));
+        let seven = I256(Sign::Positive, U256::from(7));
+
+        // takes the sign of the dividend
+        assert_eq!(neg_hundred % seven, I256(Sign::Negative, U256::from(2)));
+        assert_eq!(hundred % I256::zero(), I256::zero());
+    }
 }
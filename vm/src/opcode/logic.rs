@@ -1,6 +1,6 @@
 use super::Control;
 use crate::opcode::i256::I256;
-use crate::{Vm, VmError};
+use crate::Vm;
 use core::ops::{BitAnd, BitOr, BitXor};
 use primitive_types::{H256, U256};
 
@@ -90,14 +90,13 @@ pub fn not(vm: &mut Vm) -> Control {
 
 // 0x1a
 pub fn byte(vm: &mut Vm) -> Control {
-    pop_usize!(vm, offset);
-    pop_u256!(vm, value);
+    pop_u256!(vm, offset, value);
 
-    if offset > 32 {
+    if offset >= U256::from(32) {
         push_u256!(vm, U256::zero());
     } else {
         // U256 is little endian
-        let byte = value.byte(32 - offset - 1);
+        let byte = value.byte(31 - offset.as_usize());
         push_u256!(vm, U256::from(byte));
     }
     Control::Continue(1)
@@ -105,7 +104,7 @@ pub fn byte(vm: &mut Vm) -> Control {
 
 // 0x1b
 pub fn shl(vm: &mut Vm) -> Control {
-    pop_u256!(vm, value, shift);
+    pop_u256!(vm, shift, value);
     if shift >= U256::from(256) || value == U256::zero() {
         push_u256!(vm, U256::zero());
     } else {
@@ -116,7 +115,7 @@ pub fn shl(vm: &mut Vm) -> Control {
 
 // 0x1c
 pub fn shr(vm: &mut Vm) -> Control {
-    pop_u256!(vm, value, shift);
+    pop_u256!(vm, shift, value);
     if shift >= U256::from(256) || value == U256::zero() {
         push_u256!(vm, U256::zero());
     } else {
@@ -124,3 +123,23 @@ pub fn shr(vm: &mut Vm) -> Control {
     }
     Control::Continue(1)
 }
+
+// 0x1d
+pub fn sar(vm: &mut Vm) -> Control {
+    pop_u256!(vm, shift, value);
+    let negative = value.bit(255);
+    let res = if shift >= U256::from(255) {
+        if negative {
+            U256::MAX
+        } else {
+            U256::zero()
+        }
+    } else if negative {
+        // shift in ones from the left
+        !(!value >> shift)
+    } else {
+        value >> shift
+    };
+    push_u256!(vm, res);
+    Control::Continue(1)
+}
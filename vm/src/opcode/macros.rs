@@ -76,23 +76,6 @@ macro_rules! push_u256 {
     };
 }
 
-macro_rules! pop_usize {
-    ($vm: expr, $($id:ident),*) => {
-        $(
-            let $id = match $vm.stack.pop() {
-                Ok(v) => {
-                    let x = h256_to_u256!(v);
-                    if x > U256::from(usize::MAX) {
-                        return Control::Error(VmError::UnsupportedOperation);
-                    }
-                    x.as_usize()
-                },
-                Err(e) => return Control::Error(e)
-            };
-        )*
-    };
-}
-
 // pop signed integers
 macro_rules! pop_i256 {
     ($vm: expr, $($id:ident),*) => {
@@ -107,3 +90,49 @@ macro_rules! pop_i256 {
             )*
         };
 }
+
+macro_rules! pop_address {
+    ($vm: expr, $($id:ident),*) => {
+        $(
+            let $id = match $vm.stack.pop() {
+                Ok(v) => H160::from(v),
+                Err(e) => return Control::Error(e)
+            };
+        )*
+    };
+}
+
+// unwraps a result or halts with its error
+macro_rules! check {
+    ($e: expr) => {
+        match $e {
+            Ok(v) => v,
+            Err(e) => return Control::Error(e),
+        }
+    };
+}
+
+// charges dynamic gas or halts out of gas
+macro_rules! gas {
+    ($vm: expr, $cost: expr) => {
+        if !$vm.gas.record_cost($cost) {
+            return Control::Error(VmError::OutOfGas);
+        }
+    };
+}
+
+// halts on opcodes introduced after the configured fork
+macro_rules! check_fork {
+    ($vm: expr, $fork: expr) => {
+        if $vm.env.cfg.fork < $fork {
+            return Control::Error(VmError::InvalidOpcode($vm.code[$vm.pc]));
+        }
+    };
+}
+
+// expands memory for a range given as stack words and charges for it
+macro_rules! memory_range {
+    ($vm: expr, $offset: expr, $size: expr) => {
+        check!($vm.resize_memory($offset, $size))
+    };
+}
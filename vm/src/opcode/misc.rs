@@ -1,5 +1,7 @@
+use super::environment::copy_padded;
 use super::Control;
-use crate::{Vm, VmError};
+use crate::gas;
+use crate::{crypto, Vm, VmError};
 use primitive_types::{H256, U256};
 
 // 0x00
@@ -7,6 +9,16 @@ pub fn stop(_vm: &mut Vm) -> Control {
     Control::Stop
 }
 
+// 0x20
+pub fn keccak256(vm: &mut Vm) -> Control {
+    pop_u256!(vm, offset, size);
+    let (offset, size) = memory_range!(vm, offset, size);
+    gas!(vm, gas::KECCAK_WORD * gas::words(size));
+    let data = vm.memory.read(offset, size);
+    push!(vm, crypto::keccak256(&data));
+    Control::Continue(1)
+}
+
 // 0x38
 pub fn codesize(vm: &mut Vm) -> Control {
     let size = vm.code.len();
@@ -16,43 +28,52 @@ pub fn codesize(vm: &mut Vm) -> Control {
 
 // 0x39
 pub fn codecopy(vm: &mut Vm) -> Control {
-    pop_usize!(vm, mem_offset, code_offset, code_size);
-    let code_slice = vm.code[code_offset..(code_offset + code_size)].to_vec();
+    pop_u256!(vm, mem_offset, code_offset, code_size);
+    let (mem_offset, code_size) = memory_range!(vm, mem_offset, code_size);
+    gas!(vm, gas::copy_cost(code_size));
+    let mut code_slice = vec![0u8; code_size];
+    copy_padded(&mut code_slice, &vm.code, code_offset);
     vm.memory.write(mem_offset, &code_slice);
     Control::Continue(1)
 }
 
 // 0x56
 pub fn jump(vm: &mut Vm) -> Control {
-    pop_usize!(vm, dest);
-    if vm.is_valid_jump(dest) {
-        Control::Jump(dest)
-    } else {
-        Control::Error(VmError::InvalidJump)
-    }
+    pop_u256!(vm, dest);
+    jump_to(vm, dest)
 }
 
 // 0x57
 pub fn jumpi(vm: &mut Vm) -> Control {
-    pop_usize!(vm, dest);
+    pop_u256!(vm, dest);
     pop_u256!(vm, a);
     if a != U256::zero() {
-        if vm.is_valid_jump(dest) {
-            Control::Jump(dest)
-        } else {
-            Control::Error(VmError::InvalidJump)
-        }
+        jump_to(vm, dest)
     } else {
         Control::Continue(1)
     }
 }
 
+fn jump_to(vm: &Vm, dest: U256) -> Control {
+    if dest <= U256::from(usize::MAX) && vm.is_valid_jump(dest.as_usize()) {
+        Control::Jump(dest.as_usize())
+    } else {
+        Control::Error(VmError::InvalidJump(dest))
+    }
+}
+
 // 0x58
 pub fn pc(vm: &mut Vm) -> Control {
     push_u256!(vm, U256::from(vm.pc));
     Control::Continue(1)
 }
 
+// 0x5a
+pub fn gas(vm: &mut Vm) -> Control {
+    push_u256!(vm, U256::from(vm.gas.remaining()));
+    Control::Continue(1)
+}
+
 // 0x5b
 pub fn jumpdest(_vm: &mut Vm) -> Control {
     Control::Continue(1)
@@ -60,19 +81,19 @@ pub fn jumpdest(_vm: &mut Vm) -> Control {
 
 // 0xf3
 pub fn return_(vm: &mut Vm) -> Control {
-    pop_usize!(vm, offset, size);
-    vm.return_data = (offset, size);
+    pop_u256!(vm, offset, size);
+    vm.return_data = memory_range!(vm, offset, size);
     Control::Return
 }
 
 // 0xfd
 pub fn revert(vm: &mut Vm) -> Control {
-    pop_usize!(vm, offset, size);
-    vm.return_data = (offset, size);
+    pop_u256!(vm, offset, size);
+    vm.return_data = memory_range!(vm, offset, size);
     Control::Revert
 }
 
 // 0xfe
-pub fn invalid(_vm: &mut Vm) -> Control {
-    Control::Error(VmError::InvalidOpcode)
+pub fn invalid(vm: &mut Vm) -> Control {
+    Control::Error(VmError::InvalidOpcode(vm.code[vm.pc]))
 }
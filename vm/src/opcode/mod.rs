@@ -1,16 +1,27 @@
 #[macro_use]
 mod macros;
 mod arithmetic;
+mod block;
+mod environment;
 mod i256;
 mod logic;
 mod misc;
+mod storage;
 mod store;
+mod system;
 
 use crate::{error::VmError, Vm};
 use arithmetic::*;
+use block::*;
+use environment::*;
 use logic::*;
 use misc::*;
+use storage::*;
 use store::*;
+use system::*;
+
+pub use system::CALL_DEPTH_LIMIT;
+pub(crate) use system::{call_frame, create_frame, Frame};
 
 type OpcodeFunction = fn(&mut Vm) -> Control;
 
@@ -20,17 +31,19 @@ pub enum Control {
     Jump(usize),
     Stop,
     Return,
+    SelfDestruct,
     Revert,
     Error(VmError),
 }
 
 macro_rules! make_opcode {
-    ($code: expr, $name: ident, $fn: ident) => {
+    ($code: expr, $name: ident, $fn: ident, $gas: expr) => {
         #[allow(dead_code)]
         pub const $name: Opcode = Opcode {
             code: $code,
             mnemonic: stringify!($name),
             exec: $fn,
+            gas: $gas,
         };
     };
 }
@@ -39,119 +52,175 @@ pub struct Opcode {
     pub code: u8,
     pub mnemonic: &'static str,
     pub exec: OpcodeFunction,
+    // constant gas charged before execution
+    pub gas: u64,
 }
 
 impl Opcode {
-    make_opcode!(0x00, STOP, stop);
-    make_opcode!(0x01, ADD, add);
-    make_opcode!(0x02, MUL, mul);
-    make_opcode!(0x03, SUB, sub);
-    make_opcode!(0x04, DIV, div);
-    make_opcode!(0x05, SDIV, sdiv);
-    make_opcode!(0x06, MOD, rem);
-    make_opcode!(0x07, SMOD, srem);
-    make_opcode!(0x08, ADDMOD, addmod);
-    make_opcode!(0x09, MULMOD, mulmod);
-    make_opcode!(0x0a, EXP, exp);
-    // make_opcode!(0x0b, SIGNEXTEND, signextend);
+    make_opcode!(0x00, STOP, stop, 0);
+    make_opcode!(0x01, ADD, add, 3);
+    make_opcode!(0x02, MUL, mul, 5);
+    make_opcode!(0x03, SUB, sub, 3);
+    make_opcode!(0x04, DIV, div, 5);
+    make_opcode!(0x05, SDIV, sdiv, 5);
+    make_opcode!(0x06, MOD, rem, 5);
+    make_opcode!(0x07, SMOD, srem, 5);
+    make_opcode!(0x08, ADDMOD, addmod, 8);
+    make_opcode!(0x09, MULMOD, mulmod, 8);
+    make_opcode!(0x0a, EXP, exp, 10);
+    make_opcode!(0x0b, SIGNEXTEND, signextend, 5);
+
+    make_opcode!(0x10, LT, lt, 3);
+    make_opcode!(0x11, GT, gt, 3);
+    make_opcode!(0x12, SLT, slt, 3);
+    make_opcode!(0x13, SGT, sgt, 3);
+    make_opcode!(0x14, EQ, eq, 3);
+    make_opcode!(0x15, ISZERO, iszero, 3);
+    make_opcode!(0x16, AND, and, 3);
+    make_opcode!(0x17, OR, or, 3);
+    make_opcode!(0x18, XOR, xor, 3);
+    make_opcode!(0x19, NOT, not, 3);
+    make_opcode!(0x1a, BYTE, byte, 3);
+    make_opcode!(0x1b, SHL, shl, 3);
+    make_opcode!(0x1c, SHR, shr, 3);
+    make_opcode!(0x1d, SAR, sar, 3);
+
+    make_opcode!(0x20, KECCAK256, keccak256, 30);
+
+    make_opcode!(0x30, ADDRESS, address, 2);
+    make_opcode!(0x31, BALANCE, balance, 0);
+    make_opcode!(0x32, ORIGIN, origin, 2);
+    make_opcode!(0x33, CALLER, caller, 2);
+    make_opcode!(0x34, CALLVALUE, callvalue, 2);
+    make_opcode!(0x35, CALLDATALOAD, calldataload, 3);
+    make_opcode!(0x36, CALLDATASIZE, calldatasize, 2);
+    make_opcode!(0x37, CALLDATACOPY, calldatacopy, 3);
+    make_opcode!(0x38, CODESIZE, codesize, 2);
+    make_opcode!(0x39, CODECOPY, codecopy, 3);
+    make_opcode!(0x3a, GASPRICE, gasprice, 2);
+    make_opcode!(0x3b, EXTCODESIZE, extcodesize, 0);
+    make_opcode!(0x3c, EXTCODECOPY, extcodecopy, 0);
+    make_opcode!(0x3d, RETURNDATASIZE, returndatasize, 2);
+    make_opcode!(0x3e, RETURNDATACOPY, returndatacopy, 3);
+    make_opcode!(0x3f, EXTCODEHASH, extcodehash, 0);
 
-    make_opcode!(0x10, LT, lt);
-    make_opcode!(0x11, GT, gt);
-    make_opcode!(0x12, SLT, slt);
-    make_opcode!(0x13, SGT, sgt);
-    make_opcode!(0x14, EQ, eq);
-    make_opcode!(0x15, ISZERO, iszero);
-    make_opcode!(0x16, AND, and);
-    make_opcode!(0x17, OR, or);
-    make_opcode!(0x18, XOR, xor);
-    make_opcode!(0x19, NOT, not);
-    make_opcode!(0x1a, BYTE, byte);
-    make_opcode!(0x1b, SHL, shl);
-    make_opcode!(0x1c, SHR, shr);
+    make_opcode!(0x40, BLOCKHASH, blockhash, 20);
+    make_opcode!(0x41, COINBASE, coinbase, 2);
+    make_opcode!(0x42, TIMESTAMP, timestamp, 2);
+    make_opcode!(0x43, NUMBER, number, 2);
+    make_opcode!(0x44, PREVRANDAO, prevrandao, 2);
+    make_opcode!(0x45, GASLIMIT, gaslimit, 2);
+    make_opcode!(0x46, CHAINID, chainid, 2);
+    make_opcode!(0x47, SELFBALANCE, selfbalance, 5);
+    make_opcode!(0x48, BASEFEE, basefee, 2);
+    make_opcode!(0x49, BLOBHASH, blobhash, 3);
+    make_opcode!(0x4a, BLOBBASEFEE, blobbasefee, 2);
 
-    make_opcode!(0x50, POP, pop);
-    make_opcode!(0x51, MLOAD, mload);
-    make_opcode!(0x52, MSTORE, mstore);
-    make_opcode!(0x53, MSTORE8, mstore8);
-    make_opcode!(0x56, JUMP, jump);
-    make_opcode!(0x57, JUMPI, jumpi);
-    make_opcode!(0x58, PC, pc);
-    make_opcode!(0x59, MSIZE, msize);
-    make_opcode!(0x5b, JUMPDEST, jumpdest);
+    make_opcode!(0x50, POP, pop, 2);
+    make_opcode!(0x51, MLOAD, mload, 3);
+    make_opcode!(0x52, MSTORE, mstore, 3);
+    make_opcode!(0x53, MSTORE8, mstore8, 3);
+    make_opcode!(0x54, SLOAD, sload, 0);
+    make_opcode!(0x55, SSTORE, sstore, 0);
+    make_opcode!(0x56, JUMP, jump, 8);
+    make_opcode!(0x57, JUMPI, jumpi, 10);
+    make_opcode!(0x58, PC, pc, 2);
+    make_opcode!(0x59, MSIZE, msize, 2);
+    make_opcode!(0x5a, GAS, gas, 2);
+    make_opcode!(0x5b, JUMPDEST, jumpdest, 1);
+    make_opcode!(0x5c, TLOAD, tload, 100);
+    make_opcode!(0x5d, TSTORE, tstore, 100);
+    make_opcode!(0x5e, MCOPY, mcopy, 3);
+
+    make_opcode!(0x5f, PUSH0, push0, 2);
 
     // PUSH1 - PUSH32
-    make_opcode!(0x60, PUSH1, push1);
-    make_opcode!(0x61, PUSH2, push2);
-    make_opcode!(0x62, PUSH3, push3);
-    make_opcode!(0x63, PUSH4, push4);
-    make_opcode!(0x64, PUSH5, push5);
-    make_opcode!(0x65, PUSH6, push6);
-    make_opcode!(0x66, PUSH7, push7);
-    make_opcode!(0x67, PUSH8, push8);
-    make_opcode!(0x68, PUSH9, push9);
-    make_opcode!(0x69, PUSH10, push10);
-    make_opcode!(0x6a, PUSH11, push11);
-    make_opcode!(0x6b, PUSH12, push12);
-    make_opcode!(0x6c, PUSH13, push13);
-    make_opcode!(0x6d, PUSH14, push14);
-    make_opcode!(0x6e, PUSH15, push15);
-    make_opcode!(0x6f, PUSH16, push16);
-    make_opcode!(0x70, PUSH17, push17);
-    make_opcode!(0x71, PUSH18, push18);
-    make_opcode!(0x72, PUSH19, push19);
-    make_opcode!(0x73, PUSH20, push20);
-    make_opcode!(0x74, PUSH21, push21);
-    make_opcode!(0x75, PUSH22, push22);
-    make_opcode!(0x76, PUSH23, push23);
-    make_opcode!(0x77, PUSH24, push24);
-    make_opcode!(0x78, PUSH25, push25);
-    make_opcode!(0x79, PUSH26, push26);
-    make_opcode!(0x7a, PUSH27, push27);
-    make_opcode!(0x7b, PUSH28, push28);
-    make_opcode!(0x7c, PUSH29, push29);
-    make_opcode!(0x7d, PUSH30, push30);
-    make_opcode!(0x7e, PUSH31, push31);
-    make_opcode!(0x7f, PUSH32, push32);
+    make_opcode!(0x60, PUSH1, push1, 3);
+    make_opcode!(0x61, PUSH2, push2, 3);
+    make_opcode!(0x62, PUSH3, push3, 3);
+    make_opcode!(0x63, PUSH4, push4, 3);
+    make_opcode!(0x64, PUSH5, push5, 3);
+    make_opcode!(0x65, PUSH6, push6, 3);
+    make_opcode!(0x66, PUSH7, push7, 3);
+    make_opcode!(0x67, PUSH8, push8, 3);
+    make_opcode!(0x68, PUSH9, push9, 3);
+    make_opcode!(0x69, PUSH10, push10, 3);
+    make_opcode!(0x6a, PUSH11, push11, 3);
+    make_opcode!(0x6b, PUSH12, push12, 3);
+    make_opcode!(0x6c, PUSH13, push13, 3);
+    make_opcode!(0x6d, PUSH14, push14, 3);
+    make_opcode!(0x6e, PUSH15, push15, 3);
+    make_opcode!(0x6f, PUSH16, push16, 3);
+    make_opcode!(0x70, PUSH17, push17, 3);
+    make_opcode!(0x71, PUSH18, push18, 3);
+    make_opcode!(0x72, PUSH19, push19, 3);
+    make_opcode!(0x73, PUSH20, push20, 3);
+    make_opcode!(0x74, PUSH21, push21, 3);
+    make_opcode!(0x75, PUSH22, push22, 3);
+    make_opcode!(0x76, PUSH23, push23, 3);
+    make_opcode!(0x77, PUSH24, push24, 3);
+    make_opcode!(0x78, PUSH25, push25, 3);
+    make_opcode!(0x79, PUSH26, push26, 3);
+    make_opcode!(0x7a, PUSH27, push27, 3);
+    make_opcode!(0x7b, PUSH28, push28, 3);
+    make_opcode!(0x7c, PUSH29, push29, 3);
+    make_opcode!(0x7d, PUSH30, push30, 3);
+    make_opcode!(0x7e, PUSH31, push31, 3);
+    make_opcode!(0x7f, PUSH32, push32, 3);
 
     // DUP1 - DUP16
-    make_opcode!(0x80, DUP1, dup1);
-    make_opcode!(0x81, DUP2, dup2);
-    make_opcode!(0x82, DUP3, dup3);
-    make_opcode!(0x83, DUP4, dup4);
-    make_opcode!(0x84, DUP5, dup5);
-    make_opcode!(0x85, DUP6, dup6);
-    make_opcode!(0x86, DUP7, dup7);
-    make_opcode!(0x87, DUP8, dup8);
-    make_opcode!(0x88, DUP9, dup9);
-    make_opcode!(0x89, DUP10, dup10);
-    make_opcode!(0x8a, DUP11, dup11);
-    make_opcode!(0x8b, DUP12, dup12);
-    make_opcode!(0x8c, DUP13, dup13);
-    make_opcode!(0x8d, DUP14, dup14);
-    make_opcode!(0x8e, DUP15, dup15);
-    make_opcode!(0x8f, DUP16, dup16);
+    make_opcode!(0x80, DUP1, dup1, 3);
+    make_opcode!(0x81, DUP2, dup2, 3);
+    make_opcode!(0x82, DUP3, dup3, 3);
+    make_opcode!(0x83, DUP4, dup4, 3);
+    make_opcode!(0x84, DUP5, dup5, 3);
+    make_opcode!(0x85, DUP6, dup6, 3);
+    make_opcode!(0x86, DUP7, dup7, 3);
+    make_opcode!(0x87, DUP8, dup8, 3);
+    make_opcode!(0x88, DUP9, dup9, 3);
+    make_opcode!(0x89, DUP10, dup10, 3);
+    make_opcode!(0x8a, DUP11, dup11, 3);
+    make_opcode!(0x8b, DUP12, dup12, 3);
+    make_opcode!(0x8c, DUP13, dup13, 3);
+    make_opcode!(0x8d, DUP14, dup14, 3);
+    make_opcode!(0x8e, DUP15, dup15, 3);
+    make_opcode!(0x8f, DUP16, dup16, 3);
 
     // SWAP1 - SWAP16
-    make_opcode!(0x90, SWAP1, swap1);
-    make_opcode!(0x91, SWAP2, swap2);
-    make_opcode!(0x92, SWAP3, swap3);
-    make_opcode!(0x93, SWAP4, swap4);
-    make_opcode!(0x94, SWAP5, swap5);
-    make_opcode!(0x95, SWAP6, swap6);
-    make_opcode!(0x96, SWAP7, swap7);
-    make_opcode!(0x97, SWAP8, swap8);
-    make_opcode!(0x98, SWAP9, swap9);
-    make_opcode!(0x99, SWAP10, swap10);
-    make_opcode!(0x9a, SWAP11, swap11);
-    make_opcode!(0x9b, SWAP12, swap12);
-    make_opcode!(0x9c, SWAP13, swap13);
-    make_opcode!(0x9d, SWAP14, swap14);
-    make_opcode!(0x9e, SWAP15, swap15);
-    make_opcode!(0x9f, SWAP16, swap16);
+    make_opcode!(0x90, SWAP1, swap1, 3);
+    make_opcode!(0x91, SWAP2, swap2, 3);
+    make_opcode!(0x92, SWAP3, swap3, 3);
+    make_opcode!(0x93, SWAP4, swap4, 3);
+    make_opcode!(0x94, SWAP5, swap5, 3);
+    make_opcode!(0x95, SWAP6, swap6, 3);
+    make_opcode!(0x96, SWAP7, swap7, 3);
+    make_opcode!(0x97, SWAP8, swap8, 3);
+    make_opcode!(0x98, SWAP9, swap9, 3);
+    make_opcode!(0x99, SWAP10, swap10, 3);
+    make_opcode!(0x9a, SWAP11, swap11, 3);
+    make_opcode!(0x9b, SWAP12, swap12, 3);
+    make_opcode!(0x9c, SWAP13, swap13, 3);
+    make_opcode!(0x9d, SWAP14, swap14, 3);
+    make_opcode!(0x9e, SWAP15, swap15, 3);
+    make_opcode!(0x9f, SWAP16, swap16, 3);
+
+    // LOG0 - LOG4
+    make_opcode!(0xa0, LOG0, log0, 375);
+    make_opcode!(0xa1, LOG1, log1, 375);
+    make_opcode!(0xa2, LOG2, log2, 375);
+    make_opcode!(0xa3, LOG3, log3, 375);
+    make_opcode!(0xa4, LOG4, log4, 375);
 
-    make_opcode!(0xf3, RETURN, return_);
-    make_opcode!(0xfd, REVERT, revert);
-    make_opcode!(0xfe, INVALID, invalid);
+    make_opcode!(0xf0, CREATE, create, 32000);
+    make_opcode!(0xf1, CALL, call, 0);
+    make_opcode!(0xf2, CALLCODE, callcode, 0);
+    make_opcode!(0xf3, RETURN, return_, 0);
+    make_opcode!(0xf4, DELEGATECALL, delegatecall, 0);
+    make_opcode!(0xf5, CREATE2, create2, 32000);
+    make_opcode!(0xfa, STATICCALL, staticcall, 0);
+    make_opcode!(0xfd, REVERT, revert, 0);
+    make_opcode!(0xfe, INVALID, invalid, 0);
+    make_opcode!(0xff, SELFDESTRUCT, selfdestruct, 5000);
 
     pub fn get(code: u8) -> Option<&'static Self> {
         OPCODE_LIST.get(code as usize)
@@ -173,7 +242,7 @@ pub const OPCODE_LIST: [Opcode; 256] = {
     opcodes[Opcode::ADDMOD.code as usize] = Opcode::ADDMOD;
     opcodes[Opcode::MULMOD.code as usize] = Opcode::MULMOD;
     opcodes[Opcode::EXP.code as usize] = Opcode::EXP;
-    // opcodes[Opcode::SIGNEXTEND.code as usize] = Opcode::SIGNEXTEND;
+    opcodes[Opcode::SIGNEXTEND.code as usize] = Opcode::SIGNEXTEND;
 
     opcodes[Opcode::LT.code as usize] = Opcode::LT;
     opcodes[Opcode::GT.code as usize] = Opcode::GT;
@@ -187,17 +256,53 @@ pub const OPCODE_LIST: [Opcode; 256] = {
     opcodes[Opcode::NOT.code as usize] = Opcode::NOT;
     opcodes[Opcode::SHL.code as usize] = Opcode::SHL;
     opcodes[Opcode::SHR.code as usize] = Opcode::SHR;
-    // opcodes[Opcode::SAR.code as usize] = Opcode::SAR;
+    opcodes[Opcode::SAR.code as usize] = Opcode::SAR;
     opcodes[Opcode::BYTE.code as usize] = Opcode::BYTE;
+    opcodes[Opcode::KECCAK256.code as usize] = Opcode::KECCAK256;
+    opcodes[Opcode::ADDRESS.code as usize] = Opcode::ADDRESS;
+    opcodes[Opcode::BALANCE.code as usize] = Opcode::BALANCE;
+    opcodes[Opcode::ORIGIN.code as usize] = Opcode::ORIGIN;
+    opcodes[Opcode::CALLER.code as usize] = Opcode::CALLER;
+    opcodes[Opcode::CALLVALUE.code as usize] = Opcode::CALLVALUE;
+    opcodes[Opcode::CALLDATALOAD.code as usize] = Opcode::CALLDATALOAD;
+    opcodes[Opcode::CALLDATASIZE.code as usize] = Opcode::CALLDATASIZE;
+    opcodes[Opcode::CALLDATACOPY.code as usize] = Opcode::CALLDATACOPY;
+    opcodes[Opcode::CODESIZE.code as usize] = Opcode::CODESIZE;
+    opcodes[Opcode::CODECOPY.code as usize] = Opcode::CODECOPY;
+    opcodes[Opcode::GASPRICE.code as usize] = Opcode::GASPRICE;
+    opcodes[Opcode::EXTCODESIZE.code as usize] = Opcode::EXTCODESIZE;
+    opcodes[Opcode::EXTCODECOPY.code as usize] = Opcode::EXTCODECOPY;
+    opcodes[Opcode::RETURNDATASIZE.code as usize] = Opcode::RETURNDATASIZE;
+    opcodes[Opcode::RETURNDATACOPY.code as usize] = Opcode::RETURNDATACOPY;
+    opcodes[Opcode::EXTCODEHASH.code as usize] = Opcode::EXTCODEHASH;
+    opcodes[Opcode::BLOCKHASH.code as usize] = Opcode::BLOCKHASH;
+    opcodes[Opcode::COINBASE.code as usize] = Opcode::COINBASE;
+    opcodes[Opcode::TIMESTAMP.code as usize] = Opcode::TIMESTAMP;
+    opcodes[Opcode::NUMBER.code as usize] = Opcode::NUMBER;
+    opcodes[Opcode::PREVRANDAO.code as usize] = Opcode::PREVRANDAO;
+    opcodes[Opcode::GASLIMIT.code as usize] = Opcode::GASLIMIT;
+    opcodes[Opcode::CHAINID.code as usize] = Opcode::CHAINID;
+    opcodes[Opcode::SELFBALANCE.code as usize] = Opcode::SELFBALANCE;
+    opcodes[Opcode::BASEFEE.code as usize] = Opcode::BASEFEE;
+    opcodes[Opcode::BLOBHASH.code as usize] = Opcode::BLOBHASH;
+    opcodes[Opcode::BLOBBASEFEE.code as usize] = Opcode::BLOBBASEFEE;
+
     opcodes[Opcode::POP.code as usize] = Opcode::POP;
     opcodes[Opcode::MLOAD.code as usize] = Opcode::MLOAD;
     opcodes[Opcode::MSTORE.code as usize] = Opcode::MSTORE;
     opcodes[Opcode::MSTORE8.code as usize] = Opcode::MSTORE8;
+    opcodes[Opcode::SLOAD.code as usize] = Opcode::SLOAD;
+    opcodes[Opcode::SSTORE.code as usize] = Opcode::SSTORE;
     opcodes[Opcode::JUMP.code as usize] = Opcode::JUMP;
     opcodes[Opcode::JUMPI.code as usize] = Opcode::JUMPI;
     opcodes[Opcode::PC.code as usize] = Opcode::PC;
     opcodes[Opcode::MSIZE.code as usize] = Opcode::MSIZE;
+    opcodes[Opcode::GAS.code as usize] = Opcode::GAS;
     opcodes[Opcode::JUMPDEST.code as usize] = Opcode::JUMPDEST;
+    opcodes[Opcode::TLOAD.code as usize] = Opcode::TLOAD;
+    opcodes[Opcode::TSTORE.code as usize] = Opcode::TSTORE;
+    opcodes[Opcode::MCOPY.code as usize] = Opcode::MCOPY;
+    opcodes[Opcode::PUSH0.code as usize] = Opcode::PUSH0;
 
     opcodes[Opcode::PUSH1.code as usize] = Opcode::PUSH1;
     opcodes[Opcode::PUSH2.code as usize] = Opcode::PUSH2;
@@ -247,6 +352,7 @@ pub const OPCODE_LIST: [Opcode; 256] = {
     opcodes[Opcode::DUP13.code as usize] = Opcode::DUP13;
     opcodes[Opcode::DUP14.code as usize] = Opcode::DUP14;
     opcodes[Opcode::DUP15.code as usize] = Opcode::DUP15;
+    opcodes[Opcode::DUP16.code as usize] = Opcode::DUP16;
 
     opcodes[Opcode::SWAP1.code as usize] = Opcode::SWAP1;
     opcodes[Opcode::SWAP2.code as usize] = Opcode::SWAP2;
@@ -265,9 +371,22 @@ pub const OPCODE_LIST: [Opcode; 256] = {
     opcodes[Opcode::SWAP15.code as usize] = Opcode::SWAP15;
     opcodes[Opcode::SWAP16.code as usize] = Opcode::SWAP16;
 
+    opcodes[Opcode::LOG0.code as usize] = Opcode::LOG0;
+    opcodes[Opcode::LOG1.code as usize] = Opcode::LOG1;
+    opcodes[Opcode::LOG2.code as usize] = Opcode::LOG2;
+    opcodes[Opcode::LOG3.code as usize] = Opcode::LOG3;
+    opcodes[Opcode::LOG4.code as usize] = Opcode::LOG4;
+
+    opcodes[Opcode::CREATE.code as usize] = Opcode::CREATE;
+    opcodes[Opcode::CALL.code as usize] = Opcode::CALL;
+    opcodes[Opcode::CALLCODE.code as usize] = Opcode::CALLCODE;
     opcodes[Opcode::RETURN.code as usize] = Opcode::RETURN;
+    opcodes[Opcode::DELEGATECALL.code as usize] = Opcode::DELEGATECALL;
+    opcodes[Opcode::CREATE2.code as usize] = Opcode::CREATE2;
+    opcodes[Opcode::STATICCALL.code as usize] = Opcode::STATICCALL;
     opcodes[Opcode::REVERT.code as usize] = Opcode::REVERT;
     opcodes[Opcode::INVALID.code as usize] = Opcode::INVALID;
+    opcodes[Opcode::SELFDESTRUCT.code as usize] = Opcode::SELFDESTRUCT;
 
     opcodes
 };
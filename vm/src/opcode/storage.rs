@@ -0,0 +1,59 @@
+use super::Control;
+use crate::env::Fork;
+use crate::gas;
+use crate::{Vm, VmError};
+
+// 0x54
+pub fn sload(vm: &mut Vm) -> Control {
+    pop!(vm, key);
+    let cost = if vm.state.warm_slot(vm.context.address, key) {
+        gas::COLD_SLOAD
+    } else {
+        gas::WARM_STORAGE_READ
+    };
+    gas!(vm, cost);
+    let value = check!(vm.state.storage(&vm.context.address, &key));
+    push!(vm, value);
+    Control::Continue(1)
+}
+
+// 0x55
+pub fn sstore(vm: &mut Vm) -> Control {
+    if vm.context.is_static {
+        return Control::Error(VmError::WriteProtection);
+    }
+    if vm.gas.remaining() <= gas::SSTORE_SENTRY {
+        return Control::Error(VmError::OutOfGas);
+    }
+    pop!(vm, key, value);
+    let address = vm.context.address;
+    let is_cold = vm.state.warm_slot(address, key);
+    let original = check!(vm.state.original_storage(&address, &key));
+    let current = check!(vm.state.storage(&address, &key));
+    let (cost, refund) = gas::sstore_cost(original, current, value, is_cold, vm.env.cfg.fork);
+    gas!(vm, cost);
+    vm.gas.record_refund(refund);
+    check!(vm.state.set_storage(address, key, value));
+    Control::Continue(1)
+}
+
+// 0x5c
+pub fn tload(vm: &mut Vm) -> Control {
+    check_fork!(vm, Fork::Cancun);
+    pop!(vm, key);
+    let value = vm.state.transient_storage(&vm.context.address, &key);
+    push!(vm, value);
+    Control::Continue(1)
+}
+
+// 0x5d
+pub fn tstore(vm: &mut Vm) -> Control {
+    check_fork!(vm, Fork::Cancun);
+    if vm.context.is_static {
+        return Control::Error(VmError::WriteProtection);
+    }
+    pop!(vm, key, value);
+    vm.state
+        .set_transient_storage(vm.context.address, key, value);
+    Control::Continue(1)
+}
@@ -1,4 +1,6 @@
 use super::Control;
+use crate::env::Fork;
+use crate::gas;
 use crate::{Vm, VmError};
 use primitive_types::{H256, U256};
 
@@ -8,8 +10,9 @@ macro_rules! make_push_fn {
         pub fn $name(vm: &mut Vm) -> Control {
             let end = std::cmp::min(vm.pc + $n + 1, vm.code.len());
             let slice = &vm.code[(vm.pc + 1)..end];
+            // code cut short reads as zeros, i.e. the value is right-padded
             let mut value = [0u8; 32];
-            value[(32 - slice.len())..32].copy_from_slice(slice);
+            value[(32 - $n)..(32 - $n + slice.len())].copy_from_slice(slice);
             push!(vm, H256(value));
             Control::Continue($n + 1)
         }
@@ -49,23 +52,27 @@ pub fn pop(vm: &mut Vm) -> Control {
 
 // 0x51
 pub fn mload(vm: &mut Vm) -> Control {
-    pop_usize!(vm, a);
-    vm.memory.load(a);
+    pop_u256!(vm, offset);
+    let (offset, _) = memory_range!(vm, offset, U256::from(32));
+    let value = vm.memory.load(offset);
+    push!(vm, H256::from_slice(&value));
     Control::Continue(1)
 }
 
 // 0x52
 pub fn mstore(vm: &mut Vm) -> Control {
-    pop_usize!(vm, offset);
+    pop_u256!(vm, offset);
     pop!(vm, value);
+    let (offset, _) = memory_range!(vm, offset, U256::from(32));
     vm.memory.store(offset, value.as_fixed_bytes());
     Control::Continue(1)
 }
 
 // 0x53
 pub fn mstore8(vm: &mut Vm) -> Control {
-    pop_usize!(vm, offset);
+    pop_u256!(vm, offset);
     pop!(vm, value);
+    let (offset, _) = memory_range!(vm, offset, U256::one());
     let byte = value.as_fixed_bytes()[31];
     vm.memory.store8(offset, byte);
     Control::Continue(1)
@@ -77,6 +84,28 @@ pub fn msize(vm: &mut Vm) -> Control {
     Control::Continue(1)
 }
 
+// 0x5e
+pub fn mcopy(vm: &mut Vm) -> Control {
+    check_fork!(vm, Fork::Cancun);
+    pop_u256!(vm, dest, src, size);
+    // one expansion covering both ranges
+    let end = std::cmp::max(dest, src);
+    let (_, size) = memory_range!(vm, end, size);
+    gas!(vm, gas::copy_cost(size));
+    if size > 0 {
+        let data = vm.memory.read(src.as_usize(), size);
+        vm.memory.write(dest.as_usize(), &data);
+    }
+    Control::Continue(1)
+}
+
+// 0x5f
+pub fn push0(vm: &mut Vm) -> Control {
+    check_fork!(vm, Fork::Shanghai);
+    push!(vm, H256::zero());
+    Control::Continue(1)
+}
+
 // 0x60 - 0x7f
 make_push_fn!(push1, 1); // PUSH1
 make_push_fn!(push2, 2); // PUSH2
@@ -0,0 +1,477 @@
+use super::environment::access_account;
+use super::Control;
+use crate::crypto::{create2_address, create_address, keccak256};
+use crate::env::{Env, Fork};
+use crate::gas::{self, Gas};
+use crate::precompile::{self, is_precompile};
+use crate::state::{delegation, Log, State};
+use crate::tracer::SharedTracer;
+use crate::{Context, ExecutionResult, Vm, VmError};
+use primitive_types::{H160, H256, U256};
+use std::rc::Rc;
+
+pub const CALL_DEPTH_LIMIT: usize = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CallKind {
+    Call,
+    CallCode,
+    DelegateCall,
+    StaticCall,
+}
+
+// Outcome of a call or create frame
+pub(crate) struct Frame {
+    pub success: bool,
+    pub output: Vec<u8>,
+    pub gas: Gas,
+    // set by a create frame that succeeded
+    pub address: Option<H160>,
+}
+
+macro_rules! make_log_fn {
+    ($name: ident, $n: expr) => {
+        #[allow(dead_code, clippy::reversed_empty_ranges)]
+        pub fn $name(vm: &mut Vm) -> Control {
+            if vm.context.is_static {
+                return Control::Error(VmError::WriteProtection);
+            }
+            pop_u256!(vm, offset, size);
+            let (offset, size) = memory_range!(vm, offset, size);
+            gas!(vm, gas::LOG_TOPIC * $n + gas::LOG_DATA * size as u64);
+            let mut topics = Vec::with_capacity($n);
+            for _ in 0..$n {
+                pop!(vm, topic);
+                topics.push(topic);
+            }
+            let data = vm.memory.read(offset, size);
+            vm.state.log(Log {
+                address: vm.context.address,
+                topics,
+                data,
+            });
+            Control::Continue(1)
+        }
+    };
+}
+
+// 0xa0 - 0xa4
+make_log_fn!(log0, 0); // LOG0
+make_log_fn!(log1, 1); // LOG1
+make_log_fn!(log2, 2); // LOG2
+make_log_fn!(log3, 3); // LOG3
+make_log_fn!(log4, 4); // LOG4
+
+// 0xf0
+pub fn create(vm: &mut Vm) -> Control {
+    create_inner(vm, false)
+}
+
+// 0xf1
+pub fn call(vm: &mut Vm) -> Control {
+    call_inner(vm, CallKind::Call)
+}
+
+// 0xf2
+pub fn callcode(vm: &mut Vm) -> Control {
+    call_inner(vm, CallKind::CallCode)
+}
+
+// 0xf4
+pub fn delegatecall(vm: &mut Vm) -> Control {
+    call_inner(vm, CallKind::DelegateCall)
+}
+
+// 0xf5
+pub fn create2(vm: &mut Vm) -> Control {
+    create_inner(vm, true)
+}
+
+// 0xfa
+pub fn staticcall(vm: &mut Vm) -> Control {
+    call_inner(vm, CallKind::StaticCall)
+}
+
+// 0xff
+pub fn selfdestruct(vm: &mut Vm) -> Control {
+    if vm.context.is_static {
+        return Control::Error(VmError::WriteProtection);
+    }
+    pop_address!(vm, target);
+    let address = vm.context.address;
+
+    let mut cost = access_account(vm, target);
+    // the access cost is only charged for cold targets
+    if cost == gas::WARM_STORAGE_READ {
+        cost = 0;
+    }
+    let balance = check!(vm.state.balance(&address));
+    if !balance.is_zero() && check!(vm.state.is_empty(&target)) {
+        cost += gas::NEW_ACCOUNT;
+    }
+    gas!(vm, cost);
+
+    if target != address {
+        check!(vm.state.transfer(address, target, balance));
+    }
+    // since EIP-6780 only contracts created in the same transaction are
+    // deleted, any other contract just sends its balance
+    if vm.env.cfg.fork < Fork::Cancun || vm.state.is_created(&address) {
+        let remaining = check!(vm.state.balance(&address));
+        check!(vm.state.sub_balance(address, remaining));
+        if vm.state.selfdestruct(address) && vm.env.cfg.fork < Fork::London {
+            vm.gas.record_refund(gas::SELFDESTRUCT_REFUND);
+        }
+    }
+    Control::SelfDestruct
+}
+
+fn call_inner(vm: &mut Vm, kind: CallKind) -> Control {
+    pop_u256!(vm, gas_limit);
+    pop_address!(vm, to);
+    let value = match kind {
+        CallKind::Call | CallKind::CallCode => {
+            pop_u256!(vm, v);
+            v
+        }
+        CallKind::DelegateCall => vm.context.value,
+        CallKind::StaticCall => U256::zero(),
+    };
+    pop_u256!(vm, in_offset, in_size, out_offset, out_size);
+
+    let transfers_value = matches!(kind, CallKind::Call | CallKind::CallCode) && !value.is_zero();
+    if kind == CallKind::Call && transfers_value && vm.context.is_static {
+        return Control::Error(VmError::WriteProtection);
+    }
+
+    let (in_offset, in_size) = memory_range!(vm, in_offset, in_size);
+    let (out_offset, out_size) = memory_range!(vm, out_offset, out_size);
+
+    let mut cost = access_account(vm, to);
+    // the code of a delegated account is loaded from its delegate (EIP-7702)
+    if let Some(delegate) = delegation(&check!(vm.state.code(&to))) {
+        cost += access_account(vm, delegate);
+    }
+    if transfers_value {
+        cost += gas::CALL_VALUE;
+        if kind == CallKind::Call && check!(vm.state.is_empty(&to)) {
+            cost += gas::NEW_ACCOUNT;
+        }
+    }
+    gas!(vm, cost);
+
+    // all but one 64th of the remaining gas can be passed on (EIP-150)
+    let available = vm.gas.remaining() - vm.gas.remaining() / 64;
+    let mut gas_limit = if gas_limit > U256::from(available) {
+        available
+    } else {
+        gas_limit.as_u64()
+    };
+    gas!(vm, gas_limit);
+    trace_call_charged(vm);
+    if transfers_value {
+        gas_limit += gas::CALL_STIPEND;
+    }
+
+    let input = vm.memory.read(in_offset, in_size);
+    vm.returndata.clear();
+
+    // the call fails without entering the callee
+    if vm.context.depth >= CALL_DEPTH_LIMIT
+        || (transfers_value && check!(vm.state.balance(&vm.context.address)) < value)
+    {
+        vm.gas.erase_cost(gas_limit);
+        push_u256!(vm, U256::zero());
+        return Control::Continue(1);
+    }
+
+    let context = match kind {
+        CallKind::Call | CallKind::StaticCall => Context {
+            address: to,
+            caller: vm.context.address,
+            value,
+            input,
+            depth: vm.context.depth + 1,
+            is_static: vm.context.is_static || kind == CallKind::StaticCall,
+            gas_limit,
+        },
+        CallKind::CallCode => Context {
+            address: vm.context.address,
+            caller: vm.context.address,
+            value,
+            input,
+            depth: vm.context.depth + 1,
+            is_static: vm.context.is_static,
+            gas_limit,
+        },
+        CallKind::DelegateCall => Context {
+            address: vm.context.address,
+            caller: vm.context.caller,
+            value,
+            input,
+            depth: vm.context.depth + 1,
+            is_static: vm.context.is_static,
+            gas_limit,
+        },
+    };
+
+    let frame = check!(call_frame(
+        &mut vm.state,
+        &vm.env,
+        vm.tracer.as_ref(),
+        context,
+        to,
+        kind == CallKind::Call
+    ));
+    vm.gas.erase_cost(frame.gas.remaining());
+    if frame.success {
+        vm.gas.record_refund(frame.gas.refunded());
+    }
+
+    let n = std::cmp::min(out_size, frame.output.len());
+    if n > 0 {
+        vm.memory.write(out_offset, &frame.output[..n]);
+    }
+    vm.returndata = frame.output;
+
+    push_u256!(vm, U256::from(frame.success as u8));
+    Control::Continue(1)
+}
+
+fn create_inner(vm: &mut Vm, is_create2: bool) -> Control {
+    if vm.context.is_static {
+        return Control::Error(VmError::WriteProtection);
+    }
+    pop_u256!(vm, value, offset, size);
+    let salt = if is_create2 {
+        pop!(vm, salt);
+        Some(salt)
+    } else {
+        None
+    };
+
+    let (offset, size) = memory_range!(vm, offset, size);
+    if vm.env.cfg.fork >= Fork::Shanghai {
+        if size > gas::MAX_INITCODE_SIZE {
+            return Control::Error(VmError::OutOfGas);
+        }
+        gas!(vm, gas::INITCODE_WORD * gas::words(size));
+    }
+    if is_create2 {
+        gas!(vm, gas::KECCAK_WORD * gas::words(size));
+    }
+
+    let init_code = vm.memory.read(offset, size);
+    vm.returndata.clear();
+
+    let creator = vm.context.address;
+    let nonce = check!(vm.state.nonce(&creator));
+    if vm.context.depth >= CALL_DEPTH_LIMIT
+        || check!(vm.state.balance(&creator)) < value
+        || nonce == u64::MAX
+    {
+        push_u256!(vm, U256::zero());
+        return Control::Continue(1);
+    }
+    check!(vm.state.increment_nonce(creator));
+
+    let address = match salt {
+        Some(salt) => create2_address(creator, salt, keccak256(&init_code)),
+        None => create_address(creator, nonce),
+    };
+    let gas_limit = vm.gas.remaining() - vm.gas.remaining() / 64;
+    gas!(vm, gas_limit);
+    trace_call_charged(vm);
+
+    let frame = check!(create_frame(
+        &mut vm.state,
+        &vm.env,
+        vm.tracer.as_ref(),
+        creator,
+        address,
+        value,
+        init_code,
+        gas_limit,
+        vm.context.depth + 1
+    ));
+    vm.gas.erase_cost(frame.gas.remaining());
+    if frame.success {
+        vm.gas.record_refund(frame.gas.refunded());
+    } else {
+        vm.returndata = frame.output;
+    }
+
+    push!(vm, frame.address.map(H256::from).unwrap_or_default());
+    Control::Continue(1)
+}
+
+fn trace_call_charged(vm: &Vm) {
+    if let Some(tracer) = &vm.tracer {
+        tracer.borrow_mut().call_charged(&vm.gas);
+    }
+}
+
+// Runs the code of `code_address`, or the precompile at that address, in
+// `context`, first moving the call value to the callee if `transfer` is set. Only fatal errors, such as database
+// failures, are returned as errors.
+pub(crate) fn call_frame(
+    state: &mut State,
+    env: &Rc<Env>,
+    tracer: Option<&SharedTracer>,
+    context: Context,
+    code_address: H160,
+    transfer: bool,
+) -> Result<Frame, VmError> {
+    let mut code = state.code(&code_address)?;
+    if let Some(delegate) = delegation(&code) {
+        code = state.code(&delegate)?;
+    }
+
+    let checkpoint = state.checkpoint();
+    if transfer {
+        if let Err(e) = state.transfer(context.caller, context.address, context.value) {
+            state.revert(checkpoint);
+            return Err(e);
+        }
+    }
+
+    if is_precompile(&code_address, env.cfg.fork) {
+        let mut gas = Gas::new(context.gas_limit);
+        let output = match precompile::run(&code_address, &context.input, &mut gas) {
+            Ok(output) => output,
+            Err(e) => {
+                state.revert(checkpoint);
+                return Err(e);
+            }
+        };
+        let success = output.is_some();
+        if success {
+            state.commit(checkpoint);
+        } else {
+            state.revert(checkpoint);
+        }
+        return Ok(Frame {
+            success,
+            output: output.unwrap_or_default(),
+            gas,
+            address: None,
+        });
+    }
+
+    let mut callee = Vm::with_state(&code, std::mem::take(state), context)
+        .with_env(env.clone())
+        .with_tracer(tracer.cloned());
+    let result = callee.run();
+    *state = callee.into_state();
+    if let Some(e) = fatal_error(&result) {
+        state.revert(checkpoint);
+        return Err(e);
+    }
+
+    let success = result.is_success();
+    if success {
+        state.commit(checkpoint);
+    } else {
+        state.revert(checkpoint);
+    }
+    Ok(Frame {
+        success,
+        output: result.output().to_vec(),
+        gas: *result.gas(),
+        address: None,
+    })
+}
+
+// Deploys a contract at `address` by running `init_code`. The creator's
+// nonce has already been increased.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn create_frame(
+    state: &mut State,
+    env: &Rc<Env>,
+    tracer: Option<&SharedTracer>,
+    creator: H160,
+    address: H160,
+    value: U256,
+    init_code: Vec<u8>,
+    gas_limit: u64,
+    depth: usize,
+) -> Result<Frame, VmError> {
+    state.warm_account(address);
+    let failed = |gas: Gas, output: Vec<u8>| Frame {
+        success: false,
+        output,
+        gas,
+        address: None,
+    };
+
+    // an account with code or a nonce cannot be deployed over and the gas
+    // given to the creation is lost
+    if state.nonce(&address)? != 0 || !state.code(&address)?.is_empty() {
+        let mut gas = Gas::new(gas_limit);
+        gas.spend_all();
+        return Ok(failed(gas, Vec::new()));
+    }
+
+    let checkpoint = state.checkpoint();
+    state.mark_created(address);
+    state.increment_nonce(address)?;
+    if let Err(e) = state.transfer(creator, address, value) {
+        state.revert(checkpoint);
+        return Err(e);
+    }
+
+    let context = Context {
+        address,
+        caller: creator,
+        value,
+        input: Vec::new(),
+        depth,
+        is_static: false,
+        gas_limit,
+    };
+    let mut vm = Vm::with_state(&init_code, std::mem::take(state), context)
+        .with_env(env.clone())
+        .with_tracer(tracer.cloned());
+    let result = vm.run();
+    let output = result.output().to_vec();
+    let mut gas = *result.gas();
+    *state = vm.into_state();
+    if let Some(e) = fatal_error(&result) {
+        state.revert(checkpoint);
+        return Err(e);
+    }
+
+    if !result.is_success() {
+        state.revert(checkpoint);
+        return Ok(failed(gas, output));
+    }
+
+    // the deployed code is checked and paid for, failing like an error
+    let valid = output.len() <= gas::MAX_CODE_SIZE
+        && !(env.cfg.fork >= Fork::London && output.first() == Some(&0xef))
+        && gas.record_cost(gas::CODE_DEPOSIT * output.len() as u64);
+    if !valid {
+        state.revert(checkpoint);
+        gas.spend_all();
+        gas.clear_refund();
+        return Ok(failed(gas, Vec::new()));
+    }
+
+    state.set_code(address, output)?;
+    state.commit(checkpoint);
+    Ok(Frame {
+        success: true,
+        output: Vec::new(),
+        gas,
+        address: Some(address),
+    })
+}
+
+// A halt that must abort the whole transaction, as the enclosing frames
+// cannot carry on without the frame's result
+fn fatal_error(result: &ExecutionResult) -> Option<VmError> {
+    match result {
+        ExecutionResult::Halt { reason, .. } if reason.is_fatal() => Some(reason.clone()),
+        _ => None,
+    }
+}
@@ -0,0 +1,265 @@
+// Precompiled contracts. ECRECOVER, SHA256, RIPEMD160, IDENTITY and MODEXP
+// are implemented; calling any other fails the whole transaction with
+// `VmError::UnsupportedPrecompile` instead of running it as an account
+// without code.
+
+use crate::env::Fork;
+use crate::error::VmError;
+use crate::gas::{self, Gas};
+use crate::transaction::Signature;
+use num_bigint::BigUint;
+use primitive_types::{H160, H256, U256};
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+
+// Addresses of the precompiled contracts of `fork`, which are always warm
+pub fn addresses(fork: Fork) -> impl Iterator<Item = H160> {
+    (1..=count(fork)).map(H160::from_low_u64_be)
+}
+
+pub fn is_precompile(address: &H160, fork: Fork) -> bool {
+    address[..12].iter().all(|&b| b == 0) && (1..=count(fork)).contains(&address.to_low_u64_be())
+}
+
+// ECRECOVER to BLAKE2F, the KZG point evaluation since Cancun (EIP-4844) and
+// the BLS12-381 operations since Prague (EIP-2537)
+fn count(fork: Fork) -> u64 {
+    match fork {
+        Fork::Prague => 17,
+        Fork::Cancun => 10,
+        _ => 9,
+    }
+}
+
+// Runs the precompile at `address` on `input`, charging `gas`. Returns
+// `None` when the precompile fails, which consumes all the gas.
+pub(crate) fn run(address: &H160, input: &[u8], gas: &mut Gas) -> Result<Option<Vec<u8>>, VmError> {
+    let words = gas::words(input.len());
+    let cost = match address.to_low_u64_be() {
+        1 => gas::ECRECOVER,
+        2 => gas::SHA256 + gas::SHA256_WORD * words,
+        3 => gas::RIPEMD160 + gas::RIPEMD160_WORD * words,
+        4 => gas::IDENTITY + gas::IDENTITY_WORD * words,
+        5 => modexp_cost(input),
+        _ => return Err(VmError::UnsupportedPrecompile(*address)),
+    };
+    if !gas.record_cost(cost) {
+        gas.spend_all();
+        return Ok(None);
+    }
+
+    let output = match address.to_low_u64_be() {
+        1 => ecrecover(input),
+        2 => Sha256::digest(input).to_vec(),
+        // left-padded to a word
+        3 => H256::from(H160::from_slice(&Ripemd160::digest(input)))
+            .as_bytes()
+            .to_vec(),
+        4 => input.to_vec(),
+        _ => modexp(input),
+    };
+    Ok(Some(output))
+}
+
+// The signer's address padded to a word, or nothing if the signature is
+// invalid
+fn ecrecover(input: &[u8]) -> Vec<u8> {
+    let word = |i: u64| U256::from_big_endian(&padded(input, 32 * i, 32));
+    let v = word(1);
+    if v != U256::from(27) && v != U256::from(28) {
+        return Vec::new();
+    }
+    let signature = Signature {
+        y_parity: v == U256::from(28),
+        r: word(2),
+        s: word(3),
+    };
+    let hash = H256::from_slice(&padded(input, 0, 32));
+    match signature.recover_any_s(hash) {
+        Some(address) => H256::from(address).as_bytes().to_vec(),
+        None => Vec::new(),
+    }
+}
+
+// Lengths of the base, exponent and modulus, which follow them in the input
+fn modexp_lengths(input: &[u8]) -> (u64, u64, u64) {
+    let length = |i: u64| {
+        let length = U256::from_big_endian(&padded(input, 32 * i, 32));
+        if length > U256::from(u64::MAX) {
+            u64::MAX
+        } else {
+            length.as_u64()
+        }
+    };
+    (length(0), length(1), length(2))
+}
+
+// Cost of MODEXP since Berlin (EIP-2565)
+fn modexp_cost(input: &[u8]) -> u64 {
+    let (base_len, exp_len, mod_len) = modexp_lengths(input);
+    let words = (base_len.max(mod_len) as u128).div_ceil(8);
+    let complexity = words.saturating_mul(words);
+
+    // the bit length of the exponent, from its first 32 bytes
+    let head = padded(input, 96u64.saturating_add(base_len), exp_len.min(32));
+    let head_bits = U256::from_big_endian(&head).bits() as u128;
+    let iterations = match exp_len {
+        0..=32 => head_bits.saturating_sub(1),
+        _ => 8 * (exp_len as u128 - 32) + head_bits.saturating_sub(1),
+    };
+
+    let cost = complexity.saturating_mul(iterations.max(1)) / 3;
+    cost.clamp(gas::MODEXP_MIN as u128, u64::MAX as u128) as u64
+}
+
+// base ** exponent % modulus, left-padded to the length of the modulus
+fn modexp(input: &[u8]) -> Vec<u8> {
+    let (base_len, exp_len, mod_len) = modexp_lengths(input);
+    if mod_len == 0 {
+        return Vec::new();
+    }
+    // the cost was paid, so the lengths are small
+    let base = BigUint::from_bytes_be(&padded(input, 96, base_len));
+    let exponent = BigUint::from_bytes_be(&padded(input, 96 + base_len, exp_len));
+    let modulus = BigUint::from_bytes_be(&padded(input, 96 + base_len + exp_len, mod_len));
+
+    let mut output = vec![0; mod_len as usize];
+    if modulus != BigUint::ZERO {
+        let result = base.modpow(&exponent, &modulus).to_bytes_be();
+        output[mod_len as usize - result.len()..].copy_from_slice(&result);
+    }
+    output
+}
+
+// `len` bytes of `input` from `offset`, reading zeros past its end
+fn padded(input: &[u8], offset: u64, len: u64) -> Vec<u8> {
+    let mut bytes = vec![0; len as usize];
+    if let Some(rest) = usize::try_from(offset).ok().and_then(|o| input.get(o..)) {
+        let n = rest.len().min(bytes.len());
+        bytes[..n].copy_from_slice(&rest[..n]);
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::transaction::LocalSigner;
+
+    #[test]
+    fn precompiles_by_fork() {
+        assert_eq!(addresses(Fork::Berlin).count(), 9);
+        assert!(is_precompile(&H160::from_low_u64_be(10), Fork::Cancun));
+        assert!(!is_precompile(&H160::from_low_u64_be(10), Fork::Shanghai));
+        assert!(is_precompile(&H160::from_low_u64_be(0x11), Fork::Prague));
+        assert!(!is_precompile(&H160::zero(), Fork::Prague));
+        assert!(!is_precompile(&H160::repeat_byte(1), Fork::Prague));
+    }
+
+    fn call(address: u64, input: &[u8], gas_limit: u64) -> (Option<String>, Gas) {
+        let mut gas = Gas::new(gas_limit);
+        let output = run(&H160::from_low_u64_be(address), input, &mut gas).unwrap();
+        (output.map(hex::encode), gas)
+    }
+
+    #[test]
+    fn hashes_and_identity() {
+        let (output, gas) = call(2, &[], 100);
+        assert_eq!(
+            output.unwrap(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(gas.spent(), 60);
+        let (output, gas) = call(3, &[], 1000);
+        assert_eq!(
+            output.unwrap(),
+            "0000000000000000000000009c1185a5c5e9fc54612808977ee8f548b2258d31"
+        );
+        assert_eq!(gas.spent(), 600);
+        let (output, gas) = call(4, &[1; 33], 100);
+        assert_eq!(output.unwrap(), "01".repeat(33));
+        assert_eq!(gas.spent(), 15 + 3 * 2);
+
+        // too little gas fails and consumes it all
+        let (output, gas) = call(2, &[0; 33], 83);
+        assert_eq!(output, None);
+        assert_eq!(gas.remaining(), 0);
+        assert_eq!(
+            run(&H160::from_low_u64_be(6), &[], &mut Gas::new(1000)),
+            Err(VmError::UnsupportedPrecompile(H160::from_low_u64_be(6)))
+        );
+    }
+
+    #[test]
+    fn recovers_signers() {
+        let signer = LocalSigner::from_bytes(&[0x11; 32]).unwrap();
+        let hash = H256::repeat_byte(0xab);
+        let signature = signer.sign_hash(hash);
+        let input = |y_parity: bool, s: U256| {
+            let mut input = hash.as_bytes().to_vec();
+            for word in [U256::from(27 + y_parity as u8), signature.r, s] {
+                let mut bytes = [0; 32];
+                word.to_big_endian(&mut bytes);
+                input.extend(bytes);
+            }
+            input
+        };
+        let expected = hex::encode(H256::from(signer.address()));
+
+        let (output, gas) = call(1, &input(signature.y_parity, signature.s), 3000);
+        assert_eq!(output.unwrap(), expected);
+        assert_eq!(gas.remaining(), 0);
+        // the high-s twin of the signature, which transactions reject
+        let n = U256::from_str_radix(
+            "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141",
+            16,
+        )
+        .unwrap();
+        let high_s = input(!signature.y_parity, n - signature.s);
+        assert_eq!(call(1, &high_s, 3000).0.unwrap(), expected);
+
+        let mut invalid_v = input(signature.y_parity, signature.s);
+        invalid_v[63] = 29;
+        assert_eq!(call(1, &invalid_v, 3000).0.unwrap(), "");
+        assert_eq!(call(1, &input(signature.y_parity, n), 3000).0.unwrap(), "");
+    }
+
+    #[test]
+    fn modexp_examples_of_eip_198() {
+        // 3 ** (p - 1) % p for the secp256k1 field prime p
+        let input = hex::decode(concat!(
+            "0000000000000000000000000000000000000000000000000000000000000001",
+            "0000000000000000000000000000000000000000000000000000000000000020",
+            "0000000000000000000000000000000000000000000000000000000000000020",
+            "03",
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2e",
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
+        ))
+        .unwrap();
+        let (output, gas) = call(5, &input, 10_000);
+        assert_eq!(output.unwrap(), format!("{:064x}", 1));
+        assert_eq!(gas.spent(), 1360);
+
+        // a zero modulus gives zero, with the missing input read as zeros
+        let input = hex::decode(concat!(
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "0000000000000000000000000000000000000000000000000000000000000020",
+            "0000000000000000000000000000000000000000000000000000000000000020",
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2e",
+        ))
+        .unwrap();
+        let (output, gas) = call(5, &input, 10_000);
+        assert_eq!(output.unwrap(), "00".repeat(32));
+        assert_eq!(gas.spent(), 1360);
+
+        // a huge exponent is priced out before any work is done
+        let mut input = vec![0; 96];
+        input[31] = 1;
+        input[32..64].fill(0xff);
+        input[95] = 1;
+        let (output, gas) = call(5, &input, 10_000_000);
+        assert_eq!(output, None);
+        assert_eq!(gas.remaining(), 0);
+        assert_eq!(call(5, &[], 10_000).1.spent(), 200);
+    }
+}
@@ -0,0 +1,170 @@
+// Decoding of revert data as encoded by Solidity
+
+use crate::abi::{decode, Function, ParamType, Token};
+use primitive_types::U256;
+use std::fmt;
+
+// Selectors of `Error(string)` and `Panic(uint256)`
+pub const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+pub const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RevertReason {
+    // `revert("...")` and failed `require`s
+    Error(String),
+    // failed `assert`s, overflows, out of bounds accesses and so on
+    Panic(U256),
+    // custom error of the contract's ABI, e.g.
+    // `InsufficientBalance(uint256 available, uint256 required)`
+    Custom { name: String, args: Vec<Token> },
+}
+
+impl fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RevertReason::Error(message) => write!(f, "{}", message),
+            RevertReason::Panic(code) => match panic_message(*code) {
+                Some(message) => write!(f, "panic {:#04x}: {}", code, message),
+                None => write!(f, "panic {:#04x}", code),
+            },
+            RevertReason::Custom { name, args } => {
+                write!(f, "{}{}", name, Token::Tuple(args.clone()))
+            }
+        }
+    }
+}
+
+// Meaning of the panic codes emitted by the Solidity compiler
+pub fn panic_message(code: U256) -> Option<&'static str> {
+    if code > U256::from(u8::MAX) {
+        return None;
+    }
+    let message = match code.as_u32() {
+        0x00 => "generic compiler panic",
+        0x01 => "assertion failed",
+        0x11 => "arithmetic underflow or overflow",
+        0x12 => "division or modulo by zero",
+        0x21 => "invalid enum value",
+        0x22 => "invalid storage byte array encoding",
+        0x31 => "pop on an empty array",
+        0x32 => "array index out of bounds",
+        0x41 => "out of memory",
+        0x51 => "call to an uninitialized internal function",
+        _ => return None,
+    };
+    Some(message)
+}
+
+// Decodes the output of a reverted frame, matching custom errors against
+// `errors`, e.g. those of `Abi::from_json`. Returns `None` for empty or
+// unrecognised data.
+pub fn decode_revert(output: &[u8], errors: &[Function]) -> Option<RevertReason> {
+    let (head, data) = (output.get(..4)?, &output[4..]);
+    if head == ERROR_SELECTOR {
+        return match decode(&[ParamType::String], data).ok()?.pop()? {
+            Token::String(message) => Some(RevertReason::Error(message)),
+            _ => None,
+        };
+    }
+    if head == PANIC_SELECTOR {
+        return (data.len() == 32).then(|| RevertReason::Panic(U256::from_big_endian(data)));
+    }
+    errors.iter().find_map(|error| {
+        let args = error.decode_input(output).ok()?;
+        Some(RevertReason::Custom {
+            name: error.name.clone(),
+            args,
+        })
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::abi::selector;
+    use crate::Vm;
+    use primitive_types::H256;
+
+    fn word(value: u64) -> Vec<u8> {
+        H256::from_low_u64_be(value).as_bytes().to_vec()
+    }
+
+    #[test]
+    fn decodes_errors_panics_and_custom_errors() {
+        assert_eq!(selector("Error(string)"), ERROR_SELECTOR);
+        assert_eq!(selector("Panic(uint256)"), PANIC_SELECTOR);
+
+        let mut error = ERROR_SELECTOR.to_vec();
+        error.extend(word(32));
+        error.extend(word(9));
+        error.extend(b"not owner");
+        error.resize(4 + 3 * 32, 0);
+        let reason = decode_revert(&error, &[]).unwrap();
+        assert_eq!(reason, RevertReason::Error("not owner".into()));
+        assert_eq!(reason.to_string(), "not owner");
+        // a length past the data
+        error[4 + 63] = 0xff;
+        assert_eq!(decode_revert(&error, &[]), None);
+
+        let mut panic = PANIC_SELECTOR.to_vec();
+        panic.extend(word(0x11));
+        let reason = decode_revert(&panic, &[]).unwrap();
+        assert_eq!(reason, RevertReason::Panic(U256::from(0x11)));
+        assert_eq!(
+            reason.to_string(),
+            "panic 0x11: arithmetic underflow or overflow"
+        );
+        // trailing data is not a panic
+        panic.extend(word(0x32));
+        assert_eq!(decode_revert(&panic, &[]), None);
+        assert_eq!(
+            RevertReason::Panic(U256::from(0x99)).to_string(),
+            "panic 0x99"
+        );
+
+        let error = |name: &str, inputs| Function {
+            name: name.into(),
+            inputs,
+            outputs: Vec::new(),
+        };
+        let errors = [
+            error("Unauthorized", vec![]),
+            error(
+                "InsufficientBalance",
+                vec![ParamType::Uint(256), ParamType::Uint(256)],
+            ),
+        ];
+        let mut custom = selector("InsufficientBalance(uint256,uint256)").to_vec();
+        custom.extend(word(1));
+        custom.extend(word(2));
+        let args = [Token::Uint(U256::from(1)), Token::Uint(U256::from(2))];
+        assert_eq!(decode_revert(&custom, &[]), None);
+        let reason = decode_revert(&custom, &errors).unwrap();
+        assert_eq!(
+            reason,
+            RevertReason::Custom {
+                name: "InsufficientBalance".into(),
+                args: args.to_vec(),
+            }
+        );
+        assert_eq!(reason.to_string(), "InsufficientBalance(1, 2)");
+        // arguments that do not decode
+        assert_eq!(decode_revert(&custom[..36], &errors), None);
+        assert_eq!(decode_revert(&[], &errors), None);
+    }
+
+    #[test]
+    fn decodes_the_output_of_a_reverted_run() {
+        // MSTORE the selector and the code, then REVERT(0, 36)
+        let mut code = vec![0x7f];
+        code.extend(PANIC_SELECTOR);
+        code.extend([0; 28]);
+        code.extend([0x60, 0x00, 0x52, 0x60, 0x32, 0x60, 0x04, 0x52]);
+        code.extend([0x60, 0x24, 0x60, 0x00, 0xfd]);
+        let result = Vm::new(&code).run();
+        assert_eq!(
+            result.revert_reason(&[]).unwrap().to_string(),
+            "panic 0x32: array index out of bounds"
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use super::{Header, RlpError};
+use primitive_types::{H160, H256, U256};
+
+pub trait Decodable: Sized {
+    // Decodes one item from the front of `buf` and advances past it
+    fn rlp_decode(buf: &mut &[u8]) -> Result<Self, RlpError>;
+}
+
+// Decodes `data`, which must hold exactly one item
+pub fn decode<T: Decodable>(mut data: &[u8]) -> Result<T, RlpError> {
+    let value = T::rlp_decode(&mut data)?;
+    if !data.is_empty() {
+        return Err(RlpError::TrailingBytes);
+    }
+    Ok(value)
+}
+
+impl Header {
+    // Decodes the header at the front of `buf` and advances to its payload,
+    // which is checked to be complete. A single byte below 0x80 is its own
+    // payload, so `buf` is left at it.
+    pub fn decode(buf: &mut &[u8]) -> Result<Header, RlpError> {
+        let prefix = *buf.first().ok_or(RlpError::InputTooShort)?;
+        let (list, payload_length) = match prefix {
+            0x00..=0x7f => (false, 1),
+            0x80..=0xb7 => {
+                let len = (prefix - 0x80) as usize;
+                if len == 1 && buf.get(1).is_some_and(|&b| b < 0x80) {
+                    return Err(RlpError::NonCanonicalSingleByte);
+                }
+                *buf = &buf[1..];
+                (false, len)
+            }
+            0xb8..=0xbf => (false, long_length(buf, prefix - 0xb7)?),
+            0xc0..=0xf7 => {
+                *buf = &buf[1..];
+                (true, (prefix - 0xc0) as usize)
+            }
+            0xf8..=0xff => (true, long_length(buf, prefix - 0xf7)?),
+        };
+        if buf.len() < payload_length {
+            return Err(RlpError::InputTooShort);
+        }
+        Ok(Header {
+            list,
+            payload_length,
+        })
+    }
+
+    // Decodes a string header and returns its payload, advancing past it
+    pub fn decode_bytes<'a>(buf: &mut &'a [u8]) -> Result<&'a [u8], RlpError> {
+        let header = Header::decode(buf)?;
+        if header.list {
+            return Err(RlpError::UnexpectedList);
+        }
+        let (payload, rest) = buf.split_at(header.payload_length);
+        *buf = rest;
+        Ok(payload)
+    }
+
+    // Decodes a list header and returns its payload, advancing past it
+    pub fn decode_list<'a>(buf: &mut &'a [u8]) -> Result<&'a [u8], RlpError> {
+        let header = Header::decode(buf)?;
+        if !header.list {
+            return Err(RlpError::UnexpectedString);
+        }
+        let (payload, rest) = buf.split_at(header.payload_length);
+        *buf = rest;
+        Ok(payload)
+    }
+}
+
+// Reads a long-form length of `len_of_len` bytes following the prefix
+fn long_length(buf: &mut &[u8], len_of_len: u8) -> Result<usize, RlpError> {
+    let len_of_len = len_of_len as usize;
+    let bytes = buf.get(1..1 + len_of_len).ok_or(RlpError::InputTooShort)?;
+    if bytes[0] == 0 {
+        return Err(RlpError::NonCanonicalSize);
+    }
+    if len_of_len > std::mem::size_of::<usize>() {
+        return Err(RlpError::Overflow);
+    }
+    let len = bytes.iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+    if len < 56 {
+        return Err(RlpError::NonCanonicalSize);
+    }
+    *buf = &buf[1 + len_of_len..];
+    Ok(len)
+}
+
+// Payload of an integer of at most `max` bytes without leading zeros
+fn uint_bytes<'a>(buf: &mut &'a [u8], max: usize) -> Result<&'a [u8], RlpError> {
+    let bytes = Header::decode_bytes(buf)?;
+    if bytes.first() == Some(&0) {
+        return Err(RlpError::LeadingZero);
+    }
+    if bytes.len() > max {
+        return Err(RlpError::Overflow);
+    }
+    Ok(bytes)
+}
+
+impl Decodable for Vec<u8> {
+    fn rlp_decode(buf: &mut &[u8]) -> Result<Self, RlpError> {
+        Header::decode_bytes(buf).map(|b| b.to_vec())
+    }
+}
+
+impl<T: Decodable> Decodable for Vec<T> {
+    fn rlp_decode(buf: &mut &[u8]) -> Result<Self, RlpError> {
+        let mut payload = Header::decode_list(buf)?;
+        let mut items = Vec::new();
+        while !payload.is_empty() {
+            items.push(T::rlp_decode(&mut payload)?);
+        }
+        Ok(items)
+    }
+}
+
+impl Decodable for bool {
+    fn rlp_decode(buf: &mut &[u8]) -> Result<Self, RlpError> {
+        match u64::rlp_decode(buf)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(RlpError::Custom("invalid bool")),
+        }
+    }
+}
+
+macro_rules! impl_uint {
+    ($($t:ty),*) => {
+        $(
+            impl Decodable for $t {
+                fn rlp_decode(buf: &mut &[u8]) -> Result<Self, RlpError> {
+                    let bytes = uint_bytes(buf, std::mem::size_of::<$t>())?;
+                    Ok(bytes.iter().fold(0, |acc, &b| (acc << 8) | b as $t))
+                }
+            }
+        )*
+    };
+}
+
+impl_uint!(u16, u32, u64, u128, usize);
+
+impl Decodable for U256 {
+    fn rlp_decode(buf: &mut &[u8]) -> Result<Self, RlpError> {
+        uint_bytes(buf, 32).map(U256::from_big_endian)
+    }
+}
+
+macro_rules! impl_fixed_hash {
+    ($($t:ident),*) => {
+        $(
+            impl Decodable for $t {
+                fn rlp_decode(buf: &mut &[u8]) -> Result<Self, RlpError> {
+                    let bytes = Header::decode_bytes(buf)?;
+                    if bytes.len() != $t::len_bytes() {
+                        return Err(RlpError::UnexpectedLength);
+                    }
+                    Ok($t::from_slice(bytes))
+                }
+            }
+        )*
+    };
+}
+
+impl_fixed_hash!(H160, H256);
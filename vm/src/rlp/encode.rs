@@ -0,0 +1,137 @@
+use super::Header;
+use primitive_types::{H160, H256, U256};
+
+pub trait Encodable {
+    // Appends the encoding of `self` to `out`
+    fn rlp_append(&self, out: &mut Vec<u8>);
+}
+
+pub fn encode<T: Encodable + ?Sized>(value: &T) -> Vec<u8> {
+    let mut out = Vec::new();
+    value.rlp_append(&mut out);
+    out
+}
+
+pub fn encode_list<T: Encodable>(items: &[T]) -> Vec<u8> {
+    let mut payload = Vec::new();
+    for item in items {
+        item.rlp_append(&mut payload);
+    }
+    wrap_list(&payload)
+}
+
+// Prefixes a list payload, i.e. the concatenation of already encoded items,
+// with its header
+pub fn wrap_list(payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(payload.len() + 9);
+    Header {
+        list: true,
+        payload_length: payload.len(),
+    }
+    .encode(&mut out);
+    out.extend_from_slice(payload);
+    out
+}
+
+impl Header {
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        let offset = if self.list { 0xc0 } else { 0x80 };
+        if self.payload_length < 56 {
+            out.push(offset + self.payload_length as u8);
+        } else {
+            let len = self.payload_length.to_be_bytes();
+            let len = strip_leading_zeros(&len);
+            out.push(offset + 55 + len.len() as u8);
+            out.extend_from_slice(len);
+        }
+    }
+}
+
+fn strip_leading_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+impl Encodable for [u8] {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        if self.len() == 1 && self[0] < 0x80 {
+            out.push(self[0]);
+            return;
+        }
+        Header {
+            list: false,
+            payload_length: self.len(),
+        }
+        .encode(out);
+        out.extend_from_slice(self);
+    }
+}
+
+impl Encodable for Vec<u8> {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        self.as_slice().rlp_append(out)
+    }
+}
+
+impl<T: Encodable> Encodable for Vec<T> {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        let mut payload = Vec::new();
+        for item in self {
+            item.rlp_append(&mut payload);
+        }
+        Header {
+            list: true,
+            payload_length: payload.len(),
+        }
+        .encode(out);
+        out.extend_from_slice(&payload);
+    }
+}
+
+impl<T: Encodable + ?Sized> Encodable for &T {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        (**self).rlp_append(out)
+    }
+}
+
+impl Encodable for bool {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        (*self as u64).rlp_append(out)
+    }
+}
+
+macro_rules! impl_uint {
+    ($($t:ty),*) => {
+        $(
+            impl Encodable for $t {
+                fn rlp_append(&self, out: &mut Vec<u8>) {
+                    strip_leading_zeros(&self.to_be_bytes()).rlp_append(out)
+                }
+            }
+        )*
+    };
+}
+
+impl_uint!(u16, u32, u64, u128, usize);
+
+impl Encodable for U256 {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        let mut bytes = [0u8; 32];
+        self.to_big_endian(&mut bytes);
+        strip_leading_zeros(&bytes).rlp_append(out)
+    }
+}
+
+macro_rules! impl_fixed_hash {
+    ($($t:ty),*) => {
+        $(
+            impl Encodable for $t {
+                fn rlp_append(&self, out: &mut Vec<u8>) {
+                    self.as_bytes().rlp_append(out)
+                }
+            }
+        )*
+    };
+}
+
+impl_fixed_hash!(H160, H256);
@@ -0,0 +1,27 @@
+// Implements `Encodable` and `Decodable` for a struct encoded as the list
+// of the given fields, in order
+#[macro_export]
+macro_rules! impl_rlp {
+    ($name:ident { $($field:ident),* $(,)? }) => {
+        impl $crate::rlp::Encodable for $name {
+            fn rlp_append(&self, out: &mut Vec<u8>) {
+                let mut payload = Vec::new();
+                $( $crate::rlp::Encodable::rlp_append(&self.$field, &mut payload); )*
+                out.extend_from_slice(&$crate::rlp::wrap_list(&payload));
+            }
+        }
+
+        impl $crate::rlp::Decodable for $name {
+            fn rlp_decode(buf: &mut &[u8]) -> Result<Self, $crate::rlp::RlpError> {
+                let mut payload = $crate::rlp::Header::decode_list(buf)?;
+                let value = Self {
+                    $( $field: $crate::rlp::Decodable::rlp_decode(&mut payload)?, )*
+                };
+                if !payload.is_empty() {
+                    return Err($crate::rlp::RlpError::ListLengthMismatch);
+                }
+                Ok(value)
+            }
+        }
+    };
+}
@@ -0,0 +1,167 @@
+// Recursive Length Prefix serialization
+//
+// Byte strings (`Vec<u8>`, `[u8]`) encode as RLP strings and other vectors as
+// lists. Integers encode big-endian without leading zeros. Decoding is
+// strict: any input that is not in canonical form is rejected.
+
+#[macro_use]
+mod macros;
+mod decode;
+mod encode;
+
+pub use decode::{decode, Decodable};
+pub use encode::{encode, encode_list, wrap_list, Encodable};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RlpError {
+    InputTooShort,
+    UnexpectedList,
+    UnexpectedString,
+    // a single byte below 0x80 wrapped in a string header
+    NonCanonicalSingleByte,
+    // a length that should have used the short form or has leading zeros
+    NonCanonicalSize,
+    // an integer with leading zero bytes
+    LeadingZero,
+    Overflow,
+    // a list payload with items left over or missing
+    ListLengthMismatch,
+    // input left after the decoded item
+    TrailingBytes,
+    UnexpectedLength,
+    Custom(&'static str),
+}
+
+// Prefix of an RLP item
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Header {
+    pub list: bool,
+    pub payload_length: usize,
+}
+
+// Dynamically typed RLP item, for inspecting arbitrary input
+#[derive(Clone, Debug, PartialEq)]
+pub enum Item {
+    Bytes(Vec<u8>),
+    List(Vec<Item>),
+}
+
+impl Encodable for Item {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        match self {
+            Item::Bytes(bytes) => bytes.rlp_append(out),
+            Item::List(items) => items.rlp_append(out),
+        }
+    }
+}
+
+impl Decodable for Item {
+    fn rlp_decode(buf: &mut &[u8]) -> Result<Self, RlpError> {
+        let header = Header::decode(buf)?;
+        let (mut payload, rest) = buf.split_at(header.payload_length);
+        *buf = rest;
+        if !header.list {
+            return Ok(Item::Bytes(payload.to_vec()));
+        }
+        let mut items = Vec::new();
+        while !payload.is_empty() {
+            items.push(Item::rlp_decode(&mut payload)?);
+        }
+        Ok(Item::List(items))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use primitive_types::{H160, U256};
+    use proptest::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Sample {
+        nonce: u64,
+        to: H160,
+        value: U256,
+        data: Vec<u8>,
+        items: Vec<u64>,
+    }
+
+    impl_rlp!(Sample {
+        nonce,
+        to,
+        value,
+        data,
+        items
+    });
+
+    #[test]
+    fn known_encodings() {
+        assert_eq!(encode(&b"dog".to_vec()), vec![0x83, b'd', b'o', b'g']);
+        assert_eq!(
+            encode(&vec![b"cat".to_vec(), b"dog".to_vec()]),
+            vec![0xc8, 0x83, b'c', b'a', b't', 0x83, b'd', b'o', b'g']
+        );
+        assert_eq!(encode(&Vec::<u8>::new()), vec![0x80]);
+        assert_eq!(encode(&Vec::<u64>::new()), vec![0xc0]);
+        assert_eq!(encode(&0u64), vec![0x80]);
+        assert_eq!(encode(&15u64), vec![0x0f]);
+        assert_eq!(encode(&1024u64), vec![0x82, 0x04, 0x00]);
+        let long = vec![0x61u8; 56];
+        assert_eq!(encode(&long)[..2], [0xb8, 56]);
+    }
+
+    #[test]
+    fn rejects_non_canonical() {
+        // single byte in a string header
+        assert_eq!(
+            decode::<Vec<u8>>(&[0x81, 0x05]),
+            Err(RlpError::NonCanonicalSingleByte)
+        );
+        // long form for a short string
+        assert_eq!(
+            decode::<Vec<u8>>(&[0xb8, 0x02, 0x01, 0x02]),
+            Err(RlpError::NonCanonicalSize)
+        );
+        // integer with a leading zero
+        assert_eq!(
+            decode::<u64>(&[0x82, 0x00, 0x01]),
+            Err(RlpError::LeadingZero)
+        );
+        // zero must be the empty string
+        assert_eq!(decode::<u64>(&[0x00]), Err(RlpError::LeadingZero));
+        assert_eq!(decode::<u64>(&[0x80, 0x80]), Err(RlpError::TrailingBytes));
+        assert_eq!(decode::<u64>(&[0x83, 0x01]), Err(RlpError::InputTooShort));
+    }
+
+    proptest! {
+        #[test]
+        fn roundtrip_struct(
+            nonce: u64,
+            to: [u8; 20],
+            value: [u64; 4],
+            data: Vec<u8>,
+            items: Vec<u64>,
+        ) {
+            let sample = Sample { nonce, to: H160(to), value: U256(value), data, items };
+            prop_assert_eq!(decode::<Sample>(&encode(&sample)), Ok(sample));
+        }
+
+        #[test]
+        fn roundtrip_nested(
+            items in prop::collection::vec(
+                prop::collection::vec(prop::collection::vec(any::<u8>(), 0..80), 0..8),
+                0..8,
+            ),
+        ) {
+            prop_assert_eq!(decode::<Vec<Vec<Vec<u8>>>>(&encode(&items)), Ok(items));
+        }
+
+        // anything that decodes is canonical, i.e. re-encodes to the input
+        #[test]
+        fn decoded_input_is_canonical(input: Vec<u8>) {
+            if let Ok(item) = decode::<Item>(&input) {
+                prop_assert_eq!(encode(&item), input);
+            }
+        }
+    }
+}
@@ -0,0 +1,120 @@
+// Serde helpers for the 0x-prefixed hex encoding used by Ethereum JSON APIs
+
+use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+fn decode<E: Error>(s: &str) -> Result<Vec<u8>, E> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    if s.len() % 2 == 1 {
+        hex::decode(format!("0{}", s)).map_err(E::custom)
+    } else {
+        hex::decode(s).map_err(E::custom)
+    }
+}
+
+// Byte strings, e.g. "0x6001"
+pub mod bytes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(value: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("0x{}", hex::encode(value)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        decode(&String::deserialize(deserializer)?)
+    }
+}
+
+// Lists of byte strings
+pub mod bytes_list {
+    use super::*;
+    use serde::ser::SerializeSeq;
+
+    pub fn serialize<S: Serializer>(value: &[Vec<u8>], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(value.len()))?;
+        for item in value {
+            seq.serialize_element(&format!("0x{}", hex::encode(item)))?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Vec<u8>>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|s| decode(s))
+            .collect()
+    }
+}
+
+// Optional lists of byte strings, `null` or missing for `None`
+pub mod bytes_list_opt {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<Vec<Vec<u8>>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => super::bytes_list::serialize(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<Vec<u8>>>, D::Error> {
+        #[derive(Deserialize)]
+        struct Wrapper(#[serde(with = "super::bytes_list")] Vec<Vec<u8>>);
+
+        Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(value)| value))
+    }
+}
+
+// Quantities, e.g. "0x1a". Decimal strings and plain numbers are accepted
+// when deserializing.
+pub mod u64 {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:#x}", value))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Quantity {
+            Number(u64),
+            String(String),
+        }
+
+        match Quantity::deserialize(deserializer)? {
+            Quantity::Number(n) => Ok(n),
+            Quantity::String(s) => match s.strip_prefix("0x") {
+                Some(hex) => u64::from_str_radix(hex, 16).map_err(D::Error::custom),
+                None => s.parse().map_err(D::Error::custom),
+            },
+        }
+    }
+}
+
+// Optional quantities, `null` or missing for `None`
+pub mod u64_opt {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(value: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => super::u64::serialize(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<u64>, D::Error> {
+        #[derive(Deserialize)]
+        struct Wrapper(#[serde(with = "super::u64")] u64);
+
+        Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(value)| value))
+    }
+}
@@ -20,7 +20,10 @@ impl Stack {
 
     pub fn push(&mut self, value: H256) -> Result<(), VmError> {
         match self.data.len() {
-            x if x < self.max_size => Ok(self.data.push(value)),
+            x if x < self.max_size => {
+                self.data.push(value);
+                Ok(())
+            }
             _ => Err(VmError::StackOverflow),
         }
     }
@@ -53,6 +56,11 @@ impl Stack {
     pub fn size(&self) -> usize {
         self.data.len()
     }
+
+    // bottom first
+    pub fn values(&self) -> &[H256] {
+        &self.data
+    }
 }
 
 impl default::Default for Stack {
@@ -0,0 +1,608 @@
+use crate::db::{AccountChange, Changes, Database, EmptyDB};
+use crate::error::VmError;
+use primitive_types::{H160, H256, U256};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Account {
+    pub balance: U256,
+    pub nonce: u64,
+    pub code: Vec<u8>,
+    pub storage: HashMap<H256, H256>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Log {
+    pub address: H160,
+    pub topics: Vec<H256>,
+    pub data: Vec<u8>,
+}
+
+// Code of an account delegating to another account's code (EIP-7702)
+pub const DELEGATION_PREFIX: [u8; 3] = [0xef, 0x01, 0x00];
+
+pub fn delegation_code(address: H160) -> Vec<u8> {
+    [&DELEGATION_PREFIX[..], address.as_bytes()].concat()
+}
+
+// Account whose code is run when calling an account with `code`
+pub fn delegation(code: &[u8]) -> Option<H160> {
+    match code.strip_prefix(&DELEGATION_PREFIX) {
+        Some(address) if address.len() == 20 => Some(H160::from_slice(address)),
+        _ => None,
+    }
+}
+
+impl_rlp!(Log {
+    address,
+    topics,
+    data
+});
+
+// A single reversible change to the world state
+#[derive(Debug)]
+enum JournalEntry {
+    AccountCreated {
+        address: H160,
+    },
+    BalanceChanged {
+        address: H160,
+        prev: U256,
+    },
+    NonceChanged {
+        address: H160,
+        prev: u64,
+    },
+    CodeChanged {
+        address: H160,
+        prev: Vec<u8>,
+    },
+    StorageChanged {
+        address: H160,
+        key: H256,
+        prev: H256,
+    },
+    TransientStorageChanged {
+        address: H160,
+        key: H256,
+        prev: H256,
+    },
+    LogAdded,
+    AccountWarmed {
+        address: H160,
+    },
+    SlotWarmed {
+        address: H160,
+        key: H256,
+    },
+    ContractCreated {
+        address: H160,
+    },
+    SelfDestructed {
+        address: H160,
+    },
+}
+
+// Marks a position in the journal that the state can be reverted to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Checkpoint {
+    journal_len: usize,
+    depth: usize,
+}
+
+// World state cache on top of a `Database`. Accounts and slots are loaded
+// lazily and every mutation made through it is journaled so that a frame's
+// changes can be discarded with `revert`.
+pub struct State<'a> {
+    db: Box<dyn Database + 'a>,
+    // `None` caches an account that does not exist
+    accounts: HashMap<H160, Option<Account>>,
+    transient_storage: HashMap<(H160, H256), H256>,
+    logs: Vec<Log>,
+    journal: Vec<JournalEntry>,
+    depth: usize,
+    // accounts and slots changed by finalized transactions
+    changed: HashMap<H160, HashSet<H256>>,
+    // accounts deleted by finalized transactions, whose storage in the
+    // database is stale
+    destroyed: HashSet<H160>,
+    // accounts deleted since the last `take_changes`
+    cleared: HashSet<H160>,
+    // per-transaction bookkeeping, cleared by `finalize`
+    accessed_accounts: HashSet<H160>,
+    accessed_slots: HashSet<(H160, H256)>,
+    original_storage: HashMap<(H160, H256), H256>,
+    created: HashSet<H160>,
+    selfdestructed: HashSet<H160>,
+}
+
+impl Default for State<'_> {
+    fn default() -> Self {
+        Self::with_db(EmptyDB)
+    }
+}
+
+impl<'a> State<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_db(db: impl Database + 'a) -> Self {
+        Self {
+            db: Box::new(db),
+            accounts: HashMap::new(),
+            transient_storage: HashMap::new(),
+            logs: Vec::new(),
+            journal: Vec::new(),
+            depth: 0,
+            changed: HashMap::new(),
+            destroyed: HashSet::new(),
+            cleared: HashSet::new(),
+            accessed_accounts: HashSet::new(),
+            accessed_slots: HashSet::new(),
+            original_storage: HashMap::new(),
+            created: HashSet::new(),
+            selfdestructed: HashSet::new(),
+        }
+    }
+
+    // Inserts an account into the cache without journaling it or marking it
+    // as changed (e.g. for pre-state in tests)
+    pub fn insert_account(&mut self, address: H160, account: Account) {
+        self.accounts.insert(address, Some(account));
+    }
+
+    // Returns the account if it is already cached
+    pub fn account(&self, address: &H160) -> Option<&Account> {
+        self.accounts.get(address).and_then(|a| a.as_ref())
+    }
+
+    // Cached accounts that exist
+    pub fn accounts(&self) -> impl Iterator<Item = (&H160, &Account)> {
+        self.accounts
+            .iter()
+            .filter_map(|(address, account)| account.as_ref().map(|a| (address, a)))
+    }
+
+    pub fn load_account(&mut self, address: &H160) -> Result<Option<&Account>, VmError> {
+        if !self.accounts.contains_key(address) {
+            let account = match self.db.basic(*address)? {
+                Some(info) => {
+                    let code = match info.code {
+                        Some(code) => code,
+                        None => self.db.code_by_hash(info.code_hash)?,
+                    };
+                    Some(Account {
+                        balance: info.balance,
+                        nonce: info.nonce,
+                        code,
+                        storage: HashMap::new(),
+                    })
+                }
+                None => None,
+            };
+            self.accounts.insert(*address, account);
+        }
+        Ok(self.account(address))
+    }
+
+    pub fn exists(&mut self, address: &H160) -> Result<bool, VmError> {
+        Ok(self.load_account(address)?.is_some())
+    }
+
+    // Whether the account does not exist or is empty as defined by EIP-161
+    pub fn is_empty(&mut self, address: &H160) -> Result<bool, VmError> {
+        Ok(self.load_account(address)?.is_none_or(is_empty))
+    }
+
+    pub fn balance(&mut self, address: &H160) -> Result<U256, VmError> {
+        Ok(self
+            .load_account(address)?
+            .map(|a| a.balance)
+            .unwrap_or_default())
+    }
+
+    pub fn nonce(&mut self, address: &H160) -> Result<u64, VmError> {
+        Ok(self.load_account(address)?.map(|a| a.nonce).unwrap_or(0))
+    }
+
+    pub fn code(&mut self, address: &H160) -> Result<Vec<u8>, VmError> {
+        Ok(self
+            .load_account(address)?
+            .map(|a| a.code.clone())
+            .unwrap_or_default())
+    }
+
+    pub fn storage(&mut self, address: &H160, key: &H256) -> Result<H256, VmError> {
+        let cached = match self.load_account(address)? {
+            Some(account) => account.storage.get(key).copied(),
+            None => return Ok(H256::zero()),
+        };
+        match cached {
+            Some(value) => Ok(value),
+            None if self.destroyed.contains(address) => Ok(H256::zero()),
+            None => {
+                let value = self.db.storage(*address, *key)?;
+                if let Some(Some(account)) = self.accounts.get_mut(address) {
+                    account.storage.insert(*key, value);
+                }
+                Ok(value)
+            }
+        }
+    }
+
+    // Value of the slot before the current transaction changed it
+    pub fn original_storage(&mut self, address: &H160, key: &H256) -> Result<H256, VmError> {
+        match self.original_storage.get(&(*address, *key)) {
+            Some(value) => Ok(*value),
+            None => self.storage(address, key),
+        }
+    }
+
+    pub fn transient_storage(&self, address: &H160, key: &H256) -> H256 {
+        self.transient_storage
+            .get(&(*address, *key))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn block_hash(&mut self, number: u64) -> Result<H256, VmError> {
+        self.db.block_hash(number)
+    }
+
+    pub fn logs(&self) -> &[Log] {
+        &self.logs
+    }
+
+    // Creates an empty account at `address` if there is none
+    pub fn touch(&mut self, address: H160) -> Result<(), VmError> {
+        self.account_mut(address)?;
+        Ok(())
+    }
+
+    pub fn add_balance(&mut self, address: H160, value: U256) -> Result<(), VmError> {
+        let account = self.account_mut(address)?;
+        let prev = account.balance;
+        account.balance = prev.overflowing_add(value).0;
+        self.journal
+            .push(JournalEntry::BalanceChanged { address, prev });
+        Ok(())
+    }
+
+    pub fn sub_balance(&mut self, address: H160, value: U256) -> Result<(), VmError> {
+        if self.balance(&address)? < value {
+            return Err(VmError::InsufficientBalance);
+        }
+        let account = self.account_mut(address)?;
+        let prev = account.balance;
+        account.balance = prev - value;
+        self.journal
+            .push(JournalEntry::BalanceChanged { address, prev });
+        Ok(())
+    }
+
+    pub fn transfer(&mut self, from: H160, to: H160, value: U256) -> Result<(), VmError> {
+        self.sub_balance(from, value)?;
+        self.add_balance(to, value)
+    }
+
+    pub fn increment_nonce(&mut self, address: H160) -> Result<(), VmError> {
+        let account = self.account_mut(address)?;
+        let prev = account.nonce;
+        account.nonce += 1;
+        self.journal
+            .push(JournalEntry::NonceChanged { address, prev });
+        Ok(())
+    }
+
+    pub fn set_code(&mut self, address: H160, code: Vec<u8>) -> Result<(), VmError> {
+        let account = self.account_mut(address)?;
+        let prev = std::mem::replace(&mut account.code, code);
+        self.journal
+            .push(JournalEntry::CodeChanged { address, prev });
+        Ok(())
+    }
+
+    pub fn set_storage(&mut self, address: H160, key: H256, value: H256) -> Result<(), VmError> {
+        // load the slot so that its previous value is known
+        let prev = self.storage(&address, &key)?;
+        self.original_storage.entry((address, key)).or_insert(prev);
+        let account = self.account_mut(address)?;
+        account.storage.insert(key, value);
+        self.journal
+            .push(JournalEntry::StorageChanged { address, key, prev });
+        Ok(())
+    }
+
+    pub fn set_transient_storage(&mut self, address: H160, key: H256, value: H256) {
+        let prev = set_slot(&mut self.transient_storage, (address, key), value);
+        self.journal
+            .push(JournalEntry::TransientStorageChanged { address, key, prev });
+    }
+
+    pub fn log(&mut self, log: Log) {
+        self.logs.push(log);
+        self.journal.push(JournalEntry::LogAdded);
+    }
+
+    // Adds the account to the accessed set (EIP-2929), returning whether it
+    // was cold
+    pub fn warm_account(&mut self, address: H160) -> bool {
+        let cold = self.accessed_accounts.insert(address);
+        if cold {
+            self.journal.push(JournalEntry::AccountWarmed { address });
+        }
+        cold
+    }
+
+    // Adds the slot to the accessed set (EIP-2929), returning whether it was
+    // cold
+    pub fn warm_slot(&mut self, address: H160, key: H256) -> bool {
+        let cold = self.accessed_slots.insert((address, key));
+        if cold {
+            self.journal.push(JournalEntry::SlotWarmed { address, key });
+        }
+        cold
+    }
+
+    // Records that the contract at `address` was created by the current
+    // transaction
+    pub fn mark_created(&mut self, address: H160) {
+        if self.created.insert(address) {
+            self.journal.push(JournalEntry::ContractCreated { address });
+        }
+    }
+
+    pub fn is_created(&self, address: &H160) -> bool {
+        self.created.contains(address)
+    }
+
+    // Schedules the account for deletion at the end of the transaction,
+    // returning whether it was not already scheduled
+    pub fn selfdestruct(&mut self, address: H160) -> bool {
+        let first = self.selfdestructed.insert(address);
+        if first {
+            self.journal.push(JournalEntry::SelfDestructed { address });
+        }
+        first
+    }
+
+    // Opens a new frame
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        self.depth += 1;
+        Checkpoint {
+            journal_len: self.journal.len(),
+            depth: self.depth,
+        }
+    }
+
+    // Keeps the changes of the frame. They stay journaled so that an
+    // enclosing frame can still revert them.
+    pub fn commit(&mut self, checkpoint: Checkpoint) {
+        debug_assert_eq!(checkpoint.depth, self.depth, "commit out of order");
+        self.depth -= 1;
+    }
+
+    // Undoes every change made since `checkpoint` was taken
+    pub fn revert(&mut self, checkpoint: Checkpoint) {
+        debug_assert_eq!(checkpoint.depth, self.depth, "revert out of order");
+        self.depth -= 1;
+
+        while self.journal.len() > checkpoint.journal_len {
+            match self.journal.pop() {
+                Some(JournalEntry::AccountCreated { address }) => {
+                    self.accounts.insert(address, None);
+                }
+                Some(JournalEntry::BalanceChanged { address, prev }) => {
+                    self.account_entry(&address).balance = prev;
+                }
+                Some(JournalEntry::NonceChanged { address, prev }) => {
+                    self.account_entry(&address).nonce = prev;
+                }
+                Some(JournalEntry::CodeChanged { address, prev }) => {
+                    self.account_entry(&address).code = prev;
+                }
+                Some(JournalEntry::StorageChanged { address, key, prev }) => {
+                    self.account_entry(&address).storage.insert(key, prev);
+                }
+                Some(JournalEntry::TransientStorageChanged { address, key, prev }) => {
+                    set_slot(&mut self.transient_storage, (address, key), prev);
+                }
+                Some(JournalEntry::LogAdded) => {
+                    self.logs.pop();
+                }
+                Some(JournalEntry::AccountWarmed { address }) => {
+                    self.accessed_accounts.remove(&address);
+                }
+                Some(JournalEntry::SlotWarmed { address, key }) => {
+                    self.accessed_slots.remove(&(address, key));
+                }
+                Some(JournalEntry::ContractCreated { address }) => {
+                    self.created.remove(&address);
+                }
+                Some(JournalEntry::SelfDestructed { address }) => {
+                    self.selfdestructed.remove(&address);
+                }
+                None => break,
+            }
+        }
+    }
+
+    // Ends a transaction: deletes self-destructed and touched empty
+    // accounts (EIP-161), records what it changed, drops the journal and
+    // per-transaction state and hands out the logs emitted so far
+    pub fn finalize(&mut self) -> Vec<Log> {
+        let mut touched = HashSet::new();
+        for entry in self.journal.drain(..) {
+            match entry {
+                JournalEntry::AccountCreated { address }
+                | JournalEntry::BalanceChanged { address, .. }
+                | JournalEntry::NonceChanged { address, .. }
+                | JournalEntry::CodeChanged { address, .. } => {
+                    self.changed.entry(address).or_default();
+                    touched.insert(address);
+                }
+                JournalEntry::StorageChanged { address, key, .. } => {
+                    self.changed.entry(address).or_default().insert(key);
+                    touched.insert(address);
+                }
+                JournalEntry::TransientStorageChanged { .. }
+                | JournalEntry::LogAdded
+                | JournalEntry::AccountWarmed { .. }
+                | JournalEntry::SlotWarmed { .. }
+                | JournalEntry::ContractCreated { .. }
+                | JournalEntry::SelfDestructed { .. } => (),
+            }
+        }
+
+        let mut deleted: Vec<H160> = touched
+            .into_iter()
+            .filter(|address| self.account(address).is_some_and(is_empty))
+            .collect();
+        deleted.extend(self.selfdestructed.drain());
+        for address in deleted {
+            self.accounts.insert(address, None);
+            self.changed.insert(address, HashSet::new());
+            self.destroyed.insert(address);
+            self.cleared.insert(address);
+        }
+
+        self.transient_storage.clear();
+        self.accessed_accounts.clear();
+        self.accessed_slots.clear();
+        self.original_storage.clear();
+        self.created.clear();
+        self.depth = 0;
+        std::mem::take(&mut self.logs)
+    }
+
+    // Accounts changed by the transactions finalized since the last call,
+    // ready to be committed to a database
+    pub fn take_changes(&mut self) -> Changes {
+        let cleared = std::mem::take(&mut self.cleared);
+        std::mem::take(&mut self.changed)
+            .into_iter()
+            .map(|(address, keys)| {
+                let account = self.account(&address).map(|account| Account {
+                    storage: keys
+                        .iter()
+                        .map(|key| (*key, account.storage[key]))
+                        .collect(),
+                    ..account.clone()
+                });
+                let change = AccountChange {
+                    account,
+                    storage_cleared: cleared.contains(&address),
+                };
+                (address, change)
+            })
+            .collect()
+    }
+
+    fn account_mut(&mut self, address: H160) -> Result<&mut Account, VmError> {
+        if self.load_account(&address)?.is_none() {
+            self.journal.push(JournalEntry::AccountCreated { address });
+            self.accounts.insert(address, Some(Account::default()));
+        }
+        Ok(self.account_entry(&address))
+    }
+
+    // Journal entries are only recorded for accounts that exist
+    fn account_entry(&mut self, address: &H160) -> &mut Account {
+        self.accounts
+            .get_mut(address)
+            .and_then(|a| a.as_mut())
+            .expect("journaled account must exist")
+    }
+}
+
+fn is_empty(account: &Account) -> bool {
+    account.nonce == 0 && account.balance.is_zero() && account.code.is_empty()
+}
+
+// Zero values are not kept in transient storage
+fn set_slot<K: std::hash::Hash + Eq>(map: &mut HashMap<K, H256>, key: K, value: H256) -> H256 {
+    let prev = if value.is_zero() {
+        map.remove(&key)
+    } else {
+        map.insert(key, value)
+    };
+    prev.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn addr(n: u64) -> H160 {
+        H160::from_low_u64_be(n)
+    }
+
+    #[test]
+    fn revert_restores_frame_changes() {
+        let mut state = State::new();
+        state.insert_account(
+            addr(1),
+            Account {
+                balance: U256::from(100),
+                ..Default::default()
+            },
+        );
+
+        let outer = state.checkpoint();
+        state
+            .set_storage(addr(1), H256::from_low_u64_be(1), H256::from_low_u64_be(7))
+            .unwrap();
+
+        let inner = state.checkpoint();
+        state.transfer(addr(1), addr(2), U256::from(40)).unwrap();
+        state
+            .set_storage(addr(1), H256::from_low_u64_be(1), H256::from_low_u64_be(8))
+            .unwrap();
+        state.set_transient_storage(addr(1), H256::zero(), H256::from_low_u64_be(1));
+        state.log(Log {
+            address: addr(1),
+            topics: vec![],
+            data: vec![1],
+        });
+        state.revert(inner);
+
+        assert_eq!(state.balance(&addr(1)).unwrap(), U256::from(100));
+        assert!(!state.exists(&addr(2)).unwrap());
+        assert_eq!(
+            state.storage(&addr(1), &H256::from_low_u64_be(1)).unwrap(),
+            H256::from_low_u64_be(7)
+        );
+        assert!(state.transient_storage(&addr(1), &H256::zero()).is_zero());
+        assert!(state.logs().is_empty());
+
+        state.commit(outer);
+        assert_eq!(
+            state.storage(&addr(1), &H256::from_low_u64_be(1)).unwrap(),
+            H256::from_low_u64_be(7)
+        );
+    }
+
+    #[test]
+    fn outer_revert_undoes_committed_frame() {
+        let mut state = State::new();
+        let outer = state.checkpoint();
+        let inner = state.checkpoint();
+        state.add_balance(addr(1), U256::from(5)).unwrap();
+        state.increment_nonce(addr(1)).unwrap();
+        state.commit(inner);
+        assert_eq!(state.nonce(&addr(1)).unwrap(), 1);
+
+        state.revert(outer);
+        assert!(!state.exists(&addr(1)).unwrap());
+    }
+
+    #[test]
+    fn insufficient_balance() {
+        let mut state = State::new();
+        assert_eq!(
+            state.transfer(addr(1), addr(2), U256::one()),
+            Err(VmError::InsufficientBalance)
+        );
+    }
+}
@@ -0,0 +1,313 @@
+// System contracts the client calls outside of any transaction
+
+use crate::env::Env;
+use crate::error::{InvalidBlock, VmError};
+use crate::opcode::{call_frame, Frame};
+use crate::state::{Log, State};
+use crate::Context;
+use primitive_types::{H160, H256, U256};
+use std::rc::Rc;
+
+// Caller of system calls, never touched by them
+pub const SYSTEM_ADDRESS: H160 = H160([
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xfe,
+]);
+
+pub const SYSTEM_CALL_GAS: u64 = 30_000_000;
+
+// EIP-4788
+pub const BEACON_ROOTS_ADDRESS: H160 = H160([
+    0x00, 0x0f, 0x3d, 0xf6, 0xd7, 0x32, 0x80, 0x7e, 0xf1, 0x31, 0x9f, 0xb7, 0xb8, 0xbb, 0x85, 0x22,
+    0xd0, 0xbe, 0xac, 0x02,
+]);
+
+pub const BEACON_ROOTS_CODE: &str = "3373fffffffffffffffffffffffffffffffffffffffe14604d57602036146024575f5ffd5b5f35801560495762001fff810690815414603c575f5ffd5b62001fff01545f5260205ff35b5f5ffd5b62001fff42064281555f359062001fff015500";
+
+// EIP-2935
+pub const HISTORY_STORAGE_ADDRESS: H160 = H160([
+    0x00, 0x00, 0xf9, 0x08, 0x27, 0xf1, 0xc5, 0x3a, 0x10, 0xcb, 0x7a, 0x02, 0x33, 0x5b, 0x17, 0x53,
+    0x20, 0x00, 0x29, 0x35,
+]);
+
+pub const HISTORY_STORAGE_CODE: &str = "3373fffffffffffffffffffffffffffffffffffffffe14604657602036036042575f35600143038111604257611fff81430311604257611fff9006545f5260205ff35b5f5ffd5b5f35611fff60014303065500";
+
+// EIP-6110, the deposit contract of mainnet
+pub const DEPOSIT_CONTRACT_ADDRESS: H160 = H160([
+    0x00, 0x00, 0x00, 0x00, 0x21, 0x9a, 0xb5, 0x40, 0x35, 0x6c, 0xbb, 0x83, 0x9c, 0xbe, 0x05, 0x30,
+    0x3d, 0x77, 0x05, 0xfa,
+]);
+
+// keccak256("DepositEvent(bytes,bytes,bytes,bytes,bytes)")
+pub const DEPOSIT_EVENT_TOPIC: H256 = H256([
+    0x64, 0x9b, 0xbc, 0x62, 0xd0, 0xe3, 0x13, 0x42, 0xaf, 0xea, 0x4e, 0x5c, 0xd8, 0x2d, 0x40, 0x49,
+    0xe7, 0xe1, 0xee, 0x91, 0x2f, 0xc0, 0x88, 0x9a, 0xa7, 0x90, 0x80, 0x3b, 0xe3, 0x90, 0x38, 0xc5,
+]);
+
+// EIP-7002
+pub const WITHDRAWAL_REQUEST_ADDRESS: H160 = H160([
+    0x00, 0x00, 0x09, 0x61, 0xef, 0x48, 0x0e, 0xb5, 0x5e, 0x80, 0xd1, 0x9a, 0xd8, 0x35, 0x79, 0xa6,
+    0x4c, 0x00, 0x70, 0x02,
+]);
+
+// EIP-7251
+pub const CONSOLIDATION_REQUEST_ADDRESS: H160 = H160([
+    0x00, 0x00, 0xbb, 0xdd, 0xc7, 0xce, 0x48, 0x86, 0x42, 0xfb, 0x57, 0x9f, 0x8b, 0x00, 0xf3, 0xa5,
+    0x90, 0x00, 0x72, 0x51,
+]);
+
+// Stores the parent beacon block root keyed by the block's timestamp
+pub fn apply_beacon_root(state: &mut State, env: &Env, root: H256) -> Result<(), VmError> {
+    system_call(state, env, BEACON_ROOTS_ADDRESS, root)
+}
+
+// Stores the parent block hash keyed by its number
+pub fn apply_parent_hash(state: &mut State, env: &Env, parent_hash: H256) -> Result<(), VmError> {
+    if env.block.number == 0 {
+        return Ok(());
+    }
+    system_call(state, env, HISTORY_STORAGE_ADDRESS, parent_hash)
+}
+
+// Withdrawal requests queued by the end of the block, which the call
+// removes from the queue
+pub fn dequeue_withdrawal_requests(state: &mut State, env: &Env) -> Result<Vec<u8>, VmError> {
+    dequeue_requests(state, env, WITHDRAWAL_REQUEST_ADDRESS)
+}
+
+pub fn dequeue_consolidation_requests(state: &mut State, env: &Env) -> Result<Vec<u8>, VmError> {
+    dequeue_requests(state, env, CONSOLIDATION_REQUEST_ADDRESS)
+}
+
+// Deposits made to the deposit contract, from its logs. Each request is the
+// validator's public key, withdrawal credentials, amount, signature and
+// index, in this order.
+pub fn deposit_requests<'a>(logs: impl IntoIterator<Item = &'a Log>) -> Result<Vec<u8>, VmError> {
+    let mut requests = Vec::new();
+    for log in logs {
+        if log.address != DEPOSIT_CONTRACT_ADDRESS
+            || log.topics.first() != Some(&DEPOSIT_EVENT_TOPIC)
+        {
+            continue;
+        }
+        let request = deposit_request(&log.data)
+            .ok_or(VmError::InvalidBlock(InvalidBlock::InvalidDepositLog))?;
+        requests.extend(request);
+    }
+    Ok(requests)
+}
+
+// The event's data is the ABI encoding of its five byte strings, whose
+// offsets and lengths are fixed
+fn deposit_request(data: &[u8]) -> Option<Vec<u8>> {
+    if data.len() != 576 {
+        return None;
+    }
+    let word = |offset: usize| U256::from_big_endian(&data[offset..offset + 32]);
+    let mut request = Vec::with_capacity(192);
+    let fields = [(160, 48), (256, 32), (320, 8), (384, 96), (512, 8)];
+    for (i, (offset, len)) in fields.into_iter().enumerate() {
+        if word(32 * i) != U256::from(offset) || word(offset) != U256::from(len) {
+            return None;
+        }
+        request.extend_from_slice(&data[offset + 32..offset + 32 + len]);
+    }
+    Some(request)
+}
+
+// Calls `address` from the system address without charging or validating
+// anything. A missing contract or a failed call is ignored.
+fn system_call(state: &mut State, env: &Env, address: H160, input: H256) -> Result<(), VmError> {
+    if state.code(&address)?.is_empty() {
+        return Ok(());
+    }
+    call(state, env, address, input.as_bytes().to_vec())?;
+    Ok(())
+}
+
+// Like `system_call` with no input, returning the requests the contract
+// outputs. The block is invalid if the contract is missing or the call fails.
+fn dequeue_requests(state: &mut State, env: &Env, address: H160) -> Result<Vec<u8>, VmError> {
+    let invalid = |e| Err(VmError::InvalidBlock(e));
+    if state.code(&address)?.is_empty() {
+        return invalid(InvalidBlock::MissingSystemContract(address));
+    }
+    let frame = call(state, env, address, Vec::new())?;
+    if !frame.success {
+        return invalid(InvalidBlock::SystemCallFailed(address));
+    }
+    Ok(frame.output)
+}
+
+fn call(state: &mut State, env: &Env, address: H160, input: Vec<u8>) -> Result<Frame, VmError> {
+    let context = Context {
+        address,
+        caller: SYSTEM_ADDRESS,
+        value: U256::zero(),
+        input,
+        depth: 0,
+        is_static: false,
+        gas_limit: SYSTEM_CALL_GAS,
+    };
+    let frame = call_frame(state, &Rc::new(env.clone()), None, context, address, false)?;
+    state.finalize();
+    Ok(frame)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::Account;
+
+    fn deploy(address: H160, code: &str) -> State<'static> {
+        let mut state = State::new();
+        state.insert_account(
+            address,
+            Account {
+                code: hex::decode(code).unwrap(),
+                ..Default::default()
+            },
+        );
+        state
+    }
+
+    // Reads from the contract as a regular caller
+    fn get(state: &mut State, env: &Env, address: H160, input: u64) -> Vec<u8> {
+        let context = Context {
+            address,
+            caller: H160::repeat_byte(1),
+            input: H256::from_low_u64_be(input).as_bytes().to_vec(),
+            gas_limit: 100_000,
+            ..Default::default()
+        };
+        let frame =
+            call_frame(state, &Rc::new(env.clone()), None, context, address, false).unwrap();
+        assert!(frame.success);
+        frame.output
+    }
+
+    #[test]
+    fn beacon_root_is_stored_by_timestamp() {
+        let mut state = deploy(BEACON_ROOTS_ADDRESS, BEACON_ROOTS_CODE);
+        let mut env = Env::default();
+        env.block.timestamp = 8191 + 12;
+        let root = H256::repeat_byte(0xbe);
+
+        apply_beacon_root(&mut state, &env, root).unwrap();
+        let key = |n: u64| H256::from_low_u64_be(n);
+        assert_eq!(
+            state.storage(&BEACON_ROOTS_ADDRESS, &key(12)).unwrap(),
+            key(8191 + 12)
+        );
+        assert_eq!(
+            state
+                .storage(&BEACON_ROOTS_ADDRESS, &key(8191 + 12))
+                .unwrap(),
+            root
+        );
+        assert!(!state.exists(&SYSTEM_ADDRESS).unwrap());
+
+        let timestamp = 8191 + 12;
+        assert_eq!(
+            get(&mut state, &env, BEACON_ROOTS_ADDRESS, timestamp),
+            root.as_bytes()
+        );
+    }
+
+    #[test]
+    fn parent_hash_is_stored_by_number() {
+        let mut state = deploy(HISTORY_STORAGE_ADDRESS, HISTORY_STORAGE_CODE);
+        let mut env = Env::default();
+        env.block.number = 5;
+        let parent_hash = H256::repeat_byte(0xaa);
+
+        apply_parent_hash(&mut state, &env, parent_hash).unwrap();
+        assert_eq!(
+            state
+                .storage(&HISTORY_STORAGE_ADDRESS, &H256::from_low_u64_be(4))
+                .unwrap(),
+            parent_hash
+        );
+        assert_eq!(
+            get(&mut state, &env, HISTORY_STORAGE_ADDRESS, 4),
+            parent_hash.as_bytes()
+        );
+    }
+
+    #[test]
+    fn parses_deposit_logs() {
+        use crate::abi::{encode, Token};
+        use crate::crypto::keccak256;
+
+        assert_eq!(
+            keccak256(b"DepositEvent(bytes,bytes,bytes,bytes,bytes)"),
+            DEPOSIT_EVENT_TOPIC
+        );
+        let fields = [
+            vec![1; 48],
+            vec![2; 32],
+            vec![3; 8],
+            vec![4; 96],
+            vec![5; 8],
+        ];
+        let log = Log {
+            address: DEPOSIT_CONTRACT_ADDRESS,
+            topics: vec![DEPOSIT_EVENT_TOPIC],
+            data: encode(&fields.clone().map(Token::Bytes)),
+        };
+        let other = Log {
+            address: H160::repeat_byte(1),
+            ..log.clone()
+        };
+        assert_eq!(
+            deposit_requests([&log, &other, &log]).unwrap(),
+            [fields.concat(), fields.concat()].concat()
+        );
+
+        // the fields must have their fixed lengths
+        let mut short = fields.clone();
+        short[0].pop();
+        let short = Log {
+            data: encode(&short.map(Token::Bytes)),
+            ..log.clone()
+        };
+        assert_eq!(short.data.len(), log.data.len());
+        assert_eq!(
+            deposit_requests([&short]),
+            Err(VmError::InvalidBlock(InvalidBlock::InvalidDepositLog))
+        );
+    }
+
+    #[test]
+    fn request_contracts_must_exist_and_succeed() {
+        let env = Env::default();
+        // returns 0xabcd
+        let mut state = deploy(WITHDRAWAL_REQUEST_ADDRESS, "61abcd5f526002601ef3");
+        assert_eq!(
+            dequeue_withdrawal_requests(&mut state, &env).unwrap(),
+            [0xab, 0xcd]
+        );
+        assert_eq!(
+            dequeue_consolidation_requests(&mut state, &env),
+            Err(VmError::InvalidBlock(InvalidBlock::MissingSystemContract(
+                CONSOLIDATION_REQUEST_ADDRESS
+            )))
+        );
+
+        // reverts
+        let mut state = deploy(CONSOLIDATION_REQUEST_ADDRESS, "5f5ffd");
+        assert_eq!(
+            dequeue_consolidation_requests(&mut state, &env),
+            Err(VmError::InvalidBlock(InvalidBlock::SystemCallFailed(
+                CONSOLIDATION_REQUEST_ADDRESS
+            )))
+        );
+    }
+
+    #[test]
+    fn missing_contract_is_ignored() {
+        let mut state = State::new();
+        apply_beacon_root(&mut state, &Env::default(), H256::repeat_byte(1)).unwrap();
+        assert!(state.accounts().next().is_none());
+    }
+}
@@ -0,0 +1,93 @@
+// Hooks to follow the execution opcode by opcode, e.g. for EIP-3155 traces
+
+use crate::error::VmError;
+use crate::executor::TxResult;
+use crate::gas::Gas;
+use primitive_types::H256;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// Shared by the frames of a transaction
+pub type SharedTracer = Rc<RefCell<dyn Tracer>>;
+
+// Machine state before an opcode runs
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+    pub pc: usize,
+    pub op: u8,
+    // gas left in the frame
+    pub gas: u64,
+    pub refund: i64,
+    // 1 for the frame of the transaction
+    pub depth: usize,
+    // bottom first
+    pub stack: Vec<H256>,
+    pub memory_size: usize,
+}
+
+pub trait Tracer {
+    fn step(&mut self, step: Step);
+
+    // Called once the opcode of the last `step` of the frame ran, or failed
+    // with `error`. Steps of sub-calls come in between for the CALL and
+    // CREATE families.
+    fn step_end(&mut self, gas: &Gas, error: Option<&VmError>);
+
+    // Called by the CALL and CREATE families once their cost, which includes
+    // the gas passed on, is charged and before the sub-frame runs
+    fn call_charged(&mut self, _gas: &Gas) {}
+
+    // Called after an executed transaction, not for invalid ones
+    fn tx_end(&mut self, _result: &TxResult) {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Context, Vm};
+
+    #[derive(Default)]
+    struct Recorder {
+        steps: Vec<(Step, u64)>,
+        pending: Vec<usize>,
+    }
+
+    impl Tracer for Recorder {
+        fn step(&mut self, step: Step) {
+            self.pending.push(self.steps.len());
+            self.steps.push((step, 0));
+        }
+
+        fn step_end(&mut self, gas: &Gas, _error: Option<&VmError>) {
+            let index = self.pending.pop().unwrap();
+            let step = &mut self.steps[index];
+            step.1 = step.0.gas - gas.remaining();
+        }
+    }
+
+    #[test]
+    fn records_steps_with_their_cost() {
+        // PUSH1 1, PUSH1 0, MSTORE, STOP
+        let code = [0x60, 0x01, 0x60, 0x00, 0x52, 0x00];
+        let recorder = Rc::new(RefCell::new(Recorder::default()));
+        let context = Context {
+            gas_limit: 100,
+            ..Default::default()
+        };
+        let tracer: SharedTracer = recorder.clone();
+        let mut vm = Vm::with_state(&code, Default::default(), context).with_tracer(Some(tracer));
+        assert!(vm.run().is_success());
+
+        let recorder = recorder.borrow();
+        let ops: Vec<_> = recorder.steps.iter().map(|(step, _)| step.op).collect();
+        assert_eq!(ops, [0x60, 0x60, 0x52, 0x00]);
+        let (mstore, cost) = &recorder.steps[2];
+        assert_eq!(mstore.gas, 94);
+        assert_eq!(mstore.stack, [H256::from_low_u64_be(1), H256::zero()]);
+        assert_eq!(mstore.depth, 1);
+        // 3 for MSTORE and 3 for a word of memory
+        assert_eq!(*cost, 6);
+        assert_eq!(recorder.steps[3].0.memory_size, 32);
+        assert!(recorder.pending.is_empty());
+    }
+}
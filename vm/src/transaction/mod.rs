@@ -0,0 +1,772 @@
+// Signed transactions of every type in their EIP-2718 envelope
+
+mod signature;
+
+pub use signature::{LocalSigner, Signature};
+
+use crate::crypto::keccak256;
+use crate::env::{AccessListItem, TxEnv};
+use crate::rlp::{self, Decodable, Encodable, Header, RlpError};
+use primitive_types::{H160, H256, U256};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TxError {
+    Rlp(RlpError),
+    UnsupportedType(u8),
+    InvalidSignature,
+}
+
+impl From<RlpError> for TxError {
+    fn from(e: RlpError) -> Self {
+        TxError::Rlp(e)
+    }
+}
+
+// Recipient of a transaction, encoded as an empty string for creations
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TxKind {
+    #[default]
+    Create,
+    Call(H160),
+}
+
+impl TxKind {
+    pub fn to(&self) -> Option<H160> {
+        match self {
+            TxKind::Create => None,
+            TxKind::Call(to) => Some(*to),
+        }
+    }
+}
+
+impl Encodable for TxKind {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        match self {
+            TxKind::Create => out.push(0x80),
+            TxKind::Call(to) => to.rlp_append(out),
+        }
+    }
+}
+
+impl Decodable for TxKind {
+    fn rlp_decode(buf: &mut &[u8]) -> Result<Self, RlpError> {
+        if buf.first() == Some(&0x80) {
+            *buf = &buf[1..];
+            return Ok(TxKind::Create);
+        }
+        H160::rlp_decode(buf).map(TxKind::Call)
+    }
+}
+
+// Type 0, with EIP-155 replay protection if `chain_id` is set
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LegacyTx {
+    pub chain_id: Option<u64>,
+    pub nonce: u64,
+    pub gas_price: U256,
+    pub gas_limit: u64,
+    pub to: TxKind,
+    pub value: U256,
+    pub data: Vec<u8>,
+}
+
+// Type 1 (EIP-2930)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AccessListTx {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub gas_price: U256,
+    pub gas_limit: u64,
+    pub to: TxKind,
+    pub value: U256,
+    pub data: Vec<u8>,
+    pub access_list: Vec<AccessListItem>,
+}
+
+// Type 2 (EIP-1559)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DynamicFeeTx {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub max_priority_fee_per_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub gas_limit: u64,
+    pub to: TxKind,
+    pub value: U256,
+    pub data: Vec<u8>,
+    pub access_list: Vec<AccessListItem>,
+}
+
+// Type 3 (EIP-4844), which cannot create contracts
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BlobTx {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub max_priority_fee_per_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub gas_limit: u64,
+    pub to: H160,
+    pub value: U256,
+    pub data: Vec<u8>,
+    pub access_list: Vec<AccessListItem>,
+    pub max_fee_per_blob_gas: U256,
+    pub blob_versioned_hashes: Vec<H256>,
+}
+
+// Type 4 (EIP-7702), which cannot create contracts
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SetCodeTx {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub max_priority_fee_per_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub gas_limit: u64,
+    pub to: H160,
+    pub value: U256,
+    pub data: Vec<u8>,
+    pub access_list: Vec<AccessListItem>,
+    pub authorization_list: Vec<SignedAuthorization>,
+}
+
+// Permission for `address`'s code to be used by the signer's account
+// (EIP-7702). A zero chain id is valid on every chain.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SignedAuthorization {
+    pub chain_id: U256,
+    pub address: H160,
+    pub nonce: u64,
+    // a u8 rather than a bool: tuples with a parity above 1 decode fine and
+    // are skipped when the transaction is applied
+    pub y_parity: u8,
+    pub r: U256,
+    pub s: U256,
+}
+
+impl Encodable for SignedAuthorization {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        let mut payload = Vec::new();
+        self.chain_id.rlp_append(&mut payload);
+        self.address.rlp_append(&mut payload);
+        self.nonce.rlp_append(&mut payload);
+        u16::from(self.y_parity).rlp_append(&mut payload);
+        self.r.rlp_append(&mut payload);
+        self.s.rlp_append(&mut payload);
+        out.extend_from_slice(&rlp::wrap_list(&payload));
+    }
+}
+
+impl Decodable for SignedAuthorization {
+    fn rlp_decode(buf: &mut &[u8]) -> Result<Self, RlpError> {
+        let mut payload = Header::decode_list(buf)?;
+        let authorization = Self {
+            chain_id: Decodable::rlp_decode(&mut payload)?,
+            address: Decodable::rlp_decode(&mut payload)?,
+            nonce: Decodable::rlp_decode(&mut payload)?,
+            y_parity: u16::rlp_decode(&mut payload)?
+                .try_into()
+                .map_err(|_| RlpError::Overflow)?,
+            r: Decodable::rlp_decode(&mut payload)?,
+            s: Decodable::rlp_decode(&mut payload)?,
+        };
+        if !payload.is_empty() {
+            return Err(RlpError::ListLengthMismatch);
+        }
+        Ok(authorization)
+    }
+}
+
+impl SignedAuthorization {
+    // keccak256(0x05 || rlp([chain_id, address, nonce]))
+    pub fn signature_hash(&self) -> H256 {
+        let mut payload = Vec::new();
+        self.chain_id.rlp_append(&mut payload);
+        self.address.rlp_append(&mut payload);
+        self.nonce.rlp_append(&mut payload);
+        let mut data = vec![0x05];
+        data.extend(rlp::wrap_list(&payload));
+        keccak256(&data)
+    }
+
+    // Signs the chain id, address and nonce of the authorization
+    pub fn sign(self, signer: &LocalSigner) -> Self {
+        let signature = signer.sign_hash(self.signature_hash());
+        Self {
+            y_parity: signature.y_parity as u8,
+            r: signature.r,
+            s: signature.s,
+            ..self
+        }
+    }
+
+    // Account that signed the authorization
+    pub fn authority(&self) -> Option<H160> {
+        if self.y_parity > 1 {
+            return None;
+        }
+        let signature = Signature {
+            y_parity: self.y_parity == 1,
+            r: self.r,
+            s: self.s,
+        };
+        signature.recover(self.signature_hash())
+    }
+}
+
+// Appends and reads the fields of a transaction type in RLP order
+macro_rules! tx_fields {
+    ($name:ident { $($field:ident),* $(,)? }) => {
+        impl $name {
+            fn encode_fields(&self, out: &mut Vec<u8>) {
+                $( self.$field.rlp_append(out); )*
+            }
+
+            fn decode_fields(buf: &mut &[u8]) -> Result<Self, RlpError> {
+                Ok(Self {
+                    $( $field: Decodable::rlp_decode(buf)?, )*
+                })
+            }
+        }
+    };
+}
+
+tx_fields!(AccessListTx {
+    chain_id,
+    nonce,
+    gas_price,
+    gas_limit,
+    to,
+    value,
+    data,
+    access_list,
+});
+
+tx_fields!(DynamicFeeTx {
+    chain_id,
+    nonce,
+    max_priority_fee_per_gas,
+    max_fee_per_gas,
+    gas_limit,
+    to,
+    value,
+    data,
+    access_list,
+});
+
+tx_fields!(BlobTx {
+    chain_id,
+    nonce,
+    max_priority_fee_per_gas,
+    max_fee_per_gas,
+    gas_limit,
+    to,
+    value,
+    data,
+    access_list,
+    max_fee_per_blob_gas,
+    blob_versioned_hashes,
+});
+
+tx_fields!(SetCodeTx {
+    chain_id,
+    nonce,
+    max_priority_fee_per_gas,
+    max_fee_per_gas,
+    gas_limit,
+    to,
+    value,
+    data,
+    access_list,
+    authorization_list,
+});
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Transaction {
+    Legacy(LegacyTx),
+    AccessList(AccessListTx),
+    DynamicFee(DynamicFeeTx),
+    Blob(BlobTx),
+    SetCode(SetCodeTx),
+}
+
+impl Transaction {
+    pub fn tx_type(&self) -> u8 {
+        match self {
+            Transaction::Legacy(_) => 0,
+            Transaction::AccessList(_) => 1,
+            Transaction::DynamicFee(_) => 2,
+            Transaction::Blob(_) => 3,
+            Transaction::SetCode(_) => 4,
+        }
+    }
+
+    pub fn chain_id(&self) -> Option<u64> {
+        match self {
+            Transaction::Legacy(tx) => tx.chain_id,
+            Transaction::AccessList(tx) => Some(tx.chain_id),
+            Transaction::DynamicFee(tx) => Some(tx.chain_id),
+            Transaction::Blob(tx) => Some(tx.chain_id),
+            Transaction::SetCode(tx) => Some(tx.chain_id),
+        }
+    }
+
+    pub fn nonce(&self) -> u64 {
+        match self {
+            Transaction::Legacy(tx) => tx.nonce,
+            Transaction::AccessList(tx) => tx.nonce,
+            Transaction::DynamicFee(tx) => tx.nonce,
+            Transaction::Blob(tx) => tx.nonce,
+            Transaction::SetCode(tx) => tx.nonce,
+        }
+    }
+
+    pub fn gas_limit(&self) -> u64 {
+        match self {
+            Transaction::Legacy(tx) => tx.gas_limit,
+            Transaction::AccessList(tx) => tx.gas_limit,
+            Transaction::DynamicFee(tx) => tx.gas_limit,
+            Transaction::Blob(tx) => tx.gas_limit,
+            Transaction::SetCode(tx) => tx.gas_limit,
+        }
+    }
+
+    // Hash signed by the sender
+    pub fn signature_hash(&self) -> H256 {
+        let mut payload = Vec::new();
+        match self {
+            Transaction::Legacy(tx) => {
+                tx.nonce.rlp_append(&mut payload);
+                tx.gas_price.rlp_append(&mut payload);
+                tx.gas_limit.rlp_append(&mut payload);
+                tx.to.rlp_append(&mut payload);
+                tx.value.rlp_append(&mut payload);
+                tx.data.rlp_append(&mut payload);
+                // EIP-155
+                if let Some(chain_id) = tx.chain_id {
+                    chain_id.rlp_append(&mut payload);
+                    0u64.rlp_append(&mut payload);
+                    0u64.rlp_append(&mut payload);
+                }
+                return keccak256(&rlp::wrap_list(&payload));
+            }
+            Transaction::AccessList(tx) => tx.encode_fields(&mut payload),
+            Transaction::DynamicFee(tx) => tx.encode_fields(&mut payload),
+            Transaction::Blob(tx) => tx.encode_fields(&mut payload),
+            Transaction::SetCode(tx) => tx.encode_fields(&mut payload),
+        }
+        let mut data = vec![self.tx_type()];
+        data.extend(rlp::wrap_list(&payload));
+        keccak256(&data)
+    }
+
+    pub fn sign(self, signer: &LocalSigner) -> SignedTransaction {
+        let signature = signer.sign_hash(self.signature_hash());
+        SignedTransaction {
+            tx: self,
+            signature,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignedTransaction {
+    pub tx: Transaction,
+    pub signature: Signature,
+}
+
+impl SignedTransaction {
+    // Hash of the enveloped encoding
+    pub fn hash(&self) -> H256 {
+        keccak256(&self.encode_enveloped())
+    }
+
+    pub fn recover_sender(&self) -> Result<H160, TxError> {
+        self.signature
+            .recover(self.tx.signature_hash())
+            .ok_or(TxError::InvalidSignature)
+    }
+
+    // `rlp(tx)` for legacy transactions and `type || rlp(tx)` for typed
+    // ones, as in `eth_sendRawTransaction`
+    pub fn encode_enveloped(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        let signature = &self.signature;
+        match &self.tx {
+            Transaction::Legacy(tx) => {
+                tx.nonce.rlp_append(&mut payload);
+                tx.gas_price.rlp_append(&mut payload);
+                tx.gas_limit.rlp_append(&mut payload);
+                tx.to.rlp_append(&mut payload);
+                tx.value.rlp_append(&mut payload);
+                tx.data.rlp_append(&mut payload);
+                let v = match tx.chain_id {
+                    Some(chain_id) => U256::from(chain_id) * 2 + 35,
+                    None => U256::from(27),
+                } + signature.y_parity as u8;
+                v.rlp_append(&mut payload);
+                signature.r.rlp_append(&mut payload);
+                signature.s.rlp_append(&mut payload);
+                return rlp::wrap_list(&payload);
+            }
+            Transaction::AccessList(tx) => tx.encode_fields(&mut payload),
+            Transaction::DynamicFee(tx) => tx.encode_fields(&mut payload),
+            Transaction::Blob(tx) => tx.encode_fields(&mut payload),
+            Transaction::SetCode(tx) => tx.encode_fields(&mut payload),
+        }
+        signature.y_parity.rlp_append(&mut payload);
+        signature.r.rlp_append(&mut payload);
+        signature.s.rlp_append(&mut payload);
+
+        let mut out = vec![self.tx.tx_type()];
+        out.extend(rlp::wrap_list(&payload));
+        out
+    }
+
+    pub fn decode_enveloped(data: &[u8]) -> Result<Self, TxError> {
+        let (&first, rest) = data.split_first().ok_or(RlpError::InputTooShort)?;
+        if first >= 0xc0 {
+            return Ok(rlp::decode::<LegacyEnvelope>(data)?.0);
+        }
+
+        let mut buf = rest;
+        let mut payload = Header::decode_list(&mut buf)?;
+        if !buf.is_empty() {
+            return Err(RlpError::TrailingBytes.into());
+        }
+        let tx = match first {
+            1 => Transaction::AccessList(AccessListTx::decode_fields(&mut payload)?),
+            2 => Transaction::DynamicFee(DynamicFeeTx::decode_fields(&mut payload)?),
+            3 => Transaction::Blob(BlobTx::decode_fields(&mut payload)?),
+            4 => Transaction::SetCode(SetCodeTx::decode_fields(&mut payload)?),
+            ty => return Err(TxError::UnsupportedType(ty)),
+        };
+        let signature = Signature {
+            y_parity: bool::rlp_decode(&mut payload)?,
+            r: U256::rlp_decode(&mut payload)?,
+            s: U256::rlp_decode(&mut payload)?,
+        };
+        if !payload.is_empty() {
+            return Err(RlpError::ListLengthMismatch.into());
+        }
+        Ok(SignedTransaction { tx, signature })
+    }
+
+    // Execution environment of the transaction sent by `caller`
+    pub fn tx_env(&self, caller: H160) -> TxEnv {
+        let mut env = TxEnv {
+            caller,
+            gas_limit: self.tx.gas_limit(),
+            nonce: Some(self.tx.nonce()),
+            chain_id: self.tx.chain_id(),
+            ..Default::default()
+        };
+        match &self.tx {
+            Transaction::Legacy(tx) => {
+                env.gas_price = tx.gas_price;
+                env.to = tx.to.to();
+                env.value = tx.value;
+                env.data = tx.data.clone();
+            }
+            Transaction::AccessList(tx) => {
+                env.gas_price = tx.gas_price;
+                env.to = tx.to.to();
+                env.value = tx.value;
+                env.data = tx.data.clone();
+                env.access_list = tx.access_list.clone();
+            }
+            Transaction::DynamicFee(tx) => {
+                env.gas_price = tx.max_fee_per_gas;
+                env.gas_priority_fee = Some(tx.max_priority_fee_per_gas);
+                env.to = tx.to.to();
+                env.value = tx.value;
+                env.data = tx.data.clone();
+                env.access_list = tx.access_list.clone();
+            }
+            Transaction::Blob(tx) => {
+                env.gas_price = tx.max_fee_per_gas;
+                env.gas_priority_fee = Some(tx.max_priority_fee_per_gas);
+                env.to = Some(tx.to);
+                env.value = tx.value;
+                env.data = tx.data.clone();
+                env.access_list = tx.access_list.clone();
+                env.blob_hashes = tx.blob_versioned_hashes.clone();
+                env.max_fee_per_blob_gas = Some(tx.max_fee_per_blob_gas);
+            }
+            Transaction::SetCode(tx) => {
+                env.gas_price = tx.max_fee_per_gas;
+                env.gas_priority_fee = Some(tx.max_priority_fee_per_gas);
+                env.to = Some(tx.to);
+                env.value = tx.value;
+                env.data = tx.data.clone();
+                env.access_list = tx.access_list.clone();
+                env.authorization_list = Some(tx.authorization_list.clone());
+            }
+        }
+        env
+    }
+}
+
+// In block bodies legacy transactions are lists and typed transactions are
+// byte strings holding their envelope
+impl Encodable for SignedTransaction {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        let enveloped = self.encode_enveloped();
+        match self.tx {
+            Transaction::Legacy(_) => out.extend(enveloped),
+            _ => enveloped.rlp_append(out),
+        }
+    }
+}
+
+impl Decodable for SignedTransaction {
+    fn rlp_decode(buf: &mut &[u8]) -> Result<Self, RlpError> {
+        let start = *buf;
+        let header = Header::decode(buf)?;
+        let (envelope, rest) = if header.list {
+            let len = start.len() - buf.len() + header.payload_length;
+            (&start[..len], &start[len..])
+        } else {
+            buf.split_at(header.payload_length)
+        };
+        *buf = rest;
+        SignedTransaction::decode_enveloped(envelope).map_err(|e| match e {
+            TxError::Rlp(e) => e,
+            _ => RlpError::Custom("unsupported transaction type"),
+        })
+    }
+}
+
+// Legacy transaction with the chain id folded into `v`
+struct LegacyEnvelope(SignedTransaction);
+
+impl Decodable for LegacyEnvelope {
+    fn rlp_decode(buf: &mut &[u8]) -> Result<Self, RlpError> {
+        let mut payload = Header::decode_list(buf)?;
+        let mut tx = LegacyTx {
+            nonce: Decodable::rlp_decode(&mut payload)?,
+            gas_price: Decodable::rlp_decode(&mut payload)?,
+            gas_limit: Decodable::rlp_decode(&mut payload)?,
+            to: Decodable::rlp_decode(&mut payload)?,
+            value: Decodable::rlp_decode(&mut payload)?,
+            data: Decodable::rlp_decode(&mut payload)?,
+            chain_id: None,
+        };
+        // EIP-155 v is chain_id * 2 + 35 + y_parity, which outgrows a u64
+        // for large chain ids
+        let v = U256::rlp_decode(&mut payload)?;
+        let y_parity = if v == U256::from(27) || v == U256::from(28) {
+            v == U256::from(28)
+        } else if v >= U256::from(35) {
+            let chain_id = (v - 35) / 2;
+            if chain_id > U256::from(u64::MAX) {
+                return Err(RlpError::Custom("chain id in v does not fit in 64 bits"));
+            }
+            tx.chain_id = Some(chain_id.as_u64());
+            (v - 35).bit(0)
+        } else {
+            return Err(RlpError::Custom("invalid v"));
+        };
+        let signature = Signature {
+            y_parity,
+            r: Decodable::rlp_decode(&mut payload)?,
+            s: Decodable::rlp_decode(&mut payload)?,
+        };
+        if !payload.is_empty() {
+            return Err(RlpError::ListLengthMismatch);
+        }
+        Ok(LegacyEnvelope(SignedTransaction {
+            tx: Transaction::Legacy(tx),
+            signature,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn signer() -> LocalSigner {
+        LocalSigner::from_bytes(&[0x46; 32]).unwrap()
+    }
+
+    #[test]
+    fn eip155_example() {
+        let tx = Transaction::Legacy(LegacyTx {
+            chain_id: Some(1),
+            nonce: 9,
+            gas_price: U256::from(20_000_000_000u64),
+            gas_limit: 21000,
+            to: TxKind::Call(H160::repeat_byte(0x35)),
+            value: U256::exp10(18),
+            data: vec![],
+        });
+        assert_eq!(
+            hex::encode(tx.signature_hash()),
+            "daf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53"
+        );
+
+        let signed = tx.sign(&signer());
+        let raw = signed.encode_enveloped();
+        assert_eq!(
+            hex::encode(&raw),
+            "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a7640000\
+             8025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f7\
+             61aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"
+        );
+        assert_eq!(SignedTransaction::decode_enveloped(&raw).unwrap(), signed);
+        assert_eq!(
+            signed.recover_sender().unwrap(),
+            "9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn typed_transactions_roundtrip() {
+        let access_list = vec![AccessListItem {
+            address: H160::repeat_byte(1),
+            storage_keys: vec![H256::repeat_byte(2)],
+        }];
+        let txs = [
+            Transaction::AccessList(AccessListTx {
+                chain_id: 1,
+                gas_limit: 30000,
+                to: TxKind::Create,
+                data: vec![0x60, 0x00],
+                access_list: access_list.clone(),
+                ..Default::default()
+            }),
+            Transaction::DynamicFee(DynamicFeeTx {
+                chain_id: 1,
+                nonce: 3,
+                max_priority_fee_per_gas: U256::from(2),
+                max_fee_per_gas: U256::from(100),
+                to: TxKind::Call(H160::repeat_byte(3)),
+                value: U256::from(5),
+                access_list,
+                ..Default::default()
+            }),
+            Transaction::Blob(BlobTx {
+                chain_id: 1,
+                max_fee_per_blob_gas: U256::from(1),
+                blob_versioned_hashes: vec![H256::repeat_byte(1)],
+                ..Default::default()
+            }),
+            Transaction::SetCode(SetCodeTx {
+                chain_id: 1,
+                authorization_list: vec![SignedAuthorization {
+                    address: H160::repeat_byte(4),
+                    nonce: 1,
+                    y_parity: 1,
+                    r: U256::one(),
+                    s: U256::one(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+        ];
+
+        for tx in txs {
+            let signed = tx.sign(&signer());
+            assert_eq!(signed.recover_sender(), Ok(signer().address()));
+
+            let raw = signed.encode_enveloped();
+            assert_eq!(raw[0], signed.tx.tx_type());
+            assert_eq!(
+                SignedTransaction::decode_enveloped(&raw),
+                Ok(signed.clone())
+            );
+
+            // block body form wraps typed transactions in a string
+            let body = rlp::encode(&vec![signed.clone()]);
+            assert_eq!(
+                rlp::decode::<Vec<SignedTransaction>>(&body),
+                Ok(vec![signed])
+            );
+        }
+    }
+
+    #[test]
+    fn decodes_v_of_large_chain_ids() {
+        let tx = Transaction::Legacy(LegacyTx {
+            chain_id: Some(u64::MAX),
+            nonce: 0,
+            gas_price: U256::one(),
+            gas_limit: 21000,
+            to: TxKind::Call(H160::repeat_byte(0x35)),
+            value: U256::zero(),
+            data: vec![],
+        });
+        let signed = tx.sign(&signer());
+        let decoded = SignedTransaction::decode_enveloped(&signed.encode_enveloped()).unwrap();
+        assert_eq!(decoded, signed);
+
+        // one past the largest chain id
+        let mut payload = Vec::new();
+        for field in [U256::zero(), U256::one(), U256::from(21000)] {
+            field.rlp_append(&mut payload);
+        }
+        H160::repeat_byte(0x35).rlp_append(&mut payload);
+        U256::zero().rlp_append(&mut payload);
+        Vec::<u8>::new().rlp_append(&mut payload);
+        let v: U256 = U256::from(u64::MAX) * 2 + 37;
+        v.rlp_append(&mut payload);
+        U256::one().rlp_append(&mut payload);
+        U256::one().rlp_append(&mut payload);
+        assert!(SignedTransaction::decode_enveloped(&rlp::wrap_list(&payload)).is_err());
+    }
+
+    #[test]
+    fn rejects_high_s() {
+        let mut signed = Transaction::Legacy(LegacyTx::default()).sign(&signer());
+        // the same signature with s' = n - s and the parity flipped
+        let n = U256::from_big_endian(&[
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            0xff, 0xfe, 0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c,
+            0xd0, 0x36, 0x41, 0x41,
+        ]);
+        signed.signature.s = n - signed.signature.s;
+        signed.signature.y_parity = !signed.signature.y_parity;
+        assert_eq!(signed.recover_sender(), Err(TxError::InvalidSignature));
+    }
+
+    #[test]
+    fn replay_protection_uses_configured_chain() {
+        use crate::env::Env;
+        use crate::error::{InvalidTransaction, VmError};
+        use crate::executor::transact;
+        use crate::state::{Account, State};
+
+        let signer = signer();
+        let signed = Transaction::Legacy(LegacyTx {
+            chain_id: Some(5),
+            gas_limit: 21000,
+            to: TxKind::Call(H160::repeat_byte(1)),
+            ..Default::default()
+        })
+        .sign(&signer);
+
+        let mut state = State::new();
+        state.insert_account(
+            signer.address(),
+            Account {
+                balance: U256::exp10(18),
+                ..Default::default()
+            },
+        );
+        let mut env = Env {
+            tx: signed.tx_env(signed.recover_sender().unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(
+            transact(&mut state, &env),
+            Err(VmError::InvalidTransaction(
+                InvalidTransaction::ChainIdMismatch {
+                    expected: 1,
+                    got: 5
+                }
+            ))
+        );
+
+        env.cfg.chain_id = 5;
+        assert!(transact(&mut state, &env).unwrap().success);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::crypto::keccak256;
+use k256::ecdsa::{self, RecoveryId, SigningKey, VerifyingKey};
+use primitive_types::{H160, H256, U256};
+
+// secp256k1n / 2, the largest `s` allowed since Homestead (EIP-2)
+const SECP256K1N_HALF: U256 = U256([
+    0xdfe92f46681b20a0,
+    0x5d576e7357a4501d,
+    0xffffffffffffffff,
+    0x7fffffffffffffff,
+]);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Signature {
+    pub y_parity: bool,
+    pub r: U256,
+    pub s: U256,
+}
+
+impl Signature {
+    // Address of the key that signed `hash`, or `None` if the signature is
+    // invalid or not in canonical low-s form
+    pub fn recover(&self, hash: H256) -> Option<H160> {
+        if self.s > SECP256K1N_HALF {
+            return None;
+        }
+        self.recover_any_s(hash)
+    }
+
+    // Like `recover`, also accepting high-s signatures as the ECRECOVER
+    // precompile does
+    pub fn recover_any_s(&self, hash: H256) -> Option<H160> {
+        let mut signature =
+            ecdsa::Signature::from_scalars(to_bytes(self.r), to_bytes(self.s)).ok()?;
+        let mut y_parity = self.y_parity;
+        // k256 only recovers low-s signatures, and (r, n - s) with the other
+        // parity recovers the same key
+        if let Some(low) = signature.normalize_s() {
+            signature = low;
+            y_parity = !y_parity;
+        }
+        let recovery_id = RecoveryId::from_byte(y_parity as u8)?;
+        let key =
+            VerifyingKey::recover_from_prehash(hash.as_bytes(), &signature, recovery_id).ok()?;
+        Some(public_key_address(&key))
+    }
+}
+
+// Private key held in memory
+#[derive(Clone)]
+pub struct LocalSigner {
+    key: SigningKey,
+}
+
+impl LocalSigner {
+    pub fn from_bytes(bytes: &[u8; 32]) -> Option<Self> {
+        SigningKey::from_slice(bytes).ok().map(|key| Self { key })
+    }
+
+    pub fn address(&self) -> H160 {
+        public_key_address(self.key.verifying_key())
+    }
+
+    pub fn sign_hash(&self, hash: H256) -> Signature {
+        // signing a 32 byte prehash with a valid key cannot fail
+        let (signature, recovery_id) = self
+            .key
+            .sign_prehash_recoverable(hash.as_bytes())
+            .expect("prehash is 32 bytes");
+        let (r, s) = signature.split_bytes();
+        Signature {
+            y_parity: recovery_id.is_y_odd(),
+            r: U256::from_big_endian(&r),
+            s: U256::from_big_endian(&s),
+        }
+    }
+}
+
+// Last 20 bytes of the hash of the uncompressed public key
+fn public_key_address(key: &VerifyingKey) -> H160 {
+    let point = key.to_encoded_point(false);
+    H160::from_slice(&keccak256(&point.as_bytes()[1..])[12..])
+}
+
+fn to_bytes(value: U256) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    bytes
+}
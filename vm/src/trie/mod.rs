@@ -0,0 +1,444 @@
+mod proof;
+mod state;
+
+pub use proof::{verify_proof, AccountProof, ProofError, StorageProof};
+pub use state::StateTrie;
+
+use crate::crypto::keccak256;
+use crate::rlp::{self, Encodable};
+use primitive_types::H256;
+use std::mem;
+
+// Root hash of a trie without any entries, i.e. keccak256(rlp(""))
+pub const EMPTY_ROOT_HASH: H256 = H256([
+    0x56, 0xe8, 0x1f, 0x17, 0x1b, 0xcc, 0x55, 0xa6, 0xff, 0x83, 0x45, 0xe6, 0x92, 0xc0, 0xf8, 0x6e,
+    0x5b, 0x48, 0xe0, 0x1b, 0x99, 0x6c, 0xad, 0xc0, 0x01, 0x62, 0x2f, 0xb5, 0xe3, 0x63, 0xb4, 0x21,
+]);
+
+#[derive(Clone, Debug, Default)]
+enum NodeKind {
+    #[default]
+    Empty,
+    Leaf {
+        path: Vec<u8>,
+        value: Vec<u8>,
+    },
+    Extension {
+        path: Vec<u8>,
+        child: Box<Node>,
+    },
+    Branch {
+        children: Box<[Node; 16]>,
+        value: Option<Vec<u8>>,
+    },
+}
+
+#[derive(Clone, Debug, Default)]
+struct Node {
+    kind: NodeKind,
+    // how the node is referenced from its parent: the encoded node if it is
+    // shorter than 32 bytes, its hash otherwise. Cleared whenever the node or
+    // anything below it changes, so unchanged subtries are not rehashed.
+    cache: Option<Vec<u8>>,
+}
+
+// Merkle Patricia Trie kept in memory. Keys are used as they are; hash them
+// beforehand for a secure trie.
+#[derive(Clone, Debug, Default)]
+pub struct Trie {
+    root: Node,
+}
+
+impl Trie {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        matches!(self.root.kind, NodeKind::Empty)
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        let nibbles = to_nibbles(key);
+        let mut path = &nibbles[..];
+        let mut node = &self.root;
+        loop {
+            match &node.kind {
+                NodeKind::Empty => return None,
+                NodeKind::Leaf { path: p, value } => {
+                    return if p == path { Some(value) } else { None };
+                }
+                NodeKind::Extension { path: p, child } => {
+                    if !path.starts_with(p) {
+                        return None;
+                    }
+                    path = &path[p.len()..];
+                    node = child;
+                }
+                NodeKind::Branch { children, value } => {
+                    if path.is_empty() {
+                        return value.as_deref();
+                    }
+                    node = &children[path[0] as usize];
+                    path = &path[1..];
+                }
+            }
+        }
+    }
+
+    // Inserting an empty value removes the key
+    pub fn insert(&mut self, key: &[u8], value: Vec<u8>) {
+        if value.is_empty() {
+            self.remove(key);
+        } else {
+            insert(&mut self.root, &to_nibbles(key), value);
+        }
+    }
+
+    // Returns whether the key was present
+    pub fn remove(&mut self, key: &[u8]) -> bool {
+        remove(&mut self.root, &to_nibbles(key))
+    }
+
+    pub fn root_hash(&mut self) -> H256 {
+        let reference = self.root.reference();
+        if reference.len() == 33 {
+            H256::from_slice(&reference[1..])
+        } else {
+            keccak256(&reference)
+        }
+    }
+}
+
+// Root of a trie keyed by the RLP encoded index of each item, as used for
+// the transactions, receipts and withdrawals roots
+pub fn ordered_trie_root<I, T>(items: I) -> H256
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<[u8]>,
+{
+    let mut trie = Trie::new();
+    for (i, item) in items.into_iter().enumerate() {
+        trie.insert(&rlp::encode(&i), item.as_ref().to_vec());
+    }
+    trie.root_hash()
+}
+
+impl Node {
+    fn new(kind: NodeKind) -> Self {
+        Self { kind, cache: None }
+    }
+
+    fn is_empty(&self) -> bool {
+        matches!(self.kind, NodeKind::Empty)
+    }
+
+    fn reference(&mut self) -> Vec<u8> {
+        if let Some(reference) = &self.cache {
+            return reference.clone();
+        }
+        let encoded = self.encode();
+        let reference = if encoded.len() < 32 {
+            encoded
+        } else {
+            rlp::encode(&keccak256(&encoded))
+        };
+        self.cache = Some(reference.clone());
+        reference
+    }
+
+    fn encode(&mut self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        match &mut self.kind {
+            NodeKind::Empty => return rlp::encode(&Vec::<u8>::new()),
+            NodeKind::Leaf { path, value } => {
+                hex_prefix(path, true).rlp_append(&mut payload);
+                value.rlp_append(&mut payload);
+            }
+            NodeKind::Extension { path, child } => {
+                hex_prefix(path, false).rlp_append(&mut payload);
+                payload.extend(child.reference());
+            }
+            NodeKind::Branch { children, value } => {
+                for child in children.iter_mut() {
+                    payload.extend(child.reference());
+                }
+                value
+                    .as_deref()
+                    .unwrap_or_default()
+                    .rlp_append(&mut payload);
+            }
+        }
+        rlp::wrap_list(&payload)
+    }
+}
+
+fn insert(node: &mut Node, path: &[u8], value: Vec<u8>) {
+    node.cache = None;
+    node.kind = match mem::take(&mut node.kind) {
+        NodeKind::Empty => NodeKind::Leaf {
+            path: path.to_vec(),
+            value,
+        },
+        NodeKind::Leaf {
+            path: leaf_path,
+            value: leaf_value,
+        } => {
+            if leaf_path == path {
+                NodeKind::Leaf {
+                    path: leaf_path,
+                    value,
+                }
+            } else {
+                let n = common_prefix(&leaf_path, path);
+                let mut children: Box<[Node; 16]> = Default::default();
+                let mut branch_value = None;
+                for (p, v) in [(&leaf_path[n..], leaf_value), (&path[n..], value)] {
+                    match p.split_first() {
+                        None => branch_value = Some(v),
+                        Some((&i, rest)) => {
+                            children[i as usize] = Node::new(NodeKind::Leaf {
+                                path: rest.to_vec(),
+                                value: v,
+                            })
+                        }
+                    }
+                }
+                with_extension(
+                    &path[..n],
+                    NodeKind::Branch {
+                        children,
+                        value: branch_value,
+                    },
+                )
+            }
+        }
+        NodeKind::Extension {
+            path: ext_path,
+            mut child,
+        } => {
+            let n = common_prefix(&ext_path, path);
+            if n == ext_path.len() {
+                insert(&mut child, &path[n..], value);
+                NodeKind::Extension {
+                    path: ext_path,
+                    child,
+                }
+            } else {
+                // split the extension at the first differing nibble
+                let mut children: Box<[Node; 16]> = Default::default();
+                let rest = &ext_path[(n + 1)..];
+                children[ext_path[n] as usize] = if rest.is_empty() {
+                    *child
+                } else {
+                    Node::new(NodeKind::Extension {
+                        path: rest.to_vec(),
+                        child,
+                    })
+                };
+                let mut branch = Node::new(NodeKind::Branch {
+                    children,
+                    value: None,
+                });
+                insert(&mut branch, &path[n..], value);
+                with_extension(&path[..n], branch.kind)
+            }
+        }
+        NodeKind::Branch {
+            mut children,
+            value: branch_value,
+        } => match path.split_first() {
+            None => NodeKind::Branch {
+                children,
+                value: Some(value),
+            },
+            Some((&i, rest)) => {
+                insert(&mut children[i as usize], rest, value);
+                NodeKind::Branch {
+                    children,
+                    value: branch_value,
+                }
+            }
+        },
+    };
+}
+
+fn remove(node: &mut Node, path: &[u8]) -> bool {
+    let removed = match &mut node.kind {
+        NodeKind::Empty => false,
+        NodeKind::Leaf {
+            path: leaf_path, ..
+        } => {
+            let found = leaf_path == path;
+            if found {
+                node.kind = NodeKind::Empty;
+            }
+            found
+        }
+        NodeKind::Extension {
+            path: ext_path,
+            child,
+        } => path.starts_with(ext_path) && remove(child, &path[ext_path.len()..]),
+        NodeKind::Branch { children, value } => match path.split_first() {
+            None => value.take().is_some(),
+            Some((&i, rest)) => remove(&mut children[i as usize], rest),
+        },
+    };
+
+    if removed {
+        node.cache = None;
+        normalize(node);
+    }
+    removed
+}
+
+// Restores the canonical shape of a node after a removal below it
+fn normalize(node: &mut Node) {
+    node.kind = match mem::take(&mut node.kind) {
+        NodeKind::Extension { path, child } => {
+            let Node { kind, cache } = *child;
+            match kind {
+                NodeKind::Empty => NodeKind::Empty,
+                NodeKind::Leaf { path: p, value } => NodeKind::Leaf {
+                    path: [path, p].concat(),
+                    value,
+                },
+                NodeKind::Extension { path: p, child } => NodeKind::Extension {
+                    path: [path, p].concat(),
+                    child,
+                },
+                kind => NodeKind::Extension {
+                    path,
+                    child: Box::new(Node { kind, cache }),
+                },
+            }
+        }
+        NodeKind::Branch {
+            mut children,
+            value,
+        } => {
+            let used: Vec<usize> = (0..16).filter(|&i| !children[i].is_empty()).collect();
+            match (used.len(), value) {
+                (0, None) => NodeKind::Empty,
+                (0, Some(value)) => NodeKind::Leaf {
+                    path: Vec::new(),
+                    value,
+                },
+                (1, None) => {
+                    let i = used[0] as u8;
+                    let Node { kind, cache } = mem::take(&mut children[used[0]]);
+                    match kind {
+                        NodeKind::Leaf { path, value } => NodeKind::Leaf {
+                            path: [vec![i], path].concat(),
+                            value,
+                        },
+                        NodeKind::Extension { path, child } => NodeKind::Extension {
+                            path: [vec![i], path].concat(),
+                            child,
+                        },
+                        kind => NodeKind::Extension {
+                            path: vec![i],
+                            child: Box::new(Node { kind, cache }),
+                        },
+                    }
+                }
+                (_, value) => NodeKind::Branch { children, value },
+            }
+        }
+        kind => kind,
+    };
+}
+
+fn with_extension(path: &[u8], kind: NodeKind) -> NodeKind {
+    if path.is_empty() {
+        kind
+    } else {
+        NodeKind::Extension {
+            path: path.to_vec(),
+            child: Box::new(Node::new(kind)),
+        }
+    }
+}
+
+fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+fn to_nibbles(key: &[u8]) -> Vec<u8> {
+    key.iter().flat_map(|b| [b >> 4, b & 0x0f]).collect()
+}
+
+// Compact (hex-prefix) encoding of a nibble path
+fn hex_prefix(nibbles: &[u8], leaf: bool) -> Vec<u8> {
+    let flag = if leaf { 0x20 } else { 0x00 };
+    let mut out = Vec::with_capacity(nibbles.len() / 2 + 1);
+    let rest = if nibbles.len() % 2 == 1 {
+        out.push(flag | 0x10 | nibbles[0]);
+        &nibbles[1..]
+    } else {
+        out.push(flag);
+        nibbles
+    };
+    for pair in rest.chunks(2) {
+        out.push((pair[0] << 4) | pair[1]);
+    }
+    out
+}
+
+// Inverse of `hex_prefix`, returning the nibbles and whether they belong to
+// a leaf
+fn hex_prefix_decode(bytes: &[u8]) -> Option<(Vec<u8>, bool)> {
+    let (&first, rest) = bytes.split_first()?;
+    let flag = first >> 4;
+    if flag > 3 {
+        return None;
+    }
+    let mut nibbles = Vec::with_capacity(rest.len() * 2 + 1);
+    if flag & 1 == 1 {
+        nibbles.push(first & 0x0f);
+    }
+    nibbles.extend(to_nibbles(rest));
+    Some((nibbles, flag & 2 == 2))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn empty_root() {
+        assert_eq!(Trie::new().root_hash(), EMPTY_ROOT_HASH);
+        assert_eq!(keccak256(&[0x80]), EMPTY_ROOT_HASH);
+    }
+
+    #[test]
+    fn known_root() {
+        let mut trie = Trie::new();
+        trie.insert(b"doe", b"reindeer".to_vec());
+        trie.insert(b"dog", b"puppy".to_vec());
+        trie.insert(b"dogglesworth", b"cat".to_vec());
+        assert_eq!(
+            hex::encode(trie.root_hash()),
+            "8aad789dff2f538bca5d8ea56e8abe10f4c7ba3a5dea95fea4cd6e7c3a1168d3"
+        );
+        assert_eq!(trie.get(b"dog"), Some(&b"puppy"[..]));
+        assert_eq!(trie.get(b"do"), None);
+    }
+
+    #[test]
+    fn remove_restores_root() {
+        let mut trie = Trie::new();
+        trie.insert(b"doe", b"reindeer".to_vec());
+        trie.insert(b"dog", b"puppy".to_vec());
+        let root = trie.root_hash();
+
+        trie.insert(b"dogglesworth", b"cat".to_vec());
+        trie.insert(b"horse", b"stallion".to_vec());
+        assert_ne!(trie.root_hash(), root);
+
+        assert!(trie.remove(b"dogglesworth"));
+        assert!(trie.remove(b"horse"));
+        assert!(!trie.remove(b"horse"));
+        assert_eq!(trie.root_hash(), root);
+    }
+}
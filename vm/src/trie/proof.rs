@@ -0,0 +1,309 @@
+use super::state::{decode_account, encode_account};
+use super::{hex_prefix_decode, to_nibbles, NodeKind, StateTrie, Trie, EMPTY_ROOT_HASH};
+use crate::crypto::{keccak256, KECCAK_EMPTY};
+use crate::db::AccountInfo;
+use crate::rlp::{self, Item};
+use primitive_types::{H160, H256, U256};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProofError {
+    // the proof ends before reaching the key
+    MissingNode,
+    // a node does not hash to the reference in its parent
+    HashMismatch,
+    InvalidNode,
+    // the proven value differs from the claimed one
+    ValueMismatch,
+}
+
+// Proof for an account and some of its storage slots, in the shape returned
+// by `eth_getProof`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountProof {
+    pub address: H160,
+    #[serde(with = "crate::serde_hex::bytes_list")]
+    pub account_proof: Vec<Vec<u8>>,
+    pub balance: U256,
+    pub code_hash: H256,
+    #[serde(with = "crate::serde_hex::u64")]
+    pub nonce: u64,
+    pub storage_hash: H256,
+    pub storage_proof: Vec<StorageProof>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StorageProof {
+    pub key: H256,
+    pub value: U256,
+    #[serde(with = "crate::serde_hex::bytes_list")]
+    pub proof: Vec<Vec<u8>>,
+}
+
+impl Trie {
+    // Encoded nodes on the path from the root to `key`. Nodes embedded in
+    // their parent are not listed separately.
+    pub fn proof(&mut self, key: &[u8]) -> Vec<Vec<u8>> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+
+        let nibbles = to_nibbles(key);
+        let mut path = &nibbles[..];
+        let mut node = &mut self.root;
+        let mut proof = vec![node.encode()];
+        loop {
+            let next = match &mut node.kind {
+                NodeKind::Extension { path: p, child } if path.starts_with(p) => {
+                    path = &path[p.len()..];
+                    child
+                }
+                NodeKind::Branch { children, .. } if !path.is_empty() => {
+                    let child = &mut children[path[0] as usize];
+                    path = &path[1..];
+                    child
+                }
+                _ => return proof,
+            };
+            if next.reference().len() == 33 {
+                proof.push(next.encode());
+            }
+            node = next;
+        }
+    }
+}
+
+impl StateTrie {
+    pub fn account_proof(&mut self, address: H160, slots: &[H256]) -> AccountProof {
+        let key = keccak256(address.as_bytes());
+        let account_proof = self.accounts.proof(key.as_bytes());
+        let (info, storage_hash) = self
+            .accounts
+            .get(key.as_bytes())
+            .and_then(decode_account)
+            .unwrap_or((AccountInfo::default(), EMPTY_ROOT_HASH));
+
+        let storage_trie = self.storage.entry(address).or_default();
+        let storage_proof = slots
+            .iter()
+            .map(|slot| {
+                let key = keccak256(slot.as_bytes());
+                let value = storage_trie
+                    .get(key.as_bytes())
+                    .and_then(decode_slot)
+                    .unwrap_or_default();
+                StorageProof {
+                    key: *slot,
+                    value,
+                    proof: storage_trie.proof(key.as_bytes()),
+                }
+            })
+            .collect();
+
+        AccountProof {
+            address,
+            account_proof,
+            balance: info.balance,
+            code_hash: info.code_hash,
+            nonce: info.nonce,
+            storage_hash,
+            storage_proof,
+        }
+    }
+}
+
+impl AccountProof {
+    // Checks the account against `state_root` and every slot against the
+    // account's storage hash
+    pub fn verify(&self, state_root: H256) -> Result<(), ProofError> {
+        let key = keccak256(self.address.as_bytes());
+        let value = verify_proof(state_root, key.as_bytes(), &self.account_proof)?;
+
+        let is_empty = self.nonce == 0
+            && self.balance.is_zero()
+            && self.code_hash == KECCAK_EMPTY
+            && self.storage_hash == EMPTY_ROOT_HASH;
+        let expected = if is_empty && value.is_none() {
+            None
+        } else {
+            let info = AccountInfo {
+                balance: self.balance,
+                nonce: self.nonce,
+                code_hash: self.code_hash,
+                code: None,
+            };
+            Some(encode_account(&info, self.storage_hash))
+        };
+        if value != expected {
+            return Err(ProofError::ValueMismatch);
+        }
+
+        for slot in &self.storage_proof {
+            let key = keccak256(slot.key.as_bytes());
+            let value = verify_proof(self.storage_hash, key.as_bytes(), &slot.proof)?;
+            if value.and_then(|v| decode_slot(&v)).unwrap_or_default() != slot.value {
+                return Err(ProofError::ValueMismatch);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Walks `proof` from `root` along `key` and returns the value stored under
+// it, or `None` if the proof shows the key is absent
+pub fn verify_proof(
+    root: H256,
+    key: &[u8],
+    proof: &[Vec<u8>],
+) -> Result<Option<Vec<u8>>, ProofError> {
+    if root == EMPTY_ROOT_HASH && proof.is_empty() {
+        return Ok(None);
+    }
+
+    let nibbles = to_nibbles(key);
+    let mut path = &nibbles[..];
+    let mut nodes = proof.iter();
+    let mut expected = Reference::Hash(root);
+    loop {
+        let inline;
+        let node: &[u8] = match expected {
+            Reference::Hash(hash) => {
+                let node = nodes.next().ok_or(ProofError::MissingNode)?;
+                if keccak256(node) != hash {
+                    return Err(ProofError::HashMismatch);
+                }
+                node
+            }
+            Reference::Inline(node) => {
+                inline = node;
+                &inline
+            }
+        };
+
+        let items = match rlp::decode(node) {
+            Ok(Item::List(items)) => items,
+            _ => return Err(ProofError::InvalidNode),
+        };
+        let child = match items.len() {
+            17 => match path.split_first() {
+                None => return Ok(non_empty(bytes(&items[16])?)),
+                Some((&i, rest)) => {
+                    path = rest;
+                    &items[i as usize]
+                }
+            },
+            2 => {
+                let (node_path, is_leaf) =
+                    hex_prefix_decode(bytes(&items[0])?).ok_or(ProofError::InvalidNode)?;
+                if is_leaf {
+                    return Ok(if node_path == path {
+                        non_empty(bytes(&items[1])?)
+                    } else {
+                        None
+                    });
+                }
+                if !path.starts_with(&node_path) {
+                    return Ok(None);
+                }
+                path = &path[node_path.len()..];
+                &items[1]
+            }
+            _ => return Err(ProofError::InvalidNode),
+        };
+
+        expected = match child {
+            // decoding is canonical, so re-encoding yields the embedded node
+            Item::List(_) => Reference::Inline(rlp::encode(child)),
+            Item::Bytes(b) if b.is_empty() => return Ok(None),
+            Item::Bytes(b) if b.len() == 32 => Reference::Hash(H256::from_slice(b)),
+            _ => return Err(ProofError::InvalidNode),
+        };
+    }
+}
+
+enum Reference {
+    Hash(H256),
+    Inline(Vec<u8>),
+}
+
+fn bytes(item: &Item) -> Result<&[u8], ProofError> {
+    match item {
+        Item::Bytes(b) => Ok(b),
+        Item::List(_) => Err(ProofError::InvalidNode),
+    }
+}
+
+fn non_empty(value: &[u8]) -> Option<Vec<u8>> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_vec())
+    }
+}
+
+fn decode_slot(value: &[u8]) -> Option<U256> {
+    rlp::decode(value).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn prove_present_and_absent_keys() {
+        let mut trie = Trie::new();
+        for i in 0u8..40 {
+            trie.insert(keccak256(&[i]).as_bytes(), vec![i + 1; 40]);
+        }
+        let root = trie.root_hash();
+
+        let key = keccak256(&[7]);
+        let proof = trie.proof(key.as_bytes());
+        assert_eq!(
+            verify_proof(root, key.as_bytes(), &proof),
+            Ok(Some(vec![8; 40]))
+        );
+
+        let absent = keccak256(&[100]);
+        let proof = trie.proof(absent.as_bytes());
+        assert_eq!(verify_proof(root, absent.as_bytes(), &proof), Ok(None));
+
+        let mut tampered = trie.proof(key.as_bytes());
+        tampered.last_mut().unwrap()[5] ^= 1;
+        assert_eq!(
+            verify_proof(root, key.as_bytes(), &tampered),
+            Err(ProofError::HashMismatch)
+        );
+    }
+
+    #[test]
+    fn account_proof_roundtrip() {
+        let address = H160::from_low_u64_be(1);
+        let slot = H256::from_low_u64_be(3);
+        let mut state = StateTrie::new();
+        state.insert_account(
+            address,
+            &AccountInfo {
+                balance: U256::from(100),
+                nonce: 2,
+                ..Default::default()
+            },
+            [(slot, H256::from_low_u64_be(5))],
+        );
+        state.insert_account(H160::from_low_u64_be(2), &AccountInfo::default(), []);
+        let root = state.root();
+
+        let proof = state.account_proof(address, &[slot, H256::zero()]);
+        assert_eq!(proof.balance, U256::from(100));
+        assert_eq!(proof.storage_proof[0].value, U256::from(5));
+        assert!(proof.verify(root).is_ok());
+
+        let missing = state.account_proof(H160::from_low_u64_be(9), &[]);
+        assert!(missing.verify(root).is_ok());
+
+        let mut forged = proof;
+        forged.balance = U256::from(101);
+        assert_eq!(forged.verify(root), Err(ProofError::ValueMismatch));
+    }
+}
@@ -0,0 +1,208 @@
+use super::{Trie, EMPTY_ROOT_HASH};
+use crate::crypto::keccak256;
+use crate::db::{AccountInfo, AccountState, CacheDB, Changes};
+use crate::rlp;
+use primitive_types::{H160, H256, U256};
+use std::collections::HashMap;
+
+// Account trie together with the storage trie of every account. Tries are
+// updated in place, so recomputing the roots after a block only rehashes the
+// paths that changed.
+#[derive(Clone, Debug, Default)]
+pub struct StateTrie {
+    pub(super) accounts: Trie,
+    pub(super) storage: HashMap<H160, Trie>,
+}
+
+impl StateTrie {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Builds the tries from every account held by `db`. Only the cached
+    // storage slots are included, so this is meant for databases whose cache
+    // is the full state, such as an `InMemoryDB`.
+    pub fn from_db<ExtDB>(db: &CacheDB<ExtDB>) -> Self {
+        let mut trie = Self::new();
+        for (address, account) in &db.accounts {
+            if account.state != AccountState::NotExisting {
+                trie.insert_account(*address, &account.info, account.storage.clone());
+            }
+        }
+        trie
+    }
+
+    // Inserts an account, replacing its storage
+    pub fn insert_account(
+        &mut self,
+        address: H160,
+        info: &AccountInfo,
+        storage: impl IntoIterator<Item = (H256, H256)>,
+    ) {
+        let mut storage_trie = Trie::new();
+        for (key, value) in storage {
+            insert_slot(&mut storage_trie, &key, &value);
+        }
+        self.storage.insert(address, storage_trie);
+        self.update_account_leaf(address, info);
+    }
+
+    pub fn remove_account(&mut self, address: &H160) {
+        self.storage.remove(address);
+        self.accounts
+            .remove(keccak256(address.as_bytes()).as_bytes());
+    }
+
+    // Applies the changes made by executing a transaction or a block
+    pub fn apply(&mut self, changes: &Changes) {
+        for (address, change) in changes {
+            match &change.account {
+                Some(account) => {
+                    // the storage of a deleted account is gone even if it
+                    // was created again
+                    if change.storage_cleared {
+                        self.storage.insert(*address, Trie::new());
+                    }
+                    let storage_trie = self.storage.entry(*address).or_default();
+                    for (key, value) in &account.storage {
+                        insert_slot(storage_trie, key, value);
+                    }
+                    self.update_account_leaf(*address, &AccountInfo::from(account));
+                }
+                None => self.remove_account(address),
+            }
+        }
+    }
+
+    pub fn root(&mut self) -> H256 {
+        self.accounts.root_hash()
+    }
+
+    pub fn storage_root(&mut self, address: &H160) -> H256 {
+        self.storage
+            .get_mut(address)
+            .map(|trie| trie.root_hash())
+            .unwrap_or(EMPTY_ROOT_HASH)
+    }
+
+    fn update_account_leaf(&mut self, address: H160, info: &AccountInfo) {
+        let storage_root = self.storage_root(&address);
+        self.accounts.insert(
+            keccak256(address.as_bytes()).as_bytes(),
+            encode_account(info, storage_root),
+        );
+    }
+}
+
+// Account as stored in the state trie
+struct TrieAccount {
+    nonce: u64,
+    balance: U256,
+    storage_root: H256,
+    code_hash: H256,
+}
+
+impl_rlp!(TrieAccount {
+    nonce,
+    balance,
+    storage_root,
+    code_hash
+});
+
+pub(crate) fn encode_account(info: &AccountInfo, storage_root: H256) -> Vec<u8> {
+    rlp::encode(&TrieAccount {
+        nonce: info.nonce,
+        balance: info.balance,
+        storage_root,
+        code_hash: info.code_hash,
+    })
+}
+
+// Inverse of `encode_account`, also returning the storage root
+pub(crate) fn decode_account(bytes: &[u8]) -> Option<(AccountInfo, H256)> {
+    let account: TrieAccount = rlp::decode(bytes).ok()?;
+    let info = AccountInfo {
+        balance: account.balance,
+        nonce: account.nonce,
+        code_hash: account.code_hash,
+        code: None,
+    };
+    Some((info, account.storage_root))
+}
+
+fn insert_slot(trie: &mut Trie, key: &H256, value: &H256) {
+    let key = keccak256(key.as_bytes());
+    if value.is_zero() {
+        trie.remove(key.as_bytes());
+    } else {
+        let value = U256::from_big_endian(value.as_bytes());
+        trie.insert(key.as_bytes(), rlp::encode(&value));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::AccountChange;
+    use crate::state::Account;
+
+    #[test]
+    fn incremental_update_matches_rebuild() {
+        let a = H160::from_low_u64_be(1);
+        let b = H160::from_low_u64_be(2);
+        let slot = H256::from_low_u64_be(1);
+        let info = AccountInfo {
+            balance: U256::from(10),
+            ..Default::default()
+        };
+
+        let mut trie = StateTrie::new();
+        trie.insert_account(a, &info, []);
+        trie.insert_account(b, &info, [(slot, H256::from_low_u64_be(1))]);
+        trie.root();
+
+        let account = Account {
+            balance: U256::from(3),
+            nonce: 1,
+            storage: HashMap::from([(slot, H256::from_low_u64_be(9))]),
+            ..Default::default()
+        };
+        trie.apply(&HashMap::from([
+            (a, Some(account.clone()).into()),
+            (b, None.into()),
+        ]));
+
+        let mut rebuilt = StateTrie::new();
+        rebuilt.insert_account(a, &AccountInfo::from(&account), account.storage);
+        assert_eq!(trie.root(), rebuilt.root());
+        assert_ne!(trie.storage_root(&a), EMPTY_ROOT_HASH);
+        assert_eq!(trie.storage_root(&b), EMPTY_ROOT_HASH);
+    }
+
+    #[test]
+    fn recreated_account_gets_new_storage() {
+        let address = H160::from_low_u64_be(1);
+        let (old, new) = (H256::from_low_u64_be(1), H256::from_low_u64_be(2));
+        let one = H256::from_low_u64_be(1);
+
+        let mut trie = StateTrie::new();
+        trie.insert_account(address, &AccountInfo::default(), [(old, one)]);
+        trie.root();
+
+        let account = Account {
+            nonce: 1,
+            storage: HashMap::from([(new, one)]),
+            ..Default::default()
+        };
+        let change = AccountChange {
+            account: Some(account.clone()),
+            storage_cleared: true,
+        };
+        trie.apply(&HashMap::from([(address, change)]));
+
+        let mut rebuilt = StateTrie::new();
+        rebuilt.insert_account(address, &AccountInfo::from(&account), account.storage);
+        assert_eq!(trie.storage_root(&address), rebuilt.storage_root(&address));
+        assert_eq!(trie.root(), rebuilt.root());
+    }
+}